@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use anyhow::Result;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use clap::{Subcommand, ValueEnum};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -79,6 +79,15 @@ pub struct Allergy {
     pub inactive_at: Option<String>,
     pub inactive_reason: Option<String>,
     pub note: Option<String>,
+    /// Stable key of the external record this allergy was imported from
+    /// (e.g. `fhir:AllergyIntolerance/123`), so re-importing updates rather
+    /// than duplicates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_key: Option<String>,
+    /// Filename of the journal entry this allergy was promoted from
+    /// (`gitehr journal promote`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_entry: Option<String>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -134,45 +143,77 @@ pub fn add(
     severity: AllergySeverity,
     note: Option<&str>,
 ) -> Result<Allergy> {
+    let (allergy, entry) = add_as(
+        agent,
+        reaction,
+        severity,
+        note,
+        None,
+        contributor::get_current_contributor(),
+    )?;
+    println!("Created journal entry: {}", entry);
+    println!("Added allergy: {}", allergy.id);
+    Ok(allergy)
+}
+
+/// Record an active allergy attributed to `author`, returning it with the
+/// journal entry that records the change. `source_entry` names the journal
+/// entry it was promoted from, if any. Prints nothing.
+pub fn add_as(
+    agent: &str,
+    reaction: &str,
+    severity: AllergySeverity,
+    note: Option<&str>,
+    source_entry: Option<&str>,
+    author: Option<String>,
+) -> Result<(Allergy, String)> {
     typed_state::ensure_gitehr_repository()?;
     let agent = require_text(agent, "--agent")?;
     let reaction = require_text(reaction, "--reaction")?;
     let now = Utc::now();
     let allergy = Allergy {
-        id: format!(
-            "ALG-{}-{}",
-            now.format("%Y%m%dT%H%M%SZ"),
-            Uuid::new_v4()
-                .to_string()
-                .chars()
-                .take(8)
-                .collect::<String>()
-        ),
+        id: new_id(now),
         agent: agent.to_string(),
         reaction: reaction.to_string(),
         severity,
         status: AllergyStatus::Active,
         recorded_at: now.to_rfc3339(),
-        recorded_by: contributor::get_current_contributor(),
+        recorded_by: author.clone(),
         inactive_at: None,
         inactive_reason: None,
         note: note.and_then(cleaned_str),
+        source_key: None,
+        source_entry: source_entry.map(str::to_string),
     };
 
     let mut state = load()?;
     state.allergies.push(allergy.clone());
-    persist_with_journal(
-        &state,
-        note.unwrap_or(&format!(
+    let mut body = note.map(str::to_string).unwrap_or_else(|| {
+        format!(
             "Added allergy: {} - {} ({})",
             allergy.agent, allergy.reaction, allergy.severity
-        )),
-    )?;
-    println!("Added allergy: {}", allergy.id);
-    Ok(allergy)
+        )
+    });
+    if let Some(source) = source_entry {
+        body.push_str(&format!("\n\nPromoted from journal entry {}.", source));
+    }
+    let entry = persist_with_journal(&state, &body, author)?;
+    Ok((allergy, entry))
 }
 
 pub fn inactive(id: &str, reason: Option<&str>) -> Result<Allergy> {
+    let (allergy, entry) = inactive_as(id, reason, contributor::get_current_contributor())?;
+    println!("Created journal entry: {}", entry);
+    println!("Marked allergy inactive: {}", allergy.id);
+    Ok(allergy)
+}
+
+/// Mark an allergy inactive, journalled as `author`. Prints nothing.
+pub fn inactive_as(
+    id: &str,
+    reason: Option<&str>,
+    author: Option<String>,
+) -> Result<(Allergy, String)> {
     typed_state::ensure_gitehr_repository()?;
     let mut state = load()?;
     let allergy = state
@@ -186,21 +227,44 @@ pub fn inactive(id: &str, reason: Option<&str>) -> Result<Allergy> {
     allergy.inactive_reason = reason.and_then(cleaned_str);
     let changed = allergy.clone();
 
-    persist_with_journal(
+    let entry = persist_with_journal(
         &state,
         reason.unwrap_or(&format!("Marked allergy inactive: {}", changed.agent)),
+        author,
     )?;
-    println!("Marked allergy inactive: {}", changed.id);
-    Ok(changed)
+    Ok((changed, entry))
+}
+
+/// Mint a new allergy id (`ALG-<timestamp>-<random>`).
+pub fn new_id(now: DateTime<Utc>) -> String {
+    format!(
+        "ALG-{}-{}",
+        now.format("%Y%m%dT%H%M%SZ"),
+        Uuid::new_v4()
+            .to_string()
+            .chars()
+            .take(8)
+            .collect::<String>()
+    )
 }
 
-fn persist_with_journal(state: &AllergiesState, journal_body: &str) -> Result<()> {
+/// Write and stage the allergies state file without committing, for callers
+/// that batch several state changes under one journal entry.
+pub fn save(state: &AllergiesState) -> Result<()> {
     let path = typed_state::write_front_matter(STATE_FILE, state)?;
     git::git_add(&path.to_string_lossy())?;
-    journal::create_journal_entry(journal_body)?;
     Ok(())
 }
 
+fn persist_with_journal(
+    state: &AllergiesState,
+    journal_body: &str,
+    author: Option<String>,
+) -> Result<String> {
+    save(state)?;
+    journal::create_journal_entry_as(journal_body, Vec::new(), author)
+}
+
 fn require_text<'a>(value: &'a str, label: &str) -> Result<&'a str> {
     let trimmed = value.trim();
     if trimmed.is_empty() {
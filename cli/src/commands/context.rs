@@ -40,6 +40,12 @@ pub fn resolve_store_root() -> Result<PathBuf> {
     }
 }
 
+/// The Store the current directory is in, if any. Unlike
+/// [`resolve_store_root`], the configured Store is not consulted.
+pub fn enclosing_store_root() -> Result<Option<PathBuf>> {
+    find_up(STORE_MARKER)
+}
+
 /// Resolve the subject repo for a repo-level command: the nearest `.gitehr/`
 /// ancestor, or - at a Store root with exactly one subject - that subject.
 pub fn resolve_repo_root() -> Result<PathBuf> {
@@ -62,6 +62,26 @@ pub fn get_current_contributor() -> Option<String> {
         .and_then(|config| config.current_contributor)
 }
 
+/// Look up a contributor by id.
+pub fn get_contributor(id: &str) -> Option<Contributor> {
+    load_config()
+        .ok()
+        .and_then(|mut config| config.contributors.remove(id))
+}
+
+/// Display names for every known contributor, keyed by id.
+pub fn contributor_names() -> HashMap<String, String> {
+    load_config()
+        .map(|config| {
+            config
+                .contributors
+                .into_values()
+                .map(|contributor| (contributor.id, contributor.name))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 pub fn add_contributor(
     id: &str,
     name: &str,
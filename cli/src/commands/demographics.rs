@@ -6,7 +6,7 @@ use chrono::NaiveDate;
 use clap::Subcommand;
 use serde::{Deserialize, Serialize};
 
-use super::{git, journal, typed_state};
+use super::{contributor, git, journal, typed_state};
 
 const STATE_FILE: &str = "demographics.md";
 
@@ -119,6 +119,18 @@ pub fn load() -> Result<Demographics> {
 }
 
 pub fn update(update: DemographicsUpdate) -> Result<Demographics> {
+    let (demographics, entry) = update_as(update, contributor::get_current_contributor())?;
+    println!("Created journal entry: {}", entry);
+    println!("Updated demographics");
+    Ok(demographics)
+}
+
+/// Merge `update` into the demographics, journalled as `author`, returning the
+/// result with the journal entry that records the change. Prints nothing.
+pub fn update_as(
+    update: DemographicsUpdate,
+    author: Option<String>,
+) -> Result<(Demographics, String)> {
     typed_state::ensure_gitehr_repository()?;
     let note = update.note.clone();
     if update.title.is_none()
@@ -139,17 +151,25 @@ pub fn update(update: DemographicsUpdate) -> Result<Demographics> {
 
     let mut demographics = load()?;
     apply(update, &mut demographics);
-    let path = typed_state::write_front_matter(STATE_FILE, &demographics)?;
-    git::git_add(&path.to_string_lossy())?;
+    save(&demographics)?;
     let body = note.unwrap_or_else(|| {
         state_change_body("Updated demographics", demographics.full_name.as_deref())
     });
-    journal::create_journal_entry(&body)?;
-    println!("Updated demographics");
-    Ok(demographics)
+    let entry = journal::create_journal_entry_as(&body, Vec::new(), author)?;
+    Ok((demographics, entry))
+}
+
+/// Write and stage the demographics state file without committing, for
+/// callers that batch several state changes under one journal entry.
+pub fn save(demographics: &Demographics) -> Result<()> {
+    let path = typed_state::write_front_matter(STATE_FILE, demographics)?;
+    git::git_add(&path.to_string_lossy())?;
+    Ok(())
 }
 
-fn apply(update: DemographicsUpdate, demographics: &mut Demographics) {
+/// Merge the supplied fields over `demographics`; absent or blank fields keep
+/// their current value and identifiers are upserted by type.
+pub fn apply(update: DemographicsUpdate, demographics: &mut Demographics) {
     if let Some(value) = cleaned(update.title) {
         demographics.title = Some(value);
     }
@@ -95,9 +95,7 @@ pub fn run_many_with_sources(
 
     let mut stored_documents = Vec::with_capacity(sources.len());
     for source in sources {
-        let stored = store_document(source)?;
-        crate::commands::git::git_add(&stored.path)?;
-        stored_documents.push(stored);
+        stored_documents.push(store_and_stage_document(source)?);
     }
 
     let body = message
@@ -125,6 +123,28 @@ pub fn run_many_with_sources(
         .collect())
 }
 
+/// Copy one source into the record and stage it, without writing a journal
+/// entry. For callers (such as bulk import) that link several Documents from a
+/// single entry of their own.
+pub fn stage(source: &DocumentSource) -> Result<DocumentRef> {
+    ensure_gitehr_repository()?;
+    if !source.path.exists() {
+        bail!("No such file or directory: {}", source.path.display());
+    }
+    let stored = store_and_stage_document(source)?;
+    Ok(DocumentRef {
+        path: stored.path,
+        sha256: stored.sha256,
+        original_filename: Some(stored.original_filename),
+    })
+}
+
+fn store_and_stage_document(source: &DocumentSource) -> Result<StoredDocument> {
+    let stored = store_document(source)?;
+    crate::commands::git::git_add(&stored.path)?;
+    Ok(stored)
+}
+
 fn store_document(source: &DocumentSource) -> Result<StoredDocument> {
     let original_filename = source
         .path
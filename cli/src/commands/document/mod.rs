@@ -198,6 +198,74 @@ pub fn collect_refs() -> Result<Vec<(String, DocumentRef)>> {
     Ok(refs)
 }
 
+/// A reference to a Document already in the record, for linking it from a
+/// new journal entry. `record_path` must sit directly under a Document root;
+/// the hash is taken from the file, or from the manifest for a directory.
+pub fn existing_ref(record_path: &str) -> Result<DocumentRef> {
+    let record_path = record_path.trim_end_matches('/');
+    if !is_record_path(record_path) {
+        bail!(
+            "Not a Document path: {} (expected documents/<name> or imaging/<name>)",
+            record_path
+        );
+    }
+    let path = Path::new(record_path);
+    if !path.exists() {
+        bail!("Document not found: {}", record_path);
+    }
+    let sha256 = if path.is_dir() {
+        hash_file(&path.join(MANIFEST_FILENAME))?
+    } else {
+        hash_file(path)?
+    };
+    let original_filename = collect_refs()?
+        .into_iter()
+        .find(|(_, doc)| doc.path == record_path)
+        .and_then(|(_, doc)| doc.original_filename);
+    Ok(DocumentRef {
+        path: record_path.to_string(),
+        sha256,
+        original_filename,
+    })
+}
+
+/// Whether `path` names an item directly under a Document root, e.g.
+/// `documents/<name>`, with no other components.
+pub fn is_record_path(path: &str) -> bool {
+    let mut components = Path::new(path).components();
+    matches!(
+        (components.next(), components.next(), components.next()),
+        (Some(std::path::Component::Normal(root)), Some(std::path::Component::Normal(_)), None)
+            if DOCUMENT_ROOTS.iter().any(|r| root == std::ffi::OsStr::new(r))
+    )
+}
+
+/// MIME type for a Document file, from its extension.
+pub fn mime_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    match extension.as_str() {
+        "pdf" => "application/pdf",
+        "jpg" | "jpeg" => "image/jpeg",
+        "png" => "image/png",
+        "gif" => "image/gif",
+        "tif" | "tiff" => "image/tiff",
+        "txt" => "text/plain",
+        "html" | "htm" => "text/html",
+        "md" => "text/markdown",
+        "xml" => "application/xml",
+        "json" => "application/json",
+        "dcm" => "application/dicom",
+        "rtf" => "application/rtf",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        _ => "application/octet-stream",
+    }
+}
+
 /// Files and directories present under the Document roots, as record paths.
 /// A directory Document counts as one item; per-folder README.md is layout
 /// scaffolding, not a Document.
@@ -294,6 +362,17 @@ mod tests {
         assert_eq!(slugify("--a--"), "a");
     }
 
+    #[test]
+    fn test_record_paths() {
+        assert!(is_record_path("documents/letter.pdf"));
+        assert!(is_record_path("imaging/ct-head"));
+        assert!(!is_record_path("documents"));
+        assert!(!is_record_path("journal/entry.md"));
+        assert!(!is_record_path("documents/../state/x"));
+        assert!(!is_record_path("documents/ct/manifest.json"));
+        assert!(!is_record_path("/documents/letter.pdf"));
+    }
+
     #[test]
     fn test_slugify_truncates() {
         let long = "x".repeat(200);
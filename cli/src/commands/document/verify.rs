@@ -5,6 +5,7 @@ use anyhow::{Result, bail};
 use std::path::Path;
 
 use super::{collect_refs, ensure_gitehr_repository, hash_file, verify_directory_document};
+use crate::commands::journal::DocumentRef;
 
 /// Verify every Document reference in the journal (or just `filter`).
 /// A reference whose target was removed from the working tree is reported but
@@ -33,41 +34,23 @@ pub fn run(filter: Option<&str>) -> Result<bool> {
         if !checked.insert((doc.path.clone(), doc.sha256.clone())) {
             continue;
         }
-        let path = Path::new(&doc.path);
-        if !path.exists() {
-            println!(
-                "MISSING  {} (removed from working tree; retained in Git history)",
-                doc.path
-            );
-            missing += 1;
-        } else if path.is_dir() {
-            match verify_directory_document(path, &doc.sha256) {
-                Ok(errors) if errors.is_empty() => {
-                    println!("OK       {}", doc.path);
-                    ok += 1;
-                }
-                Ok(errors) => {
-                    for e in errors {
-                        println!("FAILED   {}: {}", doc.path, e);
-                        failures.push(doc.path.clone());
-                    }
-                }
-                Err(e) => {
-                    println!("FAILED   {}: {}", doc.path, e);
-                    failures.push(doc.path.clone());
-                }
-            }
-        } else {
-            let actual = hash_file(path)?;
-            if actual == doc.sha256 {
+        match check(doc)? {
+            Status::Ok => {
                 println!("OK       {}", doc.path);
                 ok += 1;
-            } else {
+            }
+            Status::Missing => {
                 println!(
-                    "FAILED   {}: hash mismatch (expected {}, found {})",
-                    doc.path, doc.sha256, actual
+                    "MISSING  {} (removed from working tree; retained in Git history)",
+                    doc.path
                 );
-                failures.push(doc.path.clone());
+                missing += 1;
+            }
+            Status::Failed(errors) => {
+                for e in errors {
+                    println!("FAILED   {}: {}", doc.path, e);
+                    failures.push(doc.path.clone());
+                }
             }
         }
     }
@@ -84,3 +67,36 @@ pub fn run(filter: Option<&str>) -> Result<bool> {
 
     Ok(failures.is_empty())
 }
+
+/// Outcome of checking one Document reference against the working tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    /// Removed from the working tree; not an integrity failure.
+    Missing,
+    Failed(Vec<String>),
+}
+
+/// Check a single Document reference against its recorded SHA-256.
+pub fn check(doc: &DocumentRef) -> Result<Status> {
+    let path = Path::new(&doc.path);
+    if !path.exists() {
+        return Ok(Status::Missing);
+    }
+    if path.is_dir() {
+        return Ok(match verify_directory_document(path, &doc.sha256) {
+            Ok(errors) if errors.is_empty() => Status::Ok,
+            Ok(errors) => Status::Failed(errors),
+            Err(e) => Status::Failed(vec![e.to_string()]),
+        });
+    }
+    let actual = hash_file(path)?;
+    if actual == doc.sha256 {
+        Ok(Status::Ok)
+    } else {
+        Ok(Status::Failed(vec![format!(
+            "hash mismatch (expected {}, found {})",
+            doc.sha256, actual
+        )]))
+    }
+}
@@ -0,0 +1,188 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! FHIR journal export.
+//!
+//! Builds a FHIR R4 collection Bundle with, for each journal entry, an
+//! `Encounter` carrying the entry's metadata (encounter type, location and
+//! SNOMED CT codes as `reasonCode`) and a `DocumentReference` carrying the
+//! note itself, its tags and a link to the Encounter. This is the FHIR
+//! counterpart of the openEHR export's encounter compositions; the IPS has no
+//! section for journal entries.
+
+use anyhow::Result;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::Utc;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+use super::ips::{entry, new_full_url, patient_resource, practitioner_resource, uuid_of};
+use super::write_output;
+use crate::commands::contributor;
+use crate::commands::demographics;
+use crate::commands::journal::metadata::EncounterType;
+use crate::commands::journal::{self, ParsedEntry};
+
+const SNOMED: &str = "http://snomed.info/sct";
+const LOINC: &str = "http://loinc.org";
+const ACT_CODE: &str = "http://terminology.hl7.org/CodeSystem/v3-ActCode";
+const NULL_FLAVOR: &str = "http://terminology.hl7.org/CodeSystem/v3-NullFlavor";
+const ENCOUNTER_TYPE_SYSTEM: &str = "https://gitehr.org/CodeSystem/encounter-type";
+const TAG_SYSTEM: &str = "https://gitehr.org/CodeSystem/tag";
+const JOURNAL_ENTRY_SYSTEM: &str = "https://gitehr.org/id/journal-entry";
+
+pub fn run(output: Option<&Path>) -> Result<()> {
+    let bundle = build()?;
+    write_output(output, &serde_json::to_string_pretty(&bundle)?)
+}
+
+/// Build the collection Bundle from every journal entry, oldest first.
+pub fn build() -> Result<Value> {
+    let patient_url = new_full_url();
+    let patient_ref = json!({ "reference": patient_url });
+    let mut patient = patient_resource(&demographics::load()?);
+    patient["id"] = json!(uuid_of(&patient_url));
+    let mut entries = vec![entry(&patient_url, patient)];
+
+    // One Practitioner per contributor who wrote an entry.
+    let mut authors: HashMap<String, Value> = HashMap::new();
+    for parsed in journal::parsed_entries()? {
+        let author = parsed.metadata.author.as_deref().map(|id| {
+            authors
+                .entry(id.to_string())
+                .or_insert_with(|| match contributor::get_contributor(id) {
+                    Some(contributor) => {
+                        let url = new_full_url();
+                        let mut practitioner = practitioner_resource(&contributor);
+                        practitioner["id"] = json!(uuid_of(&url));
+                        entries.push(entry(&url, practitioner));
+                        json!({ "reference": url, "display": contributor.name })
+                    }
+                    None => json!({ "display": id }),
+                })
+                .clone()
+        });
+
+        let encounter_url = new_full_url();
+        let mut encounter = encounter_resource(&parsed, &patient_ref, author.as_ref());
+        encounter["id"] = json!(uuid_of(&encounter_url));
+        let mut note = document_reference(&parsed, &patient_ref, author.as_ref(), &encounter_url);
+        let note_url = new_full_url();
+        note["id"] = json!(uuid_of(&note_url));
+        entries.push(entry(&encounter_url, encounter));
+        entries.push(entry(&note_url, note));
+    }
+
+    Ok(json!({
+        "resourceType": "Bundle",
+        "id": Uuid::new_v4().to_string(),
+        "type": "collection",
+        "timestamp": Utc::now().to_rfc3339(),
+        "entry": entries,
+    }))
+}
+
+/// When the care happened: the clinical time as stored (FHIR dateTimes may be
+/// partial), or else when the entry was recorded.
+fn care_time(parsed: &ParsedEntry) -> String {
+    match &parsed.metadata.clinical_time {
+        Some(time) => time.to_string(),
+        None => parsed.metadata.timestamp.to_rfc3339(),
+    }
+}
+
+fn encounter_resource(parsed: &ParsedEntry, patient: &Value, author: Option<&Value>) -> Value {
+    let meta = &parsed.metadata;
+    let class = match meta.encounter_type {
+        Some(EncounterType::Consultation) => {
+            json!({ "system": ACT_CODE, "code": "AMB", "display": "ambulatory" })
+        }
+        Some(EncounterType::Phone) => {
+            json!({ "system": ACT_CODE, "code": "VR", "display": "virtual" })
+        }
+        _ => json!({ "system": NULL_FLAVOR, "code": "UNK", "display": "unknown" }),
+    };
+    let mut encounter = json!({
+        "resourceType": "Encounter",
+        "identifier": [{ "system": JOURNAL_ENTRY_SYSTEM, "value": parsed.filename }],
+        "status": "finished",
+        "class": class,
+        "subject": patient,
+        "period": { "start": care_time(parsed) },
+    });
+    if let Some(encounter_type) = meta.encounter_type {
+        encounter["type"] = json!([{
+            "coding": [{
+                "system": ENCOUNTER_TYPE_SYSTEM,
+                "code": encounter_type,
+                "display": encounter_type.to_string(),
+            }],
+            "text": encounter_type.to_string(),
+        }]);
+    }
+    if !meta.codes.is_empty() {
+        encounter["reasonCode"] = meta
+            .codes
+            .iter()
+            .map(|code| {
+                let mut coding = json!({ "system": SNOMED, "code": code.code });
+                if let Some(display) = &code.display {
+                    coding["display"] = json!(display);
+                }
+                json!({ "coding": [coding] })
+            })
+            .collect();
+    }
+    if let Some(location) = &meta.location {
+        encounter["location"] = json!([{ "location": { "display": location } }]);
+    }
+    if let Some(author) = author {
+        encounter["participant"] = json!([{ "individual": author }]);
+    }
+    encounter
+}
+
+fn document_reference(
+    parsed: &ParsedEntry,
+    patient: &Value,
+    author: Option<&Value>,
+    encounter_url: &str,
+) -> Value {
+    let meta = &parsed.metadata;
+    let mut note = json!({
+        "resourceType": "DocumentReference",
+        "masterIdentifier": { "system": JOURNAL_ENTRY_SYSTEM, "value": parsed.filename },
+        "status": "current",
+        "docStatus": "final",
+        "type": { "coding": [{ "system": LOINC, "code": "34109-9", "display": "Note" }] },
+        "subject": patient,
+        "date": meta.timestamp.to_rfc3339(),
+        "content": [{
+            "attachment": {
+                "contentType": "text/markdown",
+                "data": BASE64.encode(parsed.content.trim()),
+                "title": parsed.filename,
+            }
+        }],
+        "context": {
+            "encounter": [{ "reference": encounter_url }],
+            "period": { "start": care_time(parsed) },
+        },
+    });
+    if let Some(author) = author {
+        note["author"] = json!([author]);
+    }
+    if !meta.tags.is_empty() {
+        note["meta"] = json!({
+            "tag": meta
+                .tags
+                .iter()
+                .map(|tag| json!({ "system": TAG_SYSTEM, "code": tag }))
+                .collect::<Vec<_>>(),
+        });
+    }
+    note
+}
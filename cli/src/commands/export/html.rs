@@ -0,0 +1,411 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Static HTML export: a self-contained, offline copy of the record.
+//!
+//! The site is three pages sharing one inline stylesheet - a summary
+//! (demographics, active allergies, vaccinations), the journal timeline, and a
+//! Documents index - plus copies of every Document still in the working tree.
+//! Nothing is fetched from the network and no page references outside the
+//! export directory.
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+use super::{html_escape, subject_id};
+use crate::commands::allergies;
+use crate::commands::contributor;
+use crate::commands::demographics::{self, Demographics};
+use crate::commands::document::{self, verify};
+use crate::commands::journal::clinical_time::TimeBasis;
+use crate::commands::journal::filter::sort_entries;
+use crate::commands::journal::{self, DocumentRef, ParsedEntry};
+use crate::commands::vaccinations;
+use crate::medmd;
+
+const STYLE: &str = "
+body { font-family: sans-serif; max-width: 56em; margin: 2em auto; padding: 0 1em; color: #222; }
+nav { border-bottom: 2px solid #005eb8; padding-bottom: 0.5em; margin-bottom: 1em; }
+nav a { margin-right: 1em; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 1em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }
+article { border-left: 3px solid #ccc; padding-left: 1em; margin-bottom: 1.5em; }
+article .meta { color: #555; font-size: 0.9em; }
+.body { white-space: pre-wrap; }
+.body.medmd { white-space: normal; }
+.med-section h3, .med-section h4 { font-size: 1em; margin: 0.6em 0 0.2em; }
+.med-section .med-section { margin-left: 1.5em; }
+code { word-break: break-all; }
+.ok { color: #007f3b; }
+.missing { color: #8a6d00; }
+.failed { color: #d5281b; font-weight: bold; }
+footer { margin-top: 2em; font-size: 0.85em; color: #555; }
+";
+
+/// Write the site into `dir`, which must be empty or not yet exist, with the
+/// journal ordered by `time`.
+pub fn run(dir: &Path, time: TimeBasis) -> Result<()> {
+    if dir.exists() && fs::read_dir(dir)?.next().is_some() {
+        bail!(
+            "{} is not empty; choose a new directory for the export",
+            dir.display()
+        );
+    }
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+    let demographics = demographics::load()?;
+    let mut entries = journal::parsed_entries()?;
+    sort_entries(&mut entries, time);
+    let names = contributor::contributor_names();
+    let registry = medmd::Registry::load()?;
+    let subject = subject_name(&demographics);
+
+    let documents = copy_documents(&entries, dir)?;
+
+    write_page(
+        dir,
+        "index.html",
+        "Summary",
+        &subject,
+        &summary_body(&demographics)?,
+    )?;
+    write_page(
+        dir,
+        "journal.html",
+        "Journal",
+        &subject,
+        &journal_body(&entries, &names, &registry),
+    )?;
+    write_page(
+        dir,
+        "documents.html",
+        "Documents",
+        &subject,
+        &documents_body(&documents),
+    )?;
+
+    println!(
+        "Exported {} journal entr{} and {} Document{} to {}",
+        entries.len(),
+        if entries.len() == 1 { "y" } else { "ies" },
+        documents.len(),
+        if documents.len() == 1 { "" } else { "s" },
+        dir.display()
+    );
+    Ok(())
+}
+
+/// One row of the Documents index: a unique (path, sha256) reference.
+struct IndexedDocument {
+    doc: DocumentRef,
+    status: verify::Status,
+    /// Journal entries that reference the Document.
+    entries: Vec<String>,
+}
+
+/// Verify every referenced Document and copy whatever is still in the
+/// working tree into the export, keeping its record path.
+fn copy_documents(entries: &[ParsedEntry], dir: &Path) -> Result<Vec<IndexedDocument>> {
+    let mut indexed: BTreeMap<(String, String), IndexedDocument> = BTreeMap::new();
+    for (filename, doc) in document::collect_refs()? {
+        let key = (doc.path.clone(), doc.sha256.clone());
+        if let Some(existing) = indexed.get_mut(&key) {
+            existing.entries.push(filename);
+            continue;
+        }
+        let status = verify::check(&doc)?;
+        let source = Path::new(&doc.path);
+        if !document::is_record_path(&doc.path) {
+            eprintln!(
+                "Warning: not copying Document {}: not a path under a Document root",
+                doc.path
+            );
+        } else if source.exists() {
+            let target = dir.join(&doc.path);
+            if source.is_dir() {
+                document::copy_tree(source, &target)?;
+            } else {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(source, &target)
+                    .with_context(|| format!("Failed to copy {}", doc.path))?;
+            }
+        }
+        indexed.insert(
+            key,
+            IndexedDocument {
+                doc,
+                status,
+                entries: vec![filename],
+            },
+        );
+    }
+    // Keep the index in journal order rather than path order.
+    let order: HashMap<&str, usize> = entries
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| (entry.filename.as_str(), i))
+        .collect();
+    let mut documents: Vec<IndexedDocument> = indexed.into_values().collect();
+    documents.sort_by_key(|d| order.get(d.entries[0].as_str()).copied());
+    Ok(documents)
+}
+
+fn subject_name(demographics: &Demographics) -> String {
+    demographics
+        .full_name
+        .clone()
+        .or_else(subject_id)
+        .unwrap_or_else(|| "Health record".to_string())
+}
+
+fn write_page(dir: &Path, file: &str, title: &str, subject: &str, body: &str) -> Result<()> {
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title} - {subject}</title>
+<style>{style}</style>
+</head>
+<body>
+<nav><strong>{subject}</strong> &middot; <a href="index.html">Summary</a><a href="journal.html">Journal</a><a href="documents.html">Documents</a></nav>
+<h1>{title}</h1>
+{body}
+<footer>Exported from GitEHR on {generated}. This is a read-only copy; the Git repository is the authoritative record.</footer>
+</body>
+</html>
+"#,
+        title = html_escape(title),
+        subject = html_escape(subject),
+        style = STYLE,
+        body = body,
+        generated = Utc::now().format("%Y-%m-%d %H:%M UTC"),
+    );
+    let path = dir.join(file);
+    fs::write(&path, html).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn table(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let head: String = if headers.is_empty() {
+        String::new()
+    } else {
+        let cells: String = headers
+            .iter()
+            .map(|h| format!("<th>{}</th>", html_escape(h)))
+            .collect();
+        format!("<thead><tr>{}</tr></thead>\n", cells)
+    };
+    let body: String = rows
+        .iter()
+        .map(|row| format!("<tr>{}</tr>\n", row.concat()))
+        .collect();
+    format!("<table>\n{}<tbody>\n{}</tbody>\n</table>\n", head, body)
+}
+
+fn cell(text: &str) -> String {
+    format!("<td>{}</td>", html_escape(text))
+}
+
+fn summary_body(demographics: &Demographics) -> Result<String> {
+    let mut out = String::from("<h2>Demographics</h2>\n");
+    let mut rows = Vec::new();
+    let mut field = |label: &str, value: Option<&str>| {
+        if let Some(value) = value {
+            rows.push(vec![
+                format!("<th>{}</th>", html_escape(label)),
+                cell(value),
+            ]);
+        }
+    };
+    field("Name", demographics.full_name.as_deref());
+    field("Title", demographics.title.as_deref());
+    field("Preferred name", demographics.preferred_name.as_deref());
+    field("Date of birth", demographics.date_of_birth.as_deref());
+    field("NHS number", demographics.nhs_number.as_deref());
+    field("Address", demographics.address.as_deref());
+    field("GitEHR id", subject_id().as_deref());
+    for identifier in &demographics.identifiers {
+        field(&identifier.id_type, Some(&identifier.value));
+    }
+    if rows.is_empty() {
+        out.push_str("<p>No demographics recorded.</p>\n");
+    } else {
+        out.push_str(&table(&[], &rows));
+    }
+
+    out.push_str("<h2>Allergies</h2>\n");
+    let allergies = allergies::list(false)?;
+    if allergies.is_empty() {
+        out.push_str("<p>No active allergies recorded.</p>\n");
+    } else {
+        let rows: Vec<Vec<String>> = allergies
+            .iter()
+            .map(|allergy| {
+                vec![
+                    cell(&allergy.agent),
+                    cell(&allergy.reaction),
+                    cell(&allergy.severity.to_string()),
+                    cell(allergy.note.as_deref().unwrap_or("")),
+                    cell(&allergy.recorded_at.chars().take(10).collect::<String>()),
+                ]
+            })
+            .collect();
+        out.push_str(&table(
+            &["Substance", "Reaction", "Severity", "Note", "Recorded"],
+            &rows,
+        ));
+    }
+
+    out.push_str("<h2>Vaccinations</h2>\n");
+    let vaccinations = vaccinations::list(false)?;
+    if vaccinations.is_empty() {
+        out.push_str("<p>No vaccinations recorded.</p>\n");
+    } else {
+        let rows: Vec<Vec<String>> = vaccinations
+            .iter()
+            .map(|vaccination| {
+                vec![
+                    cell(&vaccination.date),
+                    cell(&vaccination.vaccine),
+                    cell(
+                        &vaccination
+                            .dose_sequence
+                            .map(|dose| dose.to_string())
+                            .unwrap_or_default(),
+                    ),
+                    cell(vaccination.batch_number.as_deref().unwrap_or("")),
+                ]
+            })
+            .collect();
+        out.push_str(&table(&["Date", "Vaccine", "Dose", "Batch"], &rows));
+    }
+    Ok(out)
+}
+
+/// Anchor id for a journal entry on the timeline page.
+fn entry_anchor(filename: &str) -> String {
+    filename.trim_end_matches(".md").to_string()
+}
+
+fn journal_body(
+    entries: &[ParsedEntry],
+    names: &HashMap<String, String>,
+    registry: &medmd::Registry,
+) -> String {
+    if entries.is_empty() {
+        return "<p>No journal entries.</p>\n".to_string();
+    }
+    let mut out = String::new();
+    for entry in entries {
+        let author = match &entry.metadata.author {
+            Some(id) => names.get(id).cloned().unwrap_or_else(|| id.clone()),
+            None => "Unknown author".to_string(),
+        };
+        // Entries with Medical Markdown codes are shown as their sections;
+        // anything else exactly as written.
+        let body = if medmd::has_codes(&entry.content) {
+            format!(
+                "<div class=\"body medmd\">\n{}</div>",
+                medmd::render_html(&entry.content, registry)
+            )
+        } else {
+            format!(
+                "<div class=\"body\">{}</div>",
+                html_escape(entry.content.trim())
+            )
+        };
+        let mut meta = format!(
+            "{} &middot; {}",
+            html_escape(&entry.metadata.time_label()),
+            html_escape(&author)
+        );
+        if let Some(label) = entry.metadata.metadata_label() {
+            meta.push_str(&format!(" &middot; {}", html_escape(&label)));
+        }
+        out.push_str(&format!(
+            "<article id=\"{}\">\n<p class=\"meta\">{}</p>\n{}\n",
+            html_escape(&entry_anchor(&entry.filename)),
+            meta,
+            body,
+        ));
+        if let Some(documents) = &entry.metadata.documents {
+            out.push_str("<ul>\n");
+            for doc in documents {
+                out.push_str(&format!(
+                    "<li>{}</li>\n",
+                    document_link(doc, Path::new(&doc.path).exists())
+                ));
+            }
+            out.push_str("</ul>\n");
+        }
+        out.push_str("</article>\n");
+    }
+    out
+}
+
+fn document_link(doc: &DocumentRef, present: bool) -> String {
+    let label = html_escape(doc.original_filename.as_deref().unwrap_or(&doc.path));
+    // Front matter may have been edited by hand to point anywhere.
+    if !present || !document::is_record_path(&doc.path) {
+        return label;
+    }
+    // A directory Document is opened through its manifest.
+    let href = if Path::new(&doc.path).is_dir() {
+        format!("{}/{}", doc.path, document::MANIFEST_FILENAME)
+    } else {
+        doc.path.clone()
+    };
+    format!("<a href=\"{}\">{}</a>", html_escape(&href), label)
+}
+
+fn documents_body(documents: &[IndexedDocument]) -> String {
+    if documents.is_empty() {
+        return "<p>No Documents recorded.</p>\n".to_string();
+    }
+    let rows: Vec<Vec<String>> = documents
+        .iter()
+        .map(|indexed| {
+            let status = match &indexed.status {
+                verify::Status::Ok => "<td class=\"ok\">Verified</td>".to_string(),
+                verify::Status::Missing => {
+                    "<td class=\"missing\">Removed from working tree (retained in Git history)</td>"
+                        .to_string()
+                }
+                verify::Status::Failed(errors) => format!(
+                    "<td class=\"failed\">FAILED: {}</td>",
+                    html_escape(&errors.join("; "))
+                ),
+            };
+            let referenced_by: Vec<String> = indexed
+                .entries
+                .iter()
+                .map(|filename| {
+                    format!(
+                        "<a href=\"journal.html#{}\">{}</a>",
+                        html_escape(&entry_anchor(filename)),
+                        html_escape(filename)
+                    )
+                })
+                .collect();
+            vec![
+                format!(
+                    "<td>{}</td>",
+                    document_link(&indexed.doc, indexed.status != verify::Status::Missing)
+                ),
+                cell(&indexed.doc.path),
+                format!("<td><code>{}</code></td>", html_escape(&indexed.doc.sha256)),
+                status,
+                format!("<td>{}</td>", referenced_by.join("<br>")),
+            ]
+        })
+        .collect();
+    table(
+        &["Document", "Path", "SHA-256", "Integrity", "Journal entry"],
+        &rows,
+    )
+}
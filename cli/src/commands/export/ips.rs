@@ -0,0 +1,495 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! International Patient Summary (IPS) export.
+//!
+//! Builds a FHIR R4 document Bundle whose first entry is a Composition with the
+//! three mandatory IPS sections (allergies, medications, problems) plus
+//! immunisations when any are recorded. A mandatory section with nothing to
+//! report is never omitted: it carries an explicit "no information" entry from
+//! the IPS absent/unknown code system. The Composition is authored and attested
+//! by the active contributor.
+
+use anyhow::{Result, bail};
+use chrono::Utc;
+use serde_json::{Value, json};
+use std::path::Path;
+use uuid::Uuid;
+
+use super::{html_escape, subject_id, write_output};
+use crate::commands::allergies::{self, Allergy, AllergySeverity};
+use crate::commands::contributor::{self, Contributor};
+use crate::commands::demographics::{self, Demographics};
+use crate::commands::vaccinations::{self, Vaccination};
+
+const LOINC: &str = "http://loinc.org";
+const ABSENT_UNKNOWN: &str = "http://hl7.org/fhir/uv/ips/CodeSystem/absent-unknown-uv-ips";
+const NHS_NUMBER_SYSTEM: &str = "https://fhir.nhs.uk/Id/nhs-number";
+const SUBJECT_ID_SYSTEM: &str = "https://gitehr.org/id/subject";
+const CONTRIBUTOR_ID_SYSTEM: &str = "https://gitehr.org/id/contributor";
+const XHTML_NS: &str = "http://www.w3.org/1999/xhtml";
+
+pub fn run(output: Option<&Path>, html: Option<&Path>) -> Result<()> {
+    let bundle = build()?;
+    write_output(output, &serde_json::to_string_pretty(&bundle)?)?;
+    if let Some(path) = html {
+        write_output(Some(path), &render_html(&bundle))?;
+    }
+    Ok(())
+}
+
+/// Build the IPS document Bundle from the current typed state.
+pub fn build() -> Result<Value> {
+    let author = active_author()?;
+    let now = Utc::now().to_rfc3339();
+
+    let patient_url = new_full_url();
+    let author_url = new_full_url();
+    let patient_ref = json!({ "reference": patient_url });
+
+    let mut patient = patient_resource(&demographics::load()?);
+    patient["id"] = json!(uuid_of(&patient_url));
+    let mut practitioner = practitioner_resource(&author);
+    practitioner["id"] = json!(uuid_of(&author_url));
+    let mut entries = vec![
+        entry(&patient_url, patient),
+        entry(&author_url, practitioner),
+    ];
+
+    // Allergies (mandatory)
+    let allergies = allergies::list(false)?;
+    let allergy_resources: Vec<Value> = if allergies.is_empty() {
+        vec![no_information(
+            "AllergyIntolerance",
+            "no-allergy-info",
+            "No information about allergies",
+            &patient_ref,
+        )]
+    } else {
+        allergies
+            .iter()
+            .map(|allergy| allergy_resource(allergy, &patient_ref))
+            .collect()
+    };
+    let allergy_section = section(
+        "Allergies and Intolerances",
+        "48765-2",
+        "Allergies and adverse reactions Document",
+        allergies_narrative(&allergies),
+        add_entries(&mut entries, allergy_resources),
+    );
+
+    // Medications and problems (mandatory). GitEHR has no typed state for
+    // either yet, so both always say so explicitly.
+    let medication_section = section(
+        "Medication Summary",
+        "10160-0",
+        "History of Medication use Narrative",
+        narrative_paragraph("No information about medications."),
+        add_entries(
+            &mut entries,
+            vec![no_information(
+                "MedicationStatement",
+                "no-medication-info",
+                "No information about medications",
+                &patient_ref,
+            )],
+        ),
+    );
+    let problem_section = section(
+        "Problem List",
+        "11450-4",
+        "Problem list - Reported",
+        narrative_paragraph("No information about problems."),
+        add_entries(
+            &mut entries,
+            vec![no_information(
+                "Condition",
+                "no-problem-info",
+                "No information about problems",
+                &patient_ref,
+            )],
+        ),
+    );
+
+    let mut sections = vec![allergy_section, medication_section, problem_section];
+
+    // Immunisations (recommended): included only when something is recorded.
+    let vaccinations = vaccinations::list(false)?;
+    if !vaccinations.is_empty() {
+        let resources = vaccinations
+            .iter()
+            .map(|vaccination| immunization_resource(vaccination, &patient_ref))
+            .collect();
+        sections.push(section(
+            "Immunizations",
+            "11369-6",
+            "History of Immunization Narrative",
+            immunizations_narrative(&vaccinations),
+            add_entries(&mut entries, resources),
+        ));
+    }
+
+    let composition_url = new_full_url();
+    let composition = json!({
+        "resourceType": "Composition",
+        "id": uuid_of(&composition_url),
+        "status": "final",
+        "type": {
+            "coding": [{ "system": LOINC, "code": "60591-5", "display": "Patient summary Document" }]
+        },
+        "subject": patient_ref,
+        "date": now,
+        "author": [{ "reference": author_url, "display": author.name }],
+        "title": "International Patient Summary",
+        "attester": [{ "mode": "legal", "time": now, "party": { "reference": author_url } }],
+        "section": sections,
+    });
+    entries.insert(0, entry(&composition_url, composition));
+
+    Ok(json!({
+        "resourceType": "Bundle",
+        "id": Uuid::new_v4().to_string(),
+        "identifier": { "system": "urn:ietf:rfc:3986", "value": new_full_url() },
+        "type": "document",
+        "timestamp": now,
+        "entry": entries,
+    }))
+}
+
+fn active_author() -> Result<Contributor> {
+    let Some(id) = contributor::get_current_contributor() else {
+        bail!(
+            "An IPS is signed by its author, but no contributor is active. Run `gitehr user activate <id>` first."
+        );
+    };
+    contributor::get_contributor(&id)
+        .ok_or_else(|| anyhow::anyhow!("Active contributor '{}' is not in contributors.json", id))
+}
+
+pub(super) fn new_full_url() -> String {
+    format!("urn:uuid:{}", Uuid::new_v4())
+}
+
+pub(super) fn uuid_of(full_url: &str) -> &str {
+    full_url.trim_start_matches("urn:uuid:")
+}
+
+pub(super) fn entry(full_url: &str, resource: Value) -> Value {
+    json!({ "fullUrl": full_url, "resource": resource })
+}
+
+/// Append resources to the Bundle, returning section entry references to them.
+fn add_entries(entries: &mut Vec<Value>, resources: Vec<Value>) -> Vec<Value> {
+    resources
+        .into_iter()
+        .map(|mut resource| {
+            let full_url = new_full_url();
+            resource["id"] = json!(uuid_of(&full_url));
+            entries.push(entry(&full_url, resource));
+            json!({ "reference": full_url })
+        })
+        .collect()
+}
+
+fn section(title: &str, code: &str, display: &str, div: String, refs: Vec<Value>) -> Value {
+    json!({
+        "title": title,
+        "code": { "coding": [{ "system": LOINC, "code": code, "display": display }] },
+        "text": { "status": "generated", "div": div },
+        "entry": refs,
+    })
+}
+
+fn no_information(resource_type: &str, code: &str, display: &str, patient: &Value) -> Value {
+    let concept =
+        json!({ "coding": [{ "system": ABSENT_UNKNOWN, "code": code, "display": display }] });
+    match resource_type {
+        "AllergyIntolerance" => json!({
+            "resourceType": resource_type,
+            "code": concept,
+            "patient": patient,
+        }),
+        "MedicationStatement" => json!({
+            "resourceType": resource_type,
+            "status": "unknown",
+            "medicationCodeableConcept": concept,
+            "subject": patient,
+        }),
+        _ => json!({
+            "resourceType": resource_type,
+            "code": concept,
+            "subject": patient,
+        }),
+    }
+}
+
+pub(super) fn patient_resource(demographics: &Demographics) -> Value {
+    let mut identifiers = Vec::new();
+    if let Some(id) = subject_id() {
+        identifiers.push(json!({ "system": SUBJECT_ID_SYSTEM, "value": id }));
+    }
+    if let Some(nhs) = &demographics.nhs_number {
+        identifiers.push(json!({ "system": NHS_NUMBER_SYSTEM, "value": nhs }));
+    }
+    for identifier in &demographics.identifiers {
+        if identifier.id_type.eq_ignore_ascii_case("NHS") {
+            continue;
+        }
+        identifiers
+            .push(json!({ "type": { "text": identifier.id_type }, "value": identifier.value }));
+    }
+
+    let mut names = Vec::new();
+    if let Some(full_name) = &demographics.full_name {
+        let mut name = json!({ "use": "official", "text": full_name });
+        if let Some(title) = &demographics.title {
+            name["prefix"] = json!([title]);
+        }
+        names.push(name);
+    }
+    if let Some(preferred) = &demographics.preferred_name {
+        names.push(json!({ "use": "usual", "text": preferred }));
+    }
+
+    let mut patient =
+        json!({ "resourceType": "Patient", "identifier": identifiers, "name": names });
+    if let Some(dob) = &demographics.date_of_birth {
+        patient["birthDate"] = json!(dob);
+    }
+    if let Some(address) = &demographics.address {
+        patient["address"] = json!([{ "text": address }]);
+    }
+    patient
+}
+
+pub(super) fn practitioner_resource(author: &Contributor) -> Value {
+    let mut practitioner = json!({
+        "resourceType": "Practitioner",
+        "identifier": [{ "system": CONTRIBUTOR_ID_SYSTEM, "value": author.id }],
+        "name": [{ "text": author.name }],
+    });
+    if let Some(email) = &author.email {
+        practitioner["telecom"] = json!([{ "system": "email", "value": email }]);
+    }
+    practitioner
+}
+
+fn allergy_resource(allergy: &Allergy, patient: &Value) -> Value {
+    let mut resource = json!({
+        "resourceType": "AllergyIntolerance",
+        "clinicalStatus": {
+            "coding": [{
+                "system": "http://terminology.hl7.org/CodeSystem/allergyintolerance-clinical",
+                "code": "active"
+            }]
+        },
+        "code": { "text": allergy.agent },
+        "patient": patient,
+        "recordedDate": allergy.recorded_at,
+        "reaction": [{
+            "manifestation": [{ "text": allergy.reaction }],
+            "severity": match allergy.severity {
+                AllergySeverity::Low => "mild",
+                AllergySeverity::Moderate => "moderate",
+                AllergySeverity::High | AllergySeverity::Critical => "severe",
+            }
+        }],
+    });
+    if allergy.severity == AllergySeverity::Critical {
+        resource["criticality"] = json!("high");
+    }
+    if let Some(note) = &allergy.note {
+        resource["note"] = json!([{ "text": note }]);
+    }
+    resource
+}
+
+fn immunization_resource(vaccination: &Vaccination, patient: &Value) -> Value {
+    let mut resource = json!({
+        "resourceType": "Immunization",
+        "status": "completed",
+        "vaccineCode": { "text": vaccination.vaccine },
+        "patient": patient,
+        "occurrenceDateTime": vaccination.date,
+    });
+    if let Some(batch) = &vaccination.batch_number {
+        resource["lotNumber"] = json!(batch);
+    }
+    if let Some(site) = &vaccination.anatomical_site {
+        resource["site"] = json!({ "text": site });
+    }
+    if let Some(route) = &vaccination.route {
+        resource["route"] = json!({ "text": route });
+    }
+    if let Some(manufacturer) = &vaccination.manufacturer {
+        resource["manufacturer"] = json!({ "display": manufacturer });
+    }
+    if vaccination.dose_sequence.is_some() || !vaccination.target_disease.is_empty() {
+        let mut protocol = json!({});
+        if let Some(dose) = vaccination.dose_sequence {
+            protocol["doseNumberPositiveInt"] = json!(dose);
+        }
+        if !vaccination.target_disease.is_empty() {
+            protocol["targetDisease"] = vaccination
+                .target_disease
+                .iter()
+                .map(|disease| json!({ "text": disease }))
+                .collect();
+        }
+        resource["protocolApplied"] = json!([protocol]);
+    }
+    resource
+}
+
+fn narrative_paragraph(text: &str) -> String {
+    format!(
+        r#"<div xmlns="{}"><p>{}</p></div>"#,
+        XHTML_NS,
+        html_escape(text)
+    )
+}
+
+fn narrative_table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let head: String = headers
+        .iter()
+        .map(|h| format!("<th>{}</th>", html_escape(h)))
+        .collect();
+    let body: String = rows
+        .iter()
+        .map(|row| {
+            let cells: String = row
+                .iter()
+                .map(|cell| format!("<td>{}</td>", html_escape(cell)))
+                .collect();
+            format!("<tr>{}</tr>", cells)
+        })
+        .collect();
+    format!(
+        r#"<div xmlns="{}"><table><thead><tr>{}</tr></thead><tbody>{}</tbody></table></div>"#,
+        XHTML_NS, head, body
+    )
+}
+
+fn allergies_narrative(allergies: &[Allergy]) -> String {
+    if allergies.is_empty() {
+        return narrative_paragraph("No information about allergies.");
+    }
+    narrative_table(
+        &["Substance", "Reaction", "Severity", "Recorded"],
+        allergies
+            .iter()
+            .map(|allergy| {
+                vec![
+                    allergy.agent.clone(),
+                    allergy.reaction.clone(),
+                    allergy.severity.to_string(),
+                    allergy.recorded_at.chars().take(10).collect(),
+                ]
+            })
+            .collect(),
+    )
+}
+
+fn immunizations_narrative(vaccinations: &[Vaccination]) -> String {
+    narrative_table(
+        &["Vaccine", "Date", "Dose", "Batch"],
+        vaccinations
+            .iter()
+            .map(|vaccination| {
+                vec![
+                    vaccination.vaccine.clone(),
+                    vaccination.date.clone(),
+                    vaccination
+                        .dose_sequence
+                        .map(|dose| dose.to_string())
+                        .unwrap_or_default(),
+                    vaccination.batch_number.clone().unwrap_or_default(),
+                ]
+            })
+            .collect(),
+    )
+}
+
+/// Render an IPS Bundle as a standalone, offline HTML page: a patient header
+/// followed by each Composition section's narrative.
+pub fn render_html(bundle: &Value) -> String {
+    let resources: Vec<&Value> = bundle["entry"]
+        .as_array()
+        .map(|entries| entries.iter().map(|e| &e["resource"]).collect())
+        .unwrap_or_default();
+    let find = |resource_type: &str| {
+        resources
+            .iter()
+            .find(|r| r["resourceType"] == resource_type)
+            .copied()
+    };
+    let composition = find("Composition").cloned().unwrap_or(Value::Null);
+    let patient = find("Patient").cloned().unwrap_or(Value::Null);
+
+    let name = patient["name"][0]["text"]
+        .as_str()
+        .unwrap_or("Name not recorded");
+    let mut details = Vec::new();
+    if let Some(dob) = patient["birthDate"].as_str() {
+        details.push(format!("Date of birth: {}", dob));
+    }
+    for identifier in patient["identifier"].as_array().into_iter().flatten() {
+        let label = if identifier["system"] == NHS_NUMBER_SYSTEM {
+            "NHS number"
+        } else if identifier["system"] == SUBJECT_ID_SYSTEM {
+            "GitEHR id"
+        } else {
+            identifier["type"]["text"].as_str().unwrap_or("Identifier")
+        };
+        details.push(format!(
+            "{}: {}",
+            label,
+            identifier["value"].as_str().unwrap_or("")
+        ));
+    }
+
+    let mut sections = String::new();
+    for section in composition["section"].as_array().into_iter().flatten() {
+        sections.push_str(&format!(
+            "<section>\n<h2>{}</h2>\n{}\n</section>\n",
+            html_escape(section["title"].as_str().unwrap_or("")),
+            section["text"]["div"].as_str().unwrap_or("")
+        ));
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>International Patient Summary - {name}</title>
+<style>
+body {{ font-family: sans-serif; max-width: 50em; margin: 2em auto; color: #222; }}
+header {{ border-bottom: 2px solid #005eb8; margin-bottom: 1em; }}
+table {{ border-collapse: collapse; width: 100%; }}
+th, td {{ border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }}
+footer {{ margin-top: 2em; font-size: 0.85em; color: #555; }}
+</style>
+</head>
+<body>
+<header>
+<h1>International Patient Summary</h1>
+<p><strong>{name}</strong></p>
+<p>{details}</p>
+</header>
+{sections}<footer>Author: {author} &middot; Generated: {date}</footer>
+</body>
+</html>
+"#,
+        name = html_escape(name),
+        details = details
+            .iter()
+            .map(|d| html_escape(d))
+            .collect::<Vec<_>>()
+            .join(" &middot; "),
+        sections = sections,
+        author = html_escape(composition["author"][0]["display"].as_str().unwrap_or("")),
+        date = html_escape(composition["date"].as_str().unwrap_or("")),
+    )
+}
@@ -0,0 +1,162 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::{Context, Result};
+use clap::Subcommand;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::journal::clinical_time::TimeBasis;
+use super::typed_state;
+
+pub mod fhir_journal;
+pub mod html;
+pub mod ips;
+pub mod openehr;
+pub mod pdf;
+
+#[derive(Subcommand)]
+pub enum ExportCommands {
+    #[command(about = "Build a FHIR International Patient Summary (IPS) document Bundle")]
+    Ips {
+        #[arg(
+            short,
+            long,
+            value_name = "PATH",
+            help = "Write the Bundle JSON here (default: stdout)"
+        )]
+        output: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "PATH",
+            help = "Also render the summary to a standalone HTML file"
+        )]
+        html: Option<PathBuf>,
+    },
+    #[command(about = "Build a FHIR collection Bundle of the journal's encounters and notes")]
+    FhirJournal {
+        #[arg(
+            short,
+            long,
+            value_name = "PATH",
+            help = "Write the Bundle JSON here (default: stdout)"
+        )]
+        output: Option<PathBuf>,
+    },
+    #[command(
+        about = "Write a static, offline HTML copy of the record",
+        arg_required_else_help = true
+    )]
+    Html {
+        #[arg(help = "Directory to write the site into (must be empty or new)")]
+        dir: PathBuf,
+        #[arg(
+            long,
+            value_enum,
+            default_value_t,
+            help = "Order the journal by when entries were recorded or their clinical time"
+        )]
+        time: TimeBasis,
+    },
+    #[command(
+        about = "Render a printable PDF: patient summary, full record or a single entry",
+        arg_required_else_help = true
+    )]
+    Pdf {
+        #[arg(value_enum, help = "Which template to render")]
+        template: pdf::PdfTemplate,
+        #[arg(help = "Entry ref for single-entry (LATEST, LATEST~N, or a filename)")]
+        entry: Option<String>,
+        #[arg(short, long, value_name = "PATH", help = "Where to write the PDF")]
+        output: PathBuf,
+        #[arg(
+            long,
+            value_enum,
+            default_value_t,
+            help = "Order the full record's journal by when entries were recorded or their clinical time"
+        )]
+        time: TimeBasis,
+    },
+    #[command(about = "Build an openEHR EHR extract in canonical JSON")]
+    Openehr {
+        #[arg(
+            short,
+            long,
+            value_name = "PATH",
+            help = "Write the extract JSON here (default: stdout)"
+        )]
+        output: Option<PathBuf>,
+    },
+}
+
+pub fn run(command: ExportCommands) -> Result<()> {
+    typed_state::ensure_gitehr_repository()?;
+    match command {
+        ExportCommands::Ips { output, html } => ips::run(output.as_deref(), html.as_deref()),
+        ExportCommands::FhirJournal { output } => fhir_journal::run(output.as_deref()),
+        ExportCommands::Html { dir, time } => html::run(&dir, time),
+        ExportCommands::Pdf {
+            template,
+            entry,
+            output,
+            time,
+        } => pdf::run(template, entry.as_deref(), &output, time),
+        ExportCommands::Openehr { output } => openehr::run(output.as_deref()),
+    }
+}
+
+// ── Shared helper functions ───────────────────────────────────────────────────
+
+/// The subject's canonical id from `.gitehr/ID`, if the repo has one.
+pub fn subject_id() -> Option<String> {
+    fs::read_to_string(".gitehr/ID")
+        .ok()
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
+/// Write `content` to `output`, or to stdout when no path is given.
+pub fn write_output(output: Option<&Path>, content: &str) -> Result<()> {
+    match output {
+        Some(path) => {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)?;
+            }
+            fs::write(path, content)
+                .with_context(|| format!("Failed to write {}", path.display()))?;
+            eprintln!("Wrote {}", path.display());
+        }
+        None => println!("{}", content),
+    }
+    Ok(())
+}
+
+/// Escape text for inclusion in HTML or XHTML element content and attributes.
+pub fn html_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_html_escape() {
+        assert_eq!(
+            html_escape(r#"<b>"Tom" & 'Jerry'</b>"#),
+            "&lt;b&gt;&quot;Tom&quot; &amp; &#39;Jerry&#39;&lt;/b&gt;"
+        );
+        assert_eq!(html_escape("plain text"), "plain text");
+    }
+}
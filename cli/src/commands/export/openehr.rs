@@ -0,0 +1,613 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! openEHR export: an EHR extract in canonical JSON.
+//!
+//! Typed state becomes persistent compositions - the international allergy
+//! list (`adverse_reaction_risk` evaluations) and an immunisation summary
+//! (`medication` actions) - and every journal entry becomes an encounter
+//! composition holding a `clinical_synopsis`, the generic clinical note, with
+//! the entry's encounter type, tags, SNOMED CT codes and location in its
+//! context.
+//! openEHR keeps demographics outside the EHR, so they travel as a `PERSON`
+//! alongside the compositions.
+//!
+//! Each composition is wrapped in a versioned object whose `ORIGINAL_VERSION`
+//! is filled from Git: the version count is the number of commits that touched
+//! the source file, the contribution is the latest commit, and the committer is
+//! the contributor who authored that commit's journal entry.
+//!
+//! This is an export, not native openEHR storage (see `spec/openehr.md`):
+//! archetype node ids cover the main nodes of each CKM archetype and nothing
+//! is validated against operational templates.
+
+use anyhow::Result;
+use chrono::Utc;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::path::Path;
+use uuid::Uuid;
+
+use super::{subject_id, write_output};
+use crate::commands::allergies::{self, AllergySeverity};
+use crate::commands::contributor;
+use crate::commands::demographics::{self, Demographics};
+use crate::commands::git::{self, CommitInfo};
+use crate::commands::journal::{self, ParsedEntry};
+use crate::commands::vaccinations;
+use crate::utils::sha256_hex;
+
+const SYSTEM_ID: &str = "gitehr";
+const RM_VERSION: &str = "1.0.4";
+
+pub fn run(output: Option<&Path>) -> Result<()> {
+    let extract = build()?;
+    write_output(output, &serde_json::to_string_pretty(&extract)?)
+}
+
+/// Build the EHR extract from typed state, the journal and Git history.
+pub fn build() -> Result<Value> {
+    let subject = subject_id();
+    let ehr_id = stable_uuid(&format!("ehr:{}", subject.as_deref().unwrap_or("")));
+    let entries = journal::parsed_entries()?;
+    let history = History::load(&entries)?;
+
+    let mut compositions = vec![
+        versioned(
+            &ehr_id,
+            &stable_uuid(&format!("{}:allergies", ehr_id)),
+            &history,
+            "state/allergies.md",
+            allergy_list_composition(&history.composer_for("state/allergies.md"))?,
+        ),
+        versioned(
+            &ehr_id,
+            &stable_uuid(&format!("{}:vaccinations", ehr_id)),
+            &history,
+            "state/vaccinations.md",
+            immunisation_summary_composition(&history.composer_for("state/vaccinations.md"))?,
+        ),
+    ];
+    for entry in &entries {
+        let path = format!("journal/{}", entry.filename);
+        compositions.push(versioned(
+            &ehr_id,
+            &entry_uuid(entry),
+            &history,
+            &path,
+            clinical_note_composition(entry, &history),
+        ));
+    }
+
+    let demographics = demographics::load()?;
+    let person_uid = stable_uuid(&format!("{}:person", ehr_id));
+    let mut party = versioned(
+        &ehr_id,
+        &person_uid,
+        &history,
+        "state/demographics.md",
+        person(&demographics, &person_uid, subject.as_deref()),
+    );
+    party["_type"] = json!("X_VERSIONED_PARTY");
+
+    let mut entity_identifier = json!({
+        "_type": "EXTRACT_ENTITY_IDENTIFIER",
+        "entity_id": hier_object_id(&ehr_id),
+    });
+    if let Some(id) = &subject {
+        entity_identifier["subject_id"] = json!(id);
+    }
+
+    Ok(json!({
+        "_type": "EXTRACT",
+        "name": dv_text("GitEHR EHR extract"),
+        "archetype_node_id": "openEHR-EXTRACT-EXTRACT.generic.v1",
+        "time_created": dv_date_time(&Utc::now().to_rfc3339()),
+        "system_id": hier_object_id(SYSTEM_ID),
+        "sequence_nr": 1,
+        "chapters": [{
+            "_type": "EXTRACT_CHAPTER",
+            "name": dv_text("EHR"),
+            "archetype_node_id": "openEHR-EXTRACT-EXTRACT_CHAPTER.generic.v1",
+            "entity_identifier": entity_identifier,
+            "content": {
+                "_type": "OPENEHR_CONTENT",
+                "name": dv_text("EHR content"),
+                "archetype_node_id": "openEHR-EXTRACT-OPENEHR_CONTENT.generic.v1",
+                "compositions": compositions,
+                "demographics": party,
+            }
+        }]
+    }))
+}
+
+// ── Git-derived version and audit details ─────────────────────────────────────
+
+struct History {
+    commits: Vec<CommitInfo>,
+    /// Journal entry path -> author contributor id.
+    journal_authors: HashMap<String, String>,
+    names: HashMap<String, String>,
+}
+
+impl History {
+    fn load(entries: &[ParsedEntry]) -> Result<Self> {
+        Ok(History {
+            commits: git::log_with_files(&["journal", "state"])?,
+            journal_authors: entries
+                .iter()
+                .filter_map(|entry| {
+                    let author = entry.metadata.author.clone()?;
+                    Some((format!("journal/{}", entry.filename), author))
+                })
+                .collect(),
+            names: contributor::contributor_names(),
+        })
+    }
+
+    /// Commits that touched `path`, newest first.
+    fn commits_for(&self, path: &str) -> Vec<&CommitInfo> {
+        self.commits
+            .iter()
+            .filter(|commit| commit.files.iter().any(|f| f == path))
+            .collect()
+    }
+
+    /// Every state change is committed with a journal entry, so the author of
+    /// that entry is the contributor behind the commit.
+    fn contributor_for(&self, commit: &CommitInfo) -> Option<String> {
+        commit
+            .files
+            .iter()
+            .find_map(|file| self.journal_authors.get(file).cloned())
+    }
+
+    fn party(&self, contributor_id: Option<&str>, fallback_name: &str) -> Value {
+        match contributor_id {
+            Some(id) => json!({
+                "_type": "PARTY_IDENTIFIED",
+                "name": self.names.get(id).cloned().unwrap_or_else(|| id.to_string()),
+                "external_ref": {
+                    "_type": "PARTY_REF",
+                    "id": { "_type": "GENERIC_ID", "value": id, "scheme": "gitehr-contributor" },
+                    "namespace": SYSTEM_ID,
+                    "type": "PERSON"
+                }
+            }),
+            None => json!({ "_type": "PARTY_IDENTIFIED", "name": fallback_name }),
+        }
+    }
+
+    fn committer(&self, commit: &CommitInfo) -> Value {
+        self.party(self.contributor_for(commit).as_deref(), &commit.author_name)
+    }
+
+    /// The composer of a state composition is whoever last changed it.
+    fn composer_for(&self, path: &str) -> Value {
+        match self.commits_for(path).first() {
+            Some(commit) => self.committer(commit),
+            None => self.party(None, "Unknown"),
+        }
+    }
+}
+
+fn versioned(ehr_id: &str, object_uid: &str, history: &History, path: &str, data: Value) -> Value {
+    let commits = history.commits_for(path);
+    let count = commits.len().max(1);
+    let latest = commits.first();
+    let created = commits.last();
+
+    let mut version = json!({
+        "_type": "ORIGINAL_VERSION",
+        "uid": { "_type": "OBJECT_VERSION_ID", "value": format!("{}::{}::{}", object_uid, SYSTEM_ID, count) },
+        "lifecycle_state": dv_coded("openehr", "532", "complete"),
+        "data": data,
+    });
+    if count > 1 {
+        version["preceding_version_uid"] = json!({
+            "_type": "OBJECT_VERSION_ID",
+            "value": format!("{}::{}::{}", object_uid, SYSTEM_ID, count - 1),
+        });
+    }
+    match latest {
+        Some(commit) => {
+            version["contribution"] = json!({
+                "_type": "OBJECT_REF",
+                "id": hier_object_id(&commit.hash),
+                "namespace": SYSTEM_ID,
+                "type": "CONTRIBUTION",
+            });
+            version["commit_audit"] = json!({
+                "_type": "AUDIT_DETAILS",
+                "system_id": SYSTEM_ID,
+                "time_committed": dv_date_time(&commit.time),
+                "change_type": if count == 1 {
+                    dv_coded("openehr", "249", "creation")
+                } else {
+                    dv_coded("openehr", "251", "modification")
+                },
+                "committer": history.committer(commit),
+                "description": dv_text(&commit.subject),
+            });
+        }
+        None => {
+            // Not yet committed (or never written): no contribution to cite.
+            version["commit_audit"] = json!({
+                "_type": "AUDIT_DETAILS",
+                "system_id": SYSTEM_ID,
+                "time_committed": dv_date_time(&Utc::now().to_rfc3339()),
+                "change_type": dv_coded("openehr", "249", "creation"),
+                "committer": history.party(None, "Unknown"),
+                "description": dv_text("Not committed"),
+            });
+        }
+    }
+
+    json!({
+        "_type": "X_VERSIONED_COMPOSITION",
+        "uid": hier_object_id(object_uid),
+        "owner_id": {
+            "_type": "OBJECT_REF",
+            "id": hier_object_id(ehr_id),
+            "namespace": "local",
+            "type": "EHR",
+        },
+        "time_created": dv_date_time(
+            &created
+                .map(|commit| commit.time.clone())
+                .unwrap_or_else(|| Utc::now().to_rfc3339())
+        ),
+        "total_version_count": count,
+        "extract_version_count": 1,
+        "versions": [version],
+    })
+}
+
+// ── Compositions ──────────────────────────────────────────────────────────────
+
+fn composition(
+    archetype: &str,
+    name: &str,
+    event: bool,
+    composer: Value,
+    content: Vec<Value>,
+) -> Value {
+    json!({
+        "_type": "COMPOSITION",
+        "name": dv_text(name),
+        "archetype_node_id": archetype,
+        "archetype_details": archetyped(archetype),
+        "language": code_phrase("ISO_639-1", "en"),
+        "territory": code_phrase("ISO_3166-1", "GB"),
+        "category": if event {
+            dv_coded("openehr", "433", "event")
+        } else {
+            dv_coded("openehr", "431", "persistent")
+        },
+        "composer": composer,
+        "content": content,
+    })
+}
+
+fn allergy_list_composition(composer: &Value) -> Result<Value> {
+    let content = allergies::list(false)?
+        .iter()
+        .map(|allergy| {
+            let (code, value) = match allergy.severity {
+                AllergySeverity::High | AllergySeverity::Critical => ("at0103", "High"),
+                AllergySeverity::Low | AllergySeverity::Moderate => ("at0102", "Low"),
+            };
+            let mut items = vec![
+                element("at0002", "Substance", dv_text(&allergy.agent)),
+                element("at0101", "Criticality", dv_coded("local", code, value)),
+                cluster(
+                    "at0009",
+                    "Reaction event",
+                    vec![element(
+                        "at0011",
+                        "Manifestation",
+                        dv_text(&allergy.reaction),
+                    )],
+                ),
+            ];
+            let mut comment = format!("GitEHR severity: {}", allergy.severity);
+            if let Some(note) = &allergy.note {
+                comment.push_str(&format!(". {}", note));
+            }
+            items.push(element("at0006", "Comment", dv_text(&comment)));
+            entry(
+                "EVALUATION",
+                "openEHR-EHR-EVALUATION.adverse_reaction_risk.v1",
+                "Adverse reaction risk",
+                "data",
+                item_tree(items),
+            )
+        })
+        .collect();
+    Ok(composition(
+        "openEHR-EHR-COMPOSITION.adverse_reaction_list.v1",
+        "Adverse reaction list",
+        false,
+        composer.clone(),
+        content,
+    ))
+}
+
+fn immunisation_summary_composition(composer: &Value) -> Result<Value> {
+    let content = vaccinations::list(false)?
+        .iter()
+        .map(|vaccination| {
+            let mut details = Vec::new();
+            if let Some(dose) = vaccination.dose_sequence {
+                details.push(format!("Dose {}", dose));
+            }
+            if let Some(batch) = &vaccination.batch_number {
+                details.push(format!("Batch {}", batch));
+            }
+            if let Some(route) = &vaccination.route {
+                details.push(format!("Route {}", route));
+            }
+            if let Some(site) = &vaccination.anatomical_site {
+                details.push(format!("Site {}", site));
+            }
+            let mut items = vec![element(
+                "at0020",
+                "Medication item",
+                dv_text(&vaccination.vaccine),
+            )];
+            if !details.is_empty() {
+                items.push(element("at0024", "Comment", dv_text(&details.join("; "))));
+            }
+            let mut action = entry(
+                "ACTION",
+                "openEHR-EHR-ACTION.medication.v1",
+                "Immunisation",
+                "description",
+                item_tree(items),
+            );
+            action["time"] = dv_date_time(&vaccination.date);
+            action["ism_transition"] = json!({
+                "_type": "ISM_TRANSITION",
+                "current_state": dv_coded("openehr", "532", "completed"),
+                "careflow_step": dv_coded("local", "at0006", "Medication administered"),
+            });
+            action
+        })
+        .collect();
+    Ok(composition(
+        "openEHR-EHR-COMPOSITION.health_summary.v1",
+        "Immunisation summary",
+        false,
+        composer.clone(),
+        content,
+    ))
+}
+
+fn clinical_note_composition(entry_data: &ParsedEntry, history: &History) -> Value {
+    let composer = history.party(entry_data.metadata.author.as_deref(), "Unknown");
+    let note = entry(
+        "EVALUATION",
+        "openEHR-EHR-EVALUATION.clinical_synopsis.v1",
+        "Clinical synopsis",
+        "data",
+        item_tree(vec![element(
+            "at0002",
+            "Synopsis",
+            dv_text(entry_data.content.trim()),
+        )]),
+    );
+    let mut composition = composition(
+        "openEHR-EHR-COMPOSITION.encounter.v1",
+        "Clinical note",
+        true,
+        composer,
+        vec![note],
+    );
+    composition["context"] = json!({
+        "_type": "EVENT_CONTEXT",
+        // When the care happened; openEHR date-times may be partial.
+        "start_time": dv_date_time(&match &entry_data.metadata.clinical_time {
+            Some(time) => time.to_string(),
+            None => entry_data.metadata.timestamp.to_rfc3339(),
+        }),
+        "setting": dv_coded("openehr", "238", "other care"),
+    });
+    let meta = &entry_data.metadata;
+    if let Some(location) = &meta.location {
+        composition["context"]["health_care_facility"] = json!({
+            "_type": "PARTY_IDENTIFIED",
+            "name": location,
+        });
+    }
+    // The encounter archetype leaves other_context open; these node ids are
+    // GitEHR's own.
+    let mut other = Vec::new();
+    if let Some(encounter_type) = meta.encounter_type {
+        other.push(element(
+            "at0002",
+            "Encounter type",
+            dv_text(&encounter_type.to_string()),
+        ));
+    }
+    for tag in &meta.tags {
+        other.push(element("at0003", "Tag", dv_text(tag)));
+    }
+    for code in &meta.codes {
+        other.push(element(
+            "at0004",
+            "Concept",
+            dv_coded(
+                "SNOMED-CT",
+                &code.code,
+                code.display.as_deref().unwrap_or(&code.code),
+            ),
+        ));
+    }
+    if !other.is_empty() {
+        composition["context"]["other_context"] = item_tree(other);
+    }
+    composition
+}
+
+fn person(demographics: &Demographics, uid: &str, subject: Option<&str>) -> Value {
+    let name = demographics
+        .full_name
+        .clone()
+        .unwrap_or_else(|| "Name not recorded".to_string());
+    let mut name_items = vec![element("at0002", "Unstructured name", dv_text(&name))];
+    if let Some(title) = &demographics.title {
+        name_items.push(element("at0003", "Title", dv_text(title)));
+    }
+    let mut identities = vec![json!({
+        "_type": "PARTY_IDENTITY",
+        "name": dv_text("Legal name"),
+        "archetype_node_id": "openEHR-DEMOGRAPHIC-PARTY_IDENTITY.person_name.v1",
+        "details": item_tree(name_items),
+    })];
+    if let Some(preferred) = &demographics.preferred_name {
+        identities.push(json!({
+            "_type": "PARTY_IDENTITY",
+            "name": dv_text("Preferred name"),
+            "archetype_node_id": "openEHR-DEMOGRAPHIC-PARTY_IDENTITY.person_name.v1",
+            "details": item_tree(vec![element("at0002", "Unstructured name", dv_text(preferred))]),
+        }));
+    }
+
+    let mut details = Vec::new();
+    if let Some(dob) = &demographics.date_of_birth {
+        details.push(element(
+            "at0010",
+            "Date of birth",
+            json!({ "_type": "DV_DATE", "value": dob }),
+        ));
+    }
+    if let Some(address) = &demographics.address {
+        details.push(element("at0020", "Address", dv_text(address)));
+    }
+    let mut identifiers: Vec<(String, String)> = Vec::new();
+    if let Some(id) = subject {
+        identifiers.push(("GitEHR".to_string(), id.to_string()));
+    }
+    if let Some(nhs) = &demographics.nhs_number {
+        identifiers.push(("NHS number".to_string(), nhs.clone()));
+    }
+    for identifier in &demographics.identifiers {
+        if identifier.id_type.eq_ignore_ascii_case("NHS") {
+            continue;
+        }
+        identifiers.push((identifier.id_type.clone(), identifier.value.clone()));
+    }
+    for (issuer, id) in identifiers {
+        details.push(element(
+            "at0030",
+            "Identifier",
+            json!({
+                "_type": "DV_IDENTIFIER",
+                "issuer": issuer,
+                "assigner": issuer,
+                "id": id,
+                "type": "person",
+            }),
+        ));
+    }
+
+    json!({
+        "_type": "PERSON",
+        "name": dv_text(&name),
+        "archetype_node_id": "openEHR-DEMOGRAPHIC-PERSON.person.v2",
+        "archetype_details": archetyped("openEHR-DEMOGRAPHIC-PERSON.person.v2"),
+        "uid": hier_object_id(uid),
+        "identities": identities,
+        "details": item_tree(details),
+    })
+}
+
+// ── Canonical JSON building blocks ────────────────────────────────────────────
+
+fn entry(rm_type: &str, archetype: &str, name: &str, slot: &str, tree: Value) -> Value {
+    let mut value = json!({
+        "_type": rm_type,
+        "name": dv_text(name),
+        "archetype_node_id": archetype,
+        "archetype_details": archetyped(archetype),
+        "language": code_phrase("ISO_639-1", "en"),
+        "encoding": code_phrase("IANA_character-sets", "UTF-8"),
+        "subject": { "_type": "PARTY_SELF" },
+    });
+    value[slot] = tree;
+    value
+}
+
+fn archetyped(archetype: &str) -> Value {
+    json!({
+        "_type": "ARCHETYPED",
+        "archetype_id": { "_type": "ARCHETYPE_ID", "value": archetype },
+        "rm_version": RM_VERSION,
+    })
+}
+
+fn item_tree(items: Vec<Value>) -> Value {
+    json!({
+        "_type": "ITEM_TREE",
+        "name": dv_text("Tree"),
+        "archetype_node_id": "at0001",
+        "items": items,
+    })
+}
+
+fn cluster(node: &str, name: &str, items: Vec<Value>) -> Value {
+    json!({ "_type": "CLUSTER", "name": dv_text(name), "archetype_node_id": node, "items": items })
+}
+
+fn element(node: &str, name: &str, value: Value) -> Value {
+    json!({ "_type": "ELEMENT", "name": dv_text(name), "archetype_node_id": node, "value": value })
+}
+
+fn dv_text(value: &str) -> Value {
+    json!({ "_type": "DV_TEXT", "value": value })
+}
+
+fn dv_coded(terminology: &str, code: &str, value: &str) -> Value {
+    json!({
+        "_type": "DV_CODED_TEXT",
+        "value": value,
+        "defining_code": code_phrase(terminology, code),
+    })
+}
+
+fn code_phrase(terminology: &str, code: &str) -> Value {
+    json!({
+        "_type": "CODE_PHRASE",
+        "terminology_id": { "_type": "TERMINOLOGY_ID", "value": terminology },
+        "code_string": code,
+    })
+}
+
+fn dv_date_time(value: &str) -> Value {
+    json!({ "_type": "DV_DATE_TIME", "value": value })
+}
+
+fn hier_object_id(value: &str) -> Value {
+    json!({ "_type": "HIER_OBJECT_ID", "value": value })
+}
+
+/// A UUID derived from `key`, so repeated exports keep the same identifiers.
+fn stable_uuid(key: &str) -> String {
+    let hash = sha256_hex(key.as_bytes());
+    let mut bytes = [0u8; 16];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hash[i * 2..i * 2 + 2], 16).unwrap_or(0);
+    }
+    uuid::Builder::from_random_bytes(bytes)
+        .into_uuid()
+        .to_string()
+}
+
+/// Journal filenames already end in a UUID; reuse it as the object id.
+fn entry_uuid(entry: &ParsedEntry) -> String {
+    let stem = entry.filename.trim_end_matches(".md");
+    stem.split_once('-')
+        .and_then(|(_, uuid)| Uuid::parse_str(uuid).ok())
+        .map(|uuid| uuid.to_string())
+        .unwrap_or_else(|| stable_uuid(&entry.filename))
+}
@@ -0,0 +1,249 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Printable PDF export: patient summary, full record, or a single entry.
+//!
+//! Every page carries a footer naming the subject's canonical id, when the PDF
+//! was generated, and the Git commit it was generated from, so a printout can
+//! be traced back to an exact version of the record.
+
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::subject_id;
+use crate::commands::allergies;
+use crate::commands::contributor;
+use crate::commands::demographics::{self, Demographics};
+use crate::commands::git;
+use crate::commands::journal::clinical_time::TimeBasis;
+use crate::commands::journal::filter::sort_entries;
+use crate::commands::journal::{self, ParsedEntry};
+use crate::commands::vaccinations;
+
+pub mod writer;
+
+use writer::{Font, PdfDocument};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum PdfTemplate {
+    /// Demographics, active allergies and vaccinations.
+    PatientSummary,
+    /// The summary followed by every journal entry, oldest first (by
+    /// recorded or clinical time).
+    FullRecord,
+    /// One journal entry as a letter, chosen by entry ref.
+    SingleEntry,
+}
+
+pub fn run(
+    template: PdfTemplate,
+    entry: Option<&str>,
+    output: &Path,
+    time: TimeBasis,
+) -> Result<()> {
+    let bytes = render(template, entry, time)?;
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(output, bytes).with_context(|| format!("Failed to write {}", output.display()))?;
+    eprintln!("Wrote {}", output.display());
+    Ok(())
+}
+
+/// Render the chosen template to PDF bytes, ordering a full record's journal
+/// by `time`.
+pub fn render(template: PdfTemplate, entry: Option<&str>, time: TimeBasis) -> Result<Vec<u8>> {
+    if template != PdfTemplate::SingleEntry && entry.is_some() {
+        bail!("An entry ref is only used with the single-entry template");
+    }
+    let demographics = demographics::load()?;
+    let names = contributor::contributor_names();
+
+    let title = match template {
+        PdfTemplate::PatientSummary => "Patient summary",
+        PdfTemplate::FullRecord => "Full record",
+        PdfTemplate::SingleEntry => "Clinical letter",
+    };
+    let mut doc = PdfDocument::new(title);
+    patient_header(&mut doc, title, &demographics);
+
+    match template {
+        PdfTemplate::PatientSummary => summary(&mut doc, &demographics)?,
+        PdfTemplate::FullRecord => {
+            summary(&mut doc, &demographics)?;
+            doc.heading("Journal", 14.0);
+            let mut entries = journal::parsed_entries()?;
+            sort_entries(&mut entries, time);
+            if entries.is_empty() {
+                doc.paragraph("No journal entries.");
+            }
+            for entry in &entries {
+                journal_entry(&mut doc, entry, &names);
+            }
+        }
+        PdfTemplate::SingleEntry => {
+            let Some(entry_ref) = entry else {
+                bail!("The single-entry template needs an entry ref (e.g. LATEST, a filename)");
+            };
+            let filename = journal::resolve_entry(entry_ref)?;
+            let path = Path::new("journal").join(&filename);
+            if !path.exists() {
+                bail!("Journal entry not found: {}", filename);
+            }
+            let entry = journal::parse_journal_file(&path)?;
+            journal_entry(&mut doc, &entry, &names);
+        }
+    }
+
+    let unprintable = doc.unprintable();
+    if !unprintable.is_empty() {
+        eprintln!(
+            "Warning: the PDF's standard fonts cannot show {}; printed as '?'",
+            unprintable
+                .iter()
+                .map(|c| format!("'{}'", c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    let now = Utc::now();
+    Ok(doc.finish(&footer(now.format("%Y-%m-%d %H:%M UTC").to_string()), now))
+}
+
+fn footer(generated: String) -> String {
+    let commit = match git::head_commit() {
+        Some(hash) if git::has_uncommitted_changes() => {
+            format!("{} (plus uncommitted changes)", hash)
+        }
+        Some(hash) => hash,
+        None => "none (no commits yet)".to_string(),
+    };
+    format!(
+        "Subject {} | Generated {} | Record commit {}",
+        subject_id().unwrap_or_else(|| "unknown".to_string()),
+        generated,
+        commit
+    )
+}
+
+fn patient_header(doc: &mut PdfDocument, title: &str, demographics: &Demographics) {
+    doc.text(title, Font::Bold, 18.0, 0.0);
+    let mut name = demographics
+        .full_name
+        .clone()
+        .unwrap_or_else(|| "Name not recorded".to_string());
+    if let Some(title) = &demographics.title {
+        name = format!("{} {}", title, name);
+    }
+    doc.text(&name, Font::Bold, 12.0, 0.0);
+    let mut line = Vec::new();
+    if let Some(dob) = &demographics.date_of_birth {
+        line.push(format!("Born {}", dob));
+    }
+    if let Some(nhs) = &demographics.nhs_number {
+        line.push(format!("NHS number {}", nhs));
+    }
+    if !line.is_empty() {
+        doc.paragraph(&line.join("   "));
+    }
+    doc.rule();
+}
+
+fn summary(doc: &mut PdfDocument, demographics: &Demographics) -> Result<()> {
+    doc.heading("Demographics", 14.0);
+    let fields = [
+        ("Name", demographics.full_name.as_deref()),
+        ("Preferred name", demographics.preferred_name.as_deref()),
+        ("Date of birth", demographics.date_of_birth.as_deref()),
+        ("NHS number", demographics.nhs_number.as_deref()),
+        ("Address", demographics.address.as_deref()),
+    ];
+    let mut any = false;
+    for (label, value) in fields {
+        if let Some(value) = value {
+            doc.field(label, value);
+            any = true;
+        }
+    }
+    for identifier in &demographics.identifiers {
+        doc.field(&identifier.id_type, &identifier.value);
+        any = true;
+    }
+    if !any {
+        doc.paragraph("No demographics recorded.");
+    }
+
+    doc.heading("Allergies", 14.0);
+    let allergies = allergies::list(false)?;
+    if allergies.is_empty() {
+        doc.paragraph("No active allergies recorded.");
+    }
+    for allergy in &allergies {
+        doc.text(
+            &format!("{} ({})", allergy.agent, allergy.severity),
+            Font::Bold,
+            10.0,
+            0.0,
+        );
+        let mut detail = format!("Reaction: {}", allergy.reaction);
+        if let Some(note) = &allergy.note {
+            detail.push_str(&format!(". {}", note));
+        }
+        doc.text(&detail, Font::Regular, 10.0, 12.0);
+    }
+
+    doc.heading("Vaccinations", 14.0);
+    let vaccinations = vaccinations::list(false)?;
+    if vaccinations.is_empty() {
+        doc.paragraph("No vaccinations recorded.");
+    }
+    for vaccination in &vaccinations {
+        let mut line = format!("{}  {}", vaccination.date, vaccination.vaccine);
+        if let Some(dose) = vaccination.dose_sequence {
+            line.push_str(&format!(", dose {}", dose));
+        }
+        if let Some(batch) = &vaccination.batch_number {
+            line.push_str(&format!(", batch {}", batch));
+        }
+        doc.paragraph(&line);
+    }
+    Ok(())
+}
+
+fn journal_entry(doc: &mut PdfDocument, entry: &ParsedEntry, names: &HashMap<String, String>) {
+    let author = match &entry.metadata.author {
+        Some(id) => names.get(id).cloned().unwrap_or_else(|| id.clone()),
+        None => "Unknown author".to_string(),
+    };
+    doc.space(6.0);
+    doc.text(
+        &format!("{}  -  {}", entry.metadata.time_label(), author),
+        Font::Bold,
+        10.0,
+        0.0,
+    );
+    if let Some(label) = entry.metadata.metadata_label() {
+        doc.text(&label, Font::Regular, 9.0, 0.0);
+    }
+    doc.text(&entry.filename, Font::Regular, 7.5, 0.0);
+    doc.space(4.0);
+    doc.paragraph(entry.content.trim());
+    if let Some(documents) = &entry.metadata.documents {
+        doc.space(4.0);
+        doc.text("Documents:", Font::Bold, 9.0, 0.0);
+        for document in documents {
+            doc.text(
+                &format!("{} (sha256 {})", document.path, document.sha256),
+                Font::Regular,
+                8.0,
+                12.0,
+            );
+        }
+    }
+    doc.rule();
+}
@@ -0,0 +1,427 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! A deliberately small PDF 1.4 writer.
+//!
+//! Text-only A4 pages set in the two standard Helvetica faces, which every PDF
+//! reader provides, so nothing is embedded and no font files are needed.
+//! Content streams are left uncompressed; a printout of a record is small and
+//! a readable file is easier to audit.
+
+use chrono::{DateTime, Utc};
+use std::collections::BTreeSet;
+
+const PAGE_WIDTH: f32 = 595.0;
+const PAGE_HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+const FOOTER_SPACE: f32 = 40.0;
+const TEXT_WIDTH: f32 = PAGE_WIDTH - 2.0 * MARGIN;
+
+/// Helvetica advance widths (1/1000 em) for ASCII 32..=126, from the AFM.
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278,
+    278, // space - /
+    556, 556, 556, 556, 556, 556, 556, 556, 556, 556, // 0 - 9
+    278, 278, 584, 584, 584, 556, 1015, // : - @
+    667, 667, 722, 722, 667, 611, 778, 722, 278, 500, 667, 556, 833, // A - M
+    722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667, 667, 611, // N - Z
+    278, 278, 278, 469, 556, 333, // [ - `
+    556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500, 222, 833, // a - m
+    556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, // n - z
+    334, 260, 334, 584, // { - ~
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+}
+
+/// Map a char to a WinAnsi byte. Latin-1 maps directly; the standard fonts
+/// cannot show anything else.
+fn win_ansi(c: char) -> Option<u8> {
+    match c {
+        '\u{2018}' | '\u{2019}' => Some(b'\''),
+        '\u{201C}' | '\u{201D}' => Some(b'"'),
+        '\u{2013}' | '\u{2014}' => Some(b'-'),
+        '\u{2022}' => Some(0x95),
+        '\t' => Some(b' '),
+        c if (' '..='~').contains(&c) => Some(c as u8),
+        c if ('\u{A0}'..='\u{FF}').contains(&c) => Some(c as u32 as u8),
+        _ => None,
+    }
+}
+
+/// ASCII spellings of symbols common in clinical notes that WinAnsi lacks,
+/// so "≥ 38.5" prints as ">= 38.5" rather than "? 38.5".
+fn transliterate(c: char) -> Option<&'static str> {
+    Some(match c {
+        '\u{2265}' => ">=",
+        '\u{2264}' => "<=",
+        '\u{2260}' => "!=",
+        '\u{2248}' => "~",
+        '\u{2192}' => "->",
+        '\u{2190}' => "<-",
+        '\u{2194}' => "<->",
+        '\u{21D2}' => "=>",
+        '\u{2212}' => "-",
+        '\u{2026}' => "...",
+        _ => return None,
+    })
+}
+
+/// Whether `c` can be printed, directly or spelt out.
+fn printable(c: char) -> bool {
+    win_ansi(c).is_some() || transliterate(c).is_some()
+}
+
+/// `text` as WinAnsi bytes. A char that cannot be printed becomes '?'.
+fn encode(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    for c in text.chars() {
+        match (transliterate(c), win_ansi(c)) {
+            (Some(spelling), _) => bytes.extend_from_slice(spelling.as_bytes()),
+            (None, Some(byte)) => bytes.push(byte),
+            (None, None) => bytes.push(b'?'),
+        }
+    }
+    bytes
+}
+
+fn text_width(text: &str, font: Font, size: f32) -> f32 {
+    let units: u32 = encode(text)
+        .into_iter()
+        .map(|byte| {
+            if (32..=126).contains(&byte) {
+                HELVETICA_WIDTHS[(byte - 32) as usize] as u32
+            } else {
+                556
+            }
+        })
+        .sum();
+    // Bold is a little wider than regular; close enough for line breaking.
+    let scale = if font == Font::Bold { 1.06 } else { 1.0 };
+    units as f32 * size / 1000.0 * scale
+}
+
+/// Encode text as a PDF literal string.
+fn pdf_string(text: &str) -> String {
+    let mut out = String::from("(");
+    for byte in encode(text) {
+        match byte {
+            b'(' => out.push_str("\\("),
+            b')' => out.push_str("\\)"),
+            b'\\' => out.push_str("\\\\"),
+            byte if byte < 128 => out.push(byte as char),
+            byte => out.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// Greedy word wrap to `width` points; words longer than a line are split.
+fn wrap(text: &str, font: Font, size: f32, width: f32) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split(' ') {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{} {}", line, word)
+        };
+        if text_width(&candidate, font, size) <= width {
+            line = candidate;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        let mut rest: Vec<char> = word.chars().collect();
+        while text_width(&rest.iter().collect::<String>(), font, size) > width {
+            let mut take = rest.len() - 1;
+            while take > 1
+                && text_width(&rest[..take].iter().collect::<String>(), font, size) > width
+            {
+                take -= 1;
+            }
+            lines.push(rest[..take].iter().collect());
+            rest.drain(..take);
+        }
+        let rest: String = rest.into_iter().collect();
+        line = rest;
+    }
+    lines.push(line);
+    lines
+}
+
+/// Lays text out top-to-bottom across as many pages as it needs.
+pub struct PdfDocument {
+    title: String,
+    pages: Vec<String>,
+    y: f32,
+    /// Chars in the text that were printed as '?'.
+    unprintable: BTreeSet<char>,
+}
+
+impl PdfDocument {
+    pub fn new(title: &str) -> Self {
+        PdfDocument {
+            title: title.to_string(),
+            pages: vec![String::new()],
+            y: PAGE_HEIGHT - MARGIN,
+            unprintable: BTreeSet::new(),
+        }
+    }
+
+    /// The chars written so far that the standard fonts cannot show and
+    /// that were printed as '?' instead.
+    pub fn unprintable(&self) -> &BTreeSet<char> {
+        &self.unprintable
+    }
+
+    fn ensure_space(&mut self, height: f32) {
+        if self.y - height < MARGIN + FOOTER_SPACE {
+            self.pages.push(String::new());
+            self.y = PAGE_HEIGHT - MARGIN;
+        }
+    }
+
+    fn line(&mut self, text: &str, font: Font, size: f32, indent: f32) {
+        let leading = size * 1.35;
+        self.ensure_space(leading);
+        self.y -= leading;
+        self.unprintable
+            .extend(text.chars().filter(|&c| !printable(c)));
+        let page = self.pages.last_mut().expect("at least one page");
+        page.push_str(&format!(
+            "BT /{} {} Tf {:.2} {:.2} Td {} Tj ET\n",
+            font.resource(),
+            size,
+            MARGIN + indent,
+            self.y,
+            pdf_string(text)
+        ));
+    }
+
+    /// Write wrapped text. Newlines in `text` start new lines; blank lines
+    /// are kept.
+    pub fn text(&mut self, text: &str, font: Font, size: f32, indent: f32) {
+        for paragraph in text.lines() {
+            for line in wrap(paragraph, font, size, TEXT_WIDTH - indent) {
+                self.line(&line, font, size, indent);
+            }
+        }
+    }
+
+    pub fn heading(&mut self, text: &str, size: f32) {
+        // Keep a heading with at least a couple of lines of what follows.
+        self.ensure_space(size * 1.35 + 30.0);
+        self.space(size * 0.5);
+        self.text(text, Font::Bold, size, 0.0);
+    }
+
+    pub fn paragraph(&mut self, text: &str) {
+        self.text(text, Font::Regular, 10.0, 0.0);
+    }
+
+    /// A "Label: value" line with the label in bold.
+    pub fn field(&mut self, label: &str, value: &str) {
+        let label = format!("{}:", label);
+        let indent = 110.0;
+        let lines = wrap(value, Font::Regular, 10.0, TEXT_WIDTH - indent);
+        for (i, line) in lines.iter().enumerate() {
+            if i == 0 {
+                self.line(&label, Font::Bold, 10.0, 0.0);
+                // Same baseline as the label.
+                self.y += 10.0 * 1.35;
+            }
+            self.line(line, Font::Regular, 10.0, indent);
+        }
+    }
+
+    pub fn space(&mut self, height: f32) {
+        self.y -= height;
+    }
+
+    pub fn rule(&mut self) {
+        self.ensure_space(8.0);
+        self.y -= 4.0;
+        let page = self.pages.last_mut().expect("at least one page");
+        page.push_str(&format!(
+            "0.6 G 0.5 w {:.2} {:.2} m {:.2} {:.2} l S 0 G\n",
+            MARGIN,
+            self.y,
+            PAGE_WIDTH - MARGIN,
+            self.y
+        ));
+        self.y -= 4.0;
+    }
+
+    /// Serialise the document, stamping `footer` and "Page n of m" on every
+    /// page.
+    pub fn finish(self, footer: &str, created: DateTime<Utc>) -> Vec<u8> {
+        let page_count = self.pages.len();
+        let first_page_obj = 5;
+
+        let mut objects: Vec<String> = Vec::new();
+        objects.push("<< /Type /Catalog /Pages 2 0 R >>".to_string());
+        let kids: Vec<String> = (0..page_count)
+            .map(|i| format!("{} 0 R", first_page_obj + 2 * i))
+            .collect();
+        objects.push(format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            page_count
+        ));
+        objects.push(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+        );
+        objects.push(
+            "<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>"
+                .to_string(),
+        );
+
+        for (i, mut content) in self.pages.into_iter().enumerate() {
+            let footer_lines = wrap(footer, Font::Regular, 7.5, TEXT_WIDTH - 60.0);
+            let mut y = MARGIN - 10.0;
+            for line in footer_lines.iter().rev() {
+                content.push_str(&format!(
+                    "0.3 g BT /F1 7.5 Tf {:.2} {:.2} Td {} Tj ET 0 g\n",
+                    MARGIN,
+                    y,
+                    pdf_string(line)
+                ));
+                y += 10.0;
+            }
+            let page_label = format!("Page {} of {}", i + 1, page_count);
+            content.push_str(&format!(
+                "0.3 g BT /F1 7.5 Tf {:.2} {:.2} Td {} Tj ET 0 g\n",
+                PAGE_WIDTH - MARGIN - text_width(&page_label, Font::Regular, 7.5),
+                MARGIN - 10.0,
+                pdf_string(&page_label)
+            ));
+
+            let content_obj = first_page_obj + 2 * i + 1;
+            objects.push(format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                PAGE_WIDTH, PAGE_HEIGHT, content_obj
+            ));
+            objects.push(format!(
+                "<< /Length {} >>\nstream\n{}endstream",
+                content.len(),
+                content
+            ));
+        }
+
+        let info_obj = objects.len() + 1;
+        objects.push(format!(
+            "<< /Title {} /Producer (GitEHR) /CreationDate (D:{}Z) >>",
+            pdf_string(&self.title),
+            created.format("%Y%m%d%H%M%S")
+        ));
+
+        let mut out = b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n".to_vec();
+        let mut offsets = Vec::with_capacity(objects.len());
+        for (i, object) in objects.iter().enumerate() {
+            offsets.push(out.len());
+            out.extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", i + 1, object).as_bytes());
+        }
+        let xref_offset = out.len();
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+        for offset in offsets {
+            xref.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        out.extend_from_slice(xref.as_bytes());
+        out.extend_from_slice(
+            format!(
+                "trailer\n<< /Size {} /Root 1 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+                objects.len() + 1,
+                info_obj,
+                xref_offset
+            )
+            .as_bytes(),
+        );
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdf_string_escapes_and_encodes() {
+        assert_eq!(pdf_string(r"a(b)\c"), r"(a\(b\)\\c)");
+        assert_eq!(pdf_string("café"), r"(caf\351)");
+        assert_eq!(pdf_string("日本"), "(??)");
+        assert_eq!(pdf_string("T ≥ 38.5 → screen"), "(T >= 38.5 -> screen)");
+    }
+
+    #[test]
+    fn test_unprintable_chars_are_collected() {
+        let mut doc = PdfDocument::new("Test");
+        doc.paragraph("Café, ≤ 5 mg");
+        assert!(doc.unprintable().is_empty());
+        doc.field("Name", "山田 太郎 ✓");
+        assert_eq!(doc.unprintable().iter().collect::<String>(), "✓太山田郎");
+    }
+
+    #[test]
+    fn test_wrap_respects_width() {
+        let text = "The quick brown fox jumps over the lazy dog ".repeat(10);
+        let lines = wrap(text.trim(), Font::Regular, 10.0, 200.0);
+        assert!(lines.len() > 1);
+        for line in &lines {
+            assert!(text_width(line, Font::Regular, 10.0) <= 200.0);
+        }
+        let long = wrap(&"x".repeat(200), Font::Regular, 10.0, 100.0);
+        assert!(long.len() > 1);
+    }
+
+    #[test]
+    fn test_xref_offsets_point_at_objects() {
+        let mut doc = PdfDocument::new("Test");
+        for i in 0..120 {
+            doc.paragraph(&format!("Line {}", i));
+        }
+        let bytes = doc.finish("footer", Utc::now());
+        // Skip the binary marker comment so byte offsets survive decoding.
+        let body = &bytes[15..];
+        let text = std::str::from_utf8(body).unwrap();
+        assert!(bytes.starts_with(b"%PDF-1.4"));
+        assert!(text.contains("/Count 3"));
+        assert!(text.contains("(Page 3 of 3)"));
+
+        let startxref: usize = text
+            .rsplit("startxref\n")
+            .next()
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(bytes[startxref..].starts_with(b"xref"));
+        let xref = &text[startxref - 15..];
+        for (n, line) in xref
+            .lines()
+            .skip(3)
+            .take_while(|l| l.ends_with(" n "))
+            .enumerate()
+        {
+            let offset: usize = line[..10].parse().unwrap();
+            assert!(bytes[offset..].starts_with(format!("{} 0 obj", n + 1).as_bytes()));
+        }
+    }
+}
@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use anyhow::Result;
+use std::collections::{HashMap, HashSet};
 use std::process::Command;
 
 /// Execute a git command with the given arguments
@@ -37,6 +38,22 @@ pub fn git_commit(message: &str) -> Result<()> {
     run_git_command(&["commit", "-m", message])
 }
 
+/// Commit `paths` alone with the given message, leaving anything else that is
+/// staged for a later commit
+pub fn git_commit_only(message: &str, paths: &[&str]) -> Result<()> {
+    let mut args = vec!["commit", "-m", message, "--"];
+    args.extend(paths);
+    run_git_command(&args)
+}
+
+/// Whether `path` has staged changes not yet committed
+pub fn has_staged_changes(path: &str) -> bool {
+    Command::new("git")
+        .args(["diff", "--cached", "--quiet", "--", path])
+        .status()
+        .is_ok_and(|status| status.code() == Some(1))
+}
+
 /// Get the installed git version string
 pub fn get_git_version() -> Option<String> {
     use std::process::Command;
@@ -47,3 +64,116 @@ pub fn get_git_version() -> Option<String> {
     let stdout = String::from_utf8_lossy(&output.stdout);
     Some(stdout.trim().to_string())
 }
+
+/// Full hash of the commit at HEAD, if there is one
+pub fn head_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Whether the working tree has changes (including untracked files) not in HEAD
+pub fn has_uncommitted_changes() -> bool {
+    Command::new("git")
+        .args(["status", "--porcelain"])
+        .output()
+        .map(|output| output.status.success() && !output.stdout.is_empty())
+        .unwrap_or(false)
+}
+
+/// One commit from `git log`, with the files it touched.
+#[derive(Debug, Clone)]
+pub struct CommitInfo {
+    pub hash: String,
+    /// Committer date, RFC 3339.
+    pub time: String,
+    pub author_name: String,
+    pub subject: String,
+    pub files: Vec<String>,
+}
+
+/// Commits touching `paths`, newest first. Empty when there is no history.
+pub fn log_with_files(paths: &[&str]) -> Result<Vec<CommitInfo>> {
+    let mut args = vec![
+        "log",
+        "--format=%x1e%H%x1f%cI%x1f%an%x1f%s",
+        "--name-only",
+        "--",
+    ];
+    args.extend_from_slice(paths);
+    let output = Command::new("git").args(&args).output()?;
+    if !output.status.success() {
+        // A repository with no commits yet has no history to report.
+        return Ok(Vec::new());
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut commits = Vec::new();
+    for record in stdout.split('\x1e').filter(|r| !r.trim().is_empty()) {
+        let mut lines = record.lines();
+        let header: Vec<&str> = lines.next().unwrap_or_default().split('\x1f').collect();
+        if header.len() < 4 {
+            continue;
+        }
+        commits.push(CommitInfo {
+            hash: header[0].to_string(),
+            time: header[1].to_string(),
+            author_name: header[2].to_string(),
+            subject: header[3].to_string(),
+            files: lines
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .map(str::to_string)
+                .collect(),
+        });
+    }
+    Ok(commits)
+}
+
+/// Blob ids of the files under `path` in the git index, keyed by path.
+pub fn staged_blobs(path: &str) -> Result<HashMap<String, String>> {
+    let stdout = git_output(&["ls-files", "--stage", "-z", "--", path])?;
+    Ok(stdout
+        .split('\0')
+        .filter_map(|record| {
+            // <mode> SP <object> SP <stage> TAB <path>
+            let (info, file) = record.split_once('\t')?;
+            let blob = info.split(' ').nth(1)?;
+            Some((file.to_string(), blob.to_string()))
+        })
+        .collect())
+}
+
+/// Files under `path` that differ from the git index: modified, deleted or
+/// untracked (ignored files excluded).
+pub fn unstaged_files(path: &str) -> Result<HashSet<String>> {
+    let stdout = git_output(&[
+        "ls-files",
+        "--modified",
+        "--others",
+        "--exclude-standard",
+        "-z",
+        "--",
+        path,
+    ])?;
+    Ok(stdout
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+fn git_output(args: &[&str]) -> Result<String> {
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        anyhow::bail!(
+            "Git command failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,834 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! FHIR R4 Bundle import into typed state.
+//!
+//! Each supported resource is upserted into its typed state file, keyed by a
+//! stable source key (`fhir:<ResourceType>/<id>`), so importing the same Bundle
+//! twice changes nothing. Everything lands in a single commit with one summary
+//! journal entry. The Bundle itself is kept as a Document, so resource types
+//! GitEHR has no typed state for yet are retained and counted in the summary.
+
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use chrono::{NaiveDate, Utc};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use crate::commands::allergies::{self, Allergy, AllergySeverity, AllergyStatus};
+use crate::commands::demographics::{self, DemographicsUpdate, Identifier};
+use crate::commands::document::{self, add::DocumentSource};
+use crate::commands::journal::{self, DocumentRef};
+use crate::commands::vaccinations::{self, Vaccination, VaccinationStatus};
+use crate::utils::sha256_hex;
+
+const NHS_NUMBER_SYSTEM: &str = "https://fhir.nhs.uk/Id/nhs-number";
+
+/// Added / updated / unchanged counts for one kind of typed state.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Tally {
+    pub added: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+}
+
+impl Tally {
+    fn changed(&self) -> bool {
+        self.added + self.updated > 0
+    }
+}
+
+/// What an import did (or, with `--dry-run`, would do).
+#[derive(Debug, Default)]
+pub struct FhirImportSummary {
+    pub demographics_updated: bool,
+    pub allergies: Tally,
+    pub vaccinations: Tally,
+    /// New Documents, including the source Bundle on its first import.
+    pub documents_added: usize,
+    /// Resource counts by type for resources with no typed state yet.
+    pub not_imported: BTreeMap<String, usize>,
+    pub warnings: Vec<String>,
+    /// False when the record already matched the Bundle and nothing was written.
+    pub committed: bool,
+}
+
+impl FhirImportSummary {
+    fn has_changes(&self) -> bool {
+        self.demographics_updated
+            || self.allergies.changed()
+            || self.vaccinations.changed()
+            || self.documents_added > 0
+    }
+}
+
+/// Decoded attachment waiting to be stored as a Document.
+struct PendingDocument {
+    title: String,
+    extension: String,
+    imaging: bool,
+    bytes: Vec<u8>,
+}
+
+/// Import a FHIR R4 Bundle. With `dry_run`, report what would change and write
+/// nothing.
+pub fn run(source: &Path, dry_run: bool) -> Result<FhirImportSummary> {
+    let content = fs::read_to_string(source)
+        .with_context(|| format!("Failed to read FHIR Bundle {}", source.display()))?;
+    let bundle: Value = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse FHIR JSON {}", source.display()))?;
+    if bundle.get("resourceType").and_then(Value::as_str) != Some("Bundle") {
+        bail!("--mode fhir expects a FHIR R4 Bundle resource");
+    }
+
+    let entries: Vec<(Option<&str>, &Value)> = bundle
+        .get("entry")
+        .and_then(Value::as_array)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| {
+                    let resource = entry.get("resource")?;
+                    Some((entry.get("fullUrl").and_then(Value::as_str), resource))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let mut summary = FhirImportSummary::default();
+    let mut by_type: BTreeMap<&str, Vec<(Option<&str>, &Value)>> = BTreeMap::new();
+    for (full_url, resource) in &entries {
+        let resource_type = resource_type(resource);
+        by_type
+            .entry(resource_type)
+            .or_default()
+            .push((*full_url, *resource));
+    }
+
+    // Demographics
+    let patients = by_type.remove("Patient").unwrap_or_default();
+    if patients.len() > 1 {
+        bail!(
+            "Bundle contains {} Patient resources; a GitEHR record holds one subject, so import each patient into their own repo",
+            patients.len()
+        );
+    }
+    let current_demographics = demographics::load()?;
+    let mut new_demographics = current_demographics.clone();
+    if let Some((_, patient)) = patients.first() {
+        demographics::apply(
+            patient_update(patient, &mut summary.warnings),
+            &mut new_demographics,
+        );
+        summary.demographics_updated = serde_json::to_value(&new_demographics)?
+            != serde_json::to_value(&current_demographics)?;
+    }
+
+    // Allergies
+    let mut allergy_state = allergies::load()?;
+    for (full_url, resource) in by_type.remove("AllergyIntolerance").unwrap_or_default() {
+        let key = source_key(resource, full_url);
+        match allergy_from_fhir(resource, &key) {
+            Ok(allergy) => upsert_allergy(&mut allergy_state.allergies, allergy, &mut summary),
+            Err(e) => summary.warnings.push(format!("Skipped {}: {}", key, e)),
+        }
+    }
+
+    // Vaccinations
+    let mut vaccination_state = vaccinations::load()?;
+    for (full_url, resource) in by_type.remove("Immunization").unwrap_or_default() {
+        let key = source_key(resource, full_url);
+        match vaccination_from_fhir(resource, &key) {
+            Ok(Some(vaccination)) => upsert_vaccination(
+                &mut vaccination_state.vaccinations,
+                vaccination,
+                &mut summary,
+            ),
+            Ok(None) => summary
+                .warnings
+                .push(format!("Skipped {}: status is not-done", key)),
+            Err(e) => summary.warnings.push(format!("Skipped {}: {}", key, e)),
+        }
+    }
+
+    // Documents: DocumentReference attachments, then any Binary they did not use.
+    let binaries = by_type.remove("Binary").unwrap_or_default();
+    let document_references = by_type.remove("DocumentReference").unwrap_or_default();
+    let pending = collect_attachments(&binaries, &document_references, &mut summary.warnings);
+
+    let mut known_hashes: BTreeSet<String> = document::collect_refs()?
+        .into_iter()
+        .map(|(_, doc)| doc.sha256)
+        .collect();
+    let mut new_documents = Vec::new();
+    for attachment in pending {
+        if known_hashes.insert(sha256_hex(&attachment.bytes)) {
+            new_documents.push(attachment);
+        }
+    }
+    let bundle_is_new = known_hashes.insert(sha256_hex(content.as_bytes()));
+    summary.documents_added = new_documents.len() + usize::from(bundle_is_new);
+
+    for (resource_type, resources) in by_type {
+        summary
+            .not_imported
+            .insert(resource_type.to_string(), resources.len());
+    }
+
+    let source_name = source
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| source.display().to_string());
+    let body = summary_body(&source_name, patients.first().map(|p| p.1), &summary);
+
+    if dry_run {
+        println!("Dry run: nothing was written.\n");
+        println!("{}", body);
+        print_warnings(&summary.warnings);
+        return Ok(summary);
+    }
+    if !summary.has_changes() {
+        println!(
+            "Nothing to import: the record already matches {}.",
+            source_name
+        );
+        print_warnings(&summary.warnings);
+        return Ok(summary);
+    }
+
+    if summary.demographics_updated {
+        demographics::save(&new_demographics)?;
+    }
+    if summary.allergies.changed() {
+        allergies::save(&allergy_state)?;
+    }
+    if summary.vaccinations.changed() {
+        vaccinations::save(&vaccination_state)?;
+    }
+
+    let mut document_refs = stage_documents(&new_documents)?;
+    if bundle_is_new {
+        document_refs.push(document::add::stage(&DocumentSource {
+            path: source.to_path_buf(),
+            imaging: false,
+            title: Some(format!(
+                "FHIR Bundle {}",
+                source.file_stem().and_then(|s| s.to_str()).unwrap_or("")
+            )),
+        })?);
+    }
+
+    journal::create_journal_entry_with_documents(&body, document_refs)?;
+    summary.committed = true;
+    print_warnings(&summary.warnings);
+    Ok(summary)
+}
+
+fn resource_type(resource: &Value) -> &str {
+    resource
+        .get("resourceType")
+        .and_then(Value::as_str)
+        .unwrap_or("Unknown")
+}
+
+/// `fhir:<ResourceType>/<id>`, falling back to the entry's fullUrl and then to
+/// a content hash so resources without ids still import idempotently.
+fn source_key(resource: &Value, full_url: Option<&str>) -> String {
+    let resource_type = resource_type(resource);
+    if let Some(id) = resource.get("id").and_then(Value::as_str) {
+        return format!("fhir:{}/{}", resource_type, id);
+    }
+    if let Some(url) = full_url {
+        return format!("fhir:{}", url);
+    }
+    let canonical = serde_json::to_vec(resource).unwrap_or_default();
+    format!(
+        "fhir:{}/sha256-{}",
+        resource_type,
+        &sha256_hex(&canonical)[..16]
+    )
+}
+
+/// Display text for a CodeableConcept: `text`, else the first coding display.
+fn concept_text(concept: Option<&Value>) -> Option<String> {
+    let concept = concept?;
+    concept
+        .get("text")
+        .and_then(Value::as_str)
+        .or_else(|| {
+            concept
+                .get("coding")
+                .and_then(Value::as_array)?
+                .iter()
+                .find_map(|coding| coding.get("display").and_then(Value::as_str))
+        })
+        .map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+fn first_code(concept: Option<&Value>) -> Option<&str> {
+    concept?
+        .get("coding")
+        .and_then(Value::as_array)?
+        .iter()
+        .find_map(|coding| coding.get("code").and_then(Value::as_str))
+}
+
+fn str_field(resource: &Value, field: &str) -> Option<String> {
+    resource
+        .get(field)
+        .and_then(Value::as_str)
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+}
+
+/// First 10 characters of a FHIR date/dateTime, if they form a full date.
+fn full_date(value: Option<&str>) -> Option<String> {
+    let date = value?.get(..10)?;
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+    Some(date.to_string())
+}
+
+fn patient_update(patient: &Value, warnings: &mut Vec<String>) -> DemographicsUpdate {
+    let names = patient
+        .get("name")
+        .and_then(Value::as_array)
+        .cloned()
+        .unwrap_or_default();
+    let official = names
+        .iter()
+        .find(|name| name.get("use").and_then(Value::as_str) == Some("official"))
+        .or_else(|| names.first());
+    let preferred = names.iter().find(|name| {
+        matches!(
+            name.get("use").and_then(Value::as_str),
+            Some("usual") | Some("nickname")
+        )
+    });
+
+    let mut update = DemographicsUpdate::default();
+    if let Some(name) = official {
+        update.title = name
+            .get("prefix")
+            .and_then(Value::as_array)
+            .and_then(|prefixes| prefixes.first())
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        update.full_name = human_name(name);
+    }
+    if let Some(name) = preferred {
+        update.preferred_name = name
+            .get("given")
+            .and_then(Value::as_array)
+            .and_then(|given| given.first())
+            .and_then(Value::as_str)
+            .map(str::to_string)
+            .or_else(|| human_name(name));
+    }
+
+    if let Some(raw) = patient.get("birthDate").and_then(Value::as_str) {
+        match full_date(Some(raw)) {
+            Some(date) if date.len() == raw.len() => update.date_of_birth = Some(date),
+            _ => warnings.push(format!(
+                "Patient birthDate '{}' is not a full YYYY-MM-DD date; date of birth not updated",
+                raw
+            )),
+        }
+    }
+
+    if let Some(address) = patient
+        .get("address")
+        .and_then(Value::as_array)
+        .and_then(|addresses| addresses.first())
+    {
+        update.address = str_field(address, "text").or_else(|| {
+            let mut parts: Vec<String> = address
+                .get("line")
+                .and_then(Value::as_array)
+                .map(|lines| {
+                    lines
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default();
+            for field in ["city", "district", "postalCode", "country"] {
+                parts.extend(str_field(address, field));
+            }
+            (!parts.is_empty()).then(|| parts.join(", "))
+        });
+    }
+
+    for identifier in patient
+        .get("identifier")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+    {
+        let Some(value) = str_field(identifier, "value") else {
+            continue;
+        };
+        let system = identifier.get("system").and_then(Value::as_str);
+        if system == Some(NHS_NUMBER_SYSTEM) {
+            update.nhs_number = Some(value);
+            continue;
+        }
+        let id_type = concept_text(identifier.get("type"))
+            .or_else(|| first_code(identifier.get("type")).map(str::to_string))
+            .or_else(|| {
+                system
+                    .and_then(|s| s.trim_end_matches('/').rsplit(['/', ':']).next())
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| "FHIR".to_string());
+        update.identifiers.push(Identifier { id_type, value });
+    }
+
+    update
+}
+
+fn human_name(name: &Value) -> Option<String> {
+    if let Some(text) = str_field(name, "text") {
+        return Some(text);
+    }
+    let mut parts: Vec<String> = name
+        .get("given")
+        .and_then(Value::as_array)
+        .map(|given| {
+            given
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default();
+    parts.extend(str_field(name, "family"));
+    (!parts.is_empty()).then(|| parts.join(" "))
+}
+
+fn allergy_from_fhir(resource: &Value, key: &str) -> Result<Allergy> {
+    let agent = concept_text(resource.get("code"))
+        .ok_or_else(|| anyhow::anyhow!("no code text or display for the substance"))?;
+    let reaction = resource
+        .get("reaction")
+        .and_then(Value::as_array)
+        .and_then(|reactions| reactions.first());
+    let reaction_text = reaction
+        .and_then(|reaction| reaction.get("manifestation"))
+        .and_then(Value::as_array)
+        .map(|manifestations| {
+            manifestations
+                .iter()
+                .filter_map(|m| concept_text(Some(m)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .filter(|text| !text.is_empty())
+        .or_else(|| reaction.and_then(|r| str_field(r, "description")))
+        .unwrap_or_else(|| "Not recorded".to_string());
+
+    let severity = if resource.get("criticality").and_then(Value::as_str) == Some("high") {
+        AllergySeverity::Critical
+    } else {
+        match reaction
+            .and_then(|r| r.get("severity"))
+            .and_then(Value::as_str)
+        {
+            Some("mild") => AllergySeverity::Low,
+            Some("severe") => AllergySeverity::High,
+            _ => AllergySeverity::Moderate,
+        }
+    };
+
+    let inactive = matches!(
+        first_code(resource.get("clinicalStatus")),
+        Some("inactive") | Some("resolved")
+    ) || first_code(resource.get("verificationStatus")) == Some("entered-in-error");
+
+    let now = Utc::now();
+    Ok(Allergy {
+        id: allergies::new_id(now),
+        agent,
+        reaction: reaction_text,
+        severity,
+        status: if inactive {
+            AllergyStatus::Inactive
+        } else {
+            AllergyStatus::Active
+        },
+        recorded_at: str_field(resource, "recordedDate").unwrap_or_else(|| now.to_rfc3339()),
+        recorded_by: crate::commands::contributor::get_current_contributor(),
+        inactive_at: inactive.then(|| now.to_rfc3339()),
+        inactive_reason: None,
+        note: first_note(resource),
+        source_key: Some(key.to_string()),
+        source_entry: None,
+    })
+}
+
+fn first_note(resource: &Value) -> Option<String> {
+    resource
+        .get("note")
+        .and_then(Value::as_array)
+        .and_then(|notes| notes.first())
+        .and_then(|note| str_field(note, "text"))
+}
+
+/// Upsert by source key. An existing allergy keeps its id and recording
+/// provenance; only the clinical content is refreshed from the resource.
+fn upsert_allergy(existing: &mut Vec<Allergy>, incoming: Allergy, summary: &mut FhirImportSummary) {
+    let Some(current) = existing
+        .iter_mut()
+        .find(|allergy| allergy.source_key == incoming.source_key)
+    else {
+        existing.push(incoming);
+        summary.allergies.added += 1;
+        return;
+    };
+
+    let mut updated = current.clone();
+    updated.agent = incoming.agent;
+    updated.reaction = incoming.reaction;
+    updated.severity = incoming.severity;
+    updated.note = incoming.note;
+    if updated.status != incoming.status {
+        updated.status = incoming.status;
+        updated.inactive_at = incoming.inactive_at;
+    }
+
+    if serde_json::to_value(&updated).ok() == serde_json::to_value(&*current).ok() {
+        summary.allergies.unchanged += 1;
+    } else {
+        *current = updated;
+        summary.allergies.updated += 1;
+    }
+}
+
+fn vaccination_from_fhir(resource: &Value, key: &str) -> Result<Option<Vaccination>> {
+    let status = match resource.get("status").and_then(Value::as_str) {
+        Some("not-done") => return Ok(None),
+        Some("entered-in-error") => VaccinationStatus::EnteredInError,
+        _ => VaccinationStatus::Completed,
+    };
+    let vaccine = concept_text(resource.get("vaccineCode"))
+        .ok_or_else(|| anyhow::anyhow!("no vaccineCode text or display"))?;
+    let raw_date = resource.get("occurrenceDateTime").and_then(Value::as_str);
+    let date = full_date(raw_date).ok_or_else(|| {
+        anyhow::anyhow!(
+            "occurrenceDateTime {} is not a full date",
+            raw_date.unwrap_or("is missing and")
+        )
+    })?;
+
+    let protocol = resource
+        .get("protocolApplied")
+        .and_then(Value::as_array)
+        .and_then(|protocols| protocols.first());
+    let dose_sequence = protocol
+        .and_then(|p| p.get("doseNumberPositiveInt"))
+        .and_then(Value::as_u64)
+        .and_then(|dose| u32::try_from(dose).ok());
+    let target_disease = protocol
+        .and_then(|p| p.get("targetDisease"))
+        .and_then(Value::as_array)
+        .map(|diseases| {
+            diseases
+                .iter()
+                .filter_map(|d| concept_text(Some(d)))
+                .collect()
+        })
+        .unwrap_or_default();
+    let performer = resource
+        .get("performer")
+        .and_then(Value::as_array)
+        .and_then(|performers| performers.first())
+        .and_then(|performer| performer.get("actor"))
+        .and_then(|actor| str_field(actor, "display"));
+
+    let now = Utc::now();
+    Ok(Some(Vaccination {
+        id: vaccinations::new_id(now),
+        status,
+        vaccine,
+        date,
+        dose_sequence,
+        target_disease,
+        anatomical_site: concept_text(resource.get("site")),
+        route: concept_text(resource.get("route")),
+        product: None,
+        manufacturer: resource
+            .get("manufacturer")
+            .and_then(|m| str_field(m, "display")),
+        batch_number: str_field(resource, "lotNumber"),
+        performer,
+        recorded_at: str_field(resource, "recorded").unwrap_or_else(|| now.to_rfc3339()),
+        recorded_by: crate::commands::contributor::get_current_contributor(),
+        entered_in_error_at: (status == VaccinationStatus::EnteredInError)
+            .then(|| now.to_rfc3339()),
+        entered_in_error_reason: None,
+        note: first_note(resource),
+        fhir_r4: Some(resource.clone()),
+        source_key: Some(key.to_string()),
+        source_entry: None,
+    }))
+}
+
+fn upsert_vaccination(
+    existing: &mut Vec<Vaccination>,
+    incoming: Vaccination,
+    summary: &mut FhirImportSummary,
+) {
+    let Some(current) = existing
+        .iter_mut()
+        .find(|vaccination| vaccination.source_key == incoming.source_key)
+    else {
+        existing.push(incoming);
+        summary.vaccinations.added += 1;
+        return;
+    };
+
+    let mut updated = incoming;
+    updated.id = current.id.clone();
+    updated.recorded_at = current.recorded_at.clone();
+    updated.recorded_by = current.recorded_by.clone();
+    if updated.status == current.status {
+        updated.entered_in_error_at = current.entered_in_error_at.clone();
+        updated.entered_in_error_reason = current.entered_in_error_reason.clone();
+    }
+
+    if serde_json::to_value(&updated).ok() == serde_json::to_value(&*current).ok() {
+        summary.vaccinations.unchanged += 1;
+    } else {
+        *current = updated;
+        summary.vaccinations.updated += 1;
+    }
+}
+
+/// Decode DocumentReference attachments (inline or pointing at a Binary in the
+/// same Bundle), then any Binary no DocumentReference used. URLs outside the
+/// Bundle are not fetched: import never needs network access.
+fn collect_attachments(
+    binaries: &[(Option<&str>, &Value)],
+    document_references: &[(Option<&str>, &Value)],
+    warnings: &mut Vec<String>,
+) -> Vec<PendingDocument> {
+    let mut binary_index: HashMap<String, usize> = HashMap::new();
+    for (i, (full_url, binary)) in binaries.iter().enumerate() {
+        if let Some(id) = binary.get("id").and_then(Value::as_str) {
+            binary_index.insert(format!("Binary/{}", id), i);
+        }
+        if let Some(url) = full_url {
+            binary_index.insert(url.to_string(), i);
+        }
+    }
+    let mut used = vec![false; binaries.len()];
+    let mut pending = Vec::new();
+
+    for (full_url, reference) in document_references {
+        let key = source_key(reference, *full_url);
+        let fallback_title = str_field(reference, "description")
+            .or_else(|| concept_text(reference.get("type")))
+            .unwrap_or_else(|| "FHIR document".to_string());
+        for content in reference
+            .get("content")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let Some(attachment) = content.get("attachment") else {
+                continue;
+            };
+            let title = str_field(attachment, "title").unwrap_or_else(|| fallback_title.clone());
+            let content_type = attachment.get("contentType").and_then(Value::as_str);
+
+            let data = if let Some(data) = attachment.get("data").and_then(Value::as_str) {
+                Some((data, content_type))
+            } else if let Some(url) = attachment.get("url").and_then(Value::as_str) {
+                let binary = binary_index
+                    .iter()
+                    .find(|(k, _)| url == k.as_str() || url.ends_with(&format!("/{}", k)))
+                    .map(|(_, i)| *i);
+                match binary {
+                    Some(i) => {
+                        used[i] = true;
+                        let binary = binaries[i].1;
+                        binary.get("data").and_then(Value::as_str).map(|data| {
+                            (
+                                data,
+                                content_type
+                                    .or_else(|| binary.get("contentType").and_then(Value::as_str)),
+                            )
+                        })
+                    }
+                    None => {
+                        warnings.push(format!(
+                            "Skipped attachment '{}' on {}: {} is outside the Bundle and was not fetched",
+                            title, key, url
+                        ));
+                        continue;
+                    }
+                }
+            } else {
+                None
+            };
+
+            match data.map(|(data, content_type)| decode(data, content_type, &title)) {
+                Some(Ok(document)) => pending.push(document),
+                Some(Err(e)) => warnings.push(format!("Skipped attachment on {}: {}", key, e)),
+                None => warnings.push(format!(
+                    "Skipped attachment '{}' on {}: no data",
+                    title, key
+                )),
+            }
+        }
+    }
+
+    for (i, (full_url, binary)) in binaries.iter().enumerate() {
+        if used[i] {
+            continue;
+        }
+        let key = source_key(binary, *full_url);
+        let title = binary
+            .get("id")
+            .and_then(Value::as_str)
+            .map(|id| format!("FHIR Binary {}", id))
+            .unwrap_or_else(|| "FHIR Binary".to_string());
+        match binary.get("data").and_then(Value::as_str) {
+            Some(data) => match decode(
+                data,
+                binary.get("contentType").and_then(Value::as_str),
+                &title,
+            ) {
+                Ok(document) => pending.push(document),
+                Err(e) => warnings.push(format!("Skipped {}: {}", key, e)),
+            },
+            None => warnings.push(format!("Skipped {}: no data", key)),
+        }
+    }
+
+    pending
+}
+
+fn decode(data: &str, content_type: Option<&str>, title: &str) -> Result<PendingDocument> {
+    let bytes = BASE64
+        .decode(data.trim())
+        .context("attachment data is not valid base64")?;
+    if bytes.is_empty() {
+        bail!("attachment data is empty");
+    }
+    let content_type = content_type.unwrap_or("application/octet-stream");
+    Ok(PendingDocument {
+        title: title.to_string(),
+        extension: extension_for(content_type).to_string(),
+        imaging: content_type == "application/dicom",
+        bytes,
+    })
+}
+
+fn extension_for(content_type: &str) -> &'static str {
+    match content_type.split(';').next().unwrap_or("").trim() {
+        "application/pdf" => "pdf",
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/tiff" => "tiff",
+        "text/plain" => "txt",
+        "text/html" => "html",
+        "text/markdown" => "md",
+        "text/xml" | "application/xml" => "xml",
+        "application/json" | "application/fhir+json" => "json",
+        "application/dicom" => "dcm",
+        "application/rtf" | "text/rtf" => "rtf",
+        "application/msword" => "doc",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document" => "docx",
+        _ => "bin",
+    }
+}
+
+/// Write each decoded attachment to a scratch file and store it through the
+/// normal Document path, so it is hashed and named like any other Document.
+fn stage_documents(documents: &[PendingDocument]) -> Result<Vec<DocumentRef>> {
+    if documents.is_empty() {
+        return Ok(Vec::new());
+    }
+    let scratch = std::env::temp_dir().join(format!("gitehr-fhir-{}", Uuid::new_v4()));
+    fs::create_dir_all(&scratch)?;
+
+    let result = documents
+        .iter()
+        .map(|pending| {
+            let path: PathBuf = scratch.join(format!(
+                "{}.{}",
+                document::slugify(&pending.title),
+                pending.extension
+            ));
+            fs::write(&path, &pending.bytes)?;
+            document::add::stage(&DocumentSource {
+                path,
+                imaging: pending.imaging,
+                title: Some(pending.title.clone()),
+            })
+        })
+        .collect::<Result<Vec<_>>>();
+
+    let _ = fs::remove_dir_all(&scratch);
+    result
+}
+
+fn summary_body(source_name: &str, patient: Option<&Value>, summary: &FhirImportSummary) -> String {
+    let mut lines = vec![
+        format!("Imported FHIR R4 Bundle: {}", source_name),
+        String::new(),
+    ];
+
+    if let Some(patient) = patient {
+        let key = source_key(patient, None);
+        lines.push(if summary.demographics_updated {
+            format!("- Demographics: updated from {}", key)
+        } else {
+            format!("- Demographics: unchanged ({})", key)
+        });
+    }
+    for (label, tally) in [
+        ("Allergies", summary.allergies),
+        ("Vaccinations", summary.vaccinations),
+    ] {
+        if tally != Tally::default() {
+            lines.push(format!(
+                "- {}: {} added, {} updated, {} unchanged",
+                label, tally.added, tally.updated, tally.unchanged
+            ));
+        }
+    }
+    lines.push(format!("- Documents: {} added", summary.documents_added));
+    if !summary.not_imported.is_empty() {
+        let counts = summary
+            .not_imported
+            .iter()
+            .map(|(resource_type, count)| format!("{} ({})", resource_type, count))
+            .collect::<Vec<_>>()
+            .join(", ");
+        lines.push(format!(
+            "- Retained in the source Bundle only (no typed state yet): {}",
+            counts
+        ));
+    }
+    if !summary.warnings.is_empty() {
+        lines.push(format!("- Warnings: {}", summary.warnings.len()));
+    }
+
+    lines.join("\n")
+}
+
+fn print_warnings(warnings: &[String]) {
+    for warning in warnings {
+        eprintln!("Warning: {}", warning);
+    }
+}
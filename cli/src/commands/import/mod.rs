@@ -10,19 +10,25 @@ use walkdir::{DirEntry, WalkDir};
 use super::git;
 use super::journal::{self, is_journal_entry_file, parse_journal_file};
 
+pub mod fhir;
+
 #[derive(Copy, Clone, Debug, ValueEnum)]
 pub enum ImportMode {
     /// Import well-formed gitehr journal entries, preserved verbatim.
     Journal,
     /// Import documents of any format; each gets a journal entry linking to it.
     Documents,
+    /// Import a FHIR R4 Bundle into typed state and Documents, as one commit.
+    Fhir,
 }
 
-/// Import journal entries or documents from another gitehr instance.
+/// Import journal entries or documents from another gitehr instance, or a FHIR
+/// R4 Bundle.
 ///
 /// `source` may be a single file or a directory. Directories are walked
-/// recursively; files that don't match the mode are skipped.
-pub fn run(mode: ImportMode, source: &Path) -> Result<()> {
+/// recursively; files that don't match the mode are skipped. A FHIR import
+/// takes a single Bundle file.
+pub fn run(mode: ImportMode, source: &Path, dry_run: bool) -> Result<()> {
     if !PathBuf::from(".gitehr").exists() {
         bail!(
             "Not a GitEHR repository (or not in the repository root). Run 'gitehr store init' to create a new repository."
@@ -32,11 +38,19 @@ pub fn run(mode: ImportMode, source: &Path) -> Result<()> {
         bail!("No such file or directory: {}", source.display());
     }
 
-    let files = collect_files(source);
+    if dry_run && !matches!(mode, ImportMode::Fhir) {
+        bail!("--dry-run is only supported with --mode fhir");
+    }
 
     match mode {
-        ImportMode::Journal => import_journal(&files),
-        ImportMode::Documents => import_documents(&files),
+        ImportMode::Journal => import_journal(&collect_files(source)),
+        ImportMode::Documents => import_documents(&collect_files(source)),
+        ImportMode::Fhir => {
+            if source.is_dir() {
+                bail!("--mode fhir expects a single Bundle JSON file, not a directory");
+            }
+            fhir::run(source, dry_run).map(|_| ())
+        }
     }
 }
 
@@ -0,0 +1,67 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! `gitehr index`: manage the disposable journal index in `.gitehr/cache/`.
+//! Commands keep it up to date on their own; these are for inspecting it and
+//! for starting afresh.
+
+use anyhow::Result;
+use clap::Subcommand;
+use std::time::Instant;
+
+use super::journal::index;
+use super::typed_state;
+
+#[derive(Subcommand)]
+pub enum IndexCommands {
+    #[command(about = "Discard the journal index and build it again")]
+    Rebuild,
+    #[command(about = "Show how the journal index compares with the journal")]
+    Status {
+        #[arg(long, help = "Emit JSON for GUI or automation callers")]
+        json: bool,
+    },
+}
+
+pub fn run(command: IndexCommands) -> Result<()> {
+    typed_state::ensure_gitehr_repository()?;
+    match command {
+        IndexCommands::Rebuild => {
+            let started = Instant::now();
+            let count = index::rebuild()?;
+            println!(
+                "Indexed {} journal entr{} in {} ms",
+                count,
+                if count == 1 { "y" } else { "ies" },
+                started.elapsed().as_millis()
+            );
+            Ok(())
+        }
+        IndexCommands::Status { json } => {
+            let status = index::status()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&status)?);
+                return Ok(());
+            }
+            println!("Index:    {}", status.path);
+            if !status.enabled {
+                println!("State:    not used (no journal, or the repository is marked encrypted)");
+                return Ok(());
+            }
+            if !status.exists {
+                println!("State:    not built yet (the next journal command builds it)");
+            } else if status.current {
+                println!("State:    current");
+            } else {
+                println!("State:    out of date (the next journal command updates it)");
+            }
+            println!(
+                "Entries:  {} in journal/, {} indexed",
+                status.entries, status.indexed
+            );
+            println!("Pending:  {} to read from disk", status.pending);
+            println!("Size:     {} bytes", status.bytes);
+            Ok(())
+        }
+    }
+}
@@ -2,31 +2,141 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use anyhow::{Context, Result, bail};
+use chrono::Utc;
 use std::io::{IsTerminal, Read};
+use std::path::Path;
 use uuid::Uuid;
 
+use super::JournalEntry;
+use super::clinical_time::ClinicalTime;
+use super::drafts;
+use super::metadata::MetadataArgs;
+use super::templates;
+use super::thread::LinkArgs;
+use crate::commands::contributor;
+use crate::medmd;
+
 /// Add a journal entry. The body comes from, in order of precedence:
 /// `--file <path>` (or `--file -` for stdin), the inline `text` argument, piped
 /// stdin, or - on a terminal with none of those - your `$EDITOR`. The entry is
 /// written to `journal/` and git-committed immediately; entries are append-only.
-pub fn run(text: Option<String>, file: Option<String>) -> Result<()> {
-    let body = match (file, text) {
-        (Some(f), _) => read_source(&f)?,
-        (None, Some(t)) => t,
+///
+/// `at` is the clinical time the entry describes, if not now; it, the
+/// `metadata` (encounter type, tags, codes, location) and the `links` to
+/// earlier entries are checked before the editor opens.
+///
+/// `template` names a journal template to pre-populate the editor with, its
+/// placeholders filled from the record. An entry left exactly as the template
+/// was is not recorded.
+///
+/// With `validate`, and always after the editor closes, unknown or malformed
+/// Medical Markdown codes are reported on stderr before the commit. They
+/// never stop the entry being recorded.
+///
+/// Text written in the editor is saved as a draft (see `journal draft`) if
+/// the editor fails or the entry cannot be written.
+pub fn run(
+    text: Option<String>,
+    file: Option<String>,
+    at: Option<String>,
+    template: Option<String>,
+    metadata: MetadataArgs,
+    links: LinkArgs,
+    validate: bool,
+) -> Result<()> {
+    let (entry, prefill) = prepare(at, template, metadata, links)?;
+    let (body, edited) = match read_body(text, file, prefill.is_some())? {
+        Some(body) => (body, false),
+        None => (from_editor(prefill.as_deref().unwrap_or(""), &entry)?, true),
+    };
+
+    let body = body.trim();
+    if body.is_empty() {
+        bail!("Aborting: the journal entry is empty.");
+    }
+    if prefill.as_deref().map(str::trim) == Some(body) {
+        bail!("Aborting: the template was not filled in; the entry was not recorded.");
+    }
+    if validate || edited {
+        warn_codes(body);
+    }
+    // Stamped when recorded, not when the editor opened, so entries stay in
+    // the order they were committed.
+    let entry = JournalEntry {
+        timestamp: Utc::now(),
+        ..entry
+    };
+    let filename = match super::write_journal_entry(&entry, body) {
+        Ok(filename) => filename,
+        // Text typed in the editor exists nowhere else.
+        Err(e) if edited => {
+            let id = drafts::save(&entry, body)?;
+            return Err(e.context(format!(
+                "The entry was not recorded; your text was saved as draft {}. Commit it with `gitehr journal draft commit {}` once the problem is fixed",
+                id, id
+            )));
+        }
+        Err(e) => return Err(e),
+    };
+    println!("Created journal entry: {}", filename);
+    Ok(())
+}
+
+/// Front matter for a new entry or draft, with the clinical time, metadata
+/// and links checked, and the rendered template if one is named.
+pub(super) fn prepare(
+    at: Option<String>,
+    template: Option<String>,
+    metadata: MetadataArgs,
+    links: LinkArgs,
+) -> Result<(JournalEntry, Option<String>)> {
+    let clinical_time = at.as_deref().map(ClinicalTime::parse).transpose()?;
+    let entry = links.apply(metadata.apply(JournalEntry {
+        clinical_time,
+        ..JournalEntry::new(contributor::get_current_contributor())
+    })?)?;
+    let prefill = match template {
+        Some(name) => Some(templates::find(&name)?.render(&templates::placeholders()?)),
+        None => None,
+    };
+    Ok((entry, prefill))
+}
+
+/// The body from `--file`, the inline text or piped stdin, or `None` when
+/// it should be written in the editor: on a terminal with none of those.
+/// `templated` bodies always come from the editor.
+pub(super) fn read_body(
+    text: Option<String>,
+    file: Option<String>,
+    templated: bool,
+) -> Result<Option<String>> {
+    match (file, text) {
+        (Some(f), _) => Ok(Some(read_source(&f)?)),
+        (None, Some(t)) => Ok(Some(t)),
         (None, None) => {
             if std::io::stdin().is_terminal() {
-                from_editor()?
+                Ok(None)
+            } else if templated {
+                bail!("--template opens your $EDITOR, so it needs a terminal.");
             } else {
-                read_source("-")?
+                Ok(Some(read_source("-")?))
             }
         }
-    };
+    }
+}
 
-    let body = body.trim();
-    if body.is_empty() {
-        bail!("Aborting: the journal entry is empty.");
+/// Print a warning for each problem `medmd::validate` finds in `body`.
+pub(super) fn warn_codes(body: &str) {
+    let registry = medmd::Registry::load().unwrap_or_else(|e| {
+        eprintln!(
+            "Warning: {:#}; checking against the built-in codes only.",
+            e
+        );
+        medmd::Registry::default()
+    });
+    for diagnostic in medmd::validate(body, &registry) {
+        eprintln!("Warning: line {}: {}", diagnostic.line, diagnostic.message);
     }
-    super::create_journal_entry(body)
 }
 
 /// Read from a file path, or from stdin when the path is `-`.
@@ -43,21 +153,42 @@ fn read_source(path: &str) -> Result<String> {
     }
 }
 
-/// Open the user's editor on an ephemeral temp file and return what they wrote.
-fn from_editor() -> Result<String> {
+/// Open the user's editor on an ephemeral temp file holding `initial` and
+/// return what they wrote. If the editor fails, anything written is saved
+/// as a draft of `entry` rather than lost.
+fn from_editor(initial: &str, entry: &JournalEntry) -> Result<String> {
     let path = std::env::temp_dir().join(format!("gitehr-journal-{}.md", Uuid::new_v4()));
-    std::fs::write(&path, "")?;
+    std::fs::write(&path, initial)?;
 
-    let editor = std::env::var("EDITOR")
-        .or_else(|_| std::env::var("VISUAL"))
-        .unwrap_or_else(|_| "vi".to_string());
-    let status = std::process::Command::new(&editor).arg(&path).status()?;
+    let status = edit(&path);
 
     let content = std::fs::read_to_string(&path).unwrap_or_default();
     let _ = std::fs::remove_file(&path);
 
-    if !status.success() {
+    if !status? {
+        let written = content.trim();
+        if !written.is_empty() && written != initial.trim() {
+            let id = drafts::save(entry, written)?;
+            bail!(
+                "Editor exited with a non-zero status; the entry was not recorded. Your text was saved as draft {}: resume it with `gitehr journal draft edit {}`.",
+                id,
+                id
+            );
+        }
         bail!("Editor exited with a non-zero status; the entry was not recorded.");
     }
     Ok(content)
 }
+
+/// Run the user's editor (`$EDITOR`, then `$VISUAL`, then `vi`) on `path`.
+/// Returns whether it exited successfully.
+pub(super) fn edit(path: &Path) -> Result<bool> {
+    let editor = std::env::var("EDITOR")
+        .or_else(|_| std::env::var("VISUAL"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let status = std::process::Command::new(&editor)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to start the editor '{}'", editor))?;
+    Ok(status.success())
+}
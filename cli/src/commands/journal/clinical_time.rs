@@ -0,0 +1,259 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! When the care an entry describes happened, as distinct from when the
+//! entry was recorded.
+//!
+//! A clinical time is stored as written down to its precision: a year
+//! (`2026`), month (`2026-10`) or day (`2026-10-18`), or an instant with its
+//! UTC offset (`2026-10-18T14:30:00+01:00`). A partial date stands for the
+//! whole period, so it covers any filter bound inside it.
+
+use anyhow::Result;
+use chrono::{
+    DateTime, Datelike, Duration, FixedOffset, Local, NaiveDate, NaiveDateTime, SecondsFormat,
+    TimeZone, Utc,
+};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+/// Which of an entry's times to sort and filter by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TimeBasis {
+    /// When the entry was recorded (its `timestamp`).
+    #[default]
+    Recorded,
+    /// The entry's `clinical_time`, or when it was recorded if it has none.
+    Clinical,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ClinicalTime(String);
+
+/// A stored clinical time, resolved.
+enum Value {
+    Year(i32),
+    Month(i32, u32),
+    Day(NaiveDate),
+    Instant(DateTime<FixedOffset>),
+}
+
+impl ClinicalTime {
+    /// Parse `--at` input: `today`, `yesterday`, `YYYY`, `YYYY-MM`,
+    /// `YYYY-MM-DD`, a local `YYYY-MM-DD HH:MM[:SS]` (or with `T`), or RFC
+    /// 3339. Local times are stored with the local UTC offset. Times in the
+    /// future are refused.
+    pub fn parse(input: &str) -> Result<Self> {
+        Self::parse_in(input, &Local, Utc::now())
+    }
+
+    fn parse_in<Tz: TimeZone>(input: &str, zone: &Tz, now: DateTime<Utc>) -> Result<Self> {
+        let input = input.trim();
+        let today = now.with_timezone(zone).date_naive();
+        let text = match input.to_lowercase().as_str() {
+            "today" => today.format("%Y-%m-%d").to_string(),
+            "yesterday" => (today - Duration::days(1)).format("%Y-%m-%d").to_string(),
+            _ => match local_datetime(input) {
+                Some(naive) => zone
+                    .from_local_datetime(&naive)
+                    .earliest()
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("{} does not exist in the local time zone", input)
+                    })?
+                    .fixed_offset()
+                    .to_rfc3339_opts(SecondsFormat::Secs, false),
+                None => input.to_string(),
+            },
+        };
+        let time = ClinicalTime::try_from(text).map_err(|_| {
+            anyhow::anyhow!(
+                "Invalid clinical time '{}': use YYYY, YYYY-MM, YYYY-MM-DD, 'YYYY-MM-DD HH:MM' (local time), RFC 3339, today or yesterday",
+                input
+            )
+        })?;
+        // A partial date is in the future only once its period starts after
+        // `today` in the same zone it was read in.
+        let future = match time.value() {
+            Value::Year(y) => y > today.year(),
+            Value::Month(y, m) => (y, m) > (today.year(), today.month()),
+            Value::Day(d) => d > today,
+            Value::Instant(t) => t > now,
+        };
+        if future {
+            anyhow::bail!("Clinical time {} is in the future", input);
+        }
+        Ok(time)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The first instant the time covers.
+    pub fn start(&self) -> DateTime<Utc> {
+        match self.value() {
+            Value::Year(y) => day_start(y, 1, 1),
+            Value::Month(y, m) => day_start(y, m, 1),
+            Value::Day(d) => d.and_hms_opt(0, 0, 0).expect("midnight").and_utc(),
+            Value::Instant(t) => t.with_timezone(&Utc),
+        }
+    }
+
+    /// The last instant the time covers.
+    pub fn end(&self) -> DateTime<Utc> {
+        let next = match self.value() {
+            Value::Year(y) => day_start(y + 1, 1, 1),
+            Value::Month(y, 12) => day_start(y + 1, 1, 1),
+            Value::Month(y, m) => day_start(y, m + 1, 1),
+            Value::Day(d) => {
+                d.and_hms_opt(0, 0, 0).expect("midnight").and_utc() + Duration::days(1)
+            }
+            Value::Instant(t) => return t.with_timezone(&Utc),
+        };
+        next - Duration::milliseconds(1)
+    }
+
+    /// The day, for a day or an instant (in its own offset).
+    pub fn date(&self) -> Option<NaiveDate> {
+        match self.value() {
+            Value::Day(d) => Some(d),
+            Value::Instant(t) => Some(t.date_naive()),
+            _ => None,
+        }
+    }
+
+    /// For tables: partial dates as written, instants as
+    /// `YYYY-MM-DD HH:MM` in their own offset.
+    pub fn short(&self) -> String {
+        match self.value() {
+            Value::Instant(t) => t.format("%Y-%m-%d %H:%M").to_string(),
+            _ => self.0.clone(),
+        }
+    }
+
+    fn value(&self) -> Value {
+        resolve(&self.0).expect("validated when constructed")
+    }
+}
+
+impl TryFrom<String> for ClinicalTime {
+    type Error = String;
+
+    /// Accepts the stored forms only; see [`ClinicalTime::parse`] for input.
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        match resolve(&text) {
+            Some(_) => Ok(ClinicalTime(text)),
+            None => Err(format!("invalid clinical_time '{}'", text)),
+        }
+    }
+}
+
+impl From<ClinicalTime> for String {
+    fn from(time: ClinicalTime) -> Self {
+        time.0
+    }
+}
+
+impl std::fmt::Display for ClinicalTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+fn resolve(text: &str) -> Option<Value> {
+    let digits = |s: &str, n: usize| s.len() == n && s.chars().all(|c| c.is_ascii_digit());
+    let parts: Vec<&str> = text.split('-').collect();
+    match parts.as_slice() {
+        [y] if digits(y, 4) => Some(Value::Year(y.parse().ok()?)),
+        [y, m] if digits(y, 4) && digits(m, 2) => {
+            let (y, m) = (y.parse().ok()?, m.parse().ok()?);
+            NaiveDate::from_ymd_opt(y, m, 1)?;
+            Some(Value::Month(y, m))
+        }
+        [_, _, d] if digits(d, 2) => NaiveDate::parse_from_str(text, "%Y-%m-%d")
+            .ok()
+            .filter(|date| date.year() >= 1000)
+            .map(Value::Day),
+        _ => DateTime::parse_from_rfc3339(text).ok().map(Value::Instant),
+    }
+}
+
+/// `YYYY-MM-DD HH:MM[:SS]`, with a space or `T`, and no offset.
+fn local_datetime(input: &str) -> Option<NaiveDateTime> {
+    let input = input.replacen(' ', "T", 1);
+    ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&input, format).ok())
+}
+
+fn day_start(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+    NaiveDate::from_ymd_opt(year, month, day)
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .expect("valid date")
+        .and_utc()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(input: &str) -> Result<ClinicalTime> {
+        let zone = FixedOffset::east_opt(3600).unwrap();
+        let now = DateTime::parse_from_rfc3339("2026-10-19T09:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        ClinicalTime::parse_in(input, &zone, now)
+    }
+
+    #[test]
+    fn test_parse_inputs() {
+        assert_eq!(parse("yesterday").unwrap().as_str(), "2026-10-18");
+        assert_eq!(parse("2026-10").unwrap().as_str(), "2026-10");
+        assert_eq!(
+            parse("2026-10-18 14:30").unwrap().as_str(),
+            "2026-10-18T14:30:00+01:00"
+        );
+        assert_eq!(
+            parse("2026-10-18T14:30:00Z").unwrap().as_str(),
+            "2026-10-18T14:30:00Z"
+        );
+        for bad in ["2026-13", "2026-02-30", "18/10/2026", "soon", "26"] {
+            assert!(parse(bad).is_err(), "{}", bad);
+        }
+        assert!(parse("2026-10-20").is_err(), "future");
+    }
+
+    #[test]
+    fn test_today_is_read_in_the_local_zone() {
+        // 00:30 on the 20th at +01:00, still the 19th in UTC.
+        let zone = FixedOffset::east_opt(3600).unwrap();
+        let now = DateTime::parse_from_rfc3339("2026-10-19T23:30:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        let parse = |input| ClinicalTime::parse_in(input, &zone, now);
+        assert_eq!(parse("today").unwrap().as_str(), "2026-10-20");
+        assert_eq!(parse("yesterday").unwrap().as_str(), "2026-10-19");
+        assert!(parse("2026-10-20").is_ok());
+        assert!(parse("2026-10").is_ok());
+        assert!(parse("2026-10-20 00:20").is_ok());
+        assert!(parse("2026-10-20 00:40").is_err(), "future instant");
+        assert!(parse("2026-10-21").is_err(), "future day");
+        assert!(parse("2026-11").is_err(), "future month");
+        assert!(parse("2027").is_err(), "future year");
+    }
+
+    #[test]
+    fn test_partial_dates_cover_their_period() {
+        let month = parse("2026-02").unwrap();
+        assert_eq!(month.start().to_rfc3339(), "2026-02-01T00:00:00+00:00");
+        assert_eq!(month.end().to_rfc3339(), "2026-02-28T23:59:59.999+00:00");
+        let year = parse("2025").unwrap();
+        assert_eq!(year.end().to_rfc3339(), "2025-12-31T23:59:59.999+00:00");
+        let instant = parse("2026-10-18 14:30").unwrap();
+        assert_eq!(instant.start(), instant.end());
+        assert_eq!(instant.short(), "2026-10-18 14:30");
+        assert_eq!(instant.date(), NaiveDate::from_ymd_opt(2026, 10, 18));
+        assert_eq!(month.date(), None);
+    }
+}
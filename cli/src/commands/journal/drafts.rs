@@ -0,0 +1,360 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Unfinished journal entries.
+//!
+//! A draft is `.gitehr/drafts/<id>.md`, laid out like a journal entry: the
+//! front matter it will be recorded with, then the body. The id is the UTC
+//! time the draft was started, `YYYYMMDD-HHMMSS`. Drafts are never committed
+//! (the directory carries its own `.gitignore`) and are plain text even in an
+//! encrypted repository. Committing a draft records it as a new entry,
+//! timestamped and attributed when it is committed, and deletes the draft.
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Local, Utc};
+use clap::Subcommand;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::add;
+use super::metadata::{self, ConceptCode, MetadataArgs};
+use super::thread::{self, LinkArgs};
+use super::{JournalEntry, ParsedEntry, filter, parse_journal_file};
+use crate::commands::contributor;
+
+pub const DRAFTS_DIR: &str = ".gitehr/drafts";
+
+#[derive(Subcommand)]
+pub enum DraftCommands {
+    #[command(
+        about = "Start a draft (inline text, --file <path>, --file - for stdin, or your editor)"
+    )]
+    New {
+        #[arg(help = "Draft text. Omit (on a terminal) to open your $EDITOR, or use --file.")]
+        text: Option<String>,
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with = "text",
+            help = "Read the draft from a file, or '-' for stdin"
+        )]
+        file: Option<String>,
+        #[arg(
+            long,
+            value_name = "WHEN",
+            help = "When the care happened: YYYY[-MM[-DD]], 'YYYY-MM-DD HH:MM' (local), RFC 3339, today or yesterday"
+        )]
+        at: Option<String>,
+        #[arg(
+            long,
+            value_name = "NAME",
+            conflicts_with_all = ["text", "file"],
+            help = "Start the draft in your $EDITOR from a template"
+        )]
+        template: Option<String>,
+        #[command(flatten)]
+        metadata: MetadataArgs,
+        #[command(flatten)]
+        links: LinkArgs,
+    },
+    #[command(aliases = ["ls"], about = "List drafts, oldest first")]
+    List {
+        #[arg(long, help = "Emit JSON for GUI or automation callers")]
+        json: bool,
+    },
+    #[command(about = "Open a draft in your $EDITOR")]
+    Edit {
+        #[arg(help = "Draft id (or LATEST)")]
+        draft: String,
+    },
+    #[command(about = "Record a draft as a journal entry and delete the draft")]
+    Commit {
+        #[arg(help = "Draft id (or LATEST)")]
+        draft: String,
+        #[arg(
+            long,
+            help = "Warn about unknown or malformed Medical Markdown codes (the entry is still recorded)"
+        )]
+        validate: bool,
+    },
+    #[command(about = "Delete a draft without recording it")]
+    Discard {
+        #[arg(help = "Draft id (or LATEST)")]
+        draft: String,
+    },
+}
+
+pub fn run(command: DraftCommands) -> Result<()> {
+    match command {
+        DraftCommands::New {
+            text,
+            file,
+            at,
+            template,
+            metadata,
+            links,
+        } => new(text, file, at, template, metadata, links),
+        DraftCommands::List { json } => print(&list()?, json),
+        DraftCommands::Edit { draft } => {
+            let path = path(&resolve(&draft)?);
+            if !add::edit(&path)? {
+                bail!(
+                    "Editor exited with a non-zero status; the draft keeps whatever the editor saved."
+                );
+            }
+            warn_unreadable(&path);
+            Ok(())
+        }
+        DraftCommands::Commit { draft, validate } => {
+            let id = resolve(&draft)?;
+            let filename = commit(&id, validate)?;
+            println!("Created journal entry: {} (from draft {})", filename, id);
+            Ok(())
+        }
+        DraftCommands::Discard { draft } => {
+            let id = resolve(&draft)?;
+            discard(&id)?;
+            println!("Discarded draft {}", id);
+            Ok(())
+        }
+    }
+}
+
+/// `journal draft new`. Text given on the command line is saved as it is;
+/// otherwise the editor is opened on the draft file itself, so whatever it
+/// saves survives the editor or the terminal going away.
+fn new(
+    text: Option<String>,
+    file: Option<String>,
+    at: Option<String>,
+    template: Option<String>,
+    metadata: MetadataArgs,
+    links: LinkArgs,
+) -> Result<()> {
+    let (entry, prefill) = add::prepare(at, template, metadata, links)?;
+    let id = match add::read_body(text, file, prefill.is_some())? {
+        Some(body) => {
+            let body = body.trim();
+            if body.is_empty() {
+                bail!("Aborting: the draft is empty.");
+            }
+            save(&entry, body)?
+        }
+        None => {
+            let id = save(&entry, prefill.as_deref().unwrap_or(""))?;
+            let path = path(&id);
+            if !add::edit(&path)? {
+                bail!(
+                    "Editor exited with a non-zero status; draft {} keeps whatever the editor saved.",
+                    id
+                );
+            }
+            if parse_journal_file(&path).is_ok_and(|d| d.content.is_empty()) {
+                fs::remove_file(&path)?;
+                bail!("Aborting: the draft is empty.");
+            }
+            warn_unreadable(&path);
+            id
+        }
+    };
+    println!(
+        "Saved draft {}. Record it with `gitehr journal draft commit {}`.",
+        id, id
+    );
+    Ok(())
+}
+
+/// Save `body` as a new draft to be recorded with `entry`'s front matter,
+/// returning its id.
+pub fn save(entry: &JournalEntry, body: &str) -> Result<String> {
+    fs::create_dir_all(DRAFTS_DIR)?;
+    let ignore = Path::new(DRAFTS_DIR).join(".gitignore");
+    if !ignore.exists() {
+        fs::write(
+            &ignore,
+            "# Unfinished journal entries; never committed.\n*\n",
+        )?;
+    }
+    let now = Utc::now();
+    let stem = now.format("%Y%m%d-%H%M%S").to_string();
+    let mut id = stem.clone();
+    let mut n = 1;
+    while path(&id).exists() {
+        n += 1;
+        id = format!("{}-{}", stem, n);
+    }
+    let draft = JournalEntry {
+        timestamp: now,
+        ..entry.clone()
+    };
+    fs::write(path(&id), super::file_content(&draft, body)?)
+        .with_context(|| format!("Failed to save draft {}", id))?;
+    Ok(id)
+}
+
+pub struct Draft {
+    pub id: String,
+    /// When the draft file was last written.
+    pub updated: DateTime<Utc>,
+    /// The draft parsed as an entry, or why it cannot be.
+    pub parsed: Result<ParsedEntry, String>,
+}
+
+/// Every draft, oldest first.
+pub fn list() -> Result<Vec<Draft>> {
+    let mut drafts = Vec::new();
+    for id in ids()? {
+        let path = path(&id);
+        let updated = fs::metadata(&path)?.modified()?.into();
+        let parsed = parse_journal_file(&path).map_err(|e| e.to_string());
+        drafts.push(Draft {
+            id,
+            updated,
+            parsed,
+        });
+    }
+    Ok(drafts)
+}
+
+/// Resolve a draft reference: an id, optionally with `.md`, or `LATEST` for
+/// the most recently started.
+pub fn resolve(reference: &str) -> Result<String> {
+    let ids = ids()?;
+    if reference == "LATEST" {
+        return ids
+            .last()
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("There are no drafts"));
+    }
+    let id = reference.trim_end_matches(".md");
+    if ids.iter().any(|i| i == id) {
+        Ok(id.to_string())
+    } else {
+        bail!("No draft '{}'. See `gitehr journal draft list`.", reference)
+    }
+}
+
+/// Record draft `id` as a journal entry, timestamped now and attributed to
+/// the current contributor, then delete the draft. Returns the entry's
+/// record path.
+pub fn commit(id: &str, validate: bool) -> Result<String> {
+    let path = path(id);
+    let draft = parse_journal_file(&path).with_context(|| {
+        format!(
+            "Draft {} cannot be read; fix it with `gitehr journal draft edit {}`",
+            id, id
+        )
+    })?;
+    if draft.content.is_empty() {
+        bail!("Draft {} is empty; nothing was recorded.", id);
+    }
+    // Front matter may have been edited by hand.
+    let meta = draft.metadata;
+    for tag in &meta.tags {
+        metadata::normalise_tag(tag)?;
+    }
+    for code in &meta.codes {
+        ConceptCode::parse(&code.code)?;
+    }
+    thread::check(&meta)?;
+    if validate {
+        add::warn_codes(&draft.content);
+    }
+    let entry = JournalEntry {
+        timestamp: Utc::now(),
+        author: contributor::get_current_contributor(),
+        ..meta
+    };
+    let filename = super::write_journal_entry(&entry, &draft.content)?;
+    fs::remove_file(&path)
+        .with_context(|| format!("Recorded {}, but could not delete draft {}", filename, id))?;
+    Ok(filename)
+}
+
+pub fn discard(id: &str) -> Result<()> {
+    fs::remove_file(path(id)).with_context(|| format!("Failed to delete draft {}", id))
+}
+
+fn path(id: &str) -> PathBuf {
+    Path::new(DRAFTS_DIR).join(format!("{}.md", id))
+}
+
+/// Draft ids, oldest first.
+fn ids() -> Result<Vec<String>> {
+    let dir = Path::new(DRAFTS_DIR);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut ids = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let name = entry?.file_name().to_string_lossy().to_string();
+        if let Some(id) = name.strip_suffix(".md") {
+            ids.push(id.to_string());
+        }
+    }
+    ids.sort();
+    Ok(ids)
+}
+
+/// After an edit, say so if the draft can no longer be committed.
+fn warn_unreadable(path: &Path) {
+    if let Err(e) = parse_journal_file(&path.to_path_buf()) {
+        eprintln!(
+            "Warning: {} cannot be read as an entry ({}); fix its front matter before committing it.",
+            path.display(),
+            e
+        );
+    }
+}
+
+#[derive(Serialize)]
+struct DraftOutput<'a> {
+    id: &'a str,
+    updated: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<&'a JournalEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+}
+
+fn print(drafts: &[Draft], json: bool) -> Result<()> {
+    if json {
+        let output: Vec<_> = drafts
+            .iter()
+            .map(|d| DraftOutput {
+                id: &d.id,
+                updated: d.updated,
+                metadata: d.parsed.as_ref().ok().map(|p| &p.metadata),
+                content: d.parsed.as_ref().ok().map(|p| p.content.as_str()),
+                error: d.parsed.as_ref().err().map(String::as_str),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+    if drafts.is_empty() {
+        println!("No drafts.");
+        return Ok(());
+    }
+    for draft in drafts {
+        let line = match &draft.parsed {
+            Ok(parsed) => filter::first_line(&parsed.content).to_string(),
+            Err(_) => "(front matter cannot be read)".to_string(),
+        };
+        println!(
+            "{:<17}  {}  {}",
+            draft.id,
+            draft.updated.with_timezone(&Local).format("%Y-%m-%d %H:%M"),
+            line
+        );
+    }
+    println!(
+        "\n({} draft{})",
+        drafts.len(),
+        if drafts.len() == 1 { "" } else { "s" }
+    );
+    Ok(())
+}
@@ -0,0 +1,444 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Selecting journal entries by date, author, Documents, tags, metadata and
+//! text, and printing them as a table or JSON. Shared by `journal list` and
+//! `journal search`.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use super::clinical_time::{ClinicalTime, TimeBasis};
+use super::metadata::{ConceptCode, EncounterType};
+use super::{DocumentRef, ParsedEntry, parsed_entries};
+use crate::commands::contributor;
+use crate::utils::parse_bound;
+
+#[derive(Args, Default)]
+pub struct FilterArgs {
+    #[arg(
+        long,
+        help = "Only entries on or after this date (YYYY-MM-DD or RFC 3339)"
+    )]
+    pub since: Option<String>,
+    #[arg(
+        long,
+        help = "Only entries on or before this date (YYYY-MM-DD or RFC 3339)"
+    )]
+    pub until: Option<String>,
+    #[arg(
+        long,
+        help = "Only entries by this contributor (id, or part of their name)"
+    )]
+    pub author: Option<String>,
+    #[arg(long, help = "Only entries that reference Documents")]
+    pub has_documents: bool,
+    #[arg(
+        long = "tag",
+        value_name = "TAG",
+        help = "Only entries tagged TAG, with --tag or #TAG in the body (repeatable; all must match)"
+    )]
+    pub tags: Vec<String>,
+    #[arg(
+        long = "type",
+        value_enum,
+        help = "Only entries of this encounter type"
+    )]
+    pub encounter_type: Option<EncounterType>,
+    #[arg(
+        long = "code",
+        value_name = "SCTID",
+        help = "Only entries coded with this SNOMED CT concept (repeatable; all must match)"
+    )]
+    pub codes: Vec<String>,
+    #[arg(
+        long,
+        value_name = "TEXT",
+        help = "Only entries whose location contains TEXT (case-insensitive)"
+    )]
+    pub location: Option<String>,
+    #[arg(
+        long,
+        value_enum,
+        default_value_t,
+        help = "Order, show and filter --since/--until by when entries were recorded or their clinical time"
+    )]
+    pub time: TimeBasis,
+    #[arg(long, help = "Emit JSON for GUI or automation callers")]
+    pub json: bool,
+}
+
+/// A text query over entry bodies.
+pub enum Query {
+    /// Words and `"quoted phrases"`, all of which must appear
+    /// (case-insensitive).
+    Terms(Vec<String>),
+    Regex(Regex),
+}
+
+impl Query {
+    pub fn parse(text: &str, regex: bool) -> Result<Self> {
+        if regex {
+            let re = RegexBuilder::new(text)
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| anyhow::anyhow!("Invalid regular expression: {}", e))?;
+            return Ok(Query::Regex(re));
+        }
+        let mut terms = Vec::new();
+        for (i, part) in text.split('"').enumerate() {
+            if i % 2 == 1 {
+                // Inside quotes: a phrase, with its spacing normalised.
+                let phrase = part.split_whitespace().collect::<Vec<_>>().join(" ");
+                if !phrase.is_empty() {
+                    terms.push(phrase.to_lowercase());
+                }
+            } else {
+                terms.extend(part.split_whitespace().map(str::to_lowercase));
+            }
+        }
+        if terms.is_empty() {
+            anyhow::bail!("Empty search query");
+        }
+        Ok(Query::Terms(terms))
+    }
+
+    pub fn matches(&self, content: &str) -> bool {
+        match self {
+            Query::Regex(re) => re.is_match(content),
+            Query::Terms(terms) => {
+                let text = content
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ")
+                    .to_lowercase();
+                terms.iter().all(|term| text.contains(term.as_str()))
+            }
+        }
+    }
+}
+
+/// Everything an entry must satisfy to be selected.
+#[derive(Default)]
+pub struct Filter {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub author: Option<String>,
+    pub has_documents: bool,
+    pub tags: Vec<String>,
+    pub encounter_type: Option<EncounterType>,
+    /// SNOMED CT concept ids.
+    pub codes: Vec<String>,
+    pub location: Option<String>,
+    pub query: Option<Query>,
+    /// Which time `since` and `until` apply to, and the order of the results.
+    pub time: TimeBasis,
+}
+
+impl Filter {
+    pub fn from_args(args: &FilterArgs, query: Option<Query>) -> Result<Self> {
+        Ok(Filter {
+            since: args
+                .since
+                .as_deref()
+                .map(|s| parse_bound(s, false))
+                .transpose()?,
+            until: args
+                .until
+                .as_deref()
+                .map(|s| parse_bound(s, true))
+                .transpose()?,
+            author: args.author.clone(),
+            has_documents: args.has_documents,
+            tags: args.tags.clone(),
+            encounter_type: args.encounter_type,
+            codes: args
+                .codes
+                .iter()
+                .map(|code| ConceptCode::parse(code).map(|c| c.code))
+                .collect::<Result<_>>()?,
+            location: args.location.clone(),
+            query,
+            time: args.time,
+        })
+    }
+
+    /// Does `entry` pass? `names` maps contributor ids to display names, so
+    /// `--author` can match either.
+    pub fn matches(&self, entry: &ParsedEntry, names: &HashMap<String, String>) -> bool {
+        let meta = &entry.metadata;
+        // A partial clinical date matches if any part of it is in range.
+        let (start, end) = meta.time_span(self.time);
+        if self.since.is_some_and(|since| end < since)
+            || self.until.is_some_and(|until| start > until)
+        {
+            return false;
+        }
+        if let Some(wanted) = &self.author {
+            let wanted = wanted.to_lowercase();
+            let Some(id) = meta.author.as_deref() else {
+                return false;
+            };
+            let by_name = names
+                .get(id)
+                .is_some_and(|name| name.to_lowercase().contains(&wanted));
+            if id.to_lowercase() != wanted && !by_name {
+                return false;
+            }
+        }
+        if self.has_documents && meta.documents.as_ref().is_none_or(Vec::is_empty) {
+            return false;
+        }
+        if self
+            .encounter_type
+            .is_some_and(|wanted| meta.encounter_type != Some(wanted))
+        {
+            return false;
+        }
+        if !self
+            .codes
+            .iter()
+            .all(|wanted| meta.codes.iter().any(|c| &c.code == wanted))
+        {
+            return false;
+        }
+        if let Some(wanted) = &self.location {
+            let wanted = wanted.to_lowercase();
+            if !meta
+                .location
+                .as_ref()
+                .is_some_and(|l| l.to_lowercase().contains(&wanted))
+            {
+                return false;
+            }
+        }
+        if !self.tags.is_empty() {
+            let tags = entry_tags(entry);
+            let tagged = |tag: &String| {
+                let tag = tag.trim_start_matches('#').to_lowercase();
+                tags.contains(&tag)
+            };
+            if !self.tags.iter().all(tagged) {
+                return false;
+            }
+        }
+        self.query
+            .as_ref()
+            .is_none_or(|q| q.matches(&entry.content))
+    }
+}
+
+/// Journal entries passing `filter`, oldest first by `filter.time`.
+pub fn matching_entries(filter: &Filter) -> Result<Vec<ParsedEntry>> {
+    let names = contributor::contributor_names();
+    let mut entries: Vec<ParsedEntry> = parsed_entries()?
+        .into_iter()
+        .filter(|entry| filter.matches(entry, &names))
+        .collect();
+    sort_entries(&mut entries, filter.time);
+    Ok(entries)
+}
+
+/// Order entries, already in recorded order, by `time`. Entries with the
+/// same start keep their recorded order.
+pub fn sort_entries(entries: &mut [ParsedEntry], time: TimeBasis) {
+    if time == TimeBasis::Clinical {
+        entries.sort_by_key(|entry| entry.metadata.time_span(time).0);
+    }
+}
+
+/// `#tags` in an entry body, lowercased, in order of first appearance. A tag
+/// starts with a letter and runs over letters, digits, `-` and `_`; Markdown
+/// headings (`# Title`) and references like `#12` are not tags.
+pub fn hashtags(content: &str) -> Vec<String> {
+    let mut tags: Vec<String> = Vec::new();
+    for word in content.split_whitespace() {
+        let Some(rest) = word.strip_prefix('#') else {
+            continue;
+        };
+        let tag: String = rest
+            .chars()
+            .take_while(|c| c.is_alphanumeric() || matches!(c, '-' | '_'))
+            .collect();
+        let tag = tag.trim_end_matches(['-', '_']).to_lowercase();
+        if tag.chars().next().is_some_and(char::is_alphabetic) && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// An entry's front-matter tags, then any other `#tags` in its body.
+pub fn entry_tags(entry: &ParsedEntry) -> Vec<String> {
+    let mut tags = entry.metadata.tags.clone();
+    for tag in hashtags(&entry.content) {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+/// The first non-blank line of a body, without Markdown heading marks.
+pub fn first_line(content: &str) -> &str {
+    let line = content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("");
+    let unheaded = line.trim_start_matches('#');
+    if unheaded.len() < line.len() && unheaded.starts_with(' ') {
+        unheaded.trim_start()
+    } else {
+        line
+    }
+}
+
+#[derive(Serialize)]
+struct EntryOutput<'a> {
+    filename: &'a str,
+    timestamp: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    clinical_time: Option<&'a ClinicalTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author_name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encounter_type: Option<EncounterType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    location: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    codes: &'a [ConceptCode],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    documents: Option<&'a Vec<DocumentRef>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    in_reply_to: Option<&'a str>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    related: &'a [String],
+    tags: Vec<String>,
+    content: &'a str,
+}
+
+/// The author's display name, or their id if they are not a known contributor.
+pub(super) fn author_name<'a>(
+    entry: &'a ParsedEntry,
+    names: &'a HashMap<String, String>,
+) -> Option<&'a str> {
+    entry
+        .metadata
+        .author
+        .as_deref()
+        .map(|id| names.get(id).map(String::as_str).unwrap_or(id))
+}
+
+/// Longest first line shown in the table.
+const LINE_WIDTH: usize = 72;
+
+/// Print entries as JSON, or as a table of time (recorded or clinical, by
+/// `time`), author and first line.
+pub fn print(entries: &[ParsedEntry], time: TimeBasis, json: bool, empty: &str) -> Result<()> {
+    let names = contributor::contributor_names();
+    let author_name = |entry| author_name(entry, &names);
+
+    if json {
+        let output: Vec<_> = entries
+            .iter()
+            .map(|entry| EntryOutput {
+                filename: &entry.filename,
+                timestamp: entry.metadata.timestamp,
+                clinical_time: entry.metadata.clinical_time.as_ref(),
+                author: entry.metadata.author.as_deref(),
+                author_name: author_name(entry),
+                encounter_type: entry.metadata.encounter_type,
+                location: entry.metadata.location.as_deref(),
+                codes: &entry.metadata.codes,
+                documents: entry.metadata.documents.as_ref(),
+                in_reply_to: entry.metadata.in_reply_to.as_deref(),
+                related: &entry.metadata.related,
+                tags: entry_tags(entry),
+                content: &entry.content,
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if entries.is_empty() {
+        println!("{}", empty);
+        return Ok(());
+    }
+    let width = entries
+        .iter()
+        .map(|e| author_name(e).unwrap_or("-").chars().count())
+        .max()
+        .unwrap_or(1);
+    for entry in entries {
+        let line = first_line(&entry.content);
+        let line = if line.chars().count() > LINE_WIDTH {
+            let cut: String = line.chars().take(LINE_WIDTH - 1).collect();
+            format!("{}…", cut)
+        } else {
+            line.to_string()
+        };
+        let when = match (time, &entry.metadata.clinical_time) {
+            (TimeBasis::Clinical, Some(clinical)) => clinical.short(),
+            _ => entry
+                .metadata
+                .timestamp
+                .format("%Y-%m-%d %H:%M")
+                .to_string(),
+        };
+        println!(
+            "{:<16}  {:<width$}  {}",
+            when,
+            author_name(entry).unwrap_or("-"),
+            line,
+        );
+    }
+    println!(
+        "\n({} entr{})",
+        entries.len(),
+        if entries.len() == 1 { "y" } else { "ies" }
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_terms_and_phrases() {
+        let q = Query::parse(r#"chest "pain  on exertion""#, false).unwrap();
+        assert!(q.matches("Complains of Chest pain on\nexertion since Monday."));
+        assert!(!q.matches("Chest pain at rest; exertion tolerated."));
+        assert!(!q.matches("Exertional chest pain."));
+        assert!(Query::parse(" \"\" ", false).is_err());
+
+        let re = Query::parse(r"\bbp \d+/\d+", true).unwrap();
+        assert!(re.matches("BP 142/90 today"));
+        assert!(!re.matches("BP normal"));
+        assert!(Query::parse("(", true).is_err());
+    }
+
+    #[test]
+    fn test_hashtags() {
+        assert_eq!(
+            hashtags("# Review\n#Diabetes follow-up, see #12. #foot-check. #diabetes"),
+            ["diabetes", "foot-check"]
+        );
+        assert!(hashtags("no tags here").is_empty());
+    }
+
+    #[test]
+    fn test_first_line() {
+        assert_eq!(first_line("\n\n## Clinic review\nBody"), "Clinic review");
+        assert_eq!(first_line("#asthma review"), "#asthma review");
+        assert_eq!(first_line(""), "");
+    }
+}
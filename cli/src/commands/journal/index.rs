@@ -0,0 +1,374 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! A disposable on-disk index of journal entries, so large journals are not
+//! re-read and re-parsed on every command.
+//!
+//! `.gitehr/cache/journal-index.json` holds each committed entry's parsed
+//! front matter and body, keyed by filename and the blob id Git has for it,
+//! with the modification time and size the entry file had when indexed. The
+//! index is used as it is only while the journal directory, the Git index
+//! and every entry file's time and size are unchanged. Otherwise it is
+//! brought up to date from `git ls-files`: only entries whose blob is new
+//! are parsed again. Entries not yet committed, or edited since, are always
+//! read from disk and never cached.
+//!
+//! The cache is never committed (it carries its own `.gitignore`), can be
+//! deleted at any time, and is not written in repositories marked encrypted,
+//! so plaintext copies of entries do not sit outside the journal.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use super::{JournalEntry, ParsedEntry, is_journal_entry_file, parse_journal_file};
+use crate::commands::git;
+
+pub const CACHE_DIR: &str = ".gitehr/cache";
+pub const INDEX_FILE: &str = ".gitehr/cache/journal-index.json";
+
+/// Bumped whenever the layout changes; an index in another format is rebuilt.
+const FORMAT: u32 = 3;
+
+/// A stamp this close to the time it is taken may be followed by changes
+/// that leave it unchanged, on filesystems with coarse timestamps, so it is
+/// not trusted.
+const RACY: Duration = Duration::from_secs(2);
+
+#[derive(Serialize, Deserialize)]
+struct Index {
+    format: u32,
+    /// What the journal looked like when every entry on disk was indexed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    stamp: Option<Stamp>,
+    entries: Vec<IndexedEntry>,
+}
+
+/// Modification times of the journal directory and of Git's index, which
+/// change when entries are added, removed or committed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Stamp {
+    journal: u128,
+    git_index: u128,
+    git_index_len: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+struct FileStamp {
+    modified: u128,
+    len: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexedEntry {
+    filename: String,
+    blob: String,
+    /// The entry file's modification time and size when it was indexed, so
+    /// an edit in place is noticed without asking Git.
+    file: FileStamp,
+    metadata: JournalEntry,
+    content: String,
+}
+
+impl From<IndexedEntry> for ParsedEntry {
+    fn from(entry: IndexedEntry) -> Self {
+        ParsedEntry {
+            filename: entry.filename,
+            metadata: entry.metadata,
+            content: entry.content,
+        }
+    }
+}
+
+/// Filenames only, for resolving entry references without deserialising
+/// every body.
+#[derive(Deserialize)]
+struct IndexNames {
+    format: u32,
+    stamp: Option<Stamp>,
+    entries: Vec<IndexedName>,
+}
+
+#[derive(Deserialize)]
+struct IndexedName {
+    filename: String,
+}
+
+/// Every journal entry, oldest first, from the index where it is current.
+/// Entries that fail to parse are skipped with a warning on stderr.
+pub fn entries() -> Result<Vec<ParsedEntry>> {
+    if !enabled() {
+        return scan();
+    }
+    let mut cached = read_index();
+    if let Some(index) = cached.take_if(|i| is_current(i)) {
+        return Ok(index.entries.into_iter().map(ParsedEntry::from).collect());
+    }
+    match refresh(cached) {
+        Ok((entries, index)) => {
+            // The index is only a cache; failing to save it costs speed, not data.
+            let _ = write_index(&index);
+            Ok(entries)
+        }
+        // Not a Git repository, or Git is unavailable.
+        Err(_) => scan(),
+    }
+}
+
+/// Every journal entry filename, oldest first. Reads only the filenames from
+/// a current index.
+pub fn filenames() -> Result<Vec<String>> {
+    if enabled()
+        && let Some(names) = fs::read_to_string(INDEX_FILE)
+            .ok()
+            .and_then(|json| serde_json::from_str::<IndexNames>(&json).ok())
+        && names.format == FORMAT
+        && names.stamp.is_some()
+        && names.stamp == current_stamp()
+    {
+        return Ok(names.entries.into_iter().map(|e| e.filename).collect());
+    }
+    Ok(entries()?.into_iter().map(|e| e.filename).collect())
+}
+
+/// Discard the index and build it again from every entry. Returns the number
+/// of entries indexed.
+pub fn rebuild() -> Result<usize> {
+    if !Path::new("journal").exists() {
+        anyhow::bail!("No journal directory.");
+    }
+    if is_encrypted() {
+        anyhow::bail!("The repository is marked encrypted; its journal is not indexed.");
+    }
+    let _ = fs::remove_file(INDEX_FILE);
+    let (_, index) = refresh(None).context("The journal index is built from Git")?;
+    write_index(&index)?;
+    Ok(index.entries.len())
+}
+
+/// How the index compares with the journal on disk.
+#[derive(Debug, Serialize)]
+pub struct IndexStatus {
+    pub path: String,
+    /// Whether commands use the index at all.
+    pub enabled: bool,
+    pub exists: bool,
+    /// Entries in the index.
+    pub indexed: usize,
+    /// Entry files in `journal/`.
+    pub entries: usize,
+    /// Entries that the next command will parse from disk: new, changed or
+    /// uncommitted.
+    pub pending: usize,
+    /// Whether the index can be used without consulting Git.
+    pub current: bool,
+    pub bytes: u64,
+}
+
+pub fn status() -> Result<IndexStatus> {
+    let index = read_index();
+    let on_disk = entry_names()?;
+    let indexed: HashMap<&str, &str> = index
+        .iter()
+        .flat_map(|i| &i.entries)
+        .map(|e| (e.filename.as_str(), e.blob.as_str()))
+        .collect();
+    let pending = match (git::staged_blobs("journal"), git::unstaged_files("journal")) {
+        (Ok(blobs), Ok(unstaged)) => on_disk
+            .iter()
+            .filter(|name| {
+                let key = format!("journal/{}", name);
+                unstaged.contains(&key)
+                    || blobs.get(&key).map(String::as_str) != indexed.get(name.as_str()).copied()
+            })
+            .count(),
+        _ => on_disk.len(),
+    };
+    Ok(IndexStatus {
+        path: INDEX_FILE.to_string(),
+        enabled: enabled(),
+        exists: index.is_some(),
+        indexed: indexed.len(),
+        entries: on_disk.len(),
+        pending,
+        current: index.as_ref().is_some_and(is_current),
+        bytes: fs::metadata(INDEX_FILE).map(|m| m.len()).unwrap_or(0),
+    })
+}
+
+fn enabled() -> bool {
+    Path::new(".gitehr").is_dir() && Path::new("journal").is_dir() && !is_encrypted()
+}
+
+fn is_encrypted() -> bool {
+    Path::new(".gitehr/ENCRYPTED").exists()
+}
+
+/// Bring `cached` up to date with the journal, parsing only entries whose
+/// blob it does not hold. Returns every entry and the new index.
+fn refresh(cached: Option<Index>) -> Result<(Vec<ParsedEntry>, Index)> {
+    // Taken first, so changes made while refreshing leave them stale.
+    let stamp = current_stamp();
+    let names: Vec<(String, Option<FileStamp>)> = entry_names()?
+        .into_iter()
+        .map(|name| {
+            let file = file_stamp(&Path::new("journal").join(&name));
+            (name, file)
+        })
+        .collect();
+    let blobs = git::staged_blobs("journal")?;
+    let unstaged = git::unstaged_files("journal")?;
+
+    let mut known: HashMap<String, IndexedEntry> = cached
+        .map(|i| i.entries)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|e| (e.filename.clone(), e))
+        .collect();
+    let mut complete = true;
+    let mut newest = 0;
+    let mut entries = Vec::new();
+    let mut indexed = Vec::new();
+    for (name, file) in names {
+        let key = format!("journal/{}", name);
+        let path = PathBuf::from("journal").join(&name);
+        match file {
+            Some(file) => newest = newest.max(file.modified),
+            None => complete = false,
+        }
+        let blob = blobs.get(&key).filter(|_| !unstaged.contains(&key));
+        if let Some(blob) = blob
+            && let Some(mut hit) = known.remove(&name).filter(|e| &e.blob == blob)
+        {
+            entries.push(ParsedEntry {
+                filename: hit.filename.clone(),
+                metadata: hit.metadata.clone(),
+                content: hit.content.clone(),
+            });
+            hit.file = file.unwrap_or(hit.file);
+            indexed.push(hit);
+            continue;
+        }
+        let parsed = match parse_journal_file(&path) {
+            Ok(parsed) => parsed,
+            Err(e) => {
+                eprintln!("Warning: skipping {}: {}", path.display(), e);
+                complete = false;
+                continue;
+            }
+        };
+        match (blob, file) {
+            (Some(blob), Some(file)) => indexed.push(IndexedEntry {
+                filename: parsed.filename.clone(),
+                blob: blob.clone(),
+                file,
+                metadata: parsed.metadata.clone(),
+                content: parsed.content.clone(),
+            }),
+            _ => complete = false,
+        }
+        entries.push(parsed);
+    }
+
+    let stamp = stamp.filter(|s| complete && !is_racy(s.journal.max(s.git_index).max(newest)));
+    Ok((
+        entries,
+        Index {
+            format: FORMAT,
+            stamp,
+            entries: indexed,
+        },
+    ))
+}
+
+/// Parse every entry from disk, without the index.
+fn scan() -> Result<Vec<ParsedEntry>> {
+    let mut entries = Vec::new();
+    for name in entry_names()? {
+        let path = PathBuf::from("journal").join(&name);
+        match parse_journal_file(&path) {
+            Ok(parsed) => entries.push(parsed),
+            Err(e) => eprintln!("Warning: skipping {}: {}", path.display(), e),
+        }
+    }
+    Ok(entries)
+}
+
+/// Entry filenames in `journal/`, sorted (oldest first).
+fn entry_names() -> Result<Vec<String>> {
+    let journal_dir = Path::new("journal");
+    if !journal_dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut names: Vec<String> = fs::read_dir(journal_dir)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_ok_and(|t| t.is_file()))
+        .filter_map(|e| e.file_name().to_str().map(str::to_string))
+        .filter(|name| is_journal_entry_file(name))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+fn read_index() -> Option<Index> {
+    let json = fs::read_to_string(INDEX_FILE).ok()?;
+    serde_json::from_str::<Index>(&json)
+        .ok()
+        .filter(|index| index.format == FORMAT)
+}
+
+/// Write the index, replacing any other atomically.
+fn write_index(index: &Index) -> Result<()> {
+    fs::create_dir_all(CACHE_DIR)?;
+    let ignore = Path::new(CACHE_DIR).join(".gitignore");
+    if !ignore.exists() {
+        fs::write(&ignore, "# Disposable cache; never committed.\n*\n")?;
+    }
+    let temp = format!("{}.{}.tmp", INDEX_FILE, std::process::id());
+    fs::write(&temp, serde_json::to_vec(index)?)?;
+    fs::rename(&temp, INDEX_FILE)?;
+    Ok(())
+}
+
+/// Whether `index` can be used without consulting Git: it was complete when
+/// written, and neither the journal, Git's index nor any entry file has
+/// changed since.
+fn is_current(index: &Index) -> bool {
+    index.stamp.is_some()
+        && index.stamp == current_stamp()
+        && index
+            .entries
+            .iter()
+            .all(|e| file_stamp(&Path::new("journal").join(&e.filename)) == Some(e.file))
+}
+
+fn file_stamp(path: &Path) -> Option<FileStamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some(FileStamp {
+        modified: nanos(metadata.modified().ok()?)?,
+        len: metadata.len(),
+    })
+}
+
+fn current_stamp() -> Option<Stamp> {
+    let journal = fs::metadata("journal").ok()?;
+    let git_index = fs::metadata(".git/index").ok()?;
+    Some(Stamp {
+        journal: nanos(journal.modified().ok()?)?,
+        git_index: nanos(git_index.modified().ok()?)?,
+        git_index_len: git_index.len(),
+    })
+}
+
+fn nanos(time: SystemTime) -> Option<u128> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_nanos())
+}
+
+/// Whether a modification time of `newest` is too recent to be sure a
+/// later change would move it.
+fn is_racy(newest: u128) -> bool {
+    nanos(SystemTime::now()).is_none_or(|now| now < newest + RACY.as_nanos())
+}
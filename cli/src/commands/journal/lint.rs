@@ -0,0 +1,48 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::Result;
+
+use super::filter::{self, Filter};
+use crate::medmd::{self, Diagnostic};
+use crate::utils::parse_bound;
+
+/// Unknown and malformed Medical Markdown codes in journal entries on or
+/// after `since`, oldest entry first, checked against the repository's
+/// registry.
+pub fn problems(since: Option<&str>) -> Result<Vec<(String, Diagnostic)>> {
+    let registry = medmd::Registry::load()?;
+    let filter = Filter {
+        since: since.map(|s| parse_bound(s, false)).transpose()?,
+        ..Filter::default()
+    };
+    let mut problems = Vec::new();
+    for entry in filter::matching_entries(&filter)? {
+        for diagnostic in medmd::validate(&entry.content, &registry) {
+            problems.push((entry.filename.clone(), diagnostic));
+        }
+    }
+    Ok(problems)
+}
+
+/// `journal lint`: one line per problem, as `filename:line: message`. Fails
+/// when there are any, so it can gate scripts and CI.
+pub fn run(since: Option<String>) -> Result<()> {
+    let problems = problems(since.as_deref())?;
+    if problems.is_empty() {
+        println!("No Medical Markdown problems found.");
+        return Ok(());
+    }
+    for (filename, diagnostic) in &problems {
+        println!("{}:{}: {}", filename, diagnostic.line, diagnostic.message);
+    }
+    let mut entries: Vec<&str> = problems.iter().map(|(f, _)| f.as_str()).collect();
+    entries.dedup();
+    anyhow::bail!(
+        "{} Medical Markdown problem{} in {} entr{}",
+        problems.len(),
+        if problems.len() == 1 { "" } else { "s" },
+        entries.len(),
+        if entries.len() == 1 { "y" } else { "ies" }
+    )
+}
@@ -2,38 +2,10 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use anyhow::Result;
-use std::{fs, path::PathBuf};
 
-use super::is_journal_entry_file;
+use super::filter::{self, Filter, FilterArgs};
 
-pub fn run() -> Result<()> {
-    let journal_dir = PathBuf::from("journal");
-    if !journal_dir.exists() {
-        println!("No journal entries found.");
-        return Ok(());
-    }
-
-    let mut entries: Vec<String> = fs::read_dir(&journal_dir)?
-        .filter_map(|e| e.ok())
-        .filter_map(|e| {
-            let name = e.file_name().to_string_lossy().to_string();
-            if is_journal_entry_file(&name) {
-                Some(name)
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    if entries.is_empty() {
-        println!("No journal entries found.");
-        return Ok(());
-    }
-
-    entries.sort();
-    for name in &entries {
-        println!("{name}");
-    }
-    println!("\n({} entries)", entries.len());
-    Ok(())
+pub fn run(args: FilterArgs) -> Result<()> {
+    let entries = filter::matching_entries(&Filter::from_args(&args, None)?)?;
+    filter::print(&entries, args.time, args.json, "No journal entries found.")
 }
@@ -0,0 +1,220 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Structured metadata an entry may carry in its front matter beside the
+//! narrative: what kind of encounter it records, free tags, and SNOMED CT
+//! concepts.
+
+use anyhow::Result;
+use clap::{Args, ValueEnum};
+use serde::{Deserialize, Serialize};
+
+use super::JournalEntry;
+
+#[derive(Args, Default)]
+pub struct MetadataArgs {
+    #[arg(
+        long = "type",
+        value_enum,
+        help = "The kind of encounter the entry records"
+    )]
+    pub encounter_type: Option<EncounterType>,
+    #[arg(
+        long = "tag",
+        value_name = "TAG",
+        help = "Tag the entry (repeatable), as well as any #tags in the body"
+    )]
+    pub tags: Vec<String>,
+    #[arg(
+        long = "code",
+        value_name = "SCTID",
+        help = "A SNOMED CT concept the entry is about, as ID or 'ID|term' (repeatable)"
+    )]
+    pub codes: Vec<String>,
+    #[arg(
+        long,
+        value_name = "PLACE",
+        help = "Where the encounter took place, or the organisation responsible"
+    )]
+    pub location: Option<String>,
+}
+
+impl MetadataArgs {
+    /// `entry` with the metadata set, checking tags and codes.
+    pub fn apply(&self, entry: JournalEntry) -> Result<JournalEntry> {
+        let mut tags: Vec<String> = Vec::new();
+        for tag in &self.tags {
+            let tag = normalise_tag(tag)?;
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        let codes = self
+            .codes
+            .iter()
+            .map(|code| ConceptCode::parse(code))
+            .collect::<Result<_>>()?;
+        let location = self
+            .location
+            .as_deref()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+            .map(str::to_string);
+        Ok(JournalEntry {
+            encounter_type: self.encounter_type,
+            location,
+            tags,
+            codes,
+            ..entry
+        })
+    }
+}
+
+/// The kind of encounter an entry records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum EncounterType {
+    /// Seen in person.
+    Consultation,
+    /// A telephone call.
+    Phone,
+    /// A letter received or sent.
+    Letter,
+    /// An investigation result.
+    Result,
+}
+
+impl std::fmt::Display for EncounterType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            EncounterType::Consultation => "Consultation",
+            EncounterType::Phone => "Telephone",
+            EncounterType::Letter => "Letter",
+            EncounterType::Result => "Result",
+        })
+    }
+}
+
+/// A SNOMED CT concept, by id, with the term the author chose.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConceptCode {
+    pub code: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+}
+
+impl ConceptCode {
+    /// Parse `--code` input: a concept id, optionally followed by its term
+    /// as `id|term` or in SNOMED's own `id |term|` form.
+    pub fn parse(input: &str) -> Result<Self> {
+        let (code, display) = match input.split_once('|') {
+            Some((code, term)) => (code.trim(), Some(term.trim().trim_end_matches('|').trim())),
+            None => (input.trim(), None),
+        };
+        if !is_concept_id(code) {
+            anyhow::bail!(
+                "'{}' is not a SNOMED CT concept id: expected 6 to 18 digits with a valid check digit",
+                code
+            );
+        }
+        Ok(ConceptCode {
+            code: code.to_string(),
+            display: display.filter(|d| !d.is_empty()).map(str::to_string),
+        })
+    }
+}
+
+impl std::fmt::Display for ConceptCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.display {
+            Some(display) => write!(f, "{} |{}|", self.code, display),
+            None => f.write_str(&self.code),
+        }
+    }
+}
+
+/// A tag as stored: `--tag Diabetes` and `--tag '#diabetes'` are both
+/// `diabetes`. Tags follow the same rule as body hashtags: a letter, then
+/// letters, digits, `-` and `_`.
+pub fn normalise_tag(input: &str) -> Result<String> {
+    let tag = input.trim().trim_start_matches('#').to_lowercase();
+    let valid = tag.chars().next().is_some_and(char::is_alphabetic)
+        && tag
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_'));
+    if !valid {
+        anyhow::bail!(
+            "Invalid tag '{}': use a letter, then letters, digits, '-' and '_'",
+            input
+        );
+    }
+    Ok(tag)
+}
+
+/// A SNOMED CT concept id: 6 to 18 digits, no leading zero, a concept
+/// partition identifier (`00`, or `10` in an extension) and a Verhoeff
+/// check digit.
+fn is_concept_id(code: &str) -> bool {
+    if !(6..=18).contains(&code.len())
+        || code.starts_with('0')
+        || !code.chars().all(|c| c.is_ascii_digit())
+    {
+        return false;
+    }
+    let partition = &code[code.len() - 3..code.len() - 1];
+    matches!(partition, "00" | "10") && verhoeff(code)
+}
+
+fn verhoeff(digits: &str) -> bool {
+    const D: [[u8; 10]; 10] = [
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+        [1, 2, 3, 4, 0, 6, 7, 8, 9, 5],
+        [2, 3, 4, 0, 1, 7, 8, 9, 5, 6],
+        [3, 4, 0, 1, 2, 8, 9, 5, 6, 7],
+        [4, 0, 1, 2, 3, 9, 5, 6, 7, 8],
+        [5, 9, 8, 7, 6, 0, 4, 3, 2, 1],
+        [6, 5, 9, 8, 7, 1, 0, 4, 3, 2],
+        [7, 6, 5, 9, 8, 2, 1, 0, 4, 3],
+        [8, 7, 6, 5, 9, 3, 2, 1, 0, 4],
+        [9, 8, 7, 6, 5, 4, 3, 2, 1, 0],
+    ];
+    const P: [[u8; 10]; 8] = [
+        [0, 1, 2, 3, 4, 5, 6, 7, 8, 9],
+        [1, 5, 7, 6, 2, 8, 3, 0, 9, 4],
+        [5, 8, 0, 3, 7, 9, 6, 1, 4, 2],
+        [8, 9, 1, 6, 0, 4, 3, 5, 2, 7],
+        [9, 4, 5, 3, 1, 2, 6, 8, 7, 0],
+        [4, 2, 8, 6, 5, 7, 3, 9, 0, 1],
+        [2, 7, 9, 3, 8, 0, 6, 4, 1, 5],
+        [7, 0, 4, 6, 9, 1, 3, 2, 5, 8],
+    ];
+    let check = digits.bytes().rev().enumerate().fold(0u8, |c, (i, b)| {
+        D[c as usize][P[i % 8][(b - b'0') as usize] as usize]
+    });
+    check == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_concept_codes() {
+        let code = ConceptCode::parse("38341003 |Hypertensive disorder|").unwrap();
+        assert_eq!(code.code, "38341003");
+        assert_eq!(code.display.as_deref(), Some("Hypertensive disorder"));
+        assert_eq!(code.to_string(), "38341003 |Hypertensive disorder|");
+        assert_eq!(ConceptCode::parse("22298006|").unwrap().display, None);
+        // A mistyped digit, a description id (partition 01), too short.
+        for bad in ["38341004", "38341011", "12345", "38341003x"] {
+            assert!(ConceptCode::parse(bad).is_err(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_normalise_tag() {
+        assert_eq!(normalise_tag("#Foot-Check").unwrap(), "foot-check");
+        assert!(normalise_tag("12").is_err());
+        assert!(normalise_tag("two words").is_err());
+    }
+}
@@ -5,14 +5,31 @@ use anyhow::Result;
 use chrono::{DateTime, Utc};
 use clap::Subcommand;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::{fs, path::PathBuf};
 use uuid::Uuid;
 
 use super::{contributor, git};
+use clinical_time::{ClinicalTime, TimeBasis};
+use drafts::DraftCommands;
+use filter::FilterArgs;
+use metadata::{ConceptCode, EncounterType, MetadataArgs};
+use templates::TemplateCommands;
+use thread::LinkArgs;
 
 pub mod add;
+pub mod clinical_time;
+pub mod drafts;
+pub mod filter;
+pub mod index;
+pub mod lint;
 pub mod list;
+pub mod metadata;
+pub mod promote;
+pub mod search;
 pub mod show;
+pub mod templates;
+pub mod thread;
 
 #[derive(Subcommand)]
 pub enum JournalCommands {
@@ -29,9 +46,50 @@ pub enum JournalCommands {
             help = "Read the entry from a file, or '-' for stdin"
         )]
         file: Option<String>,
+        #[arg(
+            long,
+            value_name = "WHEN",
+            help = "When the care happened, if not now: YYYY[-MM[-DD]], 'YYYY-MM-DD HH:MM' (local), RFC 3339, today or yesterday"
+        )]
+        at: Option<String>,
+        #[arg(
+            long,
+            value_name = "NAME",
+            conflicts_with_all = ["text", "file"],
+            help = "Start the entry in your $EDITOR from a template (see `journal templates list`)"
+        )]
+        template: Option<String>,
+        #[command(flatten)]
+        metadata: MetadataArgs,
+        #[command(flatten)]
+        links: LinkArgs,
+        #[arg(
+            long,
+            help = "Warn about unknown or malformed Medical Markdown codes (the entry is still recorded)"
+        )]
+        validate: bool,
+    },
+    #[command(
+        name = "list-entry",
+        aliases = ["list", "ls"],
+        about = "List journal entries (timestamp, author, first line), optionally filtered"
+    )]
+    List {
+        #[command(flatten)]
+        filters: FilterArgs,
+    },
+    #[command(about = "Search journal entry text (all words and \"phrases\", or --regex)")]
+    Search {
+        #[arg(help = "Words and \"quoted phrases\" that must all appear (case-insensitive)")]
+        query: String,
+        #[arg(
+            long,
+            help = "Treat the query as a case-insensitive regular expression"
+        )]
+        regex: bool,
+        #[command(flatten)]
+        filters: FilterArgs,
     },
-    #[command(name = "list-entry", aliases = ["list", "ls"], about = "List journal entries")]
-    List,
     #[command(aliases = ["cat"], about = "Show a journal entry (body by default; --raw or --metadata for more)")]
     Show {
         #[arg(help = "Journal entry filename (or LATEST, LATEST^, LATEST~N)")]
@@ -40,6 +98,63 @@ pub enum JournalCommands {
         raw: bool,
         #[arg(long, help = "Print only the frontmatter")]
         metadata: bool,
+        #[arg(
+            long,
+            conflicts_with_all = ["raw", "metadata"],
+            help = "Follow the body with its Medical Markdown coded sections"
+        )]
+        structured: bool,
+    },
+    #[command(about = "Print the Medical Markdown structured data of an entry as JSON")]
+    Extract {
+        #[arg(help = "Journal entry filename (or LATEST, LATEST^, LATEST~N)")]
+        filename: String,
+    },
+    #[command(
+        about = "Show the conversation an entry belongs to: what it replies to, and the replies"
+    )]
+    Thread {
+        #[arg(help = "Journal entry filename (or LATEST, LATEST^, LATEST~N)")]
+        filename: String,
+        #[arg(long, help = "Emit JSON for GUI or automation callers")]
+        json: bool,
+    },
+    #[command(
+        about = "Review allergies and vaccinations written in an entry and add accepted ones to the record"
+    )]
+    Promote {
+        #[arg(help = "Journal entry filename (or LATEST, LATEST^, LATEST~N)")]
+        filename: String,
+        #[arg(
+            long,
+            value_name = "N",
+            help = "Promote fact N as listed, or 'all' (repeatable); otherwise asks for each on a terminal"
+        )]
+        accept: Vec<String>,
+        #[arg(
+            long,
+            conflicts_with = "accept",
+            help = "List the entry's facts as JSON without promoting any"
+        )]
+        json: bool,
+    },
+    #[command(about = "Keep unfinished entries as drafts, outside the record, until committed")]
+    Draft {
+        #[command(subcommand)]
+        command: DraftCommands,
+    },
+    #[command(about = "List and show journal entry templates")]
+    Templates {
+        #[command(subcommand)]
+        command: TemplateCommands,
+    },
+    #[command(about = "Report unknown or malformed Medical Markdown codes in journal entries")]
+    Lint {
+        #[arg(
+            long,
+            help = "Only entries on or after this date (YYYY-MM-DD or RFC 3339)"
+        )]
+        since: Option<String>,
     },
 }
 
@@ -51,25 +166,126 @@ pub fn run(command: JournalCommands) -> Result<()> {
     }
 
     match command {
-        JournalCommands::Add { text, file } => add::run(text, file),
-        JournalCommands::List => list::run(),
+        JournalCommands::Add {
+            text,
+            file,
+            at,
+            template,
+            metadata,
+            links,
+            validate,
+        } => add::run(text, file, at, template, metadata, links, validate),
+        JournalCommands::List { filters } => list::run(filters),
+        JournalCommands::Search {
+            query,
+            regex,
+            filters,
+        } => search::run(query, regex, filters),
         JournalCommands::Show {
             filename,
             raw,
             metadata,
-        } => show::run(filename, raw, metadata),
+            structured,
+        } => show::run(filename, raw, metadata, structured),
+        JournalCommands::Extract { filename } => show::extract(filename),
+        JournalCommands::Thread { filename, json } => thread::run(filename, json),
+        JournalCommands::Promote {
+            filename,
+            accept,
+            json,
+        } => promote::run(filename, accept, json),
+        JournalCommands::Draft { command } => drafts::run(command),
+        JournalCommands::Templates { command } => templates::run(command),
+        JournalCommands::Lint { since } => lint::run(since),
     }
 }
 
 // ── Core data structures ──────────────────────────────────────────────────────
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct JournalEntry {
+    /// When the entry was recorded. Entry filenames are keyed on it.
     pub timestamp: DateTime<Utc>,
+    /// When the care it describes happened, if not when it was recorded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clinical_time: Option<ClinicalTime>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub author: Option<String>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encounter_type: Option<EncounterType>,
+    /// Where the encounter took place, or the organisation responsible.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    /// Lowercase, without `#`; see `metadata::normalise_tag`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// SNOMED CT concepts the entry is about.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub codes: Vec<ConceptCode>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub documents: Option<Vec<DocumentRef>>,
+    /// The filename of the entry this one follows up; see `thread`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_reply_to: Option<String>,
+    /// Filenames of other entries this one relates to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<String>,
+    /// Front matter keys this version does not know, kept so they survive
+    /// being read and written again.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, serde_yaml_ng::Value>,
+}
+
+impl JournalEntry {
+    /// Front matter for an entry recorded now by `author`.
+    pub fn new(author: Option<String>) -> Self {
+        JournalEntry {
+            timestamp: Utc::now(),
+            clinical_time: None,
+            author,
+            encounter_type: None,
+            location: None,
+            tags: Vec::new(),
+            codes: Vec::new(),
+            documents: None,
+            in_reply_to: None,
+            related: Vec::new(),
+            extra: BTreeMap::new(),
+        }
+    }
+
+    /// The first and last instants of the entry's time on `basis`.
+    pub fn time_span(&self, basis: TimeBasis) -> (DateTime<Utc>, DateTime<Utc>) {
+        match (basis, &self.clinical_time) {
+            (TimeBasis::Clinical, Some(time)) => (time.start(), time.end()),
+            _ => (self.timestamp, self.timestamp),
+        }
+    }
+
+    /// The recorded time, preceded by the clinical time if there is one:
+    /// `2026-10-18 (recorded 2026-10-19 10:00 UTC)`.
+    pub fn time_label(&self) -> String {
+        let recorded = self.timestamp.format("%Y-%m-%d %H:%M UTC");
+        match &self.clinical_time {
+            Some(time) => format!("{} (recorded {})", time.short(), recorded),
+            None => recorded.to_string(),
+        }
+    }
+
+    /// Encounter type, location, tags and codes on one line, for exports:
+    /// `Telephone · Riverside Surgery · #diabetes · 38341003 |Hypertensive
+    /// disorder|`. `None` when the entry has none of them.
+    pub fn metadata_label(&self) -> Option<String> {
+        let parts: Vec<String> = self
+            .encounter_type
+            .iter()
+            .map(ToString::to_string)
+            .chain(self.location.iter().cloned())
+            .chain(self.tags.iter().map(|tag| format!("#{}", tag)))
+            .chain(self.codes.iter().map(ToString::to_string))
+            .collect();
+        (!parts.is_empty()).then(|| parts.join(" · "))
+    }
 }
 
 /// A reference from a journal entry to a Document in the record.
@@ -92,26 +308,9 @@ pub struct ParsedEntry {
 
 // ── Entry resolution (LATEST syntax) ─────────────────────────────────────────
 
-/// Committed journal entry filenames, sorted newest-first.
+/// Journal entry filenames, sorted newest-first.
 pub fn sorted_entries() -> Result<Vec<String>> {
-    let dir = PathBuf::from("journal");
-    if !dir.exists() {
-        return Ok(Vec::new());
-    }
-
-    let mut entries: Vec<String> = fs::read_dir(&dir)?
-        .filter_map(|e| e.ok())
-        .filter_map(|e| {
-            let name = e.file_name().to_string_lossy().to_string();
-            if is_journal_entry_file(&name) {
-                Some(name)
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    entries.sort();
+    let mut entries = index::filenames()?;
     entries.reverse();
     Ok(entries)
 }
@@ -214,34 +413,10 @@ pub fn is_journal_entry_file(filename: &str) -> bool {
     filename.contains('T') && filename.contains('-') && filename.ends_with(".md")
 }
 
-/// Parse every journal entry, oldest first. Entries that fail to parse are
-/// skipped with a warning on stderr.
+/// Parse every journal entry, oldest first, through the journal index.
+/// Entries that fail to parse are skipped with a warning on stderr.
 pub fn parsed_entries() -> Result<Vec<ParsedEntry>> {
-    let journal_dir = PathBuf::from("journal");
-    if !journal_dir.exists() {
-        return Ok(Vec::new());
-    }
-
-    let mut paths: Vec<_> = fs::read_dir(&journal_dir)?
-        .filter_map(|e| e.ok())
-        .map(|e| e.path())
-        .filter(|path| {
-            path.file_name()
-                .and_then(|n| n.to_str())
-                .map(is_journal_entry_file)
-                .unwrap_or(false)
-        })
-        .collect();
-    paths.sort();
-
-    let mut entries = Vec::new();
-    for path in &paths {
-        match parse_journal_file(path) {
-            Ok(parsed) => entries.push(parsed),
-            Err(e) => eprintln!("Warning: skipping {}: {}", path.display(), e),
-        }
-    }
-    Ok(entries)
+    index::entries()
 }
 
 pub fn create_journal_entry(content: &str) -> Result<()> {
@@ -252,31 +427,58 @@ pub fn create_journal_entry_with_documents(
     content: &str,
     documents: Vec<DocumentRef>,
 ) -> Result<()> {
+    let filename =
+        create_journal_entry_as(content, documents, contributor::get_current_contributor())?;
+    println!("Created journal entry: {}", filename);
+    Ok(())
+}
+
+/// Write, stage and commit a journal entry attributed to `author`, returning
+/// its record path. Prints nothing, so it is safe to call from the MCP server,
+/// whose stdout is the protocol stream.
+pub fn create_journal_entry_as(
+    content: &str,
+    documents: Vec<DocumentRef>,
+    author: Option<String>,
+) -> Result<String> {
     let entry = JournalEntry {
-        timestamp: Utc::now(),
-        author: contributor::get_current_contributor(),
         documents: if documents.is_empty() {
             None
         } else {
             Some(documents)
         },
+        ..JournalEntry::new(author)
     };
+    write_journal_entry(&entry, content)
+}
 
+/// An entry file: the front matter, then the body.
+pub fn file_content(entry: &JournalEntry, content: &str) -> Result<String> {
+    let yaml = serde_yaml_ng::to_string(entry)?;
+    Ok(format!("---\n{}---\n\n{}", yaml, content))
+}
+
+/// Write, stage and commit an entry with the given front matter, returning
+/// its record path. The filename is keyed on `entry.timestamp`. Prints
+/// nothing.
+pub fn write_journal_entry(entry: &JournalEntry, content: &str) -> Result<String> {
+    let filename = stage_journal_entry(entry, content)?;
+    let commit_message = format!("Journal entry: {}", filename);
+    git::git_commit(&commit_message)?;
+
+    Ok(filename)
+}
+
+/// Write and stage an entry without committing it, for callers that commit
+/// it with other changes. Returns its record path.
+pub fn stage_journal_entry(entry: &JournalEntry, content: &str) -> Result<String> {
     let filename = format!(
         "journal/{}-{}.md",
         entry.timestamp.format("%Y%m%dT%H%M%S%.3fZ"),
         Uuid::new_v4()
     );
 
-    let yaml = serde_yaml_ng::to_string(&entry)?;
-    let file_content = format!("---\n{}---\n\n{}", yaml, content);
-
-    fs::write(&filename, file_content)?;
-    println!("Created journal entry: {}", filename);
-
+    fs::write(&filename, file_content(entry, content)?)?;
     git::git_add(&filename)?;
-    let commit_message = format!("Journal entry: {}", filename);
-    git::git_commit(&commit_message)?;
-
-    Ok(())
+    Ok(filename)
 }
@@ -0,0 +1,424 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! `journal promote`: reviewed promotion of facts written in an entry's
+//! Medical Markdown into typed state.
+//!
+//! Each line of an `ALLERGY/` or `ALL/` section is an allergy
+//! (`agent - reaction (severity)`), and each line of a `VACC/` or `IMM/`
+//! section a vaccination (`vaccine on YYYY-MM-DD`, the entry's date - its
+//! clinical day if it has one - if none is given). Nothing is written until
+//! the clinician accepts it; the state records which entry a fact came from,
+//! and the entry itself is never changed.
+
+use anyhow::Result;
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::Serialize;
+use std::io::{self, IsTerminal, Write};
+use std::sync::LazyLock;
+
+use super::ParsedEntry;
+use crate::commands::allergies::{self, AllergySeverity};
+use crate::commands::vaccinations::{self, VaccinationInput};
+use crate::commands::{contributor, typed_state};
+use crate::medmd;
+
+/// Codes whose lines are allergies.
+pub const ALLERGY_CODES: &[&str] = &["ALLERGY", "ALL"];
+/// Codes whose lines are vaccinations.
+pub const VACCINATION_CODES: &[&str] = &["VACC", "IMM"];
+
+/// Recorded when an allergy line names no reaction, as for imports.
+const NO_REACTION: &str = "Not recorded";
+
+/// Lines under an allergy code that record there are none.
+const NO_ALLERGIES: &[&str] = &[
+    "nkda",
+    "nka",
+    "none",
+    "none known",
+    "no known allergies",
+    "no known drug allergies",
+];
+
+static DATE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b\d{4}-\d{2}-\d{2}\b").unwrap());
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum Fact {
+    Allergy {
+        agent: String,
+        reaction: String,
+        severity: AllergySeverity,
+    },
+    Vaccination {
+        vaccine: String,
+        date: String,
+    },
+}
+
+impl std::fmt::Display for Fact {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fact::Allergy {
+                agent,
+                reaction,
+                severity,
+            } => write!(f, "Allergy: {} - {} ({})", agent, reaction, severity),
+            Fact::Vaccination { vaccine, date } => {
+                write!(f, "Vaccination: {} on {}", vaccine, date)
+            }
+        }
+    }
+}
+
+/// A line of an entry that may become a fact.
+#[derive(Debug, Serialize)]
+pub struct Candidate {
+    /// Body line, from 1, of the section the line belongs to.
+    pub line: usize,
+    pub code: String,
+    /// The line as written.
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fact: Option<Fact>,
+    /// Why the line will not be promoted, if it will not.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip: Option<String>,
+}
+
+/// The lines of `entry` that may be promoted, in body order, each with its
+/// fact or the reason it is skipped: unreadable, or already in state.
+pub fn candidates(entry: &ParsedEntry) -> Result<Vec<Candidate>> {
+    let allergies = allergies::list(false)?;
+    let vaccinations = vaccinations::list(false)?;
+    let meta = &entry.metadata;
+    let entry_date = meta
+        .clinical_time
+        .as_ref()
+        .and_then(|time| time.date())
+        .unwrap_or(meta.timestamp.date_naive())
+        .format("%Y-%m-%d")
+        .to_string();
+
+    let mut candidates = Vec::new();
+    for section in medmd::sections(&entry.content) {
+        let is_allergy = ALLERGY_CODES.contains(&section.code.as_str());
+        if !is_allergy && !VACCINATION_CODES.contains(&section.code.as_str()) {
+            continue;
+        }
+        for text in section
+            .notes
+            .lines()
+            .map(str::trim)
+            .filter(|l| !l.is_empty())
+        {
+            if is_allergy && NO_ALLERGIES.contains(&text.to_lowercase().trim_end_matches('.')) {
+                continue;
+            }
+            let parsed = if is_allergy {
+                parse_allergy(text)
+            } else {
+                parse_vaccination(text, &entry_date)
+            };
+            let (fact, skip) = match parsed {
+                Ok(fact) => {
+                    let recorded = match &fact {
+                        Fact::Allergy { agent, .. } => allergies
+                            .iter()
+                            .any(|a| a.agent.eq_ignore_ascii_case(agent))
+                            .then_some("already on the allergy list"),
+                        Fact::Vaccination { vaccine, date } => vaccinations
+                            .iter()
+                            .any(|v| v.vaccine.eq_ignore_ascii_case(vaccine) && &v.date == date)
+                            .then_some("already recorded"),
+                    };
+                    (Some(fact), recorded.map(str::to_string))
+                }
+                Err(reason) => (None, Some(reason)),
+            };
+            candidates.push(Candidate {
+                line: section.line,
+                code: section.code.clone(),
+                text: text.to_string(),
+                fact,
+                skip,
+            });
+        }
+    }
+    Ok(candidates)
+}
+
+/// `penicillin - rash (severe)` → agent, reaction and severity. The
+/// reaction and severity are optional.
+fn parse_allergy(text: &str) -> Result<Fact, String> {
+    let mut text = text.trim();
+    let mut severity = AllergySeverity::Moderate;
+    if let Some(open) = text.rfind('(')
+        && text.ends_with(')')
+        && let Some(parsed) = parse_severity(&text[open + 1..text.len() - 1])
+    {
+        severity = parsed;
+        text = text[..open].trim_end();
+    }
+    let (agent, reaction) = [" - ", " – ", " — ", ": "]
+        .iter()
+        .filter_map(|sep| text.split_once(sep))
+        .min_by_key(|(agent, _)| agent.len())
+        .unwrap_or((text, ""));
+    let agent = agent.trim();
+    if agent.is_empty() || agent.starts_with(['-', '–', '—', ':']) {
+        return Err("no agent".to_string());
+    }
+    let reaction = reaction.trim();
+    Ok(Fact::Allergy {
+        agent: agent.to_string(),
+        reaction: if reaction.is_empty() {
+            NO_REACTION.to_string()
+        } else {
+            reaction.to_string()
+        },
+        severity,
+    })
+}
+
+fn parse_severity(word: &str) -> Option<AllergySeverity> {
+    match word.trim().to_lowercase().as_str() {
+        "low" | "mild" => Some(AllergySeverity::Low),
+        "moderate" => Some(AllergySeverity::Moderate),
+        "high" | "severe" => Some(AllergySeverity::High),
+        "critical" => Some(AllergySeverity::Critical),
+        _ => None,
+    }
+}
+
+/// `influenza on 2026-10-01` → vaccine and date, `default_date` if the line
+/// has none.
+fn parse_vaccination(text: &str, default_date: &str) -> Result<Fact, String> {
+    let (vaccine, date) = match DATE.find(text) {
+        Some(found) => {
+            let date = found.as_str();
+            if NaiveDate::parse_from_str(date, "%Y-%m-%d").is_err() {
+                return Err(format!("{} is not a date", date));
+            }
+            let rest = format!("{} {}", &text[..found.start()], &text[found.end()..]);
+            (rest, date.to_string())
+        }
+        None => (text.to_string(), default_date.to_string()),
+    };
+    let separators = |c: char| c.is_whitespace() || matches!(c, '-' | ',' | ':' | ';');
+    let vaccine = vaccine.trim_matches(separators);
+    let vaccine = match vaccine.strip_suffix(" on") {
+        _ if vaccine.eq_ignore_ascii_case("on") => "",
+        Some(rest) => rest.trim(),
+        None => vaccine,
+    };
+    if vaccine.is_empty() {
+        return Err("no vaccine".to_string());
+    }
+    Ok(Fact::Vaccination {
+        vaccine: vaccine.to_string(),
+        date,
+    })
+}
+
+/// Write `fact` to typed state, linked to the entry `source` it came from.
+pub fn write(fact: &Fact, source: &str) -> Result<()> {
+    match fact {
+        Fact::Allergy {
+            agent,
+            reaction,
+            severity,
+        } => {
+            let (allergy, entry) = allergies::add_as(
+                agent,
+                reaction,
+                *severity,
+                None,
+                Some(source),
+                contributor::get_current_contributor(),
+            )?;
+            println!("Created journal entry: {}", entry);
+            println!("Added allergy: {}", allergy.id);
+        }
+        Fact::Vaccination { vaccine, date } => {
+            vaccinations::add(VaccinationInput {
+                vaccine: vaccine.clone(),
+                date: date.clone(),
+                dose_sequence: None,
+                target_disease: Vec::new(),
+                anatomical_site: None,
+                route: None,
+                product: None,
+                manufacturer: None,
+                batch_number: None,
+                performer: None,
+                fhir_json: None,
+                note: None,
+                source_entry: Some(source.to_string()),
+            })?;
+        }
+    }
+    Ok(())
+}
+
+/// `journal promote`: list the entry's facts, then write those accepted -
+/// by number or `all` in `accept`, or one by one on a terminal.
+pub fn run(reference: String, accept: Vec<String>, json: bool) -> Result<()> {
+    typed_state::ensure_gitehr_repository()?;
+    let (filename, path) = super::show::locate(&reference)?;
+    let entry = super::parse_journal_file(&path)?;
+    let candidates = candidates(&entry)?;
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&candidates)?);
+        return Ok(());
+    }
+    if candidates.is_empty() {
+        println!(
+            "No facts to promote in {} (ALLERGY/, ALL/, VACC/ or IMM/ lines).",
+            filename
+        );
+        return Ok(());
+    }
+    println!("Facts in {}:", filename);
+    for (i, candidate) in candidates.iter().enumerate() {
+        let described = match &candidate.fact {
+            Some(fact) => fact.to_string(),
+            None => format!("{}/ {}", candidate.code, candidate.text),
+        };
+        match &candidate.skip {
+            Some(reason) => println!("  {}. {} ({})", i + 1, described, reason),
+            None => println!("  {}. {}", i + 1, described),
+        }
+    }
+
+    let promotable: Vec<usize> = (0..candidates.len())
+        .filter(|&i| candidates[i].skip.is_none())
+        .collect();
+    let chosen = if !accept.is_empty() {
+        accepted(&accept, &candidates)?
+    } else if promotable.is_empty() {
+        println!("Nothing to promote.");
+        return Ok(());
+    } else if io::stdin().is_terminal() {
+        let mut chosen = Vec::new();
+        for &i in &promotable {
+            if confirm(&format!("Promote {}? [y/N] ", i + 1))? {
+                chosen.push(i);
+            }
+        }
+        chosen
+    } else {
+        println!("Nothing promoted. Accept facts with --accept <N> or --accept all.");
+        return Ok(());
+    };
+
+    if chosen.is_empty() {
+        println!("Nothing promoted.");
+    }
+    for i in chosen {
+        if let Some(fact) = &candidates[i].fact {
+            write(fact, &filename)?;
+        }
+    }
+    Ok(())
+}
+
+/// Indices of the promotable candidates named by `--accept` values.
+fn accepted(values: &[String], candidates: &[Candidate]) -> Result<Vec<usize>> {
+    let mut chosen = Vec::new();
+    for value in values {
+        if value.eq_ignore_ascii_case("all") {
+            chosen.extend((0..candidates.len()).filter(|&i| candidates[i].skip.is_none()));
+            continue;
+        }
+        let n: usize = value
+            .parse()
+            .ok()
+            .filter(|n| (1..=candidates.len()).contains(n))
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "--accept takes a fact number from 1 to {}, or 'all': {}",
+                    candidates.len(),
+                    value
+                )
+            })?;
+        if let Some(reason) = &candidates[n - 1].skip {
+            anyhow::bail!("Fact {} cannot be promoted: {}", n, reason);
+        }
+        chosen.push(n - 1);
+    }
+    chosen.sort_unstable();
+    chosen.dedup();
+    Ok(chosen)
+}
+
+fn confirm(message: &str) -> Result<bool> {
+    print!("{}", message);
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    Ok(matches!(input.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn allergy(agent: &str, reaction: &str, severity: AllergySeverity) -> Fact {
+        Fact::Allergy {
+            agent: agent.to_string(),
+            reaction: reaction.to_string(),
+            severity,
+        }
+    }
+
+    #[test]
+    fn test_parse_allergy() {
+        assert_eq!(
+            parse_allergy("penicillin - rash"),
+            Ok(allergy("penicillin", "rash", AllergySeverity::Moderate))
+        );
+        assert_eq!(
+            parse_allergy("Co-amoxiclav: lip swelling - urticaria (severe)"),
+            Ok(allergy(
+                "Co-amoxiclav",
+                "lip swelling - urticaria",
+                AllergySeverity::High
+            ))
+        );
+        assert_eq!(
+            parse_allergy("latex (remote history)"),
+            Ok(allergy(
+                "latex (remote history)",
+                NO_REACTION,
+                AllergySeverity::Moderate
+            ))
+        );
+        assert!(parse_allergy(" - rash").is_err());
+    }
+
+    #[test]
+    fn test_parse_vaccination() {
+        let fact = |vaccine: &str, date: &str| Fact::Vaccination {
+            vaccine: vaccine.to_string(),
+            date: date.to_string(),
+        };
+        assert_eq!(
+            parse_vaccination("Influenza on 2026-10-01", "2026-10-19"),
+            Ok(fact("Influenza", "2026-10-01"))
+        );
+        assert_eq!(
+            parse_vaccination("2026-09-30: COVID-19 booster", "2026-10-19"),
+            Ok(fact("COVID-19 booster", "2026-09-30"))
+        );
+        assert_eq!(
+            parse_vaccination("MMR dose 2", "2026-10-19"),
+            Ok(fact("MMR dose 2", "2026-10-19"))
+        );
+        assert!(parse_vaccination("Influenza on 2026-13-01", "2026-10-19").is_err());
+        assert!(parse_vaccination("on 2026-10-01", "2026-10-19").is_err());
+    }
+}
@@ -0,0 +1,17 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::Result;
+
+use super::filter::{self, Filter, FilterArgs, Query};
+
+pub fn run(query: String, regex: bool, args: FilterArgs) -> Result<()> {
+    let query = Query::parse(&query, regex)?;
+    let entries = filter::matching_entries(&Filter::from_args(&args, Some(query))?)?;
+    filter::print(
+        &entries,
+        args.time,
+        args.json,
+        "No matching journal entries.",
+    )
+}
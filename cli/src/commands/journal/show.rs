@@ -2,17 +2,14 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use anyhow::Result;
+use serde_json::Value;
 use std::{fs, path::PathBuf};
 
-use super::parse_journal_file;
+use super::{ParsedEntry, parse_journal_file, thread};
+use crate::medmd;
 
-pub fn run(filename: String, raw: bool, metadata: bool) -> Result<()> {
-    let filename = super::resolve_entry(&filename)?;
-    let path = PathBuf::from("journal").join(&filename);
-
-    if !path.exists() {
-        anyhow::bail!("Journal entry not found: {}", filename);
-    }
+pub fn run(filename: String, raw: bool, metadata: bool, structured: bool) -> Result<()> {
+    let (filename, path) = locate(&filename)?;
 
     if raw {
         let content = fs::read_to_string(&path)?;
@@ -33,5 +30,64 @@ pub fn run(filename: String, raw: bool, metadata: bool) -> Result<()> {
     // default: body only
     let parsed = parse_journal_file(&path)?;
     println!("{}", parsed.content);
+    if structured {
+        println!();
+        print_structured(&parsed, &medmd::Registry::load()?);
+    }
+    thread::print_links(&parsed)?;
     Ok(())
 }
+
+/// `journal extract`: the entry's structured data, with its `_source_map`.
+pub fn extract(filename: String) -> Result<()> {
+    let (_, path) = locate(&filename)?;
+    let parsed = parse_journal_file(&path)?;
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&medmd::extract(&parsed.content))?
+    );
+    Ok(())
+}
+
+pub(super) fn locate(reference: &str) -> Result<(String, PathBuf)> {
+    let filename = super::resolve_entry(reference)?;
+    let path = PathBuf::from("journal").join(&filename);
+    if !path.exists() {
+        anyhow::bail!("Journal entry not found: {}", filename);
+    }
+    Ok((filename, path))
+}
+
+/// Coded sections in body order, as `Heading (CODE): notes`, sub-codes
+/// indented beneath their section.
+fn print_structured(entry: &ParsedEntry, registry: &medmd::Registry) {
+    let data = medmd::extract(&entry.content);
+    let Some(source_map) = data.get("_source_map").and_then(Value::as_object) else {
+        println!("(No Medical Markdown codes.)");
+        return;
+    };
+    let mut keys: Vec<(&String, u64)> = source_map
+        .iter()
+        .map(|(key, line)| (key, line.as_u64().unwrap_or(0)))
+        .collect();
+    keys.sort_by_key(|(_, line)| *line);
+
+    println!("Medical Markdown:");
+    for (key, _) in keys {
+        let (indent, code, notes) = match key.split_once('.') {
+            Some((section, sub)) => ("    ", sub, &data[section][sub]),
+            None => ("  ", key.as_str(), &data[key.as_str()]["notes"]),
+        };
+        let mut lines = notes.as_str().unwrap_or("").lines();
+        println!(
+            "{}{} ({}): {}",
+            indent,
+            registry.heading(code),
+            code,
+            lines.next().unwrap_or("")
+        );
+        for line in lines {
+            println!("{}  {}", indent, line);
+        }
+    }
+}
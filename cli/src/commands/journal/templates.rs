@@ -0,0 +1,293 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Journal entry templates: the structures clinicians write again and again,
+//! such as a new-patient review or a telephone triage.
+//!
+//! A template is `<name>.md`, optionally starting with YAML front matter
+//! giving a `description`. It is looked for, first match wins, in:
+//!
+//! 1. the repository: `.gitehr/templates/`
+//! 2. the Store the repository is in: `gitehr-templates/` beside
+//!    `gitehr-mpi.json`
+//! 3. the user: `templates/` beside the GitEHR config file
+//!
+//! `{{placeholder}}`s are filled from the record when the template
+//! pre-populates the editor; see [`placeholders`]. Unknown ones are left as
+//! they are.
+
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use clap::Subcommand;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commands::{allergies, context, contributor, demographics};
+use crate::utils::substitute;
+
+pub const TEMPLATES_DIR: &str = ".gitehr/templates";
+pub const STORE_TEMPLATES_DIR: &str = "gitehr-templates";
+pub const USER_TEMPLATES_DIR: &str = "templates";
+
+#[derive(Subcommand)]
+pub enum TemplateCommands {
+    #[command(about = "List the journal templates available here, and where each comes from")]
+    List,
+    #[command(about = "Print a journal template")]
+    Show {
+        #[arg(help = "Template name")]
+        name: String,
+        #[arg(
+            long,
+            help = "Fill in the placeholders from the record, as `journal add --template` would"
+        )]
+        render: bool,
+    },
+}
+
+/// Where a template was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Repository,
+    Store,
+    User,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Source::Repository => "repository",
+            Source::Store => "store",
+            Source::User => "user",
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Template {
+    pub name: String,
+    pub description: Option<String>,
+    pub source: Source,
+    pub path: PathBuf,
+    pub body: String,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateFrontMatter {
+    description: Option<String>,
+}
+
+pub fn run(command: TemplateCommands) -> Result<()> {
+    match command {
+        TemplateCommands::List => {
+            let templates = available()?;
+            if templates.is_empty() {
+                println!(
+                    "No journal templates. Add <name>.md to {}/, or see `gitehr journal templates --help`.",
+                    TEMPLATES_DIR
+                );
+                return Ok(());
+            }
+            for template in templates.values() {
+                println!(
+                    "{:<24} {:<11} {}",
+                    template.name,
+                    template.source,
+                    template.description.as_deref().unwrap_or("")
+                );
+            }
+            Ok(())
+        }
+        TemplateCommands::Show { name, render } => {
+            let template = find(&name)?;
+            if render {
+                println!("{}", template.render(&placeholders()?));
+            } else {
+                eprintln!("# {} ({})", template.path.display(), template.source);
+                println!("{}", template.body);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The directories searched for templates, in order of precedence. The
+/// Store and user directories are left out when they cannot be found.
+pub fn search_path() -> Result<Vec<(Source, PathBuf)>> {
+    let mut dirs = vec![(Source::Repository, PathBuf::from(TEMPLATES_DIR))];
+    if let Some(store) = context::enclosing_store_root()? {
+        dirs.push((Source::Store, store.join(STORE_TEMPLATES_DIR)));
+    }
+    if let Ok(config) = crate::config::config_dir() {
+        dirs.push((Source::User, config.join(USER_TEMPLATES_DIR)));
+    }
+    Ok(dirs)
+}
+
+/// Every template by name. A repository template hides a Store or user one
+/// of the same name, and a Store template a user one.
+pub fn available() -> Result<BTreeMap<String, Template>> {
+    let mut templates = BTreeMap::new();
+    for (source, dir) in search_path()? {
+        if !dir.is_dir() {
+            continue;
+        }
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|s| s.to_str()) != Some("md") {
+                continue;
+            }
+            // One bad file, perhaps in a directory shared by every
+            // repository, should not hide the others.
+            match load(&path, source) {
+                Ok(template) => {
+                    templates.entry(template.name.clone()).or_insert(template);
+                }
+                Err(e) => eprintln!("Warning: skipping template: {}", e),
+            }
+        }
+    }
+    Ok(templates)
+}
+
+/// The template called `name`.
+pub fn find(name: &str) -> Result<Template> {
+    let mut templates = available()?;
+    templates.remove(name).ok_or_else(|| {
+        if templates.is_empty() {
+            anyhow::anyhow!(
+                "No journal template '{}': there are no templates. Add {}/{}.md.",
+                name,
+                TEMPLATES_DIR,
+                name
+            )
+        } else {
+            anyhow::anyhow!(
+                "No journal template '{}'. Available: {}",
+                name,
+                templates.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        }
+    })
+}
+
+impl Template {
+    /// The template body with `values` substituted.
+    pub fn render(&self, values: &BTreeMap<String, String>) -> String {
+        substitute(&self.body, values)
+    }
+}
+
+fn load(path: &Path, source: Source) -> Result<Template> {
+    let name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        anyhow::bail!(
+            "Invalid template name '{}' in {}: use letters, digits, '_' and '-'",
+            name,
+            path.display()
+        );
+    }
+
+    let content = fs::read_to_string(path)?;
+    let (description, body) = match content
+        .strip_prefix("---")
+        .and_then(|rest| rest.split_once("\n---"))
+    {
+        Some((yaml, body)) => {
+            let front_matter: TemplateFrontMatter = serde_yaml_ng::from_str(yaml).map_err(|e| {
+                anyhow::anyhow!("Invalid front matter in {}: {}", path.display(), e)
+            })?;
+            (front_matter.description, body)
+        }
+        None => (None, content.as_str()),
+    };
+
+    Ok(Template {
+        name,
+        description,
+        source,
+        path: path.to_path_buf(),
+        body: body.trim().to_string(),
+    })
+}
+
+/// Values for the placeholders, from the record and the current contributor:
+///
+/// - `{{date}}`: today, `YYYY-MM-DD`
+/// - `{{patient.name}}`, `{{patient.preferred_name}}`,
+///   `{{patient.date_of_birth}}`, `{{patient.age}}`, `{{patient.nhs_number}}`
+/// - `{{allergies}}`: active allergies, one `- agent: reaction (severity)`
+///   line each
+/// - `{{contributor.name}}`, `{{contributor.role}}`, `{{contributor.id}}`
+///
+/// Anything the record does not hold is "not recorded".
+pub fn placeholders() -> Result<BTreeMap<String, String>> {
+    const NOT_RECORDED: &str = "not recorded";
+    let today = Local::now().date_naive();
+    let d = demographics::load()?;
+    let name = [d.title.as_deref(), d.full_name.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let age = d
+        .date_of_birth
+        .as_deref()
+        .and_then(|dob| NaiveDate::parse_from_str(dob, "%Y-%m-%d").ok())
+        .and_then(|dob| today.years_since(dob))
+        .map(|years| years.to_string());
+
+    let allergies = allergies::list(false)?;
+    let allergies = if allergies.is_empty() {
+        // Not the same as "no known allergies": nothing has been recorded.
+        "None recorded.".to_string()
+    } else {
+        allergies
+            .iter()
+            .map(|a| format!("- {}: {} ({})", a.agent, a.reaction, a.severity))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let id = contributor::get_current_contributor();
+    let current = id.as_deref().and_then(contributor::get_contributor);
+
+    let values = [
+        ("date", Some(today.format("%Y-%m-%d").to_string())),
+        ("patient.name", Some(name).filter(|n| !n.is_empty())),
+        ("patient.preferred_name", d.preferred_name),
+        ("patient.date_of_birth", d.date_of_birth),
+        ("patient.age", age),
+        ("patient.nhs_number", d.nhs_number),
+        ("allergies", Some(allergies)),
+        (
+            "contributor.name",
+            current.as_ref().map(|c| c.name.clone()).or(id.clone()),
+        ),
+        (
+            "contributor.role",
+            current.as_ref().and_then(|c| c.role.clone()),
+        ),
+        ("contributor.id", id),
+    ];
+    Ok(values
+        .into_iter()
+        .map(|(key, value)| {
+            (
+                key.to_string(),
+                value.unwrap_or_else(|| NOT_RECORDED.to_string()),
+            )
+        })
+        .collect())
+}
@@ -0,0 +1,286 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Links between entries: a follow-up names the entry it replies to in
+//! `in_reply_to`, and may name others it bears on in `related`, both by
+//! filename. Only these forward links are written; replies and backlinks are
+//! derived by scanning the journal, as `document::collect_refs` derives
+//! Document references, so an entry never changes once it is recorded.
+
+use anyhow::Result;
+use clap::Args;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use super::{JournalEntry, ParsedEntry, filter, parse_journal_file, parsed_entries, show};
+use crate::commands::contributor;
+
+#[derive(Args, Default)]
+pub struct LinkArgs {
+    #[arg(
+        long,
+        value_name = "ENTRY",
+        help = "The entry this one follows up (filename or LATEST, LATEST^, LATEST~N)"
+    )]
+    pub reply_to: Option<String>,
+    #[arg(
+        long,
+        value_name = "ENTRY",
+        help = "Another entry this one relates to (repeatable)"
+    )]
+    pub related: Vec<String>,
+}
+
+impl LinkArgs {
+    /// `entry` with the links set, each resolved to an existing entry.
+    pub fn apply(&self, entry: JournalEntry) -> Result<JournalEntry> {
+        let in_reply_to = self.reply_to.as_deref().map(existing).transpose()?;
+        let mut related: Vec<String> = Vec::new();
+        for reference in &self.related {
+            let filename = existing(reference)?;
+            if !related.contains(&filename) {
+                related.push(filename);
+            }
+        }
+        Ok(JournalEntry {
+            in_reply_to,
+            related,
+            ..entry
+        })
+    }
+}
+
+/// The filename of the entry `reference` names, which must exist. The
+/// `journal/` prefix printed when an entry is created is accepted.
+fn existing(reference: &str) -> Result<String> {
+    let reference = reference.strip_prefix("journal/").unwrap_or(reference);
+    Ok(show::locate(reference)?.0)
+}
+
+/// Check the links in front matter that may have been edited by hand.
+pub fn check(entry: &JournalEntry) -> Result<()> {
+    for filename in entry.in_reply_to.iter().chain(&entry.related) {
+        existing(filename)?;
+    }
+    Ok(())
+}
+
+/// How one entry points at another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkKind {
+    InReplyTo,
+    Related,
+}
+
+/// Every link in the journal as `(from, to, kind)`, oldest entry first.
+/// This is the derived reverse lookup.
+pub fn collect_links() -> Result<Vec<(String, String, LinkKind)>> {
+    let mut links = Vec::new();
+    for entry in parsed_entries()? {
+        let meta = &entry.metadata;
+        if let Some(parent) = &meta.in_reply_to {
+            links.push((entry.filename.clone(), parent.clone(), LinkKind::InReplyTo));
+        }
+        for related in &meta.related {
+            links.push((entry.filename.clone(), related.clone(), LinkKind::Related));
+        }
+    }
+    Ok(links)
+}
+
+/// The entries that link to `filename`, oldest first.
+pub fn backlinks(filename: &str) -> Result<Vec<(String, LinkKind)>> {
+    Ok(collect_links()?
+        .into_iter()
+        .filter(|(_, to, _)| to == filename)
+        .map(|(from, _, kind)| (from, kind))
+        .collect())
+}
+
+/// One entry in a thread, with how deep it sits under the root.
+pub struct ThreadEntry {
+    pub entry: ParsedEntry,
+    pub depth: usize,
+}
+
+/// The conversation `filename` belongs to: the root it replies back to, then
+/// each reply under the entry it answers, oldest first. Also returns the
+/// root's `in_reply_to` when that entry is not in this journal.
+pub fn thread(filename: &str) -> Result<(Vec<ThreadEntry>, Option<String>)> {
+    let mut by_name: HashMap<String, ParsedEntry> = HashMap::new();
+    let mut replies: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for entry in parsed_entries()? {
+        if let Some(parent) = &entry.metadata.in_reply_to {
+            replies
+                .entry(parent.clone())
+                .or_default()
+                .push(entry.filename.clone());
+        }
+        by_name.insert(entry.filename.clone(), entry);
+    }
+
+    // Hand-edited front matter could form a loop; stop where one closes.
+    let mut root = filename.to_string();
+    let mut seen = HashSet::from([root.clone()]);
+    let mut missing = None;
+    while let Some(parent) = by_name
+        .get(&root)
+        .and_then(|e| e.metadata.in_reply_to.clone())
+    {
+        if !by_name.contains_key(&parent) {
+            missing = Some(parent);
+            break;
+        }
+        if !seen.insert(parent.clone()) {
+            break;
+        }
+        root = parent;
+    }
+
+    let mut thread = Vec::new();
+    let mut stack = vec![(root, 0)];
+    let mut placed = HashSet::new();
+    while let Some((name, depth)) = stack.pop() {
+        if !placed.insert(name.clone()) {
+            continue;
+        }
+        for child in replies.get(&name).into_iter().flatten().rev() {
+            stack.push((child.clone(), depth + 1));
+        }
+        if let Some(entry) = by_name.remove(&name) {
+            thread.push(ThreadEntry { entry, depth });
+        }
+    }
+    Ok((thread, missing))
+}
+
+pub fn run(reference: String, json: bool) -> Result<()> {
+    let (filename, _) = show::locate(&reference)?;
+    let (thread, missing) = thread(&filename)?;
+
+    if json {
+        let output: Vec<_> = thread
+            .iter()
+            .map(|t| ThreadOutput {
+                filename: &t.entry.filename,
+                depth: t.depth,
+                timestamp: t.entry.metadata.timestamp,
+                in_reply_to: t.entry.metadata.in_reply_to.as_deref(),
+                author: t.entry.metadata.author.as_deref(),
+                first_line: filter::first_line(&t.entry.content),
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    if let Some(parent) = missing {
+        println!("(in reply to {}, which is not in this journal)", parent);
+    }
+    let names = contributor::contributor_names();
+    for (i, t) in thread.iter().enumerate() {
+        println!(
+            "{}{}  {}  {}{}",
+            branch(&thread, i),
+            t.entry.metadata.timestamp.format("%Y-%m-%d %H:%M"),
+            filter::author_name(&t.entry, &names).unwrap_or("-"),
+            filter::first_line(&t.entry.content),
+            if t.entry.filename == filename {
+                "  <- this entry"
+            } else {
+                ""
+            }
+        );
+    }
+    println!(
+        "\n({} entr{})",
+        thread.len(),
+        if thread.len() == 1 { "y" } else { "ies" }
+    );
+    Ok(())
+}
+
+/// The tree drawing before entry `i`: a `│` for each ancestor with a later
+/// sibling, then `├─`, or `└─` for the last reply to its parent.
+fn branch(thread: &[ThreadEntry], i: usize) -> String {
+    let depth = thread[i].depth;
+    if depth == 0 {
+        return String::new();
+    }
+    // Whether another entry at `level` follows before the thread climbs
+    // back above it.
+    let continues = |level: usize| {
+        thread[i + 1..]
+            .iter()
+            .take_while(|t| t.depth >= level)
+            .any(|t| t.depth == level)
+    };
+    let mut prefix = String::new();
+    for level in 1..depth {
+        prefix.push_str(if continues(level) { "│  " } else { "   " });
+    }
+    prefix.push_str(if continues(depth) {
+        "├─ "
+    } else {
+        "└─ "
+    });
+    prefix
+}
+
+#[derive(Serialize)]
+struct ThreadOutput<'a> {
+    filename: &'a str,
+    depth: usize,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    in_reply_to: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    author: Option<&'a str>,
+    first_line: &'a str,
+}
+
+/// The links section `journal show` prints after an entry: what it replies
+/// to and relates to, then the entries that reply to it or relate to it.
+pub fn print_links(entry: &ParsedEntry) -> Result<()> {
+    let backlinks = backlinks(&entry.filename)?;
+    let meta = &entry.metadata;
+    if meta.in_reply_to.is_none() && meta.related.is_empty() && backlinks.is_empty() {
+        return Ok(());
+    }
+    let linking = |kind: LinkKind| -> Vec<&str> {
+        backlinks
+            .iter()
+            .filter(|(_, k)| *k == kind)
+            .map(|(from, _)| from.as_str())
+            .collect()
+    };
+    let sections = [
+        (
+            "In reply to",
+            meta.in_reply_to.iter().map(String::as_str).collect(),
+        ),
+        ("Related", meta.related.iter().map(String::as_str).collect()),
+        ("Replies", linking(LinkKind::InReplyTo)),
+        ("Referenced by", linking(LinkKind::Related)),
+    ];
+    println!();
+    for (heading, filenames) in sections {
+        if filenames.is_empty() {
+            continue;
+        }
+        println!("{}:", heading);
+        for filename in filenames {
+            println!("  {}  {}", filename, summary(filename));
+        }
+    }
+    Ok(())
+}
+
+/// An entry's first line, or a note that it is not in this journal.
+fn summary(filename: &str) -> String {
+    match parse_journal_file(&std::path::PathBuf::from("journal").join(filename)) {
+        Ok(entry) => filter::first_line(&entry.content).to_string(),
+        Err(_) => "(not in this journal)".to_string(),
+    }
+}
@@ -0,0 +1,274 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Audit log of what MCP clients read and wrote.
+//!
+//! `.gitehr/mcp-audit.jsonl` gets one JSON line per `resources/read`,
+//! `tools/call` and `prompts/get`. Lines are only ever appended, and each
+//! carries the SHA-256 of the line before it, so an edited or deleted line
+//! breaks the chain. A write commits the log beside a journal entry naming its
+//! agent; other lines wait for the next write, or for the server to stop.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use clap::Args;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::commands::typed_state;
+use crate::utils::{parse_bound, sha256_hex};
+
+pub const AUDIT_LOG: &str = ".gitehr/mcp-audit.jsonl";
+
+/// `prev` of the first line.
+const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Serialises appends from concurrent sessions in this process.
+static APPEND_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Args)]
+pub struct AuditArgs {
+    #[arg(
+        long,
+        help = "Only records on or after this date (YYYY-MM-DD or RFC 3339)"
+    )]
+    since: Option<String>,
+    #[arg(
+        long,
+        help = "Only records on or before this date (YYYY-MM-DD or RFC 3339)"
+    )]
+    until: Option<String>,
+    #[arg(long, help = "Only records from this client name (case-insensitive)")]
+    client: Option<String>,
+    #[arg(long, help = "Emit JSON for GUI or automation callers")]
+    json: bool,
+    #[arg(
+        long,
+        help = "Show the Store's log of subject lookups, from 'mcp serve --store'"
+    )]
+    pub store: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Ok,
+    /// Refused for want of authentication or scope.
+    Denied,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub timestamp: DateTime<Utc>,
+    /// `clientInfo.name` (and version) from `initialize`.
+    pub client: Option<String>,
+    pub client_version: Option<String>,
+    pub token: Option<String>,
+    pub contributor: Option<String>,
+    /// `resources/read`, `tools/call` or `prompts/get`.
+    pub operation: String,
+    /// The resource URI, or the tool or prompt name.
+    pub target: String,
+    /// SHA-256 of the request params as canonical (key-sorted) JSON, so a
+    /// call can be matched without copying clinical content into the log.
+    pub arguments_sha256: String,
+    pub outcome: Outcome,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Journal entry recording a write, when there was one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub journal_entry: Option<String>,
+    /// SHA-256 of the previous line.
+    #[serde(default)]
+    pub prev: String,
+}
+
+/// The audit log of a repository, or of a Store root for operations that
+/// address no single subject.
+pub fn log_path(root: &Path) -> PathBuf {
+    if super::is_store_root(root) {
+        root.join("gitehr-mcp-audit.jsonl")
+    } else {
+        root.join(AUDIT_LOG)
+    }
+}
+
+/// Append `record` to the repository's audit log, chaining it to the last
+/// line.
+pub fn append(repo: &Path, mut record: AuditRecord) -> Result<()> {
+    let _guard = APPEND_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let path = log_path(repo);
+    record.prev = match fs::read_to_string(&path) {
+        Ok(content) => content
+            .lines()
+            .rfind(|l| !l.trim().is_empty())
+            .map(|l| sha256_hex(l.as_bytes()))
+            .unwrap_or_else(|| GENESIS.to_string()),
+        Err(_) => GENESIS.to_string(),
+    };
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", serde_json::to_string(&record)?)?;
+    Ok(())
+}
+
+/// Every record in the log, with the 1-based line numbers where the hash
+/// chain is broken.
+pub fn read(repo: &Path) -> Result<(Vec<AuditRecord>, Vec<usize>)> {
+    let path = log_path(repo);
+    if !path.exists() {
+        return Ok((Vec::new(), Vec::new()));
+    }
+    let content = fs::read_to_string(&path)?;
+    let mut records = Vec::new();
+    let mut broken = Vec::new();
+    let mut expected = GENESIS.to_string();
+    for (i, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str::<AuditRecord>(line) {
+            Ok(record) => {
+                if record.prev != expected {
+                    broken.push(i + 1);
+                }
+                records.push(record);
+            }
+            Err(_) => broken.push(i + 1),
+        }
+        expected = sha256_hex(line.as_bytes());
+    }
+    Ok((records, broken))
+}
+
+pub fn run(args: AuditArgs) -> Result<()> {
+    let root = if args.store {
+        crate::commands::context::resolve_store_root()?
+    } else {
+        PathBuf::new()
+    };
+    if !super::is_store_root(&root) {
+        typed_state::ensure_gitehr_repository()?;
+    }
+    let since = args
+        .since
+        .as_deref()
+        .map(|s| parse_bound(s, false))
+        .transpose()?;
+    let until = args
+        .until
+        .as_deref()
+        .map(|s| parse_bound(s, true))
+        .transpose()?;
+
+    let (records, broken) = read(&root)?;
+    if !broken.is_empty() {
+        eprintln!(
+            "Warning: {} has been altered; hash chain broken at line{} {}",
+            log_path(&root).display(),
+            if broken.len() == 1 { "" } else { "s" },
+            broken
+                .iter()
+                .map(usize::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+    }
+
+    let records: Vec<_> = records
+        .into_iter()
+        .filter(|r| since.is_none_or(|since| r.timestamp >= since))
+        .filter(|r| until.is_none_or(|until| r.timestamp <= until))
+        .filter(|r| {
+            args.client.as_deref().is_none_or(|client| {
+                r.client
+                    .as_deref()
+                    .is_some_and(|c| c.eq_ignore_ascii_case(client))
+            })
+        })
+        .collect();
+
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&records)?);
+        return Ok(());
+    }
+    if records.is_empty() {
+        println!("No MCP audit records.");
+    }
+    for record in &records {
+        let mut line = format!(
+            "{}  {}  {}  {} {}  {}",
+            record.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            record.client.as_deref().unwrap_or("(unknown client)"),
+            record
+                .contributor
+                .as_deref()
+                .or(record.token.as_deref())
+                .unwrap_or("-"),
+            record.operation,
+            record.target,
+            match record.outcome {
+                Outcome::Ok => "ok",
+                Outcome::Denied => "DENIED",
+                Outcome::Error => "ERROR",
+            }
+        );
+        if let Some(error) = &record.error {
+            line.push_str(&format!(": {}", error));
+        }
+        if let Some(entry) = &record.journal_entry {
+            line.push_str(&format!("  -> {}", entry));
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(target: &str) -> AuditRecord {
+        AuditRecord {
+            timestamp: Utc::now(),
+            client: Some("test-client".to_string()),
+            client_version: None,
+            token: None,
+            contributor: Some("doc001".to_string()),
+            operation: "resources/read".to_string(),
+            target: target.to_string(),
+            arguments_sha256: sha256_hex(b"{}"),
+            outcome: Outcome::Ok,
+            error: None,
+            journal_entry: None,
+            prev: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_chain_detects_edits() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join(".gitehr")).unwrap();
+        for uri in [
+            "gitehr://repo/journal",
+            "gitehr://repo/state",
+            "gitehr://repo/status",
+        ] {
+            append(dir.path(), record(uri)).unwrap();
+        }
+        let (records, broken) = read(dir.path()).unwrap();
+        assert_eq!(records.len(), 3);
+        assert!(broken.is_empty());
+
+        let path = log_path(dir.path());
+        let edited = fs::read_to_string(&path)
+            .unwrap()
+            .replace("repo/state", "repo/other");
+        fs::write(&path, edited).unwrap();
+        let (_, broken) = read(dir.path()).unwrap();
+        assert_eq!(broken, vec![3]);
+    }
+}
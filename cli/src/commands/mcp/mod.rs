@@ -3,13 +3,26 @@
 
 use anyhow::Result;
 use clap::Subcommand;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use audit::AuditArgs;
+use token::TokenCommands;
+
+pub mod audit;
 pub mod serve;
 mod server_impl;
+pub mod token;
 
 const MCP_VERSION: &str = "2024-11-05";
 
+/// Marks a Store root. Store-level MCP files sit beside it, never in a
+/// `.gitehr/` directory, which would make the Store root look like a subject.
+const STORE_MARKER: &str = "gitehr-mpi.json";
+
+pub(crate) fn is_store_root(root: &Path) -> bool {
+    root.join(STORE_MARKER).exists()
+}
+
 fn init_tracing() {
     use tracing_subscriber::{EnvFilter, fmt};
 
@@ -27,16 +40,79 @@ pub enum McpCommands {
     Serve {
         #[arg(long, help = "Use stdio transport (default)")]
         stdio: bool,
+        #[arg(
+            long,
+            value_name = "ADDR",
+            conflicts_with_all = ["stdio", "unix"],
+            help = "Serve Streamable HTTP on ADDR (e.g. 127.0.0.1:8765) at /mcp"
+        )]
+        http: Option<String>,
+        #[arg(
+            long,
+            value_name = "PATH",
+            conflicts_with = "stdio",
+            help = "Serve newline-delimited JSON-RPC on a Unix socket at PATH"
+        )]
+        unix: Option<PathBuf>,
         #[arg(long, help = "Repository path (default: current directory)")]
         repo_path: Option<PathBuf>,
+        #[arg(
+            long,
+            value_name = "PATH",
+            num_args = 0..=1,
+            conflicts_with = "repo_path",
+            help = "Serve every subject in a Store (default: the current Store)"
+        )]
+        store: Option<Option<PathBuf>>,
+        #[arg(
+            long,
+            value_name = "ID",
+            conflicts_with_all = ["http", "unix"],
+            help = "Contributor that MCP writes are attributed to (default: the active contributor). Over HTTP and Unix sockets, writes are attributed to the token's contributor"
+        )]
+        contributor: Option<String>,
+    },
+    #[command(about = "Manage bearer tokens for the HTTP and Unix socket transports")]
+    Token {
+        #[arg(long, help = "Manage the Store's tokens, for 'mcp serve --store'")]
+        store: bool,
+        #[command(subcommand)]
+        command: TokenCommands,
     },
+    #[command(about = "Show the audit log of MCP reads and writes")]
+    Audit(AuditArgs),
 }
 
 pub fn run(command: McpCommands) -> Result<()> {
     match command {
         McpCommands::Serve {
             stdio: _,
+            http,
+            unix,
             repo_path,
-        } => serve::run(repo_path),
+            store,
+            contributor,
+        } => {
+            let transport = match (http, unix) {
+                (Some(addr), _) => serve::Transport::Http(addr),
+                (None, Some(path)) => serve::Transport::Unix(path),
+                (None, None) => serve::Transport::Stdio,
+            };
+            let (root, store) = match store {
+                Some(Some(path)) => (Some(path), true),
+                Some(None) => (Some(crate::commands::context::resolve_store_root()?), true),
+                None => (repo_path, false),
+            };
+            serve::run(transport, root, store, contributor)
+        }
+        McpCommands::Token { store, command } => {
+            let root = if store {
+                Some(crate::commands::context::resolve_store_root()?)
+            } else {
+                None
+            };
+            token::run(command, root)
+        }
+        McpCommands::Audit(args) => audit::run(args),
     }
 }
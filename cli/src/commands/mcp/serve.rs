@@ -4,20 +4,74 @@
 use anyhow::Result;
 use std::path::PathBuf;
 
-use super::server_impl::{McpServer, ServerConfig};
+use super::server_impl::{self, McpServer, ServerConfig};
+use super::token;
+use tracing::warn;
 
-pub fn run(repo_path: Option<PathBuf>) -> Result<()> {
+/// Where the server listens.
+pub enum Transport {
+    Stdio,
+    Http(String),
+    Unix(PathBuf),
+}
+
+/// Serve the repository at `root`, or with `store`, every subject in the Store
+/// at `root`.
+pub fn run(
+    transport: Transport,
+    root: Option<PathBuf>,
+    store: bool,
+    contributor: Option<String>,
+) -> Result<()> {
     super::init_tracing();
 
+    // Tools switch the working directory while they run (`with_repo_cwd`),
+    // so every other path the server uses hangs off this absolute root. One
+    // that cannot be resolved holds nothing to serve; requests report that.
+    let root = root.unwrap_or_else(|| PathBuf::from("."));
+    let repo_path = root.canonicalize().unwrap_or_else(|e| {
+        warn!("Cannot resolve {}: {}", root.display(), e);
+        root
+    });
     let config = ServerConfig {
-        repo_path: repo_path.unwrap_or_else(|| PathBuf::from(".")),
+        repo_path,
         server_name: "gitehr".to_string(),
         server_version: env!("CARGO_PKG_VERSION").to_string(),
+        contributor,
+        require_token: !matches!(transport, Transport::Stdio),
+        store,
     };
+    if store && !super::is_store_root(&config.repo_path) {
+        anyhow::bail!(
+            "Not a GitEHR Store root: {} (gitehr-mpi.json not found)",
+            config.repo_path.display()
+        );
+    }
 
     let runtime = tokio::runtime::Runtime::new()?;
-    runtime.block_on(async {
-        let mut server = McpServer::new(config);
-        server.run_stdio().await
-    })
+    if config.require_token
+        && !token::load(&config.repo_path)?
+            .tokens
+            .iter()
+            .any(|t| t.is_active())
+    {
+        warn!(
+            "No active MCP tokens in {}; create one with 'gitehr mcp token{} create'",
+            token::store_path(&config.repo_path).display(),
+            if store { " --store" } else { "" }
+        );
+    }
+
+    let served = runtime.block_on(async {
+        let shutdown = server_impl::on_signal();
+        match transport {
+            Transport::Stdio => McpServer::new(config).run_stdio(shutdown).await,
+            Transport::Http(addr) => server_impl::run_http(config, &addr, shutdown).await,
+            Transport::Unix(path) => server_impl::run_unix(config, &path, shutdown).await,
+        }
+    });
+    if let Err(e) = server_impl::commit_audit_logs() {
+        warn!("Failed to commit the MCP audit log: {}", e);
+    }
+    served
 }
@@ -0,0 +1,96 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Who a session acts as, and what it may do.
+
+use std::path::Path;
+
+use super::protocol::McpError;
+use crate::commands::mcp::token::{self, Scope, TokenEntry};
+
+/// The identity behind a session: a bearer token on the networked transports,
+/// or the local user on stdio.
+#[derive(Debug, Clone)]
+pub struct Principal {
+    /// Contributor writes are attributed to. `None` on stdio without
+    /// `--contributor` means the repository's active contributor.
+    pub contributor: Option<String>,
+    pub token_id: Option<String>,
+    scopes: Vec<Scope>,
+}
+
+impl Principal {
+    /// The local user of a stdio server, trusted with every scope.
+    pub fn local(contributor: Option<String>) -> Self {
+        Self {
+            contributor,
+            token_id: None,
+            scopes: vec![Scope::Read, Scope::JournalWrite, Scope::StateWrite],
+        }
+    }
+
+    pub fn from_token(entry: &TokenEntry) -> Self {
+        Self {
+            contributor: Some(entry.contributor.clone()),
+            token_id: Some(entry.id.clone()),
+            scopes: entry.scopes.clone(),
+        }
+    }
+
+    /// Check the principal may act with `scope` (or at all, for `None`). A
+    /// token is re-checked against the store so revocation takes effect
+    /// immediately.
+    pub fn authorize(&self, repo_path: &Path, scope: Option<Scope>) -> Result<(), McpError> {
+        if let Some(id) = &self.token_id
+            && !token::is_active(repo_path, id)
+        {
+            return Err(McpError::unauthorized("Token has been revoked"));
+        }
+        match scope {
+            Some(scope) if !self.scopes.contains(&scope) => Err(McpError::unauthorized(format!(
+                "Token does not grant the '{}' scope",
+                scope
+            ))),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The token from an `Authorization: Bearer <token>` value.
+pub fn bearer(value: &str) -> Option<&str> {
+    let (scheme, token) = value.trim().split_once(' ')?;
+    scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+}
+
+/// Resolve a presented `Authorization` value to a principal.
+pub fn authenticate(repo_path: &Path, authorization: Option<&str>) -> Result<Principal, McpError> {
+    let secret = authorization
+        .and_then(bearer)
+        .ok_or_else(|| McpError::unauthorized("Authentication required: send a bearer token"))?;
+    match token::authenticate(repo_path, secret) {
+        Ok(Some(entry)) => Ok(Principal::from_token(&entry)),
+        Ok(None) => Err(McpError::unauthorized("Invalid or revoked token")),
+        Err(e) => Err(McpError::internal_error(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bearer_parsing() {
+        assert_eq!(bearer("Bearer gehr_abc"), Some("gehr_abc"));
+        assert_eq!(bearer("bearer  gehr_abc "), Some("gehr_abc"));
+        assert_eq!(bearer("Basic dXNlcjpwYXNz"), None);
+        assert_eq!(bearer("gehr_abc"), None);
+    }
+
+    #[test]
+    fn test_local_principal_has_every_scope() {
+        let principal = Principal::local(None);
+        for scope in [Scope::Read, Scope::JournalWrite, Scope::StateWrite] {
+            assert!(principal.authorize(Path::new("."), Some(scope)).is_ok());
+        }
+    }
+}
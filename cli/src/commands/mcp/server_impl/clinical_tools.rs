@@ -0,0 +1,374 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Typed MCP tools over allergies, vaccinations and demographics.
+//!
+//! Each tool deserialises its arguments into a struct mirroring the library
+//! call it wraps, and returns the resulting typed state as structured JSON.
+//! Writes go through the same functions as the CLI, so they are journalled
+//! and committed; they are attributed to the server's MCP contributor.
+
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+
+use super::tools::{Tool, ToolResult};
+use crate::commands::allergies::{self, AllergySeverity};
+use crate::commands::demographics::{self, DemographicsUpdate, Identifier};
+use crate::commands::mcp::token::Scope;
+use crate::commands::vaccinations::{self, VaccinationInput};
+use crate::commands::{git, typed_state};
+
+pub const NAMES: &[&str] = &[
+    "list_allergies",
+    "add_allergy",
+    "inactivate_allergy",
+    "list_vaccinations",
+    "add_vaccination",
+    "vaccination_entered_in_error",
+    "get_demographics",
+    "update_demographics",
+];
+
+/// Reads need `read`; every write changes typed state.
+pub fn required_scope(name: &str) -> Option<Scope> {
+    match name {
+        "list_allergies" | "list_vaccinations" | "get_demographics" => Some(Scope::Read),
+        name if NAMES.contains(&name) => Some(Scope::StateWrite),
+        _ => None,
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ListAllergiesArgs {
+    #[serde(default)]
+    include_inactive: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AddAllergyArgs {
+    agent: String,
+    reaction: String,
+    #[serde(default = "default_severity")]
+    severity: AllergySeverity,
+    note: Option<String>,
+}
+
+fn default_severity() -> AllergySeverity {
+    AllergySeverity::Moderate
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InactivateArgs {
+    id: String,
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ListVaccinationsArgs {
+    #[serde(default)]
+    include_entered_in_error: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct AddVaccinationArgs {
+    vaccine: String,
+    date: String,
+    dose_sequence: Option<u32>,
+    #[serde(default)]
+    target_disease: Vec<String>,
+    anatomical_site: Option<String>,
+    route: Option<String>,
+    product: Option<String>,
+    manufacturer: Option<String>,
+    batch_number: Option<String>,
+    performer: Option<String>,
+    note: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct NoArgs {}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct UpdateDemographicsArgs {
+    title: Option<String>,
+    full_name: Option<String>,
+    preferred_name: Option<String>,
+    address: Option<String>,
+    date_of_birth: Option<String>,
+    nhs_number: Option<String>,
+    #[serde(default)]
+    identifiers: Vec<Identifier>,
+    note: Option<String>,
+}
+
+/// Tool definitions, with input schemas matching the argument structs above.
+pub fn tools() -> Vec<Tool> {
+    let severities: Vec<String> = AllergySeverity::value_variants()
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    let text = |description: &str| json!({ "type": "string", "description": description });
+    let reason = text("Why the record is being changed; used as the journal narrative");
+
+    vec![
+        tool(
+            "list_allergies",
+            "List recorded allergies and adverse reactions (active only unless include_inactive is set)",
+            json!({
+                "include_inactive": { "type": "boolean", "default": false, "description": "Include allergies marked inactive" }
+            }),
+            &[],
+        ),
+        tool(
+            "add_allergy",
+            "Record an active allergy or adverse reaction. Journalled and committed.",
+            json!({
+                "agent": text("Substance or agent, e.g. Penicillin"),
+                "reaction": text("Reaction observed, e.g. Anaphylaxis"),
+                "severity": { "type": "string", "enum": severities, "default": "moderate" },
+                "note": text("Optional clinical note; used as the journal narrative")
+            }),
+            &["agent", "reaction"],
+        ),
+        tool(
+            "inactivate_allergy",
+            "Mark an allergy inactive by id. Journalled and committed.",
+            json!({
+                "id": text("Allergy id (ALG-...)"),
+                "reason": reason.clone()
+            }),
+            &["id"],
+        ),
+        tool(
+            "list_vaccinations",
+            "List recorded vaccinations (completed only unless include_entered_in_error is set)",
+            json!({
+                "include_entered_in_error": { "type": "boolean", "default": false, "description": "Include entries marked entered-in-error" }
+            }),
+            &[],
+        ),
+        tool(
+            "add_vaccination",
+            "Record a vaccination or immunisation. Journalled and committed.",
+            json!({
+                "vaccine": text("Vaccine or immunisation display name"),
+                "date": { "type": "string", "format": "date", "description": "Administration date in YYYY-MM-DD format" },
+                "dose_sequence": { "type": "integer", "minimum": 0, "description": "Dose sequence number, e.g. 1, 2, 3" },
+                "target_disease": { "type": "array", "items": { "type": "string" }, "description": "Target diseases" },
+                "anatomical_site": text("Anatomical administration site"),
+                "route": text("Administration route"),
+                "product": text("Exact product administered"),
+                "manufacturer": text("Manufacturer"),
+                "batch_number": text("Batch number"),
+                "performer": text("Who administered the vaccine"),
+                "note": text("Optional clinical note; used as the journal narrative")
+            }),
+            &["vaccine", "date"],
+        ),
+        tool(
+            "vaccination_entered_in_error",
+            "Mark a vaccination as entered in error by id. Journalled and committed.",
+            json!({
+                "id": text("Vaccination id (VAC-...)"),
+                "reason": reason
+            }),
+            &["id"],
+        ),
+        tool(
+            "get_demographics",
+            "Get the patient's current demographics",
+            json!({}),
+            &[],
+        ),
+        tool(
+            "update_demographics",
+            "Update demographics. Supplied fields replace current values; identifiers are upserted by type. Journalled and committed.",
+            json!({
+                "title": text("Title, e.g. Mrs"),
+                "full_name": text("Full name"),
+                "preferred_name": text("Preferred name"),
+                "address": text("Postal address"),
+                "date_of_birth": { "type": "string", "format": "date", "description": "Date of birth in YYYY-MM-DD format" },
+                "nhs_number": text("NHS number; also stored as an NHS identifier"),
+                "identifiers": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "type": { "type": "string" },
+                            "value": { "type": "string" }
+                        },
+                        "required": ["type", "value"],
+                        "additionalProperties": false
+                    },
+                    "description": "Additional identifiers"
+                },
+                "note": text("Optional journal narrative for the change")
+            }),
+            &[],
+        ),
+    ]
+}
+
+fn tool(name: &str, description: &str, properties: Value, required: &[&str]) -> Tool {
+    Tool {
+        name: name.to_string(),
+        description: description.to_string(),
+        input_schema: json!({
+            "type": "object",
+            "properties": properties,
+            "required": required,
+            "additionalProperties": false
+        }),
+    }
+}
+
+/// Run a typed tool. Must be called with the working directory inside the
+/// repository; `author` resolves the contributor writes are attributed to.
+pub fn call(
+    name: &str,
+    arguments: Value,
+    author: impl FnOnce() -> anyhow::Result<String>,
+) -> anyhow::Result<ToolResult> {
+    typed_state::ensure_gitehr_repository()?;
+    let value = match name {
+        "list_allergies" => {
+            let args: ListAllergiesArgs = parse(name, arguments)?;
+            json!({ "allergies": allergies::list(args.include_inactive)? })
+        }
+        "add_allergy" => {
+            let args: AddAllergyArgs = parse(name, arguments)?;
+            let (allergy, entry) = allergies::add_as(
+                &args.agent,
+                &args.reaction,
+                args.severity,
+                args.note.as_deref(),
+                None,
+                Some(author()?),
+            )?;
+            written(json!({ "allergy": allergy }), entry)
+        }
+        "inactivate_allergy" => {
+            let args: InactivateArgs = parse(name, arguments)?;
+            let (allergy, entry) =
+                allergies::inactive_as(&args.id, args.reason.as_deref(), Some(author()?))?;
+            written(json!({ "allergy": allergy }), entry)
+        }
+        "list_vaccinations" => {
+            let args: ListVaccinationsArgs = parse(name, arguments)?;
+            json!({ "vaccinations": vaccinations::list(args.include_entered_in_error)? })
+        }
+        "add_vaccination" => {
+            let args: AddVaccinationArgs = parse(name, arguments)?;
+            let (vaccination, entry) = vaccinations::add_as(
+                VaccinationInput {
+                    vaccine: args.vaccine,
+                    date: args.date,
+                    dose_sequence: args.dose_sequence,
+                    target_disease: args.target_disease,
+                    anatomical_site: args.anatomical_site,
+                    route: args.route,
+                    product: args.product,
+                    manufacturer: args.manufacturer,
+                    batch_number: args.batch_number,
+                    performer: args.performer,
+                    fhir_json: None,
+                    note: args.note,
+                    source_entry: None,
+                },
+                Some(author()?),
+            )?;
+            written(json!({ "vaccination": vaccination }), entry)
+        }
+        "vaccination_entered_in_error" => {
+            let args: InactivateArgs = parse(name, arguments)?;
+            let (vaccination, entry) = vaccinations::entered_in_error_as(
+                &args.id,
+                args.reason.as_deref(),
+                Some(author()?),
+            )?;
+            written(json!({ "vaccination": vaccination }), entry)
+        }
+        "get_demographics" => {
+            let _: NoArgs = parse(name, arguments)?;
+            json!({ "demographics": demographics::load()? })
+        }
+        "update_demographics" => {
+            let args: UpdateDemographicsArgs = parse(name, arguments)?;
+            let (demographics, entry) = demographics::update_as(
+                DemographicsUpdate {
+                    title: args.title,
+                    full_name: args.full_name,
+                    preferred_name: args.preferred_name,
+                    address: args.address,
+                    date_of_birth: args.date_of_birth,
+                    nhs_number: args.nhs_number,
+                    identifiers: args.identifiers,
+                    note: args.note,
+                },
+                Some(author()?),
+            )?;
+            written(json!({ "demographics": demographics }), entry)
+        }
+        _ => anyhow::bail!("Unknown tool: {}", name),
+    };
+    ToolResult::json(value)
+}
+
+fn parse<T: DeserializeOwned>(tool: &str, arguments: Value) -> anyhow::Result<T> {
+    let arguments = if arguments.is_null() {
+        json!({})
+    } else {
+        arguments
+    };
+    serde_json::from_value(arguments)
+        .map_err(|e| anyhow::anyhow!("Invalid arguments for {}: {}", tool, e))
+}
+
+/// Add the journal entry and commit that recorded a write to its result.
+fn written(mut value: Value, journal_entry: String) -> Value {
+    value["journal_entry"] = json!(journal_entry);
+    value["commit"] = json!(git::head_commit());
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_tool_has_a_definition() {
+        let defined: Vec<String> = tools().into_iter().map(|t| t.name).collect();
+        assert_eq!(defined, NAMES);
+    }
+
+    #[test]
+    fn severity_schema_lists_every_variant() {
+        let tools = tools();
+        let add = tools.iter().find(|t| t.name == "add_allergy").unwrap();
+        let values: Vec<AllergySeverity> =
+            serde_json::from_value(add.input_schema["properties"]["severity"]["enum"].clone())
+                .unwrap();
+        assert_eq!(values, AllergySeverity::value_variants());
+    }
+
+    #[test]
+    fn unknown_arguments_are_rejected() {
+        let err = parse::<AddAllergyArgs>(
+            "add_allergy",
+            json!({ "agent": "Penicillin", "reaction": "Rash", "sevrity": "low" }),
+        )
+        .err()
+        .unwrap();
+        assert!(err.to_string().contains("sevrity"));
+    }
+}
@@ -3,9 +3,80 @@
 
 //! Internal MCP server implementation for `gitehr mcp serve`.
 
+mod auth;
+mod clinical_tools;
+mod paths;
+mod prompts;
 mod protocol;
 mod resources;
 mod server;
+mod store;
 mod tools;
+mod transport;
+mod watch;
 
 pub use server::{McpServer, ServerConfig};
+pub use transport::{on_signal, run_http, run_unix};
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::commands::git;
+use crate::commands::mcp::audit;
+
+/// The journal, state and Document code works relative to the current
+/// directory. Tools run it with the working directory switched to the
+/// repository, one call at a time. Nothing else may rely on the working
+/// directory while the server runs: other sessions switch it at any moment,
+/// so the server's own paths are all absolute.
+static CWD_LOCK: Mutex<()> = Mutex::new(());
+
+pub(crate) fn with_repo_cwd<T>(
+    repo_path: &Path,
+    f: impl FnOnce() -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let _guard = CWD_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    // The server may have been started from a directory that no longer
+    // exists; there is then nothing to restore.
+    let previous = std::env::current_dir().ok();
+    std::env::set_current_dir(repo_path)
+        .map_err(|e| anyhow::anyhow!("Cannot open repository {}: {}", repo_path.display(), e))?;
+    let result = f();
+    if let Some(previous) = previous {
+        std::env::set_current_dir(previous)?;
+    }
+    result
+}
+
+/// Repositories whose audit log has records not yet committed. Reads and
+/// refused or failed calls are only appended, so that no client can fill the
+/// record's history with commits; the next write commits them with its own
+/// record, and the server commits whatever is left when it stops.
+static UNCOMMITTED_AUDIT: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+pub(crate) fn audit_appended(repo_path: &Path) {
+    UNCOMMITTED_AUDIT
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(repo_path.to_path_buf());
+}
+
+/// Commit the audit records the server left uncommitted, one commit per
+/// repository.
+pub fn commit_audit_logs() -> anyhow::Result<()> {
+    let repos = std::mem::take(&mut *UNCOMMITTED_AUDIT.lock().unwrap_or_else(|e| e.into_inner()));
+    for repo in repos {
+        with_repo_cwd(&repo, || {
+            git::git_add(audit::AUDIT_LOG)?;
+            if git::has_staged_changes(audit::AUDIT_LOG) {
+                git::git_commit_only(
+                    "MCP audit: records since the last write",
+                    &[audit::AUDIT_LOG],
+                )?;
+            }
+            Ok(())
+        })?;
+    }
+    Ok(())
+}
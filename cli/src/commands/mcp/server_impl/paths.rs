@@ -0,0 +1,104 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Confining client-supplied names to the repository directory they address.
+//!
+//! Resource URIs and tool arguments come from the client. A name is accepted
+//! only as a single plain file name: no separators, no `.` or `..`, nothing
+//! hidden, no control characters. The resolved path must then stay under the
+//! directory once symlinks are followed.
+
+use anyhow::{Result, bail};
+use std::path::{Component, Path, PathBuf};
+
+/// The file `name` in `repo/dir`, which must not resolve outside it. With
+/// `must_exist` false the file may be missing (it is about to be written),
+/// but must not be a symlink.
+pub fn confine(repo: &Path, dir: &str, name: &str, must_exist: bool) -> Result<PathBuf> {
+    if !is_plain_name(name) {
+        bail!("Invalid {} file name: {:?}", dir, name);
+    }
+    let path = repo.join(dir).join(name);
+    match std::fs::symlink_metadata(&path) {
+        Ok(meta) if meta.is_dir() => bail!("Not a file: {}/{}", dir, name),
+        Ok(meta) if meta.file_type().is_symlink() && !must_exist => {
+            bail!("Refusing to write through a symlink: {}/{}", dir, name)
+        }
+        Ok(_) => ensure_within(repo, dir, &path)?,
+        Err(_) if must_exist => bail!("Not found: {}/{}", dir, name),
+        Err(_) => ensure_within(repo, dir, &repo.join(dir))?,
+    }
+    Ok(path)
+}
+
+/// Fail unless `path`, with symlinks resolved, lies in `repo/dir`. `dir`
+/// itself is not resolved, so a symlinked directory cannot widen it.
+pub fn ensure_within(repo: &Path, dir: &str, path: &Path) -> Result<()> {
+    let root = repo.canonicalize()?.join(dir);
+    let resolved = path.canonicalize()?;
+    if !resolved.starts_with(&root) {
+        bail!("Path escapes {}/: {}", dir, path.display());
+    }
+    Ok(())
+}
+
+/// A single, visible file name.
+fn is_plain_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+    matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(n)), None) if n == name
+    ) && !name.starts_with('.')
+        && !name
+            .chars()
+            .any(|c| c.is_control() || matches!(c, '/' | '\\' | ':'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_names() {
+        for name in ["20260614T101500.000Z-abc.md", "allergies.json", "notes"] {
+            assert!(is_plain_name(name), "{}", name);
+        }
+        for name in [
+            "",
+            ".",
+            "..",
+            "../x",
+            "a/b",
+            "a/",
+            "/etc/passwd",
+            ".hidden",
+            "..\\x",
+            "C:x",
+            "a\0b",
+            "a\nb",
+        ] {
+            assert!(!is_plain_name(name), "{:?}", name);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_cannot_escape() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path().join("repo");
+        std::fs::create_dir_all(repo.join("state")).unwrap();
+        std::fs::write(dir.path().join("secret"), "x").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("secret"), repo.join("state/link")).unwrap();
+
+        assert!(confine(&repo, "state", "link", true).is_err());
+        assert!(confine(&repo, "state", "link", false).is_err());
+        assert!(confine(&repo, "state", "new.md", false).is_ok());
+        assert!(confine(&repo, "state", "new.md", true).is_err());
+
+        std::fs::create_dir(dir.path().join("elsewhere")).unwrap();
+        std::fs::write(dir.path().join("elsewhere/entry.md"), "x").unwrap();
+        std::os::unix::fs::symlink(dir.path().join("elsewhere"), repo.join("journal")).unwrap();
+        assert!(confine(&repo, "journal", "entry.md", true).is_err());
+        assert!(confine(&repo, "journal", "new.md", false).is_err());
+    }
+}
@@ -0,0 +1,439 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! MCP Prompt Templates
+//!
+//! Clinical document templates, pre-filled with the subject's record. Templates
+//! are Markdown with `{{placeholder}}`s for their arguments and for:
+//!
+//! - `{{demographics}}`, `{{allergies}}`, `{{recent_journal}}`
+//! - `{{clinical_context}}`: all three under one heading
+//!
+//! Repositories add templates, or replace built-in ones by name, with
+//! `.gitehr/prompts/<name>.md`, optionally starting with YAML front matter
+//! giving a `description` and `arguments`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::protocol::McpError;
+use super::resources::{ClinicalContext, ResourceHandler};
+use crate::utils::substitute;
+
+pub const PROMPTS_DIR: &str = ".gitehr/prompts";
+
+/// Journal entries included in the clinical context.
+const RECENT_ENTRIES: usize = 5;
+
+/// Entry bodies longer than this are cut short in the clinical context.
+const ENTRY_EXCERPT_CHARS: usize = 1500;
+
+/// MCP Prompt definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Prompt {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<PromptArgument>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+}
+
+/// List prompts response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptsList {
+    pub prompts: Vec<Prompt>,
+}
+
+struct Template {
+    prompt: Prompt,
+    body: String,
+}
+
+/// Front matter of a repository template.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TemplateFrontMatter {
+    description: Option<String>,
+    #[serde(default)]
+    arguments: Vec<PromptArgument>,
+}
+
+const SOAP_NOTE: &str = "\
+Draft a SOAP note for this patient, who presents with: {{chief_complaint}}.
+Specialty: {{specialty}}
+
+Use these headings:
+
+**Subjective**: history of the presenting complaint, relevant past history, medications and allergies, social history.
+
+**Objective**: observations, examination findings and investigation results. Leave placeholders for anything not yet known; do not invent findings.
+
+**Assessment**: differential diagnosis and working impression.
+
+**Plan**: investigations, treatment, safety-netting and follow-up.
+
+Check the plan against the recorded allergies.
+
+{{clinical_context}}";
+
+const REFERRAL_LETTER: &str = "\
+Draft a referral letter to {{specialty}} for this patient.
+
+Reason for referral: {{reason}}
+Urgency: {{urgency}}
+
+Include the patient's identifiers, the question being asked of the specialist, relevant history and findings, current medications, allergies, and what the patient has been told. Write in plain clinical English suitable for a letter.
+
+{{clinical_context}}";
+
+const DISCHARGE_SUMMARY: &str = "\
+Draft a discharge summary for this patient.
+
+Reason for admission: {{admission_reason}}
+Admitted: {{admission_date}}
+Discharged: {{discharge_date}}
+
+Use these headings: Presenting complaint, Diagnoses, Procedures, Clinical course, Medication changes (with reasons), Allergies, Follow-up and actions for the GP, Information given to the patient.
+
+Base the course on the journal entries below, and leave placeholders for anything they do not cover.
+
+{{clinical_context}}";
+
+const MEDICATION_REVIEW: &str = "\
+Conduct a structured medication review for this patient. Focus: {{focus}}.
+
+For each medication mentioned in the record, consider indication, effectiveness, dose, interactions, adverse effects, monitoring and adherence. Flag anything that conflicts with the recorded allergies. Finish with a list of proposed changes for the clinician to confirm.
+
+{{clinical_context}}";
+
+fn builtin() -> Vec<Template> {
+    let arg = |name: &str, description: &str, required: bool| PromptArgument {
+        name: name.to_string(),
+        description: Some(description.to_string()),
+        required,
+    };
+    let template = |name: &str, description: &str, arguments, body: &str| Template {
+        prompt: Prompt {
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            arguments,
+        },
+        body: body.to_string(),
+    };
+
+    vec![
+        template(
+            "soap_note",
+            "SOAP note for a presenting complaint, pre-filled with the patient's record",
+            vec![
+                arg("chief_complaint", "The presenting complaint", true),
+                arg("specialty", "Clinical specialty, e.g. cardiology", false),
+            ],
+            SOAP_NOTE,
+        ),
+        template(
+            "referral_letter",
+            "Referral letter to a specialty, pre-filled with the patient's record",
+            vec![
+                arg("specialty", "Specialty or service referred to", true),
+                arg("reason", "Reason for referral", true),
+                arg(
+                    "urgency",
+                    "Urgency, e.g. routine, urgent, two-week wait",
+                    false,
+                ),
+            ],
+            REFERRAL_LETTER,
+        ),
+        template(
+            "discharge_summary",
+            "Hospital discharge summary, pre-filled with the patient's record",
+            vec![
+                arg("admission_reason", "Reason for admission", true),
+                arg("admission_date", "Date admitted", false),
+                arg("discharge_date", "Date discharged", false),
+            ],
+            DISCHARGE_SUMMARY,
+        ),
+        template(
+            "medication_review",
+            "Structured medication review, pre-filled with the patient's record",
+            vec![arg(
+                "focus",
+                "What to concentrate on, e.g. polypharmacy, renal dosing",
+                false,
+            )],
+            MEDICATION_REVIEW,
+        ),
+    ]
+}
+
+/// Prompt handler for GitEHR repositories
+pub struct PromptHandler {
+    repo_path: PathBuf,
+}
+
+impl PromptHandler {
+    pub fn new(repo_path: PathBuf) -> Self {
+        Self { repo_path }
+    }
+
+    /// Built-in and repository templates, by name; repository templates
+    /// replace built-in ones of the same name.
+    fn templates(&self) -> anyhow::Result<BTreeMap<String, Template>> {
+        let mut templates: BTreeMap<String, Template> = builtin()
+            .into_iter()
+            .map(|t| (t.prompt.name.clone(), t))
+            .collect();
+
+        let dir = self.repo_path.join(PROMPTS_DIR);
+        if dir.is_dir() {
+            for entry in fs::read_dir(&dir)? {
+                let path = entry?.path();
+                if path.extension().and_then(|s| s.to_str()) != Some("md") {
+                    continue;
+                }
+                let template = load_template(&path)?;
+                templates.insert(template.prompt.name.clone(), template);
+            }
+        }
+        Ok(templates)
+    }
+
+    /// List all available prompts
+    pub fn list_prompts(&self) -> anyhow::Result<PromptsList> {
+        Ok(PromptsList {
+            prompts: self.templates()?.into_values().map(|t| t.prompt).collect(),
+        })
+    }
+
+    /// Render prompt `name` with `arguments` and the subject's record.
+    pub fn get_prompt(
+        &self,
+        name: &str,
+        arguments: &serde_json::Map<String, serde_json::Value>,
+        resources: &ResourceHandler,
+    ) -> Result<serde_json::Value, McpError> {
+        let mut templates = self
+            .templates()
+            .map_err(|e| McpError::internal_error(e.to_string()))?;
+        let template = templates
+            .remove(name)
+            .ok_or_else(|| McpError::invalid_params(format!("Unknown prompt: {}", name)))?;
+
+        let mut values = BTreeMap::new();
+        for (key, value) in arguments {
+            if !template.prompt.arguments.iter().any(|a| &a.name == key) {
+                return Err(McpError::invalid_params(format!(
+                    "Unknown argument for {}: {}",
+                    name, key
+                )));
+            }
+            let value = match value {
+                serde_json::Value::String(s) => s.trim().to_string(),
+                other => other.to_string(),
+            };
+            values.insert(key.clone(), value);
+        }
+        for argument in &template.prompt.arguments {
+            let supplied = values.get(&argument.name).is_some_and(|v| !v.is_empty());
+            if argument.required && !supplied {
+                return Err(McpError::invalid_params(format!(
+                    "Missing required argument for {}: {}",
+                    name, argument.name
+                )));
+            }
+            if !supplied {
+                values.insert(argument.name.clone(), "not specified".to_string());
+            }
+        }
+
+        let context = resources
+            .clinical_context(RECENT_ENTRIES)
+            .map_err(|e| McpError::internal_error(e.to_string()))?;
+        let demographics = render_demographics(&context);
+        let allergies = render_allergies(&context);
+        let journal = render_journal(&context);
+        values.insert(
+            "clinical_context".to_string(),
+            format!(
+                "## Patient record\n\n### Demographics\n\n{}\n\n### Active allergies\n\n{}\n\n### Recent journal entries\n\n{}",
+                demographics, allergies, journal
+            ),
+        );
+        values.insert("demographics".to_string(), demographics);
+        values.insert("allergies".to_string(), allergies);
+        values.insert("recent_journal".to_string(), journal);
+
+        let mut description = template
+            .prompt
+            .description
+            .clone()
+            .unwrap_or_else(|| name.to_string());
+        if let Some(first) = template.prompt.arguments.iter().find(|a| a.required) {
+            description.push_str(&format!(": {}", values[&first.name]));
+        }
+
+        Ok(serde_json::json!({
+            "description": description,
+            "messages": [{
+                "role": "user",
+                "content": {
+                    "type": "text",
+                    "text": substitute(&template.body, &values)
+                }
+            }]
+        }))
+    }
+}
+
+fn load_template(path: &Path) -> anyhow::Result<Template> {
+    let name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string();
+    if name.is_empty()
+        || !name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        anyhow::bail!(
+            "Invalid prompt name '{}' in {}: use letters, digits, '_' and '-'",
+            name,
+            PROMPTS_DIR
+        );
+    }
+
+    let content = fs::read_to_string(path)?;
+    let (front_matter, body) = match content
+        .strip_prefix("---")
+        .and_then(|rest| rest.split_once("\n---"))
+    {
+        Some((yaml, body)) => {
+            let front_matter: TemplateFrontMatter = serde_yaml_ng::from_str(yaml).map_err(|e| {
+                anyhow::anyhow!("Invalid front matter in {}: {}", path.display(), e)
+            })?;
+            (front_matter, body)
+        }
+        None => (
+            TemplateFrontMatter {
+                description: None,
+                arguments: Vec::new(),
+            },
+            content.as_str(),
+        ),
+    };
+
+    Ok(Template {
+        prompt: Prompt {
+            name,
+            description: front_matter.description,
+            arguments: front_matter.arguments,
+        },
+        body: body.trim().to_string(),
+    })
+}
+
+fn render_demographics(context: &ClinicalContext) -> String {
+    let d = &context.demographics;
+    let mut lines = Vec::new();
+    let name = [d.title.as_deref(), d.full_name.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !name.is_empty() {
+        lines.push(format!("- Name: {}", name));
+    }
+    let fields = [
+        ("Preferred name", &d.preferred_name),
+        ("Date of birth", &d.date_of_birth),
+        ("NHS number", &d.nhs_number),
+        ("Address", &d.address),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            lines.push(format!("- {}: {}", label, value));
+        }
+    }
+    for identifier in &d.identifiers {
+        if identifier.id_type != "NHS" || d.nhs_number.is_none() {
+            lines.push(format!("- {}: {}", identifier.id_type, identifier.value));
+        }
+    }
+    if lines.is_empty() {
+        "None recorded.".to_string()
+    } else {
+        lines.join("\n")
+    }
+}
+
+fn render_allergies(context: &ClinicalContext) -> String {
+    if context.active_allergies.is_empty() {
+        // Not the same as "no known allergies": nothing has been recorded.
+        return "None recorded.".to_string();
+    }
+    context
+        .active_allergies
+        .iter()
+        .map(|a| format!("- {}: {} ({})", a.agent, a.reaction, a.severity))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn render_journal(context: &ClinicalContext) -> String {
+    if context.recent_entries.is_empty() {
+        return "None recorded.".to_string();
+    }
+    let mut sections = vec![format!(
+        "The {} most recent of {} entries, newest first.",
+        context.recent_entries.len(),
+        context.total_entries
+    )];
+    for entry in &context.recent_entries {
+        let mut body: String = entry.content.chars().take(ENTRY_EXCERPT_CHARS).collect();
+        if entry.content.chars().count() > ENTRY_EXCERPT_CHARS {
+            body.push_str(" […]");
+        }
+        sections.push(format!(
+            "#### {} by {} (journal/{})\n\n{}",
+            entry.metadata.timestamp.format("%Y-%m-%d %H:%M UTC"),
+            entry.metadata.author.as_deref().unwrap_or("unknown author"),
+            entry.filename,
+            body
+        ));
+    }
+    sections.join("\n\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_placeholders_are_declared() {
+        for template in builtin() {
+            let mut values: BTreeMap<String, String> = template
+                .prompt
+                .arguments
+                .iter()
+                .map(|a| (a.name.clone(), String::new()))
+                .collect();
+            values.insert("clinical_context".to_string(), String::new());
+            let rendered = substitute(&template.body, &values);
+            assert!(!rendered.contains("{{"), "{}", template.prompt.name);
+        }
+    }
+}
@@ -17,6 +17,18 @@ pub struct McpRequest {
     pub params: Option<serde_json::Value>,
 }
 
+impl McpRequest {
+    /// A server-to-client notification (a request without an `id`).
+    pub fn notification(method: &str, params: serde_json::Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id: None,
+            method: method.to_string(),
+            params: Some(params),
+        }
+    }
+}
+
 /// JSON-RPC 2.0 Response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct McpResponse {
@@ -53,6 +65,8 @@ pub mod error_codes {
     pub const METHOD_NOT_FOUND: i32 = -32601;
     pub const INVALID_PARAMS: i32 = -32602;
     pub const INTERNAL_ERROR: i32 = -32603;
+    /// Server-defined: missing, invalid or insufficient credentials.
+    pub const UNAUTHORIZED: i32 = -32001;
 }
 
 impl McpError {
@@ -95,6 +109,14 @@ impl McpError {
             data: None,
         }
     }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self {
+            code: error_codes::UNAUTHORIZED,
+            message: message.into(),
+            data: None,
+        }
+    }
 }
 
 impl McpResponse {
@@ -122,10 +144,15 @@ impl McpResponse {
 pub enum McpMethod {
     // Initialization
     Initialize,
+    Initialized,
+    Ping,
 
     // Resource methods
     ResourcesList,
     ResourcesRead,
+    ResourcesTemplatesList,
+    ResourcesSubscribe,
+    ResourcesUnsubscribe,
 
     // Tool methods
     ToolsList,
@@ -146,8 +173,13 @@ impl McpMethod {
     pub fn from_str(s: &str) -> Self {
         match s {
             "initialize" => McpMethod::Initialize,
+            "notifications/initialized" => McpMethod::Initialized,
+            "ping" => McpMethod::Ping,
             "resources/list" => McpMethod::ResourcesList,
             "resources/read" => McpMethod::ResourcesRead,
+            "resources/templates/list" => McpMethod::ResourcesTemplatesList,
+            "resources/subscribe" => McpMethod::ResourcesSubscribe,
+            "resources/unsubscribe" => McpMethod::ResourcesUnsubscribe,
             "tools/list" => McpMethod::ToolsList,
             "tools/call" => McpMethod::ToolsCall,
             "prompts/list" => McpMethod::PromptsList,
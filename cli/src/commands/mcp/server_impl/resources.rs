@@ -5,8 +5,24 @@
 //!
 //! Resources provide read-only access to GitEHR repository data.
 
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Component, Path, PathBuf};
+
+use super::{paths, with_repo_cwd};
+use crate::commands::allergies::{self, Allergy};
+use crate::commands::demographics::{self, Demographics};
+use crate::commands::document::{self, DOCUMENT_ROOTS, MANIFEST_FILENAME};
+use crate::commands::journal::{self, DocumentRef, ParsedEntry};
+use crate::medmd;
+use crate::utils::sha256_hex;
+
+/// Largest blob returned by one read. Bigger files are read in parts with
+/// `?offset=N&length=N`.
+pub const MAX_BLOB_BYTES: u64 = 8 * 1024 * 1024;
 
 /// MCP Resource
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -15,10 +31,30 @@ pub struct Resource {
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub description: Option<String>,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// MCP Resource template: a family of resources addressed by RFC 6570 URI
+/// template
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceTemplate {
+    #[serde(rename = "uriTemplate")]
+    pub uri_template: String,
+    pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<String>,
 }
 
+/// List resource templates response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceTemplatesList {
+    #[serde(rename = "resourceTemplates")]
+    pub resource_templates: Vec<ResourceTemplate>,
+}
+
 /// Resource content
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -44,10 +80,23 @@ pub struct ResourcesRead {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceReadContent {
     pub uri: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<String>,
     #[serde(flatten)]
     pub content: ResourceContent,
+    /// For Documents: the SHA-256 recorded in the journal, the size, and the
+    /// byte range returned.
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<serde_json::Value>,
+}
+
+/// The subject's current record, as prompts are pre-filled with it.
+pub struct ClinicalContext {
+    pub demographics: Demographics,
+    pub active_allergies: Vec<Allergy>,
+    /// Newest first.
+    pub recent_entries: Vec<ParsedEntry>,
+    pub total_entries: usize,
 }
 
 /// Resource handler for GitEHR repositories
@@ -55,7 +104,7 @@ pub struct ResourceHandler {
     repo_path: PathBuf,
 }
 
-const REPO_URI_PREFIX: &str = "gitehr://repo/";
+pub const REPO_URI_PREFIX: &str = "gitehr://repo/";
 
 impl ResourceHandler {
     pub fn new(repo_path: PathBuf) -> Self {
@@ -64,7 +113,7 @@ impl ResourceHandler {
 
     /// List all available resources
     pub fn list_resources(&self) -> anyhow::Result<ResourcesList> {
-        let resources = vec![
+        let mut resources = vec![
             Resource {
                 uri: "gitehr://repo/journal".to_string(),
                 name: "Journal Entries".to_string(),
@@ -83,11 +132,102 @@ impl ResourceHandler {
                 description: Some("Repository metadata and status".to_string()),
                 mime_type: Some("application/json".to_string()),
             },
+            Resource {
+                uri: "gitehr://repo/documents".to_string(),
+                name: "Documents".to_string(),
+                description: Some(
+                    "Letters, reports and other Documents linked from the journal".to_string(),
+                ),
+                mime_type: Some("application/json".to_string()),
+            },
+            Resource {
+                uri: "gitehr://repo/imaging".to_string(),
+                name: "Imaging".to_string(),
+                description: Some("Images and studies linked from the journal".to_string()),
+                mime_type: Some("application/json".to_string()),
+            },
         ];
 
+        for (path, (doc, entries)) in self.documents()? {
+            let local = self.repo_path.join(&path);
+            if !local.exists() {
+                continue;
+            }
+            let directory = local.is_dir();
+            resources.push(Resource {
+                uri: format!("{}{}", REPO_URI_PREFIX, path),
+                name: doc
+                    .original_filename
+                    .clone()
+                    .unwrap_or_else(|| path.clone()),
+                description: Some(format!(
+                    "{} linked from {} journal entr{}",
+                    if directory {
+                        "Directory Document (manifest)"
+                    } else {
+                        "Document"
+                    },
+                    entries.len(),
+                    if entries.len() == 1 { "y" } else { "ies" }
+                )),
+                mime_type: Some(
+                    if directory {
+                        "application/json"
+                    } else {
+                        document::mime_type(&local)
+                    }
+                    .to_string(),
+                ),
+            });
+        }
+
         Ok(ResourcesList { resources })
     }
 
+    /// Templates for the per-item resources under `prefix` (`gitehr://repo/`,
+    /// or a Store's per-subject prefix).
+    pub fn resource_templates(prefix: &str) -> Vec<ResourceTemplate> {
+        let template =
+            |path: &str, name: &str, description: &str, mime_type: Option<&str>| ResourceTemplate {
+                uri_template: format!("{}{}", prefix, path),
+                name: name.to_string(),
+                description: Some(description.to_string()),
+                mime_type: mime_type.map(str::to_string),
+            };
+        vec![
+            template(
+                "journal/{entry}",
+                "Journal Entry",
+                "A journal entry by filename",
+                Some("text/markdown"),
+            ),
+            template(
+                "journal/{entry}/structured",
+                "Journal Entry (structured)",
+                "The Medical Markdown structured data of a journal entry, with its _source_map",
+                Some("application/json"),
+            ),
+            template(
+                "state/{file}",
+                "State File",
+                "A state file by filename",
+                Some("text/plain"),
+            ),
+            template(
+                "documents/{document}",
+                "Document",
+                "A Document linked from the journal, as a base64 blob (a directory Document returns its manifest)",
+                None,
+            ),
+            template(
+                "imaging/{document}",
+                "Imaging",
+                "An image or study linked from the journal, as a base64 blob (a directory Document returns its manifest)",
+                None,
+            ),
+        ]
+    }
+
     /// Read a specific resource by URI
     pub fn read_resource(&self, uri: &str) -> anyhow::Result<ResourcesRead> {
         let rest = uri
@@ -98,8 +238,20 @@ impl ResourceHandler {
             "journal" => self.read_journal(),
             "state" => self.read_state(),
             "status" => self.read_status(),
+            root if DOCUMENT_ROOTS.contains(&root) => self.read_document_list(root),
+            path if DOCUMENT_ROOTS
+                .iter()
+                .any(|root| path.starts_with(&format!("{}/", root))) =>
+            {
+                self.read_document(path)
+            }
             _ => {
-                if let Some(entry_id) = rest.strip_prefix("journal/") {
+                if let Some(entry_id) = rest
+                    .strip_prefix("journal/")
+                    .and_then(|e| e.strip_suffix("/structured"))
+                {
+                    self.read_structured_entry(entry_id)
+                } else if let Some(entry_id) = rest.strip_prefix("journal/") {
                     self.read_journal_entry(entry_id)
                 } else if let Some(filename) = rest.strip_prefix("state/") {
                     self.read_state_file(filename)
@@ -137,17 +289,13 @@ impl ResourceHandler {
                 uri: "gitehr://repo/journal".to_string(),
                 mime_type: Some("application/json".to_string()),
                 content,
+                meta: None,
             }],
         })
     }
 
     fn read_journal_entry(&self, entry_id: &str) -> anyhow::Result<ResourcesRead> {
-        let entry_path = self.repo_path.join("journal").join(entry_id);
-
-        if !entry_path.exists() {
-            return Err(anyhow::anyhow!("Journal entry not found: {}", entry_id));
-        }
-
+        let entry_path = paths::confine(&self.repo_path, "journal", entry_id, true)?;
         let content_text = std::fs::read_to_string(&entry_path)?;
         let content = ResourceContent::Text { text: content_text };
 
@@ -156,6 +304,25 @@ impl ResourceHandler {
                 uri: format!("gitehr://repo/journal/{}", entry_id),
                 mime_type: Some("text/markdown".to_string()),
                 content,
+                meta: None,
+            }],
+        })
+    }
+
+    /// The entry's Medical Markdown structured data, derived from its body.
+    fn read_structured_entry(&self, entry_id: &str) -> anyhow::Result<ResourcesRead> {
+        let entry_path = paths::confine(&self.repo_path, "journal", entry_id, true)?;
+        let parsed = journal::parse_journal_file(&entry_path)?;
+        let content = ResourceContent::Text {
+            text: serde_json::to_string_pretty(&medmd::extract(&parsed.content))?,
+        };
+
+        Ok(ResourcesRead {
+            contents: vec![ResourceReadContent {
+                uri: format!("gitehr://repo/journal/{}/structured", entry_id),
+                mime_type: Some("application/json".to_string()),
+                content,
+                meta: None,
             }],
         })
     }
@@ -188,17 +355,13 @@ impl ResourceHandler {
                 uri: "gitehr://repo/state".to_string(),
                 mime_type: Some("application/json".to_string()),
                 content,
+                meta: None,
             }],
         })
     }
 
     fn read_state_file(&self, filename: &str) -> anyhow::Result<ResourcesRead> {
-        let file_path = self.repo_path.join("state").join(filename);
-
-        if !file_path.exists() {
-            return Err(anyhow::anyhow!("State file not found: {}", filename));
-        }
-
+        let file_path = paths::confine(&self.repo_path, "state", filename, true)?;
         let content_text = std::fs::read_to_string(&file_path)?;
         let content = ResourceContent::Text { text: content_text };
 
@@ -207,6 +370,7 @@ impl ResourceHandler {
                 uri: format!("gitehr://repo/state/{}", filename),
                 mime_type: Some("text/plain".to_string()),
                 content,
+                meta: None,
             }],
         })
     }
@@ -222,10 +386,217 @@ impl ResourceHandler {
                 uri: "gitehr://repo/status".to_string(),
                 mime_type: Some("application/json".to_string()),
                 content,
+                meta: None,
             }],
         })
     }
 
+    /// Documents referenced from the journal, by record path, with the
+    /// entries referencing each. The first reference supplies the recorded
+    /// hash. References that do not name an item directly under a Document
+    /// root are ignored.
+    fn documents(&self) -> anyhow::Result<BTreeMap<String, (DocumentRef, Vec<String>)>> {
+        let refs = with_repo_cwd(&self.repo_path, document::collect_refs)?;
+        let mut by_path: BTreeMap<String, (DocumentRef, Vec<String>)> = BTreeMap::new();
+        for (entry, doc) in refs {
+            let path = doc.path.trim_end_matches('/').to_string();
+            if !document::is_record_path(&path) {
+                continue;
+            }
+            by_path
+                .entry(path)
+                .or_insert_with(|| (doc, Vec::new()))
+                .1
+                .push(format!("journal/{}", entry));
+        }
+        Ok(by_path)
+    }
+
+    fn read_document_list(&self, root: &str) -> anyhow::Result<ResourcesRead> {
+        let mut items = Vec::new();
+        for (path, (doc, entries)) in self.documents()? {
+            if !path.starts_with(&format!("{}/", root)) {
+                continue;
+            }
+            let local = self.repo_path.join(&path);
+            let kind = if local.is_dir() { "directory" } else { "file" };
+            items.push(serde_json::json!({
+                "uri": format!("{}{}", REPO_URI_PREFIX, path),
+                "path": path,
+                "kind": kind,
+                "mimeType": if local.is_dir() { "application/json" } else { document::mime_type(&local) },
+                "size": local.is_file().then(|| local.metadata().map(|m| m.len()).ok()).flatten(),
+                "sha256": doc.sha256,
+                "original_filename": doc.original_filename,
+                "present": local.exists(),
+                "referenced_by": entries,
+            }));
+        }
+
+        Ok(ResourcesRead {
+            contents: vec![ResourceReadContent {
+                uri: format!("{}{}", REPO_URI_PREFIX, root),
+                mime_type: Some("application/json".to_string()),
+                content: ResourceContent::Text {
+                    text: serde_json::to_string_pretty(&items)?,
+                },
+                meta: None,
+            }],
+        })
+    }
+
+    /// A Document's bytes as a base64 blob. A directory Document returns its
+    /// manifest; the files within it are read as `<document>/<file>`, as
+    /// listed in the manifest. `?offset=N&length=N` reads part of a file.
+    fn read_document(&self, uri_path: &str) -> anyhow::Result<ResourcesRead> {
+        let (path, query) = match uri_path.split_once('?') {
+            Some((path, query)) => (path, Some(query)),
+            None => (uri_path, None),
+        };
+        let mut components = path.splitn(3, '/');
+        let (root, record_path) = match (components.next(), components.next()) {
+            (Some(root), Some(name)) => (root, format!("{}/{}", root, name)),
+            _ => anyhow::bail!("Unknown resource URI: {}{}", REPO_URI_PREFIX, uri_path),
+        };
+        let inner = components.next().filter(|p| !p.is_empty());
+
+        let documents = self.documents()?;
+        let (doc, _) = documents.get(&record_path).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Document not found: {} is not referenced by any journal entry",
+                record_path
+            )
+        })?;
+        let local = self.repo_path.join(&record_path);
+        if !local.exists() {
+            anyhow::bail!(
+                "Document {} has been removed from the working tree (retained in Git history)",
+                record_path
+            );
+        }
+        // A symlinked Document must not expose files outside its root.
+        paths::ensure_within(&self.repo_path, root, &local)?;
+
+        let (file, recorded_sha256, mime_type) = match (local.is_dir(), inner) {
+            (true, None) => {
+                if query.is_some() {
+                    anyhow::bail!("Ranges apply to files, not to a directory Document's manifest");
+                }
+                let manifest_path = local.join(MANIFEST_FILENAME);
+                paths::ensure_within(&self.repo_path, &record_path, &manifest_path)?;
+                let manifest = std::fs::read(manifest_path)?;
+                return Ok(ResourcesRead {
+                    contents: vec![ResourceReadContent {
+                        uri: format!("{}{}", REPO_URI_PREFIX, record_path),
+                        mime_type: Some("application/json".to_string()),
+                        meta: Some(serde_json::json!({
+                            "sha256": doc.sha256,
+                            "verified": sha256_hex(&manifest) == doc.sha256,
+                            "size": manifest.len(),
+                        })),
+                        content: ResourceContent::Text {
+                            text: String::from_utf8(manifest)?,
+                        },
+                    }],
+                });
+            }
+            (true, Some(rel)) => {
+                // Only files the manifest lists can be read, and only by
+                // plain relative paths, so `rel` cannot leave the Document.
+                if !Path::new(rel)
+                    .components()
+                    .all(|c| matches!(c, Component::Normal(_)))
+                {
+                    anyhow::bail!("Invalid path within {}: {}", record_path, rel);
+                }
+                let manifest_path = local.join(MANIFEST_FILENAME);
+                paths::ensure_within(&self.repo_path, &record_path, &manifest_path)?;
+                let manifest: serde_json::Value =
+                    serde_json::from_slice(&std::fs::read(manifest_path)?)?;
+                let sha256 = manifest["files"][rel]
+                    .as_str()
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("{} is not listed in the manifest of {}", rel, record_path)
+                    })?
+                    .to_string();
+                let file = local.join(rel);
+                paths::ensure_within(&self.repo_path, &record_path, &file)?;
+                let mime_type = document::mime_type(&file);
+                (file, sha256, mime_type)
+            }
+            (false, None) => (
+                local.clone(),
+                doc.sha256.clone(),
+                document::mime_type(&local),
+            ),
+            (false, Some(_)) => {
+                anyhow::bail!("{} is a file, not a directory Document", record_path)
+            }
+        };
+
+        let size = file.metadata()?.len();
+        let (offset, length) = parse_range(query)?;
+        let ranged = query.is_some();
+        if !ranged && size > MAX_BLOB_BYTES {
+            anyhow::bail!(
+                "{} is {} bytes, over the {} byte limit for one read; read it in parts with ?offset=0&length={}",
+                path,
+                size,
+                MAX_BLOB_BYTES,
+                MAX_BLOB_BYTES
+            );
+        }
+        let offset = offset.min(size);
+        let length = length
+            .unwrap_or(MAX_BLOB_BYTES)
+            .min(MAX_BLOB_BYTES)
+            .min(size - offset);
+
+        let mut bytes = vec![0; length as usize];
+        let mut handle = std::fs::File::open(&file)?;
+        handle.seek(SeekFrom::Start(offset))?;
+        handle.read_exact(&mut bytes)?;
+
+        let mut meta = serde_json::json!({
+            "sha256": recorded_sha256,
+            "size": size,
+        });
+        if ranged {
+            meta["offset"] = serde_json::json!(offset);
+            meta["length"] = serde_json::json!(length);
+        } else {
+            meta["verified"] = serde_json::json!(sha256_hex(&bytes) == recorded_sha256);
+        }
+
+        Ok(ResourcesRead {
+            contents: vec![ResourceReadContent {
+                uri: format!("{}{}", REPO_URI_PREFIX, uri_path),
+                mime_type: Some(mime_type.to_string()),
+                content: ResourceContent::Blob {
+                    blob: BASE64.encode(&bytes),
+                },
+                meta: Some(meta),
+            }],
+        })
+    }
+
+    /// Demographics, active allergies and the `recent` newest journal
+    /// entries.
+    pub fn clinical_context(&self, recent: usize) -> anyhow::Result<ClinicalContext> {
+        with_repo_cwd(&self.repo_path, || {
+            let mut entries = journal::parsed_entries()?;
+            let total_entries = entries.len();
+            entries.reverse();
+            entries.truncate(recent);
+            Ok(ClinicalContext {
+                demographics: demographics::load()?,
+                active_allergies: allergies::list(false)?,
+                recent_entries: entries,
+                total_entries,
+            })
+        })
+    }
+
     fn get_repo_status(&self) -> anyhow::Result<serde_json::Value> {
         let version = std::fs::read_to_string(self.repo_path.join(".gitehr/GITEHR_VERSION"))
             .unwrap_or_else(|_| "unknown".to_string());
@@ -253,6 +624,24 @@ impl ResourceHandler {
     }
 }
 
+/// `offset` and `length` from a `?offset=N&length=N` query.
+fn parse_range(query: Option<&str>) -> anyhow::Result<(u64, Option<u64>)> {
+    let mut offset = 0;
+    let mut length = None;
+    for pair in query.unwrap_or("").split('&').filter(|p| !p.is_empty()) {
+        let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+        let value: u64 = value
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid {} in resource URI: '{}'", key, value))?;
+        match key {
+            "offset" => offset = value,
+            "length" => length = Some(value),
+            _ => anyhow::bail!("Unknown resource URI parameter: {}", key),
+        }
+    }
+    Ok((offset, length))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,6 +662,17 @@ mod tests {
         assert_eq!(parsed.name, "Journal");
     }
 
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(None).unwrap(), (0, None));
+        assert_eq!(
+            parse_range(Some("offset=10&length=20")).unwrap(),
+            (10, Some(20))
+        );
+        assert!(parse_range(Some("offset=-1")).is_err());
+        assert!(parse_range(Some("start=1")).is_err());
+    }
+
     #[test]
     fn test_resource_content_text() {
         let content = ResourceContent::Text {
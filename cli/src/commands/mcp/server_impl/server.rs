@@ -3,11 +3,24 @@
 
 //! MCP Server Implementation
 
-use super::protocol::{McpError, McpMethod, McpRequest, McpResponse};
-use super::resources::ResourceHandler;
-use super::tools::ToolHandler;
+use super::auth::{self, Principal};
+use super::prompts::PromptHandler;
+use super::protocol::{McpError, McpMethod, McpRequest, McpResponse, error_codes};
+use super::resources::{REPO_URI_PREFIX, ResourceHandler, ResourceTemplatesList};
+use super::store::StoreHandler;
+use super::tools::{Attribution, ToolHandler};
+use super::transport::Shutdown;
+use super::watch::{self, WATCHED_DIRS};
+use super::{audit_appended, with_repo_cwd};
+use crate::commands::contributor;
+use crate::commands::mcp::audit::{self, AuditRecord, Outcome};
+use crate::commands::mcp::token::Scope;
+use crate::utils::sha256_hex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use tokio::sync::{broadcast, mpsc};
+use tokio::task::AbortHandle;
 use tracing::{debug, error, info};
 
 /// Server configuration
@@ -16,6 +29,14 @@ pub struct ServerConfig {
     pub repo_path: PathBuf,
     pub server_name: String,
     pub server_version: String,
+    /// Contributor id that writes are attributed to. Falls back to the
+    /// repository's active contributor.
+    pub contributor: Option<String>,
+    /// Sessions must authenticate with a bearer token from
+    /// `.gitehr/mcp-tokens.json` (the networked transports).
+    pub require_token: bool,
+    /// `repo_path` is a Store root: serve every subject in its MPI.
+    pub store: bool,
 }
 
 impl Default for ServerConfig {
@@ -24,6 +45,9 @@ impl Default for ServerConfig {
             repo_path: PathBuf::from("."),
             server_name: "gitehr".to_string(),
             server_version: env!("CARGO_PKG_VERSION").to_string(),
+            contributor: None,
+            require_token: false,
+            store: false,
         }
     }
 }
@@ -33,22 +57,88 @@ pub struct McpServer {
     config: ServerConfig,
     resource_handler: ResourceHandler,
     tool_handler: ToolHandler,
+    prompt_handler: PromptHandler,
+    /// Set when serving a Store rather than one repository.
+    store: Option<StoreHandler>,
     initialized: bool,
+    principal: Option<Principal>,
+    /// `clientInfo` name and version from `initialize`, for the audit log.
+    client: (Option<String>, Option<String>),
+    /// Subscribed resource URIs, each forwarding its updates to `notifier`.
+    subscriptions: HashMap<String, AbortHandle>,
+    notifier: mpsc::UnboundedSender<String>,
+    /// Serialised notifications for the transport to deliver; taken by the
+    /// transport while it is delivering them.
+    notifications: Option<mpsc::UnboundedReceiver<String>>,
 }
 
 impl McpServer {
-    pub fn new(config: ServerConfig) -> Self {
+    pub fn new(mut config: ServerConfig) -> Self {
+        // Handlers join client paths onto the root; a relative one would
+        // follow `with_repo_cwd` into whichever repository it last entered.
+        if let Ok(root) = std::path::absolute(&config.repo_path) {
+            config.repo_path = root;
+        }
         let resource_handler = ResourceHandler::new(config.repo_path.clone());
         let tool_handler = ToolHandler::new(config.repo_path.clone());
+        let prompt_handler = PromptHandler::new(config.repo_path.clone());
+        let store = config
+            .store
+            .then(|| StoreHandler::new(config.repo_path.clone()));
+        let principal =
+            (!config.require_token).then(|| Principal::local(config.contributor.clone()));
+        let (notifier, notifications) = mpsc::unbounded_channel();
 
         Self {
             config,
             resource_handler,
             tool_handler,
+            prompt_handler,
+            store,
             initialized: false,
+            principal,
+            client: (None, None),
+            subscriptions: HashMap::new(),
+            notifier,
+            notifications: Some(notifications),
         }
     }
 
+    /// Who this session acts as, once authenticated.
+    pub fn principal(&self) -> Option<&Principal> {
+        self.principal.as_ref()
+    }
+
+    /// Authenticate the session out of band (e.g. from an HTTP header).
+    pub fn set_principal(&mut self, principal: Principal) {
+        self.principal = Some(principal);
+    }
+
+    /// The session's outgoing notifications. `None` while another stream is
+    /// delivering them.
+    pub fn take_notifications(&mut self) -> Option<mpsc::UnboundedReceiver<String>> {
+        self.notifications.take()
+    }
+
+    /// An event stream holds the session's notifications.
+    pub fn streaming(&self) -> bool {
+        self.notifications.is_none()
+    }
+
+    /// Hand back notifications taken with `take_notifications`.
+    pub fn restore_notifications(&mut self, notifications: mpsc::UnboundedReceiver<String>) {
+        self.notifications = Some(notifications);
+    }
+
+    fn authorize(&self, scope: Option<Scope>) -> Result<&Principal, McpError> {
+        let principal = self
+            .principal
+            .as_ref()
+            .ok_or_else(|| McpError::unauthorized("Not authenticated"))?;
+        principal.authorize(&self.config.repo_path, scope)?;
+        Ok(principal)
+    }
+
     /// Handle an MCP request
     pub async fn handle_request(&mut self, request: McpRequest) -> McpResponse {
         debug!("Handling request: {:?}", request.method);
@@ -57,12 +147,30 @@ impl McpServer {
 
         let result = match method {
             McpMethod::Initialize => self.handle_initialize(&request).await,
+            McpMethod::Initialized | McpMethod::Ping => Ok(serde_json::json!({})),
             McpMethod::ResourcesList => self.handle_resources_list(&request).await,
-            McpMethod::ResourcesRead => self.handle_resources_read(&request).await,
+            McpMethod::ResourcesTemplatesList => {
+                self.handle_resources_templates_list(&request).await
+            }
+            McpMethod::ResourcesSubscribe => self.handle_resources_subscribe(&request).await,
+            McpMethod::ResourcesUnsubscribe => self.handle_resources_unsubscribe(&request).await,
+            McpMethod::ResourcesRead => {
+                let result = self.handle_resources_read(&request).await;
+                self.audit(&request, &result);
+                result
+            }
             McpMethod::ToolsList => self.handle_tools_list(&request).await,
-            McpMethod::ToolsCall => self.handle_tools_call(&request).await,
+            McpMethod::ToolsCall => {
+                let result = self.handle_tools_call(&request).await;
+                self.audit(&request, &result);
+                result
+            }
             McpMethod::PromptsList => self.handle_prompts_list(&request).await,
-            McpMethod::PromptsGet => self.handle_prompts_get(&request).await,
+            McpMethod::PromptsGet => {
+                let result = self.handle_prompts_get(&request).await;
+                self.audit(&request, &result);
+                result
+            }
             McpMethod::Unknown(method_name) => {
                 error!("Unknown method: {}", method_name);
                 Err(McpError::method_not_found(method_name))
@@ -80,16 +188,35 @@ impl McpServer {
 
     async fn handle_initialize(
         &mut self,
-        _request: &McpRequest,
+        request: &McpRequest,
     ) -> Result<serde_json::Value, McpError> {
         info!("Initializing MCP server");
 
+        let client_info = request.params.as_ref().and_then(|p| p.get("clientInfo"));
+        let field = |key: &str| {
+            client_info
+                .and_then(|c| c.get(key))
+                .and_then(|v| v.as_str())
+                .map(str::to_string)
+        };
+        self.client = (field("name"), field("version"));
+
+        // Stream transports have no headers; the token travels in `_meta`.
+        if self.principal.is_none() {
+            let authorization = request
+                .params
+                .as_ref()
+                .and_then(|p| p.pointer("/_meta/authorization"))
+                .and_then(|v| v.as_str());
+            self.principal = Some(auth::authenticate(&self.config.repo_path, authorization)?);
+        }
+
         self.initialized = true;
 
         Ok(serde_json::json!({
             "protocolVersion": super::super::MCP_VERSION,
             "capabilities": {
-                "resources": {},
+                "resources": { "subscribe": true },
                 "tools": {},
                 "prompts": {}
             },
@@ -108,14 +235,37 @@ impl McpServer {
             return Err(McpError::invalid_request("Server not initialized"));
         }
 
-        let resources = self
-            .resource_handler
-            .list_resources()
-            .map_err(|e| McpError::internal_error(e.to_string()))?;
+        self.authorize(Some(Scope::Read))?;
+        let resources = match &self.store {
+            Some(store) => store.list_resources(),
+            None => self
+                .resource_handler
+                .list_resources()
+                .map_err(|e| McpError::internal_error(e.to_string()))?,
+        };
 
         serde_json::to_value(resources).map_err(|e| McpError::internal_error(e.to_string()))
     }
 
+    async fn handle_resources_templates_list(
+        &self,
+        _request: &McpRequest,
+    ) -> Result<serde_json::Value, McpError> {
+        if !self.initialized {
+            return Err(McpError::invalid_request("Server not initialized"));
+        }
+
+        self.authorize(Some(Scope::Read))?;
+        let templates = match &self.store {
+            Some(store) => store.resource_templates(),
+            None => ResourceTemplatesList {
+                resource_templates: ResourceHandler::resource_templates(REPO_URI_PREFIX),
+            },
+        };
+
+        serde_json::to_value(templates).map_err(|e| McpError::internal_error(e.to_string()))
+    }
+
     async fn handle_resources_read(
         &self,
         request: &McpRequest,
@@ -134,14 +284,112 @@ impl McpServer {
             .and_then(|v| v.as_str())
             .ok_or_else(|| McpError::invalid_params("Missing 'uri' parameter"))?;
 
-        let content = self
-            .resource_handler
-            .read_resource(uri)
-            .map_err(|e| McpError::internal_error(e.to_string()))?;
+        self.authorize(Some(Scope::Read))?;
+        let content = match &self.store {
+            Some(store) => store.read_resource(uri),
+            None => self.resource_handler.read_resource(uri),
+        }
+        .map_err(|e| McpError::internal_error(e.to_string()))?;
 
         serde_json::to_value(content).map_err(|e| McpError::internal_error(e.to_string()))
     }
 
+    async fn handle_resources_subscribe(
+        &mut self,
+        request: &McpRequest,
+    ) -> Result<serde_json::Value, McpError> {
+        if !self.initialized {
+            return Err(McpError::invalid_request("Server not initialized"));
+        }
+
+        let uri = request
+            .params
+            .as_ref()
+            .and_then(|p| p.get("uri"))
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing 'uri' parameter"))?
+            .to_string();
+
+        self.authorize(Some(Scope::Read))?;
+        if self.subscriptions.contains_key(&uri) {
+            return Ok(serde_json::json!({}));
+        }
+
+        // Watches publish `gitehr://repo/` URIs; a Store subject's are
+        // matched in that form.
+        let (repo, repo_uri) = match &self.store {
+            Some(store) => {
+                let (id, rest) = StoreHandler::split_uri(&uri).ok_or_else(|| {
+                    McpError::invalid_params(format!("Not a subscribable resource: {}", uri))
+                })?;
+                let subject = store
+                    .subject(id)
+                    .map_err(|e| McpError::invalid_params(e.to_string()))?;
+                (subject.path, format!("{}{}", REPO_URI_PREFIX, rest))
+            }
+            None => (self.config.repo_path.clone(), uri.clone()),
+        };
+        let watched = repo_uri
+            .strip_prefix(REPO_URI_PREFIX)
+            .and_then(|rest| rest.split('/').next())
+            .is_some_and(|dir| WATCHED_DIRS.contains(&dir));
+        if !watched {
+            return Err(McpError::invalid_params(format!(
+                "Not a subscribable resource: {} (subscribe to the journal, state, documents or imaging)",
+                uri
+            )));
+        }
+
+        let watch = watch::watch(&repo).map_err(|e| McpError::internal_error(e.to_string()))?;
+        let mut updates = watch.subscribe();
+        let notifier = self.notifier.clone();
+        let notification = serde_json::to_string(&McpRequest::notification(
+            "notifications/resources/updated",
+            serde_json::json!({ "uri": uri }),
+        ))
+        .map_err(|e| McpError::internal_error(e.to_string()))?;
+        let task = tokio::spawn(async move {
+            // Holding the watch keeps the repository watched.
+            let _watch = watch;
+            loop {
+                let updated = match updates.recv().await {
+                    Ok(changed) => changed == repo_uri,
+                    // Updates were dropped; one of them may have been ours.
+                    Err(broadcast::error::RecvError::Lagged(_)) => true,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if updated && notifier.send(notification.clone()).is_err() {
+                    break;
+                }
+            }
+        });
+        debug!("Subscribed to {}", uri);
+        self.subscriptions.insert(uri, task.abort_handle());
+        Ok(serde_json::json!({}))
+    }
+
+    async fn handle_resources_unsubscribe(
+        &mut self,
+        request: &McpRequest,
+    ) -> Result<serde_json::Value, McpError> {
+        if !self.initialized {
+            return Err(McpError::invalid_request("Server not initialized"));
+        }
+
+        let uri = request
+            .params
+            .as_ref()
+            .and_then(|p| p.get("uri"))
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing 'uri' parameter"))?;
+
+        if let Some(task) = self.subscriptions.remove(uri) {
+            task.abort();
+            debug!("Unsubscribed from {}", uri);
+        }
+        Ok(serde_json::json!({}))
+    }
+
     async fn handle_tools_list(
         &self,
         _request: &McpRequest,
@@ -150,10 +398,12 @@ impl McpServer {
             return Err(McpError::invalid_request("Server not initialized"));
         }
 
-        let tools = self
-            .tool_handler
-            .list_tools()
-            .map_err(|e| McpError::internal_error(e.to_string()))?;
+        self.authorize(None)?;
+        let tools = match &self.store {
+            Some(store) => store.list_tools(),
+            None => self.tool_handler.list_tools(),
+        }
+        .map_err(|e| McpError::internal_error(e.to_string()))?;
 
         serde_json::to_value(tools).map_err(|e| McpError::internal_error(e.to_string()))
     }
@@ -178,14 +428,114 @@ impl McpServer {
             .cloned()
             .unwrap_or(serde_json::json!({}));
 
-        let result = self
-            .tool_handler
-            .call_tool(name, arguments)
-            .map_err(|e| McpError::internal_error(e.to_string()))?;
+        let principal = self.authorize(required_scope(self.store.is_some(), name))?;
+        let contributor = principal.contributor.as_deref();
+        let attribution = writes(self.store.is_some(), name).then(|| Attribution {
+            record: self.audit_record(request, name),
+            agent: self.agent(),
+        });
+        let result = match &self.store {
+            Some(store) => store.call_tool(name, arguments, contributor, attribution),
+            None => self
+                .tool_handler
+                .call_tool(name, arguments, contributor, attribution),
+        }
+        .map_err(|e| McpError::internal_error(e.to_string()))?;
 
         serde_json::to_value(result).map_err(|e| McpError::internal_error(e.to_string()))
     }
 
+    /// Append a `resources/read`, `tools/call` or `prompts/get` to the audit
+    /// log, to be committed with the next write or when the server stops. A
+    /// successful write has already committed its own record. A Store server
+    /// logs to the subject's repository, or to the Store root, which is no
+    /// repository, when no subject is addressed.
+    fn audit(&self, request: &McpRequest, result: &Result<serde_json::Value, McpError>) {
+        let params = request.params.clone().unwrap_or(serde_json::Value::Null);
+        let param = |key: &str| params.get(key).and_then(|v| v.as_str()).unwrap_or("");
+        let target = match request.method.as_str() {
+            "tools/call" | "prompts/get" => param("name"),
+            _ => param("uri"),
+        };
+        if request.method == "tools/call" && result.is_ok() && writes(self.store.is_some(), target)
+        {
+            return;
+        }
+        let subject = self.store.as_ref().and_then(|s| s.subject_of(&params));
+        let repo = match &subject {
+            Some(subject) => &subject.path,
+            None => &self.config.repo_path,
+        };
+
+        let (outcome, error) = match result {
+            Ok(_) => (Outcome::Ok, None),
+            Err(e) if e.code == error_codes::UNAUTHORIZED => {
+                (Outcome::Denied, Some(e.message.clone()))
+            }
+            Err(e) => (Outcome::Error, Some(e.message.clone())),
+        };
+        let mut record = AuditRecord {
+            outcome,
+            error,
+            ..self.audit_record(request, target)
+        };
+        let recorded = if self.store.is_some() && subject.is_none() {
+            record.contributor = self.principal.as_ref().and_then(|p| p.contributor.clone());
+            audit::append(repo, record)
+        } else {
+            audit_appended(repo);
+            with_repo_cwd(repo, || {
+                record.contributor = match &self.principal {
+                    Some(Principal {
+                        contributor: Some(id),
+                        ..
+                    }) => Some(id.clone()),
+                    Some(_) => contributor::get_current_contributor(),
+                    None => None,
+                };
+                audit::append(repo, record)
+            })
+        };
+        if let Err(e) = recorded {
+            error!("Failed to record MCP audit log: {}", e);
+        }
+    }
+
+    /// The audit record of `request` on `target`, as a success by the
+    /// session's client and token. The contributor is filled in where it is
+    /// resolved, in the repository.
+    fn audit_record(&self, request: &McpRequest, target: &str) -> AuditRecord {
+        let params = request.params.clone().unwrap_or(serde_json::Value::Null);
+        AuditRecord {
+            timestamp: chrono::Utc::now(),
+            client: self.client.0.clone(),
+            client_version: self.client.1.clone(),
+            token: self.principal.as_ref().and_then(|p| p.token_id.clone()),
+            contributor: None,
+            operation: request.method.clone(),
+            target: target.to_string(),
+            arguments_sha256: sha256_hex(params.to_string().as_bytes()),
+            outcome: Outcome::Ok,
+            error: None,
+            journal_entry: None,
+            prev: String::new(),
+        }
+    }
+
+    /// The session's client and token, as named in a write's journal entry
+    /// and commit.
+    fn agent(&self) -> String {
+        let mut agent = match &self.client {
+            (Some(name), Some(version)) => format!("\"{}\" {}", name, version),
+            (Some(name), None) => format!("\"{}\"", name),
+            _ => "an unidentified MCP client".to_string(),
+        };
+        if let Some(token) = self.principal.as_ref().and_then(|p| p.token_id.as_ref()) {
+            agent.push_str(&format!(" (token {})", token));
+        }
+        agent
+    }
+
     async fn handle_prompts_list(
         &self,
         _request: &McpRequest,
@@ -194,81 +544,159 @@ impl McpServer {
             return Err(McpError::invalid_request("Server not initialized"));
         }
 
-        // Placeholder for prompts - will implement later
-        Ok(serde_json::json!({
-            "prompts": []
-        }))
+        self.authorize(Some(Scope::Read))?;
+        let prompts = match &self.store {
+            Some(store) => store.list_prompts(),
+            None => self.prompt_handler.list_prompts(),
+        }
+        .map_err(|e| McpError::internal_error(e.to_string()))?;
+
+        serde_json::to_value(prompts).map_err(|e| McpError::internal_error(e.to_string()))
     }
 
     async fn handle_prompts_get(
         &self,
-        _request: &McpRequest,
+        request: &McpRequest,
     ) -> Result<serde_json::Value, McpError> {
         if !self.initialized {
             return Err(McpError::invalid_request("Server not initialized"));
         }
 
-        // Placeholder for prompts - will implement later
-        Err(McpError::method_not_found(
-            "prompts/get not yet implemented",
-        ))
-    }
+        let params = request
+            .params
+            .as_ref()
+            .ok_or_else(|| McpError::invalid_params("Missing params"))?;
 
-    /// Run the server on stdio (for local MCP clients)
-    pub async fn run_stdio(&mut self) -> anyhow::Result<()> {
-        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+        let name = params
+            .get("name")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| McpError::invalid_params("Missing 'name' parameter"))?;
 
-        info!("Starting MCP server on stdio");
+        let arguments = match params.get("arguments") {
+            None | Some(serde_json::Value::Null) => serde_json::Map::new(),
+            Some(serde_json::Value::Object(arguments)) => arguments.clone(),
+            Some(_) => return Err(McpError::invalid_params("'arguments' must be an object")),
+        };
 
-        let stdin = tokio::io::stdin();
-        let mut stdout = tokio::io::stdout();
-        let mut reader = BufReader::new(stdin);
-        let mut line = String::new();
+        self.authorize(Some(Scope::Read))?;
+        match &self.store {
+            Some(store) => store.get_prompt(name, &arguments),
+            None => self
+                .prompt_handler
+                .get_prompt(name, &arguments, &self.resource_handler),
+        }
+    }
 
-        loop {
-            line.clear();
-            let bytes_read = reader.read_line(&mut line).await?;
+    /// Handle one serialised JSON-RPC message, returning the serialised
+    /// response. Notifications (no `id`) are handled but get no response.
+    pub async fn handle_message(&mut self, message: &str) -> Option<String> {
+        debug!("Received: {}", message);
 
-            if bytes_read == 0 {
-                debug!("EOF on stdin, shutting down");
-                break;
+        let response = match serde_json::from_str::<McpRequest>(message) {
+            Ok(request) if request.id.is_none() => {
+                self.handle_request(request).await;
+                return None;
             }
-
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                continue;
+            Ok(request) => self.handle_request(request).await,
+            Err(e) => {
+                error!("Failed to parse request: {}", e);
+                McpResponse::error(None, McpError::parse_error(format!("Invalid JSON: {}", e)))
             }
+        };
+
+        let response_json =
+            serde_json::to_string(&response).expect("MCP responses always serialise");
+        debug!("Sending: {}", response_json);
+        Some(response_json)
+    }
+
+    /// Run the server on stdio (for local MCP clients)
+    pub async fn run_stdio(&mut self, shutdown: Shutdown) -> anyhow::Result<()> {
+        info!("Starting MCP server on stdio");
+        self.serve_lines(tokio::io::stdin(), tokio::io::stdout(), shutdown)
+            .await?;
+        info!("MCP server shutdown complete");
+        Ok(())
+    }
 
-            debug!("Received: {}", trimmed);
-
-            let request: McpRequest = match serde_json::from_str(trimmed) {
-                Ok(req) => req,
-                Err(e) => {
-                    error!("Failed to parse request: {}", e);
-                    let error_response = McpResponse::error(
-                        None,
-                        McpError::parse_error(format!("Invalid JSON: {}", e)),
-                    );
-                    let response_json = serde_json::to_string(&error_response)?;
-                    stdout.write_all(response_json.as_bytes()).await?;
-                    stdout.write_all(b"\n").await?;
-                    stdout.flush().await?;
-                    continue;
+    /// Serve newline-delimited JSON-RPC over a byte stream (stdio or a Unix
+    /// socket connection) until EOF or shutdown.
+    pub async fn serve_lines<R, W>(
+        &mut self,
+        reader: R,
+        mut writer: W,
+        mut shutdown: Shutdown,
+    ) -> anyhow::Result<()>
+    where
+        R: tokio::io::AsyncRead + Unpin,
+        W: tokio::io::AsyncWrite + Unpin,
+    {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        // `next_line` is cancel-safe, so a notification can be written while
+        // a request is half read.
+        let mut lines = BufReader::new(reader).lines();
+        let mut notifications = self.take_notifications();
+
+        loop {
+            let message = tokio::select! {
+                line = lines.next_line() => match line? {
+                    Some(line) if line.trim().is_empty() => continue,
+                    Some(line) => match self.handle_message(line.trim()).await {
+                        Some(response) => response,
+                        None => continue,
+                    },
+                    None => {
+                        debug!("EOF on input, closing stream");
+                        break;
+                    }
+                },
+                Some(notification) = next(&mut notifications) => notification,
+                _ = shutdown.wait() => {
+                    debug!("Shutdown requested, closing stream");
+                    break;
                 }
             };
 
-            let response = self.handle_request(request).await;
-            let response_json = serde_json::to_string(&response)?;
+            writer.write_all(message.as_bytes()).await?;
+            writer.write_all(b"\n").await?;
+            writer.flush().await?;
+        }
 
-            debug!("Sending: {}", response_json);
+        Ok(())
+    }
+}
 
-            stdout.write_all(response_json.as_bytes()).await?;
-            stdout.write_all(b"\n").await?;
-            stdout.flush().await?;
+impl Drop for McpServer {
+    fn drop(&mut self) {
+        for task in self.subscriptions.values() {
+            task.abort();
         }
+    }
+}
 
-        info!("MCP server shutdown complete");
-        Ok(())
+/// The next notification, or never when there is no receiver.
+async fn next(notifications: &mut Option<mpsc::UnboundedReceiver<String>>) -> Option<String> {
+    match notifications {
+        Some(notifications) => notifications.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Whether tool `name` writes to the record.
+fn writes(store: bool, name: &str) -> bool {
+    matches!(
+        required_scope(store, name),
+        Some(Scope::JournalWrite | Scope::StateWrite)
+    )
+}
+
+/// The scope a tool needs, on a Store or a single repository.
+fn required_scope(store: bool, name: &str) -> Option<Scope> {
+    if store {
+        StoreHandler::required_scope(name)
+    } else {
+        ToolHandler::required_scope(name)
     }
 }
 
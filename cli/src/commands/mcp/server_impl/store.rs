@@ -0,0 +1,381 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Store-level MCP: one server across every subject in a Store.
+//!
+//! `gitehr://store/subjects` lists the subjects in `gitehr-mpi.json`. A
+//! subject's data is addressed as `gitehr://subject/{subject}/...`, where
+//! `{subject}` is the canonical patient id or the subject's directory and
+//! `...` is any per-repo resource path. Tools and prompts take a `subject`
+//! argument. Everything per-subject runs through the per-repo handlers for that
+//! subject's repository.
+
+use serde::Deserialize;
+use serde_json::{Value, json};
+use std::path::{Component, Path, PathBuf};
+
+use super::paths;
+use super::prompts::{PromptArgument, PromptHandler, PromptsList};
+use super::protocol::McpError;
+use super::resources::{
+    REPO_URI_PREFIX, Resource, ResourceContent, ResourceHandler, ResourceReadContent,
+    ResourceTemplate, ResourceTemplatesList, ResourcesList, ResourcesRead,
+};
+use super::tools::{Attribution, Tool, ToolHandler, ToolResult, ToolsList};
+use crate::commands::mcp::token::Scope;
+use crate::commands::store::{MpiInfo, MpiPatient};
+
+pub const SUBJECTS_URI: &str = "gitehr://store/subjects";
+pub const SUBJECT_URI_PREFIX: &str = "gitehr://subject/";
+
+const MPI_FILE: &str = "gitehr-mpi.json";
+
+/// A subject that operations can address: the canonical path of its
+/// repository within the Store.
+pub struct Subject {
+    pub path: PathBuf,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FindSubjectArgs {
+    query: String,
+    #[serde(rename = "type")]
+    id_type: Option<String>,
+}
+
+/// Handler for a Store-level server
+pub struct StoreHandler {
+    root: PathBuf,
+}
+
+impl StoreHandler {
+    pub fn new(root: PathBuf) -> Self {
+        // Subject paths are built from the root and used while another
+        // session's tool has switched the working directory elsewhere.
+        let root = root.canonicalize().unwrap_or(root);
+        Self { root }
+    }
+
+    fn mpi(&self) -> anyhow::Result<MpiInfo> {
+        let path = self.root.join(MPI_FILE);
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| anyhow::anyhow!("Cannot read {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e))
+    }
+
+    /// The active subject `id` names, by canonical id or directory.
+    pub fn subject(&self, id: &str) -> anyhow::Result<Subject> {
+        let patient = self
+            .mpi()?
+            .patients
+            .into_iter()
+            .find(|p| p.patient_id == id || p.repo_path == id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown subject: {}", id))?;
+        if let Some(target) = &patient.merged_into {
+            anyhow::bail!("Subject {} was merged into {}", id, target);
+        }
+        if patient.status != "active" {
+            anyhow::bail!("Subject {} is {}", id, patient.status);
+        }
+        // The MPI names a directory within the Store; never follow it out.
+        if !Path::new(&patient.repo_path)
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)))
+        {
+            anyhow::bail!(
+                "Subject {} has an invalid repo_path in {}: {}",
+                id,
+                MPI_FILE,
+                patient.repo_path
+            );
+        }
+        let path = self.root.join(&patient.repo_path);
+        if path.exists() {
+            paths::ensure_within(&self.root, &patient.repo_path, &path)?;
+        }
+        if !path.join(".gitehr").is_dir() {
+            anyhow::bail!(
+                "Subject {} has no GitEHR repository at {}",
+                id,
+                path.display()
+            );
+        }
+        Ok(Subject {
+            path: path.canonicalize()?,
+        })
+    }
+
+    /// The subject a `gitehr://subject/...` URI addresses, and the per-repo
+    /// path within it.
+    pub fn split_uri(uri: &str) -> Option<(&str, &str)> {
+        uri.strip_prefix(SUBJECT_URI_PREFIX)?.split_once('/')
+    }
+
+    /// The subject a request addresses, for attributing it in the audit log:
+    /// from a resource URI, or a tool's or prompt's `subject` argument.
+    pub fn subject_of(&self, params: &Value) -> Option<Subject> {
+        let id = match params.get("uri").and_then(|v| v.as_str()) {
+            Some(uri) => Self::split_uri(uri)?.0,
+            None => params.pointer("/arguments/subject")?.as_str()?,
+        };
+        self.subject(id).ok()
+    }
+
+    pub fn list_resources(&self) -> ResourcesList {
+        ResourcesList {
+            resources: vec![Resource {
+                uri: SUBJECTS_URI.to_string(),
+                name: "Subjects".to_string(),
+                description: Some(
+                    "Subjects in this Store, with their identifiers, from the MPI".to_string(),
+                ),
+                mime_type: Some("application/json".to_string()),
+            }],
+        }
+    }
+
+    pub fn resource_templates(&self) -> ResourceTemplatesList {
+        let prefix = format!("{}{{subject}}/", SUBJECT_URI_PREFIX);
+        let listing = |path: &str, name: &str, description: &str| ResourceTemplate {
+            uri_template: format!("{}{}", prefix, path),
+            name: name.to_string(),
+            description: Some(description.to_string()),
+            mime_type: Some("application/json".to_string()),
+        };
+        let mut resource_templates = vec![
+            listing("journal", "Journal Entries", "A subject's journal entries"),
+            listing("state", "Current Clinical State", "A subject's state files"),
+            listing(
+                "status",
+                "Repository Status",
+                "A subject's repository status",
+            ),
+            listing("documents", "Documents", "A subject's Documents"),
+            listing("imaging", "Imaging", "A subject's images and studies"),
+        ];
+        resource_templates.extend(ResourceHandler::resource_templates(&prefix));
+        ResourceTemplatesList { resource_templates }
+    }
+
+    pub fn read_resource(&self, uri: &str) -> anyhow::Result<ResourcesRead> {
+        if uri == SUBJECTS_URI {
+            let subjects: Vec<Value> = self
+                .mpi()?
+                .patients
+                .iter()
+                .map(|p| subject_json(p, None))
+                .collect();
+            return Ok(ResourcesRead {
+                contents: vec![ResourceReadContent {
+                    uri: SUBJECTS_URI.to_string(),
+                    mime_type: Some("application/json".to_string()),
+                    content: ResourceContent::Text {
+                        text: serde_json::to_string_pretty(&subjects)?,
+                    },
+                    meta: None,
+                }],
+            });
+        }
+
+        let (id, rest) =
+            Self::split_uri(uri).ok_or_else(|| anyhow::anyhow!("Unknown resource URI: {}", uri))?;
+        let subject = self.subject(id)?;
+        let mut read = ResourceHandler::new(subject.path)
+            .read_resource(&format!("{}{}", REPO_URI_PREFIX, rest))?;
+        let prefix = format!("{}{}/", SUBJECT_URI_PREFIX, id);
+        for content in &mut read.contents {
+            if let Some(path) = content.uri.strip_prefix(REPO_URI_PREFIX) {
+                content.uri = format!("{}{}", prefix, path);
+            }
+        }
+        Ok(read)
+    }
+
+    /// The scope a caller needs to run `name`.
+    pub fn required_scope(name: &str) -> Option<Scope> {
+        match name {
+            "find_subject" => Some(Scope::Read),
+            name => ToolHandler::required_scope(name),
+        }
+    }
+
+    /// `find_subject`, then every per-repo tool with a required `subject`.
+    pub fn list_tools(&self) -> anyhow::Result<ToolsList> {
+        let mut tools = vec![Tool {
+            name: "find_subject".to_string(),
+            description: "Find subjects in the Store by identifier (e.g. NHS number, MRN), canonical id or directory name. Spaces are ignored and case does not matter.".to_string(),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "query": { "type": "string", "description": "Identifier value, canonical id or name, or part of one" },
+                    "type": { "type": "string", "description": "Only match identifiers of this type, e.g. NHS" }
+                },
+                "required": ["query"],
+                "additionalProperties": false
+            }),
+        }];
+        for mut tool in ToolHandler::new(self.root.clone()).list_tools()?.tools {
+            if let Some(properties) = tool.input_schema["properties"].as_object_mut() {
+                properties.insert("subject".to_string(), subject_property());
+            }
+            match tool.input_schema["required"].as_array_mut() {
+                Some(required) => required.insert(0, json!("subject")),
+                None => tool.input_schema["required"] = json!(["subject"]),
+            }
+            tools.push(tool);
+        }
+        Ok(ToolsList { tools })
+    }
+
+    pub fn call_tool(
+        &self,
+        name: &str,
+        mut arguments: Value,
+        contributor: Option<&str>,
+        attribution: Option<Attribution>,
+    ) -> anyhow::Result<ToolResult> {
+        if name == "find_subject" {
+            let args: FindSubjectArgs = serde_json::from_value(arguments)
+                .map_err(|e| anyhow::anyhow!("Invalid arguments for find_subject: {}", e))?;
+            return ToolResult::json(json!({ "subjects": self.find(&args)? }));
+        }
+        let id = take_subject(&mut arguments)
+            .ok_or_else(|| anyhow::anyhow!("Missing 'subject' parameter"))?;
+        let subject = self.subject(&id)?;
+        ToolHandler::new(subject.path).call_tool(name, arguments, contributor, attribution)
+    }
+
+    fn find(&self, args: &FindSubjectArgs) -> anyhow::Result<Vec<Value>> {
+        let normalise = |s: &str| {
+            s.chars()
+                .filter(|c| !c.is_whitespace())
+                .flat_map(char::to_lowercase)
+                .collect::<String>()
+        };
+        let query = normalise(&args.query);
+        if query.is_empty() {
+            anyhow::bail!("'query' must not be empty");
+        }
+
+        let mut found = Vec::new();
+        for patient in &self.mpi()?.patients {
+            let matched: Vec<&str> = patient
+                .identifiers
+                .iter()
+                .filter(|id| {
+                    args.id_type
+                        .as_deref()
+                        .is_none_or(|t| id.id_type.eq_ignore_ascii_case(t))
+                })
+                .filter(|id| normalise(&id.value).contains(&query))
+                .map(|id| id.id_type.as_str())
+                .collect();
+            let by_name = args.id_type.is_none()
+                && (normalise(&patient.patient_id) == query
+                    || normalise(&patient.repo_path).contains(&query));
+            if !matched.is_empty() || by_name {
+                found.push(subject_json(patient, Some(matched)));
+            }
+        }
+        Ok(found)
+    }
+
+    /// Built-in prompts, each with a required `subject`. Repositories'
+    /// own templates are used when a prompt is rendered for that subject.
+    pub fn list_prompts(&self) -> anyhow::Result<PromptsList> {
+        let mut list = PromptHandler::new(self.root.clone()).list_prompts()?;
+        for prompt in &mut list.prompts {
+            prompt.arguments.insert(
+                0,
+                PromptArgument {
+                    name: "subject".to_string(),
+                    description: Some(subject_description().to_string()),
+                    required: true,
+                },
+            );
+        }
+        Ok(list)
+    }
+
+    pub fn get_prompt(
+        &self,
+        name: &str,
+        arguments: &serde_json::Map<String, Value>,
+    ) -> Result<Value, McpError> {
+        let mut arguments = arguments.clone();
+        let id = match arguments.remove("subject") {
+            Some(Value::String(id)) => id,
+            _ => {
+                return Err(McpError::invalid_params(format!(
+                    "Missing required argument for {}: subject",
+                    name
+                )));
+            }
+        };
+        let subject = self
+            .subject(&id)
+            .map_err(|e| McpError::invalid_params(e.to_string()))?;
+        PromptHandler::new(subject.path.clone()).get_prompt(
+            name,
+            &arguments,
+            &ResourceHandler::new(subject.path),
+        )
+    }
+}
+
+fn subject_description() -> &'static str {
+    "Subject: canonical patient id or directory name (see find_subject)"
+}
+
+fn subject_property() -> Value {
+    json!({ "type": "string", "description": subject_description() })
+}
+
+fn take_subject(arguments: &mut Value) -> Option<String> {
+    match arguments.as_object_mut()?.remove("subject")? {
+        Value::String(id) => Some(id),
+        _ => None,
+    }
+}
+
+fn subject_json(patient: &MpiPatient, matched: Option<Vec<&str>>) -> Value {
+    let mut value = json!({
+        "id": patient.patient_id,
+        "name": patient.repo_path,
+        "uri": format!("{}{}/", SUBJECT_URI_PREFIX, patient.patient_id),
+        "status": patient.status,
+        "identifiers": patient.identifiers,
+    });
+    if let Some(target) = &patient.merged_into {
+        value["merged_into"] = json!(target);
+    }
+    if let Some(matched) = matched {
+        value["matched_identifiers"] = json!(matched);
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_uri() {
+        assert_eq!(
+            StoreHandler::split_uri("gitehr://subject/ada/journal/x.md"),
+            Some(("ada", "journal/x.md"))
+        );
+        assert_eq!(StoreHandler::split_uri("gitehr://subject/ada"), None);
+        assert_eq!(StoreHandler::split_uri("gitehr://repo/journal"), None);
+    }
+
+    #[test]
+    fn test_take_subject() {
+        let mut args = json!({ "subject": "ada", "content": "Note" });
+        assert_eq!(take_subject(&mut args).as_deref(), Some("ada"));
+        assert_eq!(args, json!({ "content": "Note" }));
+        assert_eq!(take_subject(&mut json!({ "subject": 3 })), None);
+    }
+}
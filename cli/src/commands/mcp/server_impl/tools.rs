@@ -6,7 +6,12 @@
 //! Tools allow write operations on GitEHR repositories.
 
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use super::{clinical_tools, paths, with_repo_cwd};
+use crate::commands::mcp::audit::{self, AuditRecord};
+use crate::commands::mcp::token::Scope;
+use crate::commands::{contributor, document, git, journal, typed_state};
 
 /// MCP Tool definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,6 +28,24 @@ pub struct ToolResult {
     pub content: Vec<ToolContent>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub is_error: Option<bool>,
+    /// The same result as a JSON object, for clients that read structured
+    /// tool output.
+    #[serde(rename = "structuredContent", skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<serde_json::Value>,
+}
+
+impl ToolResult {
+    /// A successful result carrying `value` both as structured content and as
+    /// pretty-printed JSON text.
+    pub fn json(value: serde_json::Value) -> anyhow::Result<Self> {
+        Ok(Self {
+            content: vec![ToolContent::Text {
+                text: serde_json::to_string_pretty(&value)?,
+            }],
+            is_error: Some(false),
+            structured_content: Some(value),
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +61,14 @@ pub struct ToolsList {
     pub tools: Vec<Tool>,
 }
 
+/// Who made a write: the call's audit record, to be completed with the
+/// journal entry and author, and the agent (client and token) to name in the
+/// journal.
+pub struct Attribution {
+    pub record: AuditRecord,
+    pub agent: String,
+}
+
 /// Tool handler for GitEHR repositories
 pub struct ToolHandler {
     repo_path: PathBuf,
@@ -48,18 +79,33 @@ impl ToolHandler {
         Self { repo_path }
     }
 
+    /// The scope a caller needs to run `name`; `None` for unknown tools.
+    pub fn required_scope(name: &str) -> Option<Scope> {
+        match name {
+            "search_repository" => Some(Scope::Read),
+            "add_journal_entry" => Some(Scope::JournalWrite),
+            "update_state" => Some(Scope::StateWrite),
+            name => clinical_tools::required_scope(name),
+        }
+    }
+
     /// List all available tools
     pub fn list_tools(&self) -> anyhow::Result<ToolsList> {
-        let tools = vec![
+        let mut tools = vec![
             Tool {
                 name: "add_journal_entry".to_string(),
-                description: "Create a new clinical journal entry".to_string(),
+                description: "Create and commit a new clinical journal entry, attributed to the server's MCP contributor. Returns the entry filename and commit hash.".to_string(),
                 input_schema: serde_json::json!({
                     "type": "object",
                     "properties": {
                         "content": {
                             "type": "string",
                             "description": "Markdown content of the journal entry"
+                        },
+                        "documents": {
+                            "type": "array",
+                            "items": { "type": "string" },
+                            "description": "Record paths of Documents already in the repository to link from the entry (e.g. documents/2026-06-14-letter-1a2b3c4d.pdf)"
                         }
                     },
                     "required": ["content"]
@@ -67,7 +113,7 @@ impl ToolHandler {
             },
             Tool {
                 name: "update_state".to_string(),
-                description: "Update a state file in the repository".to_string(),
+                description: "Write a state file in the repository and commit it".to_string(),
                 input_schema: serde_json::json!({
                     "type": "object",
                     "properties": {
@@ -98,61 +144,161 @@ impl ToolHandler {
                 }),
             },
         ];
+        tools.extend(clinical_tools::tools());
 
         Ok(ToolsList { tools })
     }
 
-    /// Execute a tool by name
+    /// Execute a tool by name. Writes are attributed to `contributor`, or to
+    /// the repository's active contributor when `None`, and record
+    /// `attribution` once they succeed.
     pub fn call_tool(
         &self,
         name: &str,
         arguments: serde_json::Value,
+        contributor: Option<&str>,
+        attribution: Option<Attribution>,
     ) -> anyhow::Result<ToolResult> {
         match name {
-            "add_journal_entry" => self.add_journal_entry(arguments),
-            "update_state" => self.update_state(arguments),
+            "add_journal_entry" => self.add_journal_entry(arguments, contributor, attribution),
+            "update_state" => self.update_state(arguments, contributor, attribution),
             "search_repository" => self.search_repository(arguments),
+            name if clinical_tools::NAMES.contains(&name) => with_repo_cwd(&self.repo_path, || {
+                let result = clinical_tools::call(name, arguments, || author(contributor))?;
+                let entry = result
+                    .structured_content
+                    .as_ref()
+                    .and_then(|value| value["journal_entry"].as_str());
+                if let (Some(attribution), Some(entry)) = (attribution, entry) {
+                    self.commit_attributed(attribution, entry, author(contributor).ok(), None)?;
+                }
+                Ok(result)
+            }),
             _ => Err(anyhow::anyhow!("Unknown tool: {}", name)),
         }
     }
 
-    fn add_journal_entry(&self, arguments: serde_json::Value) -> anyhow::Result<ToolResult> {
+    /// Record who made a write that changed `written`: a journal entry naming
+    /// the agent and the contributor it acted as, related to the write's own
+    /// entry, committed with the write's audit record in one commit. Given a
+    /// message and a path the write left staged, that path is committed with
+    /// them under the message. Runs in the repository.
+    fn commit_attributed(
+        &self,
+        attribution: Attribution,
+        written: &str,
+        author: Option<String>,
+        message: Option<(&str, &str)>,
+    ) -> anyhow::Result<()> {
+        let Attribution { record, agent } = attribution;
+        let write_entry = written.starts_with("journal/").then(|| written.to_string());
+        let body = format!(
+            "Recorded through MCP by {}, acting as {}: {}, {}.",
+            agent,
+            author.as_deref().unwrap_or("no contributor"),
+            record.target,
+            written
+        );
+        let entry = journal::JournalEntry {
+            related: write_entry.iter().cloned().collect(),
+            ..journal::JournalEntry::new(author.clone())
+        };
+        let committed = journal::stage_journal_entry(&entry, &body).and_then(|agent_entry| {
+            audit::append(
+                &self.repo_path,
+                AuditRecord {
+                    journal_entry: Some(write_entry.unwrap_or_else(|| agent_entry.clone())),
+                    contributor: author,
+                    ..record
+                },
+            )?;
+            git::git_add(audit::AUDIT_LOG)?;
+            let trailer = format!("MCP-Agent: {}", agent);
+            match message {
+                Some((message, path)) => git::git_commit_only(
+                    &format!(
+                        "{}\n\nJournal entry: {}\n\n{}",
+                        message, agent_entry, trailer
+                    ),
+                    &[path, &agent_entry, audit::AUDIT_LOG],
+                ),
+                None => git::git_commit_only(
+                    &format!("Journal entry: {}\n\n{}", agent_entry, trailer),
+                    &[&agent_entry, audit::AUDIT_LOG],
+                ),
+            }
+        });
+        if let Err(e) = committed {
+            anyhow::bail!(
+                "{} was written, but its MCP attribution was not committed: {}",
+                written,
+                e
+            );
+        }
+        Ok(())
+    }
+
+    fn add_journal_entry(
+        &self,
+        arguments: serde_json::Value,
+        contributor: Option<&str>,
+        attribution: Option<Attribution>,
+    ) -> anyhow::Result<ToolResult> {
         let content = arguments
             .get("content")
             .and_then(|v| v.as_str())
-            .ok_or_else(|| anyhow::anyhow!("Missing 'content' parameter"))?;
-
-        // This is a placeholder - in reality we would use the gitehr library
-        // For now, just validate that the journal directory exists
-        let journal_dir = self.repo_path.join("journal");
-        if !journal_dir.exists() {
-            return Err(anyhow::anyhow!("Journal directory not found"));
+            .ok_or_else(|| anyhow::anyhow!("Missing 'content' parameter"))?
+            .trim()
+            .to_string();
+        if content.is_empty() {
+            anyhow::bail!("'content' must not be empty");
         }
 
-        // Generate a placeholder filename
-        let timestamp = chrono::Utc::now().format("%Y%m%dT%H%M%S%.3fZ");
-        let uuid = "placeholder-uuid";
-        let filename = format!("{}-{}.md", timestamp, uuid);
-
-        // In a real implementation, we would:
-        // 1. Get the latest journal entry hash
-        // 2. Create the new entry with proper YAML front matter
-        // 3. Commit to git
-        // For now, return a success message
+        let document_paths: Vec<String> = match arguments.get("documents") {
+            None | Some(serde_json::Value::Null) => Vec::new(),
+            Some(value) => serde_json::from_value(value.clone())
+                .map_err(|_| anyhow::anyhow!("'documents' must be an array of strings"))?,
+        };
 
-        Ok(ToolResult {
-            content: vec![ToolContent::Text {
-                text: format!(
-                    "Would create journal entry: journal/{}\nContent length: {} characters\n\nNote: Full journal creation will be implemented when gitehr library is integrated.",
-                    filename,
-                    content.len()
-                ),
-            }],
-            is_error: Some(false),
-        })
+        let (filename, commit, author, documents) = with_repo_cwd(&self.repo_path, || {
+            typed_state::ensure_gitehr_repository()?;
+            let author = author(contributor)?;
+            let documents = document_paths
+                .iter()
+                .map(|path| {
+                    let doc = document::existing_ref(path)?;
+                    let root = doc.path.split('/').next().unwrap_or_default();
+                    paths::ensure_within(Path::new("."), root, Path::new(&doc.path))?;
+                    Ok(doc)
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let filename = journal::create_journal_entry_as(
+                &content,
+                documents.clone(),
+                Some(author.clone()),
+            )?;
+            let commit = git::head_commit()
+                .ok_or_else(|| anyhow::anyhow!("Entry written but no commit found"))?;
+            if let Some(attribution) = attribution {
+                self.commit_attributed(attribution, &filename, Some(author.clone()), None)?;
+            }
+            Ok((filename, commit, author, documents))
+        })?;
+
+        ToolResult::json(serde_json::json!({
+            "filename": filename,
+            "commit": commit,
+            "author": author,
+            "documents": documents.iter().map(|d| &d.path).collect::<Vec<_>>(),
+        }))
     }
 
-    fn update_state(&self, arguments: serde_json::Value) -> anyhow::Result<ToolResult> {
+    fn update_state(
+        &self,
+        arguments: serde_json::Value,
+        contributor: Option<&str>,
+        attribution: Option<Attribution>,
+    ) -> anyhow::Result<ToolResult> {
         let filename = arguments
             .get("filename")
             .and_then(|v| v.as_str())
@@ -168,14 +314,33 @@ impl ToolHandler {
             std::fs::create_dir_all(&state_dir)?;
         }
 
-        let file_path = state_dir.join(filename);
+        let file_path = paths::confine(&self.repo_path, "state", filename, false)?;
         std::fs::write(&file_path, content)?;
 
+        let record_path = format!("state/{}", filename);
+        let message = format!("State update: {}", record_path);
+        with_repo_cwd(&self.repo_path, || {
+            git::git_add(&record_path)?;
+            match attribution {
+                Some(attribution) => self.commit_attributed(
+                    attribution,
+                    &record_path,
+                    author(contributor).ok(),
+                    Some((&message, &record_path)),
+                ),
+                None if git::has_staged_changes(&record_path) => {
+                    git::git_commit_only(&message, &[&record_path])
+                }
+                None => Ok(()),
+            }
+        })?;
+
         Ok(ToolResult {
             content: vec![ToolContent::Text {
-                text: format!("Updated state file: state/{}", filename),
+                text: format!("Updated state file: {}", record_path),
             }],
             is_error: Some(false),
+            structured_content: None,
         })
     }
 
@@ -235,10 +400,30 @@ impl ToolHandler {
         Ok(ToolResult {
             content: vec![ToolContent::Text { text: result_text }],
             is_error: Some(false),
+            structured_content: None,
         })
     }
 }
 
+/// The contributor MCP writes are attributed to: the session's identity, else
+/// the repository's active contributor. Must be known and enabled. Call with
+/// the working directory inside the repository.
+pub(super) fn author(contributor: Option<&str>) -> anyhow::Result<String> {
+    let id = contributor
+        .map(str::to_string)
+        .or_else(contributor::get_current_contributor)
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No MCP contributor configured: start the server with --contributor <id> or activate a contributor"
+            )
+        })?;
+    match contributor::get_contributor(&id) {
+        Some(c) if c.enabled => Ok(id),
+        Some(_) => anyhow::bail!("Contributor '{}' is disabled", id),
+        None => anyhow::bail!("Unknown contributor '{}'", id),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -261,7 +446,7 @@ mod tests {
     fn test_unknown_tool_is_transport_error() {
         let handler = ToolHandler::new(PathBuf::from("."));
         let err = handler
-            .call_tool("clincalc_nonesuch", serde_json::json!({}))
+            .call_tool("clincalc_nonesuch", serde_json::json!({}), None, None)
             .unwrap_err();
         assert!(err.to_string().contains("Unknown tool"));
     }
@@ -273,6 +458,7 @@ mod tests {
                 text: "Success".to_string(),
             }],
             is_error: Some(false),
+            structured_content: None,
         };
 
         let json = serde_json::to_value(&result).unwrap();
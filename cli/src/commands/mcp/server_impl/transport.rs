@@ -0,0 +1,736 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Unix socket and Streamable HTTP transports.
+//!
+//! Every transport feeds messages to `McpServer::handle_message`. Each Unix
+//! socket connection, and each HTTP session (keyed by `Mcp-Session-Id`), gets
+//! its own `McpServer`, so sessions initialise independently. Notifications
+//! are written between responses on a Unix socket, and over HTTP are sent on
+//! the session's event stream, opened with `GET`.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpListener;
+use tokio::sync::{Mutex, mpsc, watch};
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+
+use super::auth::{self, Principal};
+use super::protocol::error_codes;
+use super::server::{McpServer, ServerConfig};
+
+/// The single Streamable HTTP endpoint.
+pub const HTTP_ENDPOINT: &str = "/mcp";
+const SESSION_HEADER: &str = "mcp-session-id";
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+/// An idle event stream sends a comment this often, so proxies keep it open
+/// and a vanished client is noticed.
+const KEEPALIVE: Duration = Duration::from_secs(15);
+/// A session nobody has used for this long is closed, so clients that vanish
+/// without a `DELETE` do not hold a server and its subscriptions forever.
+const SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+/// How often idle sessions are looked for.
+const SESSION_SWEEP: Duration = Duration::from_secs(60);
+
+/// Resolves once shutdown has been requested. Cheap to clone; one per task.
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    pub async fn wait(&mut self) {
+        let _ = self.0.wait_for(|stop| *stop).await;
+    }
+}
+
+/// A shutdown handle that fires on Ctrl-C or SIGTERM.
+pub fn on_signal() -> Shutdown {
+    let (tx, rx) = watch::channel(false);
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate() => {}
+        }
+        info!("Shutdown signal received");
+        let _ = tx.send(true);
+    });
+    Shutdown(rx)
+}
+
+#[cfg(unix)]
+async fn terminate() {
+    use tokio::signal::unix::{SignalKind, signal};
+    match signal(SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            sigterm.recv().await;
+        }
+        Err(_) => std::future::pending().await,
+    }
+}
+
+#[cfg(not(unix))]
+async fn terminate() {
+    std::future::pending().await
+}
+
+/// Serve newline-delimited JSON-RPC on a Unix socket, one session per
+/// connection. The socket file is removed on shutdown.
+#[cfg(unix)]
+pub async fn run_unix(config: ServerConfig, path: &Path, shutdown: Shutdown) -> anyhow::Result<()> {
+    // Removed on exit, after tools may have changed the working directory.
+    let path = &std::path::absolute(path)?;
+    if path.exists() {
+        use std::os::unix::fs::FileTypeExt;
+        if !std::fs::symlink_metadata(path)?.file_type().is_socket() {
+            anyhow::bail!("{} exists and is not a socket", path.display());
+        }
+        // A socket nobody is listening on is left over from an unclean exit.
+        if std::os::unix::net::UnixStream::connect(path).is_ok() {
+            anyhow::bail!("Another server is already listening on {}", path.display());
+        }
+        std::fs::remove_file(path)?;
+    }
+    let listener = tokio::net::UnixListener::bind(path)
+        .map_err(|e| anyhow::anyhow!("Cannot bind {}: {}", path.display(), e))?;
+    let _cleanup = RemoveOnDrop(path.to_path_buf());
+    info!("Starting MCP server on unix socket {}", path.display());
+
+    let mut connections = JoinSet::new();
+    let mut stop = shutdown.clone();
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                let config = config.clone();
+                let shutdown = shutdown.clone();
+                connections.spawn(async move {
+                    debug!("Unix socket session opened");
+                    let (reader, writer) = stream.into_split();
+                    let mut server = McpServer::new(config);
+                    if let Err(e) = server.serve_lines(reader, writer, shutdown).await {
+                        debug!("Unix socket session ended: {}", e);
+                    }
+                });
+            }
+            _ = stop.wait() => break,
+        }
+    }
+
+    while connections.join_next().await.is_some() {}
+    info!("MCP server shutdown complete");
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub async fn run_unix(_: ServerConfig, _: &Path, _: Shutdown) -> anyhow::Result<()> {
+    anyhow::bail!("Unix socket transport is only available on Unix platforms")
+}
+
+struct RemoveOnDrop(PathBuf);
+
+impl Drop for RemoveOnDrop {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+
+type Sessions = Arc<Mutex<HashMap<String, Session>>>;
+
+struct Session {
+    server: Arc<Mutex<McpServer>>,
+    last_used: Instant,
+}
+
+impl Session {
+    fn new(server: Arc<Mutex<McpServer>>) -> Self {
+        Self {
+            server,
+            last_used: Instant::now(),
+        }
+    }
+
+    /// Handling a request or streaming events.
+    fn busy(&self) -> bool {
+        match self.server.try_lock() {
+            Ok(server) => server.streaming(),
+            Err(_) => true,
+        }
+    }
+}
+
+/// Close the sessions idle for longer than `timeout` at `now`. A busy session
+/// counts as used, so one whose event stream has just closed gets a full
+/// timeout to reconnect.
+fn expire_idle(sessions: &mut HashMap<String, Session>, now: Instant, timeout: Duration) {
+    sessions.retain(|id, session| {
+        if session.busy() {
+            session.last_used = now;
+        }
+        let keep = now.duration_since(session.last_used) < timeout;
+        if !keep {
+            debug!("HTTP session {} closed after {:?} idle", id, timeout);
+        }
+        keep
+    });
+}
+
+/// Serve the MCP Streamable HTTP transport on `addr` at [`HTTP_ENDPOINT`].
+pub async fn run_http(config: ServerConfig, addr: &str, shutdown: Shutdown) -> anyhow::Result<()> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| anyhow::anyhow!("Cannot bind {}: {}", addr, e))?;
+    let local = listener.local_addr()?;
+    if !local.ip().is_loopback() {
+        warn!(
+            "MCP HTTP server is listening on non-loopback address {}; anyone who can reach it can read the record",
+            local
+        );
+    }
+    info!("Starting MCP server on http://{}{}", local, HTTP_ENDPOINT);
+
+    let sessions: Sessions = Arc::default();
+    let mut connections = JoinSet::new();
+    let mut stop = shutdown.clone();
+    let mut sweep =
+        tokio::time::interval_at(tokio::time::Instant::now() + SESSION_SWEEP, SESSION_SWEEP);
+    loop {
+        tokio::select! {
+            _ = sweep.tick() => {
+                expire_idle(&mut *sessions.lock().await, Instant::now(), SESSION_IDLE_TIMEOUT);
+            }
+            accepted = listener.accept() => {
+                let (stream, peer) = accepted?;
+                let config = config.clone();
+                let sessions = sessions.clone();
+                let shutdown = shutdown.clone();
+                connections.spawn(async move {
+                    if let Err(e) = serve_http_connection(stream, peer, config, sessions, shutdown).await {
+                        debug!("HTTP connection from {} ended: {}", peer, e);
+                    }
+                });
+            }
+            _ = stop.wait() => break,
+        }
+    }
+
+    while connections.join_next().await.is_some() {}
+    info!("MCP server shutdown complete");
+    Ok(())
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    headers: HashMap<String, String>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name).map(String::as_str)
+    }
+}
+
+struct HttpResponse {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: String,
+    /// Instead of a body, stream these as server-sent events.
+    events: Option<EventStream>,
+}
+
+/// A session's notifications, taken from it while a `GET` stream is open.
+struct EventStream {
+    notifications: mpsc::UnboundedReceiver<String>,
+    /// Weak, so a closed session ends its stream.
+    server: Weak<Mutex<McpServer>>,
+}
+
+impl HttpResponse {
+    fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+            events: None,
+        }
+    }
+
+    fn text(status: u16, body: &str) -> Self {
+        let mut response = Self::new(status);
+        response
+            .headers
+            .push(("Content-Type", "text/plain; charset=utf-8".to_string()));
+        response.body = format!("{}\n", body);
+        response
+    }
+
+    fn header(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+}
+
+async fn serve_http_connection(
+    stream: tokio::net::TcpStream,
+    peer: SocketAddr,
+    config: ServerConfig,
+    sessions: Sessions,
+    mut shutdown: Shutdown,
+) -> anyhow::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    loop {
+        // Shutdown stops the wait for the next request; a request already
+        // being handled is answered first.
+        let request = tokio::select! {
+            request = read_request(&mut reader) => request,
+            _ = shutdown.wait() => break,
+        };
+        let request = match request {
+            Ok(request) => request,
+            // The rest of the stream cannot be framed, so answer and close.
+            Err(ReadError::Bad(status, message)) => {
+                debug!("Unreadable request from {}: {}", peer, message);
+                write_response(&mut writer, HttpResponse::text(status, message), true).await?;
+                break;
+            }
+            Err(ReadError::Io(e)) => return Err(e.into()),
+        };
+        let Some(request) = request else { break };
+        debug!("{} {} from {}", request.method, request.path, peer);
+
+        let close = request
+            .header("connection")
+            .is_some_and(|v| v.eq_ignore_ascii_case("close"));
+        let mut response = route(&request, &config, &sessions).await;
+        if let Some(events) = response.events.take() {
+            write_head(&mut writer, &response, None).await?;
+            writer.flush().await?;
+            // The stream holds the connection until it ends.
+            return stream_events(&mut reader, &mut writer, events, &mut shutdown).await;
+        }
+        write_response(&mut writer, response, close).await?;
+        if close {
+            break;
+        }
+    }
+    Ok(())
+}
+
+async fn route(request: &HttpRequest, config: &ServerConfig, sessions: &Sessions) -> HttpResponse {
+    if request.path.split('?').next() != Some(HTTP_ENDPOINT) {
+        return HttpResponse::text(404, "Not found");
+    }
+    if let Some(origin) = request.header("origin")
+        && !is_local_origin(origin)
+    {
+        return HttpResponse::text(403, "Origin not allowed");
+    }
+
+    let principal = if config.require_token {
+        match auth::authenticate(&config.repo_path, request.header("authorization")) {
+            Ok(principal) => Some(principal),
+            Err(e) if e.code == error_codes::UNAUTHORIZED => {
+                return HttpResponse::text(401, &e.message).header("WWW-Authenticate", "Bearer");
+            }
+            Err(e) => return HttpResponse::text(500, &e.message),
+        }
+    } else {
+        None
+    };
+
+    match request.method.as_str() {
+        "POST" => post(request, config, sessions, principal).await,
+        "DELETE" => match session(request, sessions, principal.as_ref()).await {
+            Ok((id, _)) => {
+                sessions.lock().await.remove(&id);
+                debug!("HTTP session {} closed by client", id);
+                HttpResponse::new(200)
+            }
+            Err(response) => response,
+        },
+        "GET" => match session(request, sessions, principal.as_ref()).await {
+            Ok((id, server)) => {
+                let Some(notifications) = server.lock().await.take_notifications() else {
+                    return HttpResponse::text(409, "The session already has an event stream open");
+                };
+                let mut response = HttpResponse::new(200)
+                    .header("Content-Type", "text/event-stream")
+                    .header("Cache-Control", "no-cache")
+                    .header("Mcp-Session-Id", id);
+                response.events = Some(EventStream {
+                    notifications,
+                    server: Arc::downgrade(&server),
+                });
+                response
+            }
+            Err(response) => response,
+        },
+        _ => HttpResponse::text(405, "Method not allowed").header("Allow", "GET, POST, DELETE"),
+    }
+}
+
+/// The session named by the request's `Mcp-Session-Id`, which must have been
+/// opened with the same token.
+async fn session(
+    request: &HttpRequest,
+    sessions: &Sessions,
+    principal: Option<&Principal>,
+) -> Result<(String, Arc<Mutex<McpServer>>), HttpResponse> {
+    let Some(id) = request.header(SESSION_HEADER) else {
+        return Err(HttpResponse::text(400, "Missing Mcp-Session-Id header"));
+    };
+    let server = match sessions.lock().await.get_mut(id) {
+        Some(session) => {
+            session.last_used = Instant::now();
+            session.server.clone()
+        }
+        None => return Err(HttpResponse::text(404, "Unknown session")),
+    };
+    if let Some(principal) = principal {
+        let owner = server
+            .lock()
+            .await
+            .principal()
+            .and_then(|p| p.token_id.clone());
+        if owner != principal.token_id {
+            return Err(HttpResponse::text(
+                403,
+                "Session was opened with a different token",
+            ));
+        }
+    }
+    Ok((id.to_string(), server))
+}
+
+async fn post(
+    request: &HttpRequest,
+    config: &ServerConfig,
+    sessions: &Sessions,
+    principal: Option<Principal>,
+) -> HttpResponse {
+    let Ok(body) = std::str::from_utf8(&request.body) else {
+        return HttpResponse::text(400, "Body must be UTF-8 JSON");
+    };
+    let method = serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v.get("method").and_then(|m| m.as_str()).map(str::to_string));
+
+    let (session_id, server) = if method.as_deref() == Some("initialize") {
+        let id = uuid::Uuid::new_v4().to_string();
+        let mut server = McpServer::new(config.clone());
+        if let Some(principal) = principal {
+            server.set_principal(principal);
+        }
+        let server = Arc::new(Mutex::new(server));
+        sessions
+            .lock()
+            .await
+            .insert(id.clone(), Session::new(server.clone()));
+        debug!("HTTP session {} opened", id);
+        (id, server)
+    } else {
+        match session(request, sessions, principal.as_ref()).await {
+            Ok(found) => found,
+            Err(response) => return response,
+        }
+    };
+
+    let reply = server.lock().await.handle_message(body).await;
+    let Some(reply) = reply else {
+        return HttpResponse::new(202).header("Mcp-Session-Id", session_id);
+    };
+
+    let mut response = HttpResponse::new(200).header("Mcp-Session-Id", session_id);
+    if wants_event_stream(request.header("accept")) {
+        response
+            .headers
+            .push(("Content-Type", "text/event-stream".to_string()));
+        response.body = format!("event: message\ndata: {}\n\n", reply);
+    } else {
+        response
+            .headers
+            .push(("Content-Type", "application/json".to_string()));
+        response.body = reply;
+    }
+    response
+}
+
+/// Reply as SSE only when the client accepts it but not plain JSON.
+fn wants_event_stream(accept: Option<&str>) -> bool {
+    let Some(accept) = accept else { return false };
+    let types: Vec<&str> = accept
+        .split(',')
+        .map(|t| t.split(';').next().unwrap_or("").trim())
+        .collect();
+    types.contains(&"text/event-stream") && !types.contains(&"application/json")
+}
+
+/// Browsers send `Origin`; only pages served from this machine may call the
+/// server, which guards against DNS rebinding.
+fn is_local_origin(origin: &str) -> bool {
+    let host = origin
+        .split("://")
+        .nth(1)
+        .unwrap_or(origin)
+        .split('/')
+        .next()
+        .unwrap_or("");
+    let host = match host.rsplit_once(':') {
+        Some((h, port)) if port.chars().all(|c| c.is_ascii_digit()) => h,
+        _ => host,
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// Why a request could not be read.
+enum ReadError {
+    /// The connection failed; nothing can be sent back.
+    Io(std::io::Error),
+    /// The request is malformed or too large; answered with this status.
+    Bad(u16, &'static str),
+}
+
+impl From<std::io::Error> for ReadError {
+    fn from(e: std::io::Error) -> Self {
+        match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => ReadError::Bad(400, "Request ended early"),
+            std::io::ErrorKind::InvalidData => ReadError::Bad(400, "Request headers must be UTF-8"),
+            _ => ReadError::Io(e),
+        }
+    }
+}
+
+/// Read one HTTP/1.1 request. `None` on a clean EOF between requests.
+async fn read_request<R: AsyncRead + Unpin>(
+    reader: &mut BufReader<R>,
+) -> Result<Option<HttpRequest>, ReadError> {
+    let mut head = String::new();
+    loop {
+        let mut line = String::new();
+        // One more byte than allowed, so an overlong line is noticed without
+        // buffering all of it.
+        let limit = (MAX_HEADER_BYTES + 1 - head.len()) as u64;
+        let n = (&mut *reader).take(limit).read_line(&mut line).await?;
+        if n == 0 {
+            if head.is_empty() {
+                return Ok(None);
+            }
+            return Err(ReadError::Bad(400, "Request ended early"));
+        }
+        if line == "\r\n" || line == "\n" {
+            if head.is_empty() {
+                continue;
+            }
+            break;
+        }
+        head.push_str(&line);
+        if head.len() > MAX_HEADER_BYTES {
+            return Err(ReadError::Bad(413, "Request headers too large"));
+        }
+        if !line.ends_with('\n') {
+            return Err(ReadError::Bad(400, "Request ended early"));
+        }
+    }
+
+    let mut lines = head.lines();
+    let mut start = lines.next().unwrap_or("").split_whitespace();
+    let method = start.next().unwrap_or("").to_string();
+    let path = start.next().unwrap_or("").to_string();
+    let headers: HashMap<String, String> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim().to_ascii_lowercase(), v.trim().to_string()))
+        .collect();
+
+    if headers.contains_key("transfer-encoding") {
+        return Err(ReadError::Bad(
+            501,
+            "Chunked request bodies are not supported",
+        ));
+    }
+    let length: usize = match headers.get("content-length") {
+        Some(v) => v
+            .parse()
+            .map_err(|_| ReadError::Bad(400, "Invalid Content-Length"))?,
+        None => 0,
+    };
+    if length > MAX_BODY_BYTES {
+        return Err(ReadError::Bad(413, "Request body too large"));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).await?;
+
+    Ok(Some(HttpRequest {
+        method,
+        path,
+        headers,
+        body,
+    }))
+}
+
+/// Send the session's notifications as server-sent events until the client
+/// goes, the session closes or the server shuts down. The notifications are
+/// then handed back to the session for the next stream.
+async fn stream_events<R, W>(
+    reader: &mut BufReader<R>,
+    writer: &mut W,
+    mut events: EventStream,
+    shutdown: &mut Shutdown,
+) -> anyhow::Result<()>
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut keepalive =
+        tokio::time::interval_at(tokio::time::Instant::now() + KEEPALIVE, KEEPALIVE);
+    let result = loop {
+        let chunk = tokio::select! {
+            notification = events.notifications.recv() => match notification {
+                Some(message) => format!("event: message\ndata: {}\n\n", message),
+                None => break Ok(()),
+            },
+            _ = keepalive.tick() => ": keepalive\n\n".to_string(),
+            // The client sends nothing on a stream; EOF means it has gone.
+            read = reader.fill_buf() => break read.map(|_| ()).map_err(Into::into),
+            _ = shutdown.wait() => break Ok(()),
+        };
+        let written = async {
+            writer.write_all(chunk.as_bytes()).await?;
+            writer.flush().await
+        };
+        if let Err(e) = written.await {
+            break Err(e.into());
+        }
+    };
+    if let Some(server) = events.server.upgrade() {
+        server
+            .lock()
+            .await
+            .restore_notifications(events.notifications);
+    }
+    result
+}
+
+/// The status line and headers; with `length`, a `Content-Length`.
+async fn write_head<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    response: &HttpResponse,
+    length: Option<usize>,
+) -> anyhow::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        202 => "Accepted",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        409 => "Conflict",
+        413 => "Content Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        _ => "",
+    };
+    let mut head = format!("HTTP/1.1 {} {}\r\n", response.status, reason);
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if let Some(length) = length {
+        head.push_str(&format!("Content-Length: {}\r\n", length));
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes()).await?;
+    Ok(())
+}
+
+async fn write_response<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    mut response: HttpResponse,
+    close: bool,
+) -> anyhow::Result<()> {
+    if close {
+        response.headers.push(("Connection", "close".to_string()));
+    }
+    write_head(writer, &response, Some(response.body.len())).await?;
+    writer.write_all(response.body.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_local_origins() {
+        assert!(is_local_origin("http://localhost:3000"));
+        assert!(is_local_origin("http://127.0.0.1"));
+        assert!(is_local_origin("http://[::1]:8080"));
+        assert!(!is_local_origin("https://evil.example"));
+        assert!(!is_local_origin("http://localhost.evil.example"));
+    }
+
+    #[test]
+    fn test_event_stream_negotiation() {
+        assert!(!wants_event_stream(None));
+        assert!(!wants_event_stream(Some(
+            "application/json, text/event-stream"
+        )));
+        assert!(wants_event_stream(Some("text/event-stream")));
+    }
+
+    #[test]
+    fn test_idle_sessions_expire_unless_streaming() {
+        let session = || {
+            Session::new(Arc::new(Mutex::new(
+                McpServer::new(ServerConfig::default()),
+            )))
+        };
+        let mut sessions = HashMap::from([
+            ("idle".to_string(), session()),
+            ("streaming".to_string(), session()),
+        ]);
+        let streaming = sessions["streaming"].server.clone();
+        let notifications = streaming.try_lock().unwrap().take_notifications().unwrap();
+
+        let start = Instant::now();
+        expire_idle(
+            &mut sessions,
+            start + Duration::from_secs(59),
+            Duration::from_secs(60),
+        );
+        assert_eq!(sessions.len(), 2);
+        expire_idle(
+            &mut sessions,
+            start + Duration::from_secs(61),
+            Duration::from_secs(60),
+        );
+        assert!(sessions.contains_key("streaming") && !sessions.contains_key("idle"));
+
+        // Once its stream closes the session gets a full timeout from the
+        // last sweep that saw it busy.
+        streaming
+            .try_lock()
+            .unwrap()
+            .restore_notifications(notifications);
+        expire_idle(
+            &mut sessions,
+            start + Duration::from_secs(120),
+            Duration::from_secs(60),
+        );
+        assert!(sessions.contains_key("streaming"));
+        expire_idle(
+            &mut sessions,
+            start + Duration::from_secs(122),
+            Duration::from_secs(60),
+        );
+        assert!(sessions.is_empty());
+    }
+}
@@ -0,0 +1,262 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Watching a repository for changes to subscribed resources.
+//!
+//! One watcher per repository is shared by every session subscribed to it,
+//! and stops when the last subscription ends. It uses the platform's file
+//! notifications (inotify on Linux), and falls back to polling when those are
+//! unavailable, e.g. when the inotify watch limit is reached.
+//! `GITEHR_MCP_WATCH=poll` forces polling, which also sees changes made on
+//! other machines to a repository on a network filesystem.
+//!
+//! Changes are published as the `gitehr://repo/` URIs they affect: a new
+//! journal entry updates `gitehr://repo/journal` and
+//! `gitehr://repo/journal/<entry>`.
+
+use notify::{Config, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Component, Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+use super::resources::REPO_URI_PREFIX;
+use crate::commands::document::DOCUMENT_ROOTS;
+
+/// Directories whose resources can be subscribed to.
+pub const WATCHED_DIRS: [&str; 4] = ["journal", "state", DOCUMENT_ROOTS[0], DOCUMENT_ROOTS[1]];
+
+/// Changes are published once the repository has been quiet this long, so a
+/// commit touching several files sends one update per resource...
+const SETTLE: Duration = Duration::from_millis(150);
+/// ...or at least this often while changes keep coming.
+const MAX_DELAY: Duration = Duration::from_secs(1);
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+static WATCHES: Mutex<BTreeMap<PathBuf, Weak<RepoWatch>>> = Mutex::new(BTreeMap::new());
+
+/// A running watch on one repository.
+pub struct RepoWatch {
+    updates: broadcast::Sender<String>,
+    stop: Arc<AtomicBool>,
+}
+
+impl RepoWatch {
+    /// URIs of changed resources, from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<String> {
+        self.updates.subscribe()
+    }
+}
+
+impl Drop for RepoWatch {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The watch on `repo`, started if no session holds one.
+pub fn watch(repo: &Path) -> anyhow::Result<Arc<RepoWatch>> {
+    let root = repo.canonicalize()?;
+    let mut watches = WATCHES.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(watch) = watches.get(&root).and_then(Weak::upgrade) {
+        return Ok(watch);
+    }
+
+    let (events_tx, events) = mpsc::channel();
+    let watcher = start(&root, events_tx)?;
+    let (updates, _) = broadcast::channel(256);
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_root = root.clone();
+    let thread_updates = updates.clone();
+    let thread_stop = stop.clone();
+    std::thread::spawn(move || publish(thread_root, watcher, events, thread_updates, thread_stop));
+
+    let watch = Arc::new(RepoWatch { updates, stop });
+    watches.retain(|_, w| w.strong_count() > 0);
+    watches.insert(root, Arc::downgrade(&watch));
+    Ok(watch)
+}
+
+type Events = mpsc::Sender<notify::Result<notify::Event>>;
+
+fn start(root: &Path, events: Events) -> anyhow::Result<Box<dyn Watcher + Send>> {
+    if std::env::var("GITEHR_MCP_WATCH").as_deref() != Ok("poll") {
+        let native = RecommendedWatcher::new(events.clone(), Config::default())
+            .map_err(anyhow::Error::from)
+            .and_then(|mut w| add_watches(&mut w, root).map(|_| w));
+        match native {
+            Ok(watcher) => {
+                info!("Watching {} for resource changes", root.display());
+                return Ok(Box::new(watcher));
+            }
+            Err(e) => warn!(
+                "File notifications unavailable for {} ({}); polling every {:?}",
+                root.display(),
+                e,
+                POLL_INTERVAL
+            ),
+        }
+    }
+    let mut watcher =
+        PollWatcher::new(events, Config::default().with_poll_interval(POLL_INTERVAL))?;
+    add_watches(&mut watcher, root)?;
+    info!("Polling {} for resource changes", root.display());
+    Ok(Box::new(watcher))
+}
+
+/// The root itself, to see watched directories being created, and each
+/// watched directory that exists.
+fn add_watches(watcher: &mut dyn Watcher, root: &Path) -> anyhow::Result<()> {
+    watcher.watch(root, RecursiveMode::NonRecursive)?;
+    for dir in WATCHED_DIRS {
+        let path = root.join(dir);
+        if path.is_dir() {
+            watcher.watch(&path, RecursiveMode::Recursive)?;
+        }
+    }
+    Ok(())
+}
+
+/// Collect file events into resource URIs and publish them once things
+/// settle, until the last subscriber goes.
+fn publish(
+    root: PathBuf,
+    mut watcher: Box<dyn Watcher + Send>,
+    events: mpsc::Receiver<notify::Result<notify::Event>>,
+    updates: broadcast::Sender<String>,
+    stop: Arc<AtomicBool>,
+) {
+    let mut pending = BTreeSet::new();
+    let mut since: Option<Instant> = None;
+    while !stop.load(Ordering::Relaxed) {
+        match events.recv_timeout(SETTLE) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Access(_)) {
+                    continue;
+                }
+                for path in event.paths {
+                    let Ok(relative) = path.strip_prefix(&root) else {
+                        continue;
+                    };
+                    if relative.components().count() == 1
+                        && WATCHED_DIRS.iter().any(|d| relative == Path::new(d))
+                        && path.is_dir()
+                    {
+                        let _ = watcher.watch(&path, RecursiveMode::Recursive);
+                    }
+                    pending.extend(resource_uris(relative));
+                }
+                if !pending.is_empty() && since.is_none_or(|t| t.elapsed() < MAX_DELAY) {
+                    since.get_or_insert_with(Instant::now);
+                    continue;
+                }
+            }
+            Ok(Err(e)) => debug!("Watch error on {}: {}", root.display(), e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+        for uri in std::mem::take(&mut pending) {
+            // No receivers just means no session is listening right now.
+            let _ = updates.send(uri);
+        }
+        since = None;
+    }
+    debug!("Stopped watching {}", root.display());
+}
+
+/// The resources a change to `relative` (a path within the repository)
+/// updates: the listing and each level down to the file. Hidden files, such
+/// as editor swap files, are ignored.
+pub fn resource_uris(relative: &Path) -> Vec<String> {
+    let mut names = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(name) => match name.to_str() {
+                Some(name) if !name.starts_with('.') => names.push(name),
+                _ => return Vec::new(),
+            },
+            _ => return Vec::new(),
+        }
+    }
+    if !names.first().is_some_and(|d| WATCHED_DIRS.contains(d)) {
+        return Vec::new();
+    }
+    (1..=names.len())
+        .map(|n| format!("{}{}", REPO_URI_PREFIX, names[..n].join("/")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resource_uris() {
+        assert_eq!(
+            resource_uris(Path::new("journal/20260614T101500.000Z-abc.md")),
+            [
+                "gitehr://repo/journal",
+                "gitehr://repo/journal/20260614T101500.000Z-abc.md"
+            ]
+        );
+        assert_eq!(
+            resource_uris(Path::new("documents/scan/img1.dcm")),
+            [
+                "gitehr://repo/documents",
+                "gitehr://repo/documents/scan",
+                "gitehr://repo/documents/scan/img1.dcm"
+            ]
+        );
+        assert_eq!(resource_uris(Path::new("state")), ["gitehr://repo/state"]);
+        for ignored in [
+            "state/.allergies.json.swp",
+            ".gitehr/mcp-audit.jsonl",
+            ".git/index",
+            "README.md",
+            "",
+        ] {
+            assert!(resource_uris(Path::new(ignored)).is_empty(), "{}", ignored);
+        }
+    }
+
+    #[test]
+    fn test_polling_publishes_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("state")).unwrap();
+        let (events_tx, events) = mpsc::channel();
+        let mut watcher = PollWatcher::new(
+            events_tx,
+            Config::default().with_poll_interval(Duration::from_millis(50)),
+        )
+        .unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        add_watches(&mut watcher, &root).unwrap();
+        let (updates, mut rx) = broadcast::channel(16);
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = std::thread::spawn(move || {
+            publish(root, Box::new(watcher), events, updates, thread_stop)
+        });
+
+        std::thread::sleep(Duration::from_millis(200));
+        std::fs::write(dir.path().join("state/plan.md"), "Review").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut seen = BTreeSet::new();
+        while seen.len() < 2 && Instant::now() < deadline {
+            match rx.try_recv() {
+                Ok(uri) => {
+                    seen.insert(uri);
+                }
+                Err(_) => std::thread::sleep(Duration::from_millis(20)),
+            }
+        }
+        stop.store(true, Ordering::Relaxed);
+        thread.join().unwrap();
+        assert!(seen.contains("gitehr://repo/state"), "{:?}", seen);
+        assert!(seen.contains("gitehr://repo/state/plan.md"), "{:?}", seen);
+    }
+}
@@ -0,0 +1,297 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Bearer tokens for the networked MCP transports.
+//!
+//! `.gitehr/mcp-tokens.json` stores only the SHA-256 of each token, mapped to
+//! the contributor its writes are attributed to and the scopes it grants. The
+//! token itself is shown once, at creation. The file is gitignored, so tokens
+//! belong to one checkout and are never committed. A Store-level server keeps its
+//! tokens in `gitehr-mcp-tokens.json` at the Store root.
+
+use anyhow::Result;
+use chrono::Utc;
+use clap::{Subcommand, ValueEnum};
+use rand::RngExt;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::commands::{contributor, typed_state};
+use crate::utils::sha256_hex;
+
+const TOKEN_PREFIX: &str = "gehr_";
+
+#[derive(Subcommand)]
+pub enum TokenCommands {
+    #[command(about = "Create a token; it is printed once and never stored")]
+    Create {
+        #[arg(help = "Contributor id that the token's writes are attributed to")]
+        contributor: String,
+        #[arg(
+            long = "scope",
+            value_enum,
+            required = true,
+            help = "Scope to grant; repeatable"
+        )]
+        scopes: Vec<Scope>,
+        #[arg(long, help = "Free-text label, e.g. the agent or machine using it")]
+        label: Option<String>,
+    },
+    #[command(about = "Revoke a token by id")]
+    Revoke {
+        #[arg(help = "Token id (tok-...)")]
+        id: String,
+    },
+    #[command(about = "List tokens (ids, contributors and scopes; never the tokens)")]
+    List {
+        #[arg(long, help = "Include revoked tokens")]
+        all: bool,
+    },
+}
+
+/// What a token may do.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    /// List and read resources and prompts, list tools, and call read-only
+    /// tools.
+    Read,
+    /// Add journal entries.
+    JournalWrite,
+    /// Change typed or raw state.
+    StateWrite,
+}
+
+impl std::fmt::Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Scope::Read => "read",
+            Scope::JournalWrite => "journal-write",
+            Scope::StateWrite => "state-write",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TokenEntry {
+    pub id: String,
+    pub sha256: String,
+    pub contributor: String,
+    pub scopes: Vec<Scope>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub created_at: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revoked_at: Option<String>,
+}
+
+impl TokenEntry {
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none()
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TokenStore {
+    #[serde(default)]
+    pub tokens: Vec<TokenEntry>,
+}
+
+/// Run a token command against the Store at `store_root`, or else the
+/// repository, or Store root, in the current directory.
+pub fn run(command: TokenCommands, store_root: Option<PathBuf>) -> Result<()> {
+    let repo = store_root.unwrap_or_else(|| PathBuf::from("."));
+    let repo = repo.as_path();
+    if !super::is_store_root(repo) {
+        typed_state::ensure_gitehr_repository()?;
+    }
+    match command {
+        TokenCommands::Create {
+            contributor,
+            scopes,
+            label,
+        } => {
+            let (entry, secret) = create(repo, &contributor, scopes, label)?;
+            println!(
+                "Created token {} for {} ({})",
+                entry.id,
+                entry.contributor,
+                scope_list(&entry.scopes)
+            );
+            println!("This token is shown once; store it securely:");
+            println!("{}", secret);
+            Ok(())
+        }
+        TokenCommands::Revoke { id } => {
+            let entry = revoke(repo, &id)?;
+            println!("Revoked token {} ({})", entry.id, entry.contributor);
+            Ok(())
+        }
+        TokenCommands::List { all } => {
+            let store = load(repo)?;
+            let tokens: Vec<_> = store
+                .tokens
+                .iter()
+                .filter(|t| all || t.is_active())
+                .collect();
+            if tokens.is_empty() {
+                println!("No MCP tokens.");
+            }
+            for token in tokens {
+                let mut line = format!(
+                    "{}  {}  {}  created {}",
+                    token.id,
+                    token.contributor,
+                    scope_list(&token.scopes),
+                    token.created_at
+                );
+                if let Some(revoked) = &token.revoked_at {
+                    line.push_str(&format!("  revoked {}", revoked));
+                }
+                if let Some(label) = &token.label {
+                    line.push_str(&format!("  {}", label));
+                }
+                println!("{}", line);
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The token file for a repository, or for a Store root.
+pub fn store_path(root: &Path) -> PathBuf {
+    if super::is_store_root(root) {
+        root.join("gitehr-mcp-tokens.json")
+    } else {
+        root.join(".gitehr/mcp-tokens.json")
+    }
+}
+
+pub fn load(repo: &Path) -> Result<TokenStore> {
+    let path = store_path(repo);
+    if !path.exists() {
+        return Ok(TokenStore::default());
+    }
+    let content = fs::read_to_string(&path)?;
+    serde_json::from_str(&content).map_err(|e| anyhow::anyhow!("Invalid {}: {}", path.display(), e))
+}
+
+fn save(repo: &Path, store: &TokenStore) -> Result<()> {
+    if !super::is_store_root(repo) {
+        ignore_token_file(repo)?;
+    }
+    fs::write(store_path(repo), serde_json::to_string_pretty(store)?)?;
+    Ok(())
+}
+
+/// Keep a repository's token file out of Git: the hashes are credentials for
+/// this checkout, and an untracked file would leave the tree dirty. The
+/// `.gitignore` ignores itself, as the cache's and drafts' do.
+fn ignore_token_file(repo: &Path) -> Result<()> {
+    let ignore = repo.join(".gitehr/.gitignore");
+    let content = fs::read_to_string(&ignore).unwrap_or_default();
+    if content.lines().any(|l| l.trim() == "/mcp-tokens.json") {
+        return Ok(());
+    }
+    let mut content = if content.is_empty() {
+        "# Local MCP credentials; never committed.\n/.gitignore\n".to_string()
+    } else if content.ends_with('\n') {
+        content
+    } else {
+        content + "\n"
+    };
+    content.push_str("/mcp-tokens.json\n");
+    fs::write(&ignore, content)?;
+    Ok(())
+}
+
+/// Mint a token for an enabled contributor. Returns the stored entry and the
+/// token itself, which is not recoverable afterwards. Store tokens are not
+/// checked here: contributors belong to subjects, and each subject checks the
+/// contributor when a write is made.
+pub fn create(
+    repo: &Path,
+    contributor_id: &str,
+    mut scopes: Vec<Scope>,
+    label: Option<String>,
+) -> Result<(TokenEntry, String)> {
+    if !super::is_store_root(repo) {
+        match contributor::get_contributor(contributor_id) {
+            Some(c) if c.enabled => {}
+            Some(_) => anyhow::bail!("Contributor '{}' is disabled", contributor_id),
+            None => anyhow::bail!("Unknown contributor '{}'", contributor_id),
+        }
+    }
+    scopes.sort();
+    scopes.dedup();
+
+    let mut rng = rand::rng();
+    let secret_bytes: [u8; 32] = rng.random();
+    let secret = format!("{}{}", TOKEN_PREFIX, hex(&secret_bytes));
+    let id_bytes: [u8; 4] = rng.random();
+
+    let entry = TokenEntry {
+        id: format!("tok-{}", hex(&id_bytes)),
+        sha256: sha256_hex(secret.as_bytes()),
+        contributor: contributor_id.to_string(),
+        scopes,
+        label: label.filter(|l| !l.trim().is_empty()),
+        created_at: Utc::now().to_rfc3339(),
+        revoked_at: None,
+    };
+
+    let mut store = load(repo)?;
+    store.tokens.push(entry.clone());
+    save(repo, &store)?;
+    Ok((entry, secret))
+}
+
+pub fn revoke(repo: &Path, id: &str) -> Result<TokenEntry> {
+    let mut store = load(repo)?;
+    let entry = store
+        .tokens
+        .iter_mut()
+        .find(|t| t.id == id)
+        .ok_or_else(|| anyhow::anyhow!("Token not found: {}", id))?;
+    if entry.revoked_at.is_none() {
+        entry.revoked_at = Some(Utc::now().to_rfc3339());
+    }
+    let revoked = entry.clone();
+    save(repo, &store)?;
+    Ok(revoked)
+}
+
+/// The active entry for a presented token, if any.
+pub fn authenticate(repo: &Path, secret: &str) -> Result<Option<TokenEntry>> {
+    let secret = secret.trim();
+    if !secret.starts_with(TOKEN_PREFIX) {
+        return Ok(None);
+    }
+    let digest = sha256_hex(secret.as_bytes());
+    Ok(load(repo)?
+        .tokens
+        .into_iter()
+        .find(|t| t.sha256 == digest && t.is_active()))
+}
+
+/// Whether the token with `id` exists and has not been revoked.
+pub fn is_active(repo: &Path, id: &str) -> bool {
+    load(repo)
+        .map(|store| store.tokens.iter().any(|t| t.id == id && t.is_active()))
+        .unwrap_or(false)
+}
+
+fn scope_list(scopes: &[Scope]) -> String {
+    scopes
+        .iter()
+        .map(Scope::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
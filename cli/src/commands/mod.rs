@@ -10,9 +10,11 @@ pub mod decrypt;
 pub mod demographics;
 pub mod document;
 pub mod encrypt;
+pub mod export;
 mod git;
 pub mod gui;
 pub mod import;
+pub mod index;
 pub mod journal;
 pub mod mcp;
 pub mod plugin;
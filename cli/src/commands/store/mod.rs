@@ -74,7 +74,7 @@ pub struct MpiInfo {
     pub patients: Vec<MpiPatient>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MpiPatient {
     /// Canonical, stable id (a UUIDv7 in Crockford base32). Never changes.
     pub patient_id: String,
@@ -86,7 +86,7 @@ pub struct MpiPatient {
     pub identifiers: Vec<MpiIdentifier>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MpiIdentifier {
     #[serde(rename = "type")]
     pub id_type: String,
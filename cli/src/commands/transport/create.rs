@@ -4,9 +4,11 @@
 use anyhow::Result;
 use std::fs::File;
 use std::io::Read;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use walkdir::WalkDir;
 
+use crate::commands::journal;
+
 pub fn run(output_path: Option<&str>, encrypt: bool) -> Result<()> {
     if !PathBuf::from(".gitehr").exists() {
         anyhow::bail!("Not a GitEHR repository (or not in the repository root).");
@@ -29,7 +31,12 @@ pub fn run(output_path: Option<&str>, encrypt: bool) -> Result<()> {
     for dir in &dirs_to_include {
         let path = PathBuf::from(dir);
         if path.exists() {
-            for entry in WalkDir::new(&path).into_iter().filter_map(|e| e.ok()) {
+            let entries = WalkDir::new(&path)
+                .into_iter()
+                // The journal index is a disposable cache, rebuilt on arrival.
+                .filter_entry(|e| e.path() != Path::new(journal::index::CACHE_DIR))
+                .filter_map(|e| e.ok());
+            for entry in entries {
                 let entry_path = entry.path();
                 if entry_path.is_file() {
                     let mut file = File::open(entry_path)?;
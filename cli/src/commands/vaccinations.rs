@@ -2,7 +2,7 @@
 // SPDX-License-Identifier: AGPL-3.0-or-later
 
 use anyhow::{Context, Result};
-use chrono::{NaiveDate, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 use clap::Subcommand;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -96,6 +96,15 @@ pub struct Vaccination {
     pub entered_in_error_reason: Option<String>,
     pub note: Option<String>,
     pub fhir_r4: Option<JsonValue>,
+    /// Stable key of the external record this vaccination was imported from
+    /// (e.g. `fhir:Immunization/123`), so re-importing updates rather than
+    /// duplicates.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_key: Option<String>,
+    /// Filename of the journal entry this vaccination was promoted from
+    /// (`gitehr journal promote`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_entry: Option<String>,
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -118,6 +127,8 @@ pub struct VaccinationInput {
     pub performer: Option<String>,
     pub fhir_json: Option<PathBuf>,
     pub note: Option<String>,
+    /// The journal entry this vaccination is promoted from, if any.
+    pub source_entry: Option<String>,
 }
 
 pub fn run(command: VaccinationCommands) -> Result<()> {
@@ -159,6 +170,7 @@ pub fn run(command: VaccinationCommands) -> Result<()> {
                 performer,
                 fhir_json,
                 note,
+                source_entry: None,
             })?;
             Ok(())
         }
@@ -185,6 +197,15 @@ pub fn list(include_entered_in_error: bool) -> Result<Vec<Vaccination>> {
 }
 
 pub fn add(input: VaccinationInput) -> Result<Vaccination> {
+    let (vaccination, entry) = add_as(input, contributor::get_current_contributor())?;
+    println!("Created journal entry: {}", entry);
+    println!("Recorded vaccination: {}", vaccination.id);
+    Ok(vaccination)
+}
+
+/// Record a vaccination attributed to `author`, returning it with the journal
+/// entry that records the change. Prints nothing.
+pub fn add_as(input: VaccinationInput, author: Option<String>) -> Result<(Vaccination, String)> {
     typed_state::ensure_gitehr_repository()?;
     let vaccine = require_text(&input.vaccine, "--vaccine")?;
     NaiveDate::parse_from_str(&input.date, "%Y-%m-%d")
@@ -201,15 +222,7 @@ pub fn add(input: VaccinationInput) -> Result<Vaccination> {
 
     let now = Utc::now();
     let vaccination = Vaccination {
-        id: format!(
-            "VAC-{}-{}",
-            now.format("%Y%m%dT%H%M%SZ"),
-            Uuid::new_v4()
-                .to_string()
-                .chars()
-                .take(8)
-                .collect::<String>()
-        ),
+        id: new_id(now),
         status: VaccinationStatus::Completed,
         vaccine: vaccine.to_string(),
         date: input.date,
@@ -222,27 +235,45 @@ pub fn add(input: VaccinationInput) -> Result<Vaccination> {
         batch_number: input.batch_number.as_deref().and_then(cleaned_str),
         performer: input.performer.as_deref().and_then(cleaned_str),
         recorded_at: now.to_rfc3339(),
-        recorded_by: contributor::get_current_contributor(),
+        recorded_by: author.clone(),
         entered_in_error_at: None,
         entered_in_error_reason: None,
         note: input.note.as_deref().and_then(cleaned_str),
         fhir_r4,
+        source_key: None,
+        source_entry: input.source_entry.clone(),
     };
 
     let mut state = load()?;
     state.vaccinations.push(vaccination.clone());
-    persist_with_journal(
-        &state,
-        input.note.as_deref().unwrap_or(&format!(
+    let mut body = input.note.clone().unwrap_or_else(|| {
+        format!(
             "Recorded vaccination: {} on {}",
             vaccination.vaccine, vaccination.date
-        )),
-    )?;
-    println!("Recorded vaccination: {}", vaccination.id);
-    Ok(vaccination)
+        )
+    });
+    if let Some(source) = &input.source_entry {
+        body.push_str(&format!("\n\nPromoted from journal entry {}.", source));
+    }
+    let entry = persist_with_journal(&state, &body, author)?;
+    Ok((vaccination, entry))
 }
 
 pub fn entered_in_error(id: &str, reason: Option<&str>) -> Result<Vaccination> {
+    let (vaccination, entry) =
+        entered_in_error_as(id, reason, contributor::get_current_contributor())?;
+    println!("Created journal entry: {}", entry);
+    println!("Marked vaccination entered in error: {}", vaccination.id);
+    Ok(vaccination)
+}
+
+/// Mark a vaccination entered in error, journalled as `author`. Prints
+/// nothing.
+pub fn entered_in_error_as(
+    id: &str,
+    reason: Option<&str>,
+    author: Option<String>,
+) -> Result<(Vaccination, String)> {
     typed_state::ensure_gitehr_repository()?;
     let mut state = load()?;
     let vaccination = state
@@ -256,24 +287,47 @@ pub fn entered_in_error(id: &str, reason: Option<&str>) -> Result<Vaccination> {
     vaccination.entered_in_error_reason = reason.and_then(cleaned_str);
     let changed = vaccination.clone();
 
-    persist_with_journal(
+    let entry = persist_with_journal(
         &state,
         reason.unwrap_or(&format!(
             "Marked vaccination entered in error: {}",
             changed.vaccine
         )),
+        author,
     )?;
-    println!("Marked vaccination entered in error: {}", changed.id);
-    Ok(changed)
+    Ok((changed, entry))
 }
 
-fn persist_with_journal(state: &VaccinationsState, journal_body: &str) -> Result<()> {
+/// Mint a new vaccination id (`VAC-<timestamp>-<random>`).
+pub fn new_id(now: DateTime<Utc>) -> String {
+    format!(
+        "VAC-{}-{}",
+        now.format("%Y%m%dT%H%M%SZ"),
+        Uuid::new_v4()
+            .to_string()
+            .chars()
+            .take(8)
+            .collect::<String>()
+    )
+}
+
+/// Write and stage the vaccinations state file without committing, for
+/// callers that batch several state changes under one journal entry.
+pub fn save(state: &VaccinationsState) -> Result<()> {
     let path = typed_state::write_front_matter(STATE_FILE, state)?;
     git::git_add(&path.to_string_lossy())?;
-    journal::create_journal_entry(journal_body)?;
     Ok(())
 }
 
+fn persist_with_journal(
+    state: &VaccinationsState,
+    journal_body: &str,
+    author: Option<String>,
+) -> Result<String> {
+    save(state)?;
+    journal::create_journal_entry_as(journal_body, Vec::new(), author)
+}
+
 fn read_fhir_json(path: &PathBuf) -> Result<JsonValue> {
     let content = std::fs::read_to_string(path)
         .with_context(|| format!("Failed to read FHIR JSON {}", path.display()))?;
@@ -40,6 +40,16 @@ pub fn config_path() -> Result<PathBuf> {
         .join(CONFIG_FILE))
 }
 
+/// The directory holding the config file, where user-level files such as
+/// journal templates live too.
+pub fn config_dir() -> Result<PathBuf> {
+    let path = config_path()?;
+    Ok(path
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| PathBuf::from(".")))
+}
+
 pub fn load() -> Result<AppConfig> {
     load_from_path(&config_path()?)
 }
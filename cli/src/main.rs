@@ -7,12 +7,15 @@ use std::path::{Path, PathBuf};
 
 mod commands;
 mod config;
+mod medmd;
 mod utils;
 
 use commands::allergies::AllergyCommands;
 use commands::config::ConfigCommands;
 use commands::demographics::DemographicsCommands;
 use commands::document::DocumentCommands;
+use commands::export::ExportCommands;
+use commands::index::IndexCommands;
 use commands::journal::JournalCommands;
 use commands::mcp::McpCommands;
 use commands::remote::RemoteCommands;
@@ -92,10 +95,18 @@ Restart your shell after installing completions."#
         #[arg(long, help = "Key source (local or remote URL)")]
         key: Option<String>,
     },
+    #[command(
+        about = "Export the record to FHIR, HTML, PDF or openEHR",
+        arg_required_else_help = true
+    )]
+    Export {
+        #[command(subcommand)]
+        command: ExportCommands,
+    },
     #[command(about = "Launch the GitEHR graphical interface")]
     Gui,
     #[command(
-        about = "Import journal entries or documents from a file or directory",
+        about = "Import journal entries, documents, or a FHIR R4 Bundle",
         arg_required_else_help = true
     )]
     Import {
@@ -103,6 +114,19 @@ Restart your shell after installing completions."#
         mode: commands::import::ImportMode,
         #[arg(help = "File or directory to import")]
         path: std::path::PathBuf,
+        #[arg(
+            long,
+            help = "Report what would be imported without writing (fhir mode)"
+        )]
+        dry_run: bool,
+    },
+    #[command(
+        about = "Inspect or rebuild the journal index cache",
+        arg_required_else_help = true
+    )]
+    Index {
+        #[command(subcommand)]
+        command: IndexCommands,
     },
     #[command(
         about = "Manage append-only journal entries",
@@ -232,8 +256,14 @@ fn main() -> Result<()> {
         Commands::Demographics { command } => commands::demographics::run(command)?,
         Commands::Document { command } => commands::document::run(command)?,
         Commands::Encrypt { key } => commands::encrypt::run(key.as_deref())?,
+        Commands::Export { command } => commands::export::run(command)?,
         Commands::Gui => commands::gui::run()?,
-        Commands::Import { mode, path } => commands::import::run(mode, &path)?,
+        Commands::Import {
+            mode,
+            path,
+            dry_run,
+        } => commands::import::run(mode, &path, dry_run)?,
+        Commands::Index { command } => commands::index::run(command)?,
         Commands::Journal { command } => commands::journal::run(command)?,
         Commands::Mcp { command } => commands::mcp::run(command)?,
         Commands::Plugins => commands::plugin::list(&builtins)?,
@@ -260,7 +290,9 @@ fn bare_command_help_target(command: &str) -> Option<&'static str> {
         "completions" => Some("completions"),
         "config" => Some("config"),
         "demographics" => Some("demographics"),
+        "export" => Some("export"),
         "import" => Some("import"),
+        "index" => Some("index"),
         "journal" => Some("journal"),
         "mcp" => Some("mcp"),
         "store" => Some("store"),
@@ -285,6 +317,19 @@ fn absolutize_external_paths(command: &mut Commands, base: &Path) {
     }
     match command {
         Commands::Import { path, .. } => fix_pb(path, base),
+        Commands::Export { command } => match command {
+            ExportCommands::Ips { output, html } => {
+                for path in [output, html].into_iter().flatten() {
+                    fix_pb(path, base);
+                }
+            }
+            ExportCommands::FhirJournal { output: Some(path) } => fix_pb(path, base),
+            ExportCommands::FhirJournal { output: None } => {}
+            ExportCommands::Html { dir, .. } => fix_pb(dir, base),
+            ExportCommands::Pdf { output, .. } => fix_pb(output, base),
+            ExportCommands::Openehr { output: Some(path) } => fix_pb(path, base),
+            ExportCommands::Openehr { output: None } => {}
+        },
         Commands::Journal {
             command: JournalCommands::Add { file: Some(f), .. },
         } if f != "-" => fix_str(f, base),
@@ -337,7 +382,13 @@ fn apply_context(command: &mut Commands) -> Result<()> {
             command: StoreCommands::Init { .. },
         } => Ctx::None,
         Commands::Config { .. } => Ctx::None,
-        Commands::Store { .. } => Ctx::Store,
+        Commands::Store { .. }
+        | Commands::Mcp {
+            command: McpCommands::Token { store: true, .. },
+        } => Ctx::Store,
+        Commands::Mcp {
+            command: McpCommands::Audit(args),
+        } if args.store => Ctx::Store,
         Commands::Journal { .. }
         | Commands::State { .. }
         | Commands::Demographics { .. }
@@ -350,7 +401,12 @@ fn apply_context(command: &mut Commands) -> Result<()> {
         | Commands::Transport { .. }
         | Commands::Document { .. }
         | Commands::Import { .. }
-        | Commands::User { .. } => Ctx::Repo,
+        | Commands::Index { .. }
+        | Commands::Export { .. }
+        | Commands::User { .. }
+        | Commands::Mcp {
+            command: McpCommands::Token { .. } | McpCommands::Audit(_),
+        } => Ctx::Repo,
         _ => Ctx::None,
     };
     let target = match ctx {
@@ -0,0 +1,614 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+//! Medical Markdown: structured data derived from `CODE/` lines in a journal
+//! body (spec/medical-markdown.md, ADR-0004).
+//!
+//! All Medical Markdown access goes through this module, over
+//! `ParsedEntry.content`. The body stays canonical; everything here is a pure
+//! function of it, so historic entries and entries without codes stay valid
+//! and extract to an empty object.
+//!
+//! The `medical-markdown` crate is not yet published, so the parsing lives
+//! here for now, following its documented syntax and extraction schema:
+//!
+//! ```text
+//! PC/ chest pain
+//! OE/ alert
+//!   RS/ clear
+//!   CVS/ no murmurs
+//! ```
+//!
+//! extracts to
+//!
+//! ```json
+//! {
+//!   "OE": { "CVS": "no murmurs", "RS": "clear", "notes": "alert" },
+//!   "PC": { "notes": "chest pain" },
+//!   "_source_map": { "OE": 2, "OE.CVS": 4, "OE.RS": 3, "PC": 1 }
+//! }
+//! ```
+//!
+//! A code is an upper-case word (a letter, then up to seven letters or
+//! digits) followed by `/` and a space or the end of the line. Indented codes
+//! inside a section are its sub-codes. Lines that follow a code without a
+//! blank line continue its notes, as do indented lines after a blank line; a
+//! blank line then unindented text returns to the narrative. Fenced code
+//! blocks are never parsed for codes. `_source_map` gives the body line (from
+//! 1) where each code first appears.
+//!
+//! Extraction accepts any well-formed code. Which codes a repository
+//! *recognises* - for headings and for `validate` - is the [`Registry`]: the
+//! built-in vocabulary, extended by `.gitehr/medmd-registry.json`.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::commands::export::html_escape;
+
+/// Extra codes for this repository, merged over the built-in vocabulary.
+pub const REGISTRY_FILE: &str = ".gitehr/medmd-registry.json";
+
+/// A clinical code in the built-in vocabulary.
+pub struct Code {
+    pub code: &'static str,
+    pub heading: &'static str,
+}
+
+const fn code(code: &'static str, heading: &'static str) -> Code {
+    Code { code, heading }
+}
+
+/// The built-in codes.
+pub const BUILTIN_CODES: &[Code] = &[
+    code("PC", "Presenting complaint"),
+    code("HPC", "History of presenting complaint"),
+    code("PMH", "Past medical history"),
+    code("PSH", "Past surgical history"),
+    code("DH", "Drug history"),
+    code("ALL", "Allergies"),
+    code("FH", "Family history"),
+    code("SH", "Social history"),
+    code("SE", "Systems enquiry"),
+    code("ICE", "Ideas, concerns and expectations"),
+    code("OE", "On examination"),
+    code("OBS", "Observations"),
+    code("GEN", "General"),
+    code("CVS", "Cardiovascular"),
+    code("RS", "Respiratory"),
+    code("ABDO", "Abdomen"),
+    code("GI", "Gastrointestinal"),
+    code("GU", "Genitourinary"),
+    code("NEURO", "Neurological"),
+    code("MSK", "Musculoskeletal"),
+    code("ENT", "Ear, nose and throat"),
+    code("EYES", "Eyes"),
+    code("SKIN", "Skin"),
+    code("MSE", "Mental state examination"),
+    code("IMP", "Impression"),
+    code("DDX", "Differential diagnosis"),
+    code("INV", "Investigations"),
+    code("RES", "Results"),
+    code("PLAN", "Plan"),
+    code("RX", "Treatment"),
+    code("REF", "Referral"),
+    code("FU", "Follow-up"),
+    code("SN", "Safety netting"),
+    code("NB", "Note"),
+];
+
+/// Codes GitEHR adds for facts that `journal promote` can write into typed
+/// state (`ALL/` is built in).
+pub const GITEHR_CODES: &[Code] = &[
+    code("ALLERGY", "Allergy"),
+    code("VACC", "Vaccination"),
+    code("IMM", "Immunisation"),
+];
+
+/// The codes a repository recognises, with their headings.
+pub struct Registry {
+    headings: BTreeMap<String, String>,
+}
+
+/// One code in a registry file. Other keys (such as `category`) are allowed
+/// and ignored.
+#[derive(Deserialize)]
+struct RegistryCode {
+    code: String,
+    heading: String,
+}
+
+impl Default for Registry {
+    /// The built-in codes and GitEHR's own.
+    fn default() -> Self {
+        Registry {
+            headings: BUILTIN_CODES
+                .iter()
+                .chain(GITEHR_CODES)
+                .map(|c| (c.code.to_string(), c.heading.to_string()))
+                .collect(),
+        }
+    }
+}
+
+impl Registry {
+    /// The built-in codes, with the repository's registry file merged over
+    /// them if there is one.
+    pub fn load() -> Result<Self> {
+        if !Path::new(REGISTRY_FILE).exists() {
+            return Ok(Registry::default());
+        }
+        let json = fs::read_to_string(REGISTRY_FILE)
+            .with_context(|| format!("Failed to read {}", REGISTRY_FILE))?;
+        Registry::from_json(&json).with_context(|| format!("Invalid {}", REGISTRY_FILE))
+    }
+
+    /// The built-in codes with `json`, an array of `{"code", "heading"}`
+    /// objects, merged over them. A code already built in takes the new
+    /// heading.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let codes: Vec<RegistryCode> = serde_json::from_str(json)?;
+        let mut registry = Registry::default();
+        for RegistryCode { code, heading } in codes {
+            if !is_code(&code) {
+                anyhow::bail!(
+                    "'{}' is not a code: use an upper-case letter then up to seven upper-case letters or digits",
+                    code
+                );
+            }
+            if heading.trim().is_empty() {
+                anyhow::bail!("{} has no heading", code);
+            }
+            registry.headings.insert(code, heading);
+        }
+        Ok(registry)
+    }
+
+    pub fn contains(&self, code: &str) -> bool {
+        self.headings.contains_key(code)
+    }
+
+    /// The heading for `code`, or the code itself if it is not recognised.
+    pub fn heading<'a>(&'a self, code: &'a str) -> &'a str {
+        self.headings.get(code).map_or(code, String::as_str)
+    }
+}
+
+/// Structured data from a journal body: an object keyed by code, plus
+/// `_source_map`. Empty for a body without codes.
+pub fn extract(body: &str) -> Value {
+    let mut data = Map::new();
+    let mut source_map = Map::new();
+    for block in parse(body) {
+        let Block::Section(section) = block else {
+            continue;
+        };
+        source_map
+            .entry(section.code.clone())
+            .or_insert(Value::from(section.line));
+        let Value::Object(object) = data
+            .entry(section.code.clone())
+            .or_insert_with(|| Value::Object(Map::new()))
+        else {
+            unreachable!("sections are objects");
+        };
+        if !section.notes.is_empty() {
+            append(object, "notes", &section.notes);
+        }
+        for sub in &section.subs {
+            source_map
+                .entry(format!("{}.{}", section.code, sub.code))
+                .or_insert(Value::from(sub.line));
+            append(object, &sub.code, &sub.notes);
+        }
+    }
+    if !data.is_empty() {
+        data.insert("_source_map".to_string(), Value::Object(source_map));
+    }
+    Value::Object(data)
+}
+
+/// A top-level coded section of a body, in body order. Repeated codes are
+/// separate sections.
+#[derive(Debug, PartialEq)]
+pub struct Coded {
+    pub code: String,
+    /// Body line, from 1.
+    pub line: usize,
+    pub notes: String,
+}
+
+pub fn sections(body: &str) -> Vec<Coded> {
+    parse(body)
+        .into_iter()
+        .filter_map(|block| match block {
+            Block::Section(section) => Some(Coded {
+                code: section.code,
+                line: section.line,
+                notes: section.notes,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether the body has any `CODE/` lines.
+pub fn has_codes(body: &str) -> bool {
+    parse(body).iter().any(|b| matches!(b, Block::Section(_)))
+}
+
+/// A problem with the codes in a journal body.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Body line, from 1.
+    pub line: usize,
+    pub message: String,
+}
+
+/// Unknown and malformed codes in a journal body. Advisory: every body is
+/// still a valid entry, and unknown codes extract like any other.
+pub fn validate(body: &str, registry: &Registry) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut in_fence = false;
+    for (i, line) in body.lines().enumerate() {
+        if is_fence(line) {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        let message = match code_line(line) {
+            Some((code, _)) if registry.contains(code) => continue,
+            Some((code, _)) => format!("unknown code {}/", code),
+            None => match malformed(line.trim(), registry) {
+                Some(message) => message,
+                None => continue,
+            },
+        };
+        diagnostics.push(Diagnostic {
+            line: i + 1,
+            message,
+        });
+    }
+    diagnostics
+}
+
+/// What is wrong with a line that looks like it was meant to open a code
+/// but does not. Only near-misses of recognised codes are reported, so
+/// narrative such as `N/A` or `and/or` is left alone.
+fn malformed(line: &str, registry: &Registry) -> Option<String> {
+    let (word, rest) = line.split_once('/')?;
+    if word.is_empty() || !word.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+    let spaced = rest.is_empty() || rest.starts_with(char::is_whitespace);
+    let upper = word.to_ascii_uppercase();
+    if !spaced && word == upper && registry.contains(word) {
+        return Some(format!(
+            "'{}/{}' needs a space after {}/",
+            word,
+            first_word(rest),
+            word
+        ));
+    }
+    if spaced && word != upper && registry.contains(&upper) {
+        return Some(format!("'{}/' should be upper case: {}/", word, upper));
+    }
+    if spaced
+        && word == upper
+        && word.len() > 8
+        && word.starts_with(|c: char| c.is_ascii_uppercase())
+    {
+        return Some(format!(
+            "'{}/' is too long for a code (at most 8 characters)",
+            word
+        ));
+    }
+    None
+}
+
+fn first_word(text: &str) -> &str {
+    text.split_whitespace().next().unwrap_or("")
+}
+
+/// Semantic HTML for a journal body: each coded section as
+/// `<section class="med-section med-pc" data-med-code="PC">` with its
+/// heading from `registry`, sub-codes nested within, and the narrative
+/// between as escaped paragraphs.
+pub fn render_html(body: &str, registry: &Registry) -> String {
+    let mut out = String::new();
+    for block in parse(body) {
+        match block {
+            Block::Text(paragraph) => {
+                let lines: Vec<String> = paragraph.lines().map(html_escape).collect();
+                out.push_str(&format!("<p>{}</p>\n", lines.join("<br>\n")));
+            }
+            Block::Fence(text) => {
+                out.push_str(&format!("<pre><code>{}</code></pre>\n", html_escape(&text)));
+            }
+            Block::Section(section) => {
+                out.push_str(&open_section(registry, &section.code, "h3", &section.notes));
+                for sub in &section.subs {
+                    out.push_str(&open_section(registry, &sub.code, "h4", &sub.notes));
+                    out.push_str("</section>\n");
+                }
+                out.push_str("</section>\n");
+            }
+        }
+    }
+    out
+}
+
+fn open_section(registry: &Registry, code: &str, heading_tag: &str, notes: &str) -> String {
+    let mut out = format!(
+        "<section class=\"med-section med-{}\" data-med-code=\"{}\">\n<{tag}>{}</{tag}>\n",
+        html_escape(&code.to_lowercase()),
+        html_escape(code),
+        html_escape(registry.heading(code)),
+        tag = heading_tag,
+    );
+    for paragraph in notes.split("\n\n").filter(|p| !p.is_empty()) {
+        let lines: Vec<String> = paragraph.lines().map(html_escape).collect();
+        out.push_str(&format!("<p>{}</p>\n", lines.join("<br>\n")));
+    }
+    out
+}
+
+fn append(object: &mut Map<String, Value>, key: &str, text: &str) {
+    match object.get_mut(key) {
+        Some(Value::String(existing)) => {
+            if !text.is_empty() {
+                if !existing.is_empty() {
+                    existing.push('\n');
+                }
+                existing.push_str(text);
+            }
+        }
+        _ => {
+            object.insert(key.to_string(), Value::from(text));
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Block {
+    /// A paragraph of narrative.
+    Text(String),
+    /// The contents of a fenced code block.
+    Fence(String),
+    Section(Section),
+}
+
+#[derive(Debug)]
+struct Section {
+    code: String,
+    /// Body line, from 1.
+    line: usize,
+    notes: String,
+    subs: Vec<Section>,
+}
+
+impl Section {
+    fn new(code: &str, line: usize, notes: &str) -> Self {
+        Section {
+            code: code.to_string(),
+            line,
+            notes: notes.to_string(),
+            subs: Vec::new(),
+        }
+    }
+
+    /// The notes that continuation lines extend: the last sub-code's, or
+    /// the section's own.
+    fn current_notes(&mut self) -> &mut String {
+        match self.subs.last_mut() {
+            Some(sub) => &mut sub.notes,
+            None => &mut self.notes,
+        }
+    }
+}
+
+/// An upper-case letter, then up to seven upper-case letters or digits.
+fn is_code(code: &str) -> bool {
+    let mut chars = code.chars();
+    chars.next().is_some_and(|c| c.is_ascii_uppercase())
+        && code.len() <= 8
+        && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// `CODE/ notes` → `(CODE, notes)`.
+fn code_line(line: &str) -> Option<(&str, &str)> {
+    let (code, rest) = line.trim().split_once('/')?;
+    if !is_code(code) || !(rest.is_empty() || rest.starts_with(char::is_whitespace)) {
+        return None;
+    }
+    Some((code, rest.trim()))
+}
+
+fn is_indented(line: &str) -> bool {
+    line.starts_with("  ") || line.starts_with('\t')
+}
+
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+fn parse(body: &str) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut section: Option<Section> = None;
+    let mut paragraph: Vec<&str> = Vec::new();
+    let mut fence: Option<Vec<&str>> = None;
+    let mut after_blank = false;
+
+    fn flush_paragraph(blocks: &mut Vec<Block>, paragraph: &mut Vec<&str>) {
+        if !paragraph.is_empty() {
+            blocks.push(Block::Text(paragraph.join("\n")));
+            paragraph.clear();
+        }
+    }
+
+    for (i, line) in body.lines().enumerate() {
+        if let Some(lines) = &mut fence {
+            if is_fence(line) {
+                blocks.push(Block::Fence(lines.join("\n")));
+                fence = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+        if is_fence(line) {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            blocks.extend(section.take().map(Block::Section));
+            fence = Some(Vec::new());
+            continue;
+        }
+        if line.trim().is_empty() {
+            flush_paragraph(&mut blocks, &mut paragraph);
+            after_blank = true;
+            continue;
+        }
+
+        if let Some((code, notes)) = code_line(line) {
+            match &mut section {
+                Some(open) if is_indented(line) => open.subs.push(Section::new(code, i + 1, notes)),
+                _ => {
+                    flush_paragraph(&mut blocks, &mut paragraph);
+                    blocks.extend(section.take().map(Block::Section));
+                    section = Some(Section::new(code, i + 1, notes));
+                }
+            }
+        } else if let Some(open) = section
+            .as_mut()
+            .filter(|_| !after_blank || is_indented(line))
+        {
+            let notes = open.current_notes();
+            if !notes.is_empty() {
+                notes.push_str(if after_blank { "\n\n" } else { "\n" });
+            }
+            notes.push_str(line.trim());
+        } else {
+            blocks.extend(section.take().map(Block::Section));
+            paragraph.push(line.trim_end());
+        }
+        after_blank = false;
+    }
+    if let Some(lines) = fence {
+        // An unclosed fence runs to the end of the body.
+        blocks.push(Block::Fence(lines.join("\n")));
+    }
+    flush_paragraph(&mut blocks, &mut paragraph);
+    blocks.extend(section.map(Block::Section));
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_sections_and_sub_codes() {
+        let body = "PC/ chest pain\nOE/ alert\n  RS/ clear\n  CVS/ no murmurs";
+        assert_eq!(
+            extract(body),
+            json!({
+                "PC": { "notes": "chest pain" },
+                "OE": { "notes": "alert", "RS": "clear", "CVS": "no murmurs" },
+                "_source_map": { "PC": 1, "OE": 2, "OE.RS": 3, "OE.CVS": 4 }
+            })
+        );
+    }
+
+    #[test]
+    fn test_continuations_narrative_and_fences() {
+        let body = "Seen in clinic.\n\nHPC/ two days of pain\nworse on stairs\n\n  no trauma\n\nPlan discussed: see /notes.\n```\nIMP/ not a code\n```\nIMP/ sprain\nIMP/ ? fracture";
+        let data = extract(body);
+        assert_eq!(
+            data["HPC"]["notes"],
+            "two days of pain\nworse on stairs\n\nno trauma"
+        );
+        assert_eq!(data["IMP"]["notes"], "sprain\n? fracture");
+        assert_eq!(data["_source_map"]["IMP"], 12);
+        assert_eq!(data.as_object().unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_bodies_without_codes() {
+        for body in [
+            "",
+            "Patient reviewed; plan updated.",
+            "BP 120/80, see https://example.org/a",
+            "# Review\n\nN/A and and/or",
+        ] {
+            assert_eq!(extract(body), json!({}), "{:?}", body);
+            assert!(!has_codes(body));
+        }
+        assert!(has_codes("Note\nIMP/ asthma"));
+    }
+
+    #[test]
+    fn test_render_html() {
+        let html = render_html(
+            "Seen <today>.\n\nOE/ alert\n  RS/ clear & quiet",
+            &Registry::default(),
+        );
+        assert_eq!(
+            html,
+            "<p>Seen &lt;today&gt;.</p>\n\
+             <section class=\"med-section med-oe\" data-med-code=\"OE\">\n<h3>On examination</h3>\n<p>alert</p>\n\
+             <section class=\"med-section med-rs\" data-med-code=\"RS\">\n<h4>Respiratory</h4>\n<p>clear &amp; quiet</p>\n</section>\n\
+             </section>\n"
+        );
+    }
+
+    #[test]
+    fn test_registry_merges_over_builtin_codes() {
+        let registry = Registry::from_json(
+            r#"[{"code": "BP", "heading": "Blood pressure", "category": "observation"},
+                {"code": "PLAN", "heading": "Management plan"}]"#,
+        )
+        .unwrap();
+        assert_eq!(registry.heading("BP"), "Blood pressure");
+        assert_eq!(registry.heading("PLAN"), "Management plan");
+        assert_eq!(registry.heading("IMP"), "Impression");
+        assert_eq!(registry.heading("XYZ"), "XYZ");
+        assert!(!Registry::default().contains("BP"));
+
+        assert!(Registry::from_json(r#"[{"code": "bp", "heading": "BP"}]"#).is_err());
+        assert!(Registry::from_json(r#"[{"code": "BP", "heading": " "}]"#).is_err());
+        assert!(Registry::from_json(r#"{"BP": "Blood pressure"}"#).is_err());
+    }
+
+    #[test]
+    fn test_validate_unknown_and_malformed_codes() {
+        let body = "Seen today; N/A and/or see http://x/y.\nBP/ 120/80\nimp/ asthma\nPLAN/review\n```\nXYZ/ in a fence\n```\nOE/ well\n  ZZ/ odd\nOBSERVATIONS/ normal";
+        let lines: Vec<(usize, String)> = validate(body, &Registry::default())
+            .into_iter()
+            .map(|d| (d.line, d.message))
+            .collect();
+        assert_eq!(
+            lines,
+            [
+                (2, "unknown code BP/".to_string()),
+                (3, "'imp/' should be upper case: IMP/".to_string()),
+                (4, "'PLAN/review' needs a space after PLAN/".to_string()),
+                (9, "unknown code ZZ/".to_string()),
+                (
+                    10,
+                    "'OBSERVATIONS/' is too long for a code (at most 8 characters)".to_string()
+                ),
+            ]
+        );
+
+        let registry =
+            Registry::from_json(r#"[{"code": "BP", "heading": "Blood pressure"}]"#).unwrap();
+        assert!(validate("BP/ 120/80\nIMP/ well", &registry).is_empty());
+    }
+}
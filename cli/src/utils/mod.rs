@@ -3,7 +3,10 @@
 
 pub mod tsid;
 
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
 use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fmt::Write;
 
 /// SHA-256 of `bytes` as a lowercase, zero-padded hex string (64 chars).
@@ -20,3 +23,69 @@ pub fn sha256_hex(bytes: &[u8]) -> String {
     }
     out
 }
+
+/// A date bound for `--since`/`--until` filters, as YYYY-MM-DD or RFC 3339.
+/// Bare dates cover the whole day.
+pub fn parse_bound(value: &str, end_of_day: bool) -> Result<DateTime<Utc>> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(value) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("Invalid date '{}': use YYYY-MM-DD or RFC 3339", value))?;
+    let time = if end_of_day {
+        date.and_hms_milli_opt(23, 59, 59, 999)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    };
+    Ok(time.expect("valid time of day").and_utc())
+}
+
+/// Replace each `{{key}}` in a template with its value. Unknown placeholders
+/// are left as they are.
+pub fn substitute(body: &str, values: &BTreeMap<String, String>) -> String {
+    let mut out = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let key = after[..end].trim();
+                match values.get(key) {
+                    Some(value) => out.push_str(value),
+                    None => out.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_bounds() {
+        let start = parse_bound("2026-06-14", false).unwrap();
+        let end = parse_bound("2026-06-14", true).unwrap();
+        assert_eq!(start.to_rfc3339(), "2026-06-14T00:00:00+00:00");
+        assert!(end > start && end.date_naive() == start.date_naive());
+        assert!(parse_bound("14/06/2026", false).is_err());
+    }
+
+    #[test]
+    fn test_substitute() {
+        let values = BTreeMap::from([("a".to_string(), "one".to_string())]);
+        assert_eq!(
+            substitute("{{a}} and {{ a }}, {{b}} {{unclosed", &values),
+            "one and one, {{b}} {{unclosed"
+        );
+    }
+}
@@ -0,0 +1,507 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::Result;
+use serde_json::Value;
+use serial_test::serial;
+use std::fs;
+
+use gitehr::commands::allergies::{self, AllergySeverity};
+use gitehr::commands::contributor::{activate_contributor, add_contributor};
+use gitehr::commands::document::add::run as add_document;
+use gitehr::commands::export::pdf::{self, PdfTemplate};
+use gitehr::commands::export::{fhir_journal, html, ips, openehr};
+use gitehr::commands::journal::clinical_time::{ClinicalTime, TimeBasis};
+use gitehr::commands::journal::metadata::{EncounterType, MetadataArgs};
+use gitehr::commands::journal::{
+    DocumentRef, JournalEntry, create_journal_entry, write_journal_entry,
+};
+
+fn setup_with_git() -> Result<tempfile::TempDir> {
+    let temp_dir = tempfile::tempdir()?;
+    std::env::set_current_dir(&temp_dir)?;
+    fs::create_dir(".gitehr")?;
+    fs::create_dir("journal")?;
+    fs::create_dir("state")?;
+    fs::create_dir("documents")?;
+    fs::write(".gitehr/ID", "subject-123\n")?;
+    std::process::Command::new("git").args(["init"]).output()?;
+    std::process::Command::new("git")
+        .args(["config", "user.name", "Test User"])
+        .output()?;
+    std::process::Command::new("git")
+        .args(["config", "user.email", "test@example.com"])
+        .output()?;
+    std::process::Command::new("git")
+        .args(["config", "commit.gpgsign", "false"])
+        .output()?;
+    Ok(temp_dir)
+}
+
+fn with_active_author() -> Result<()> {
+    add_contributor(
+        "doc001",
+        "Dr. Smith",
+        Some("GP"),
+        Some("smith@example.org"),
+        None,
+    )?;
+    activate_contributor("doc001")
+}
+
+fn section<'a>(bundle: &'a Value, loinc: &str) -> Option<&'a Value> {
+    bundle["entry"][0]["resource"]["section"]
+        .as_array()?
+        .iter()
+        .find(|s| s["code"]["coding"][0]["code"] == loinc)
+}
+
+fn resolve<'a>(bundle: &'a Value, reference: &Value) -> &'a Value {
+    bundle["entry"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|e| e["fullUrl"] == *reference)
+        .map(|e| &e["resource"])
+        .expect("section entry resolves within the Bundle")
+}
+
+#[test]
+#[serial]
+fn ips_requires_an_active_contributor() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+    let err = ips::build().unwrap_err().to_string();
+    assert!(err.contains("gitehr user activate"));
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn ips_empty_record_has_explicit_no_information_sections() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+    with_active_author()?;
+
+    let bundle = ips::build()?;
+    assert_eq!(bundle["type"], "document");
+    let composition = &bundle["entry"][0]["resource"];
+    assert_eq!(composition["resourceType"], "Composition");
+    assert_eq!(composition["type"]["coding"][0]["code"], "60591-5");
+
+    let author = resolve(&bundle, &composition["author"][0]["reference"]);
+    assert_eq!(author["resourceType"], "Practitioner");
+    assert_eq!(author["name"][0]["text"], "Dr. Smith");
+
+    let subject = resolve(&bundle, &composition["subject"]["reference"]);
+    assert_eq!(subject["identifier"][0]["value"], "subject-123");
+
+    for (loinc, code) in [
+        ("48765-2", "no-allergy-info"),
+        ("10160-0", "no-medication-info"),
+        ("11450-4", "no-problem-info"),
+    ] {
+        let section = section(&bundle, loinc).expect("mandatory section present");
+        let resource = resolve(&bundle, &section["entry"][0]["reference"]);
+        let concept = if resource["resourceType"] == "MedicationStatement" {
+            &resource["medicationCodeableConcept"]
+        } else {
+            &resource["code"]
+        };
+        assert_eq!(concept["coding"][0]["code"], code);
+        assert!(
+            section["text"]["div"]
+                .as_str()
+                .unwrap()
+                .contains("No information")
+        );
+    }
+    assert!(section(&bundle, "11369-6").is_none());
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn ips_includes_active_allergies_and_renders_html() -> Result<()> {
+    let temp_dir = setup_with_git()?;
+    with_active_author()?;
+    allergies::add("Penicillin", "Anaphylaxis", AllergySeverity::Critical, None)?;
+    let old = allergies::add("Latex", "Rash", AllergySeverity::Low, None)?;
+    allergies::inactive(&old.id, None)?;
+
+    let bundle = ips::build()?;
+    let section = section(&bundle, "48765-2").unwrap();
+    let refs = section["entry"].as_array().unwrap();
+    assert_eq!(refs.len(), 1);
+    let allergy = resolve(&bundle, &refs[0]["reference"]);
+    assert_eq!(allergy["code"]["text"], "Penicillin");
+    assert_eq!(allergy["criticality"], "high");
+
+    let html_path = temp_dir.path().join("out/ips.html");
+    ips::run(Some(&temp_dir.path().join("ips.json")), Some(&html_path))?;
+    let html = fs::read_to_string(html_path)?;
+    assert!(html.contains("Penicillin"));
+    assert!(!html.contains("Latex"));
+    assert!(html.contains("No information about medications."));
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn html_export_writes_offline_site_with_verified_documents() -> Result<()> {
+    let temp_dir = setup_with_git()?;
+    with_active_author()?;
+    allergies::add("Penicillin", "Anaphylaxis", AllergySeverity::Critical, None)?;
+    create_journal_entry("Reviewed <asthma> plan.")?;
+    create_journal_entry("IMP/ asthma\nPLAN/ inhaler review")?;
+    fs::write("letter.pdf", b"%PDF-1.4 letter")?;
+    let letter = add_document(std::path::Path::new("letter.pdf"), None, false, None)?;
+    fs::write("scan.pdf", b"%PDF-1.4 scan")?;
+    let scan = add_document(std::path::Path::new("scan.pdf"), None, false, None)?;
+    fs::write(&scan, b"tampered")?;
+
+    let site = temp_dir.path().join("site");
+    html::run(&site, TimeBasis::Recorded)?;
+
+    let summary = fs::read_to_string(site.join("index.html"))?;
+    assert!(summary.contains("Penicillin"));
+
+    let journal = fs::read_to_string(site.join("journal.html"))?;
+    assert!(journal.contains("Dr. Smith"), "author name resolved");
+    assert!(journal.contains("Reviewed &lt;asthma&gt; plan."));
+    assert!(journal.contains(r#"<section class="med-section med-imp" data-med-code="IMP">"#));
+    assert!(journal.contains("<h3>Plan</h3>\n<p>inhaler review</p>"));
+
+    let documents = fs::read_to_string(site.join("documents.html"))?;
+    assert!(documents.contains("Verified"));
+    assert!(documents.contains("FAILED: hash mismatch"));
+    assert_eq!(fs::read(site.join(&letter))?, b"%PDF-1.4 letter");
+    assert!(!documents.contains("http://") && !documents.contains("https://"));
+
+    // Refuses to overwrite an earlier export.
+    assert!(html::run(&site, TimeBasis::Recorded).is_err());
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn html_export_does_not_copy_documents_outside_the_site() -> Result<()> {
+    let temp_dir = setup_with_git()?;
+    with_active_author()?;
+    let repo = temp_dir.path().canonicalize()?;
+    let name = repo.file_name().unwrap().to_str().unwrap().to_string();
+    fs::write("secret.txt", b"secret")?;
+    let absolute = repo.join("secret.txt").to_string_lossy().into_owned();
+    // `site/../<repo name>/secret.txt` would land beside the site.
+    let climbing = format!("../{}/secret.txt", name);
+    let entry = JournalEntry {
+        documents: Some(
+            [climbing.as_str(), &absolute, ".gitehr/contributors.json"]
+                .into_iter()
+                .map(|path| DocumentRef {
+                    path: path.to_string(),
+                    sha256: "0".repeat(64),
+                    original_filename: None,
+                })
+                .collect(),
+        ),
+        ..JournalEntry::new(None)
+    };
+    write_journal_entry(&entry, "Hand-edited Document paths.")?;
+
+    let site = repo.join("site");
+    html::run(&site, TimeBasis::Recorded)?;
+
+    assert!(!repo.join(&name).exists());
+    assert!(!site.join(".gitehr").exists());
+    assert_eq!(fs::read("secret.txt")?, b"secret");
+    let documents = fs::read_to_string(site.join("documents.html"))?;
+    assert!(documents.contains(&climbing));
+    assert!(documents.contains(".gitehr/contributors.json"));
+    assert!(!documents.contains("href=\"../") && !documents.contains("href=\"/"));
+    assert!(!documents.contains("href=\".gitehr"));
+
+    Ok(())
+}
+
+fn head_commit() -> Result<String> {
+    let output = std::process::Command::new("git")
+        .args(["rev-parse", "HEAD"])
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[test]
+#[serial]
+fn pdf_single_entry_footer_traces_record_version() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+    with_active_author()?;
+    create_journal_entry("Dear Dr Jones,\nThank you for seeing (this) patient.")?;
+    create_journal_entry("A later entry.")?;
+    // Keep the working tree clean so the footer names the commit alone.
+    std::process::Command::new("git")
+        .args(["add", "-A"])
+        .output()?;
+    std::process::Command::new("git")
+        .args(["commit", "-m", "Add contributors"])
+        .output()?;
+    let commit = head_commit()?;
+
+    let bytes = pdf::render(
+        PdfTemplate::SingleEntry,
+        Some("LATEST^"),
+        TimeBasis::Recorded,
+    )?;
+    let text = String::from_utf8_lossy(&bytes);
+    assert!(bytes.starts_with(b"%PDF-1.4"));
+    assert!(text.trim_end().ends_with("%%EOF"));
+    assert!(text.contains("(Thank you for seeing \\(this\\) patient.)"));
+    assert!(!text.contains("A later entry."));
+    assert!(text.contains("Dr. Smith"));
+    assert!(text.contains("subject-123"));
+    assert!(text.contains(&format!("Record commit {})", commit)));
+
+    assert!(pdf::render(PdfTemplate::SingleEntry, None, TimeBasis::Recorded).is_err());
+    assert!(
+        pdf::render(
+            PdfTemplate::PatientSummary,
+            Some("LATEST"),
+            TimeBasis::Recorded
+        )
+        .is_err()
+    );
+
+    Ok(())
+}
+
+/// The first page's footer as one line, wherever it wrapped. Footer lines
+/// are drawn bottom-up, before the page number.
+fn first_page_footer(pdf: &str) -> String {
+    let mut lines: Vec<(f32, &str)> = Vec::new();
+    for line in pdf.lines() {
+        let Some(rest) = line.strip_prefix("0.3 g BT /F1 7.5 Tf ") else {
+            continue;
+        };
+        let (position, text) = rest.split_once(" Td (").unwrap();
+        let text = text.strip_suffix(") Tj ET 0 g").unwrap();
+        if text.starts_with("Page ") {
+            break;
+        }
+        let y = position.split(' ').nth(1).unwrap().parse().unwrap();
+        lines.push((y, text));
+    }
+    lines.sort_by(|a, b| b.0.total_cmp(&a.0));
+    lines
+        .into_iter()
+        .map(|(_, text)| text)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[test]
+#[serial]
+fn pdf_full_record_includes_summary_and_journal() -> Result<()> {
+    let temp_dir = setup_with_git()?;
+    with_active_author()?;
+    allergies::add("Penicillin", "Anaphylaxis", AllergySeverity::Critical, None)?;
+    for i in 0..60 {
+        create_journal_entry(&format!("Entry number {}", i))?;
+    }
+    create_journal_entry("Temp ≥ 38.5 → sepsis screen")?;
+
+    let output = temp_dir.path().join("out/record.pdf");
+    pdf::run(PdfTemplate::FullRecord, None, &output, TimeBasis::Recorded)?;
+    let text = String::from_utf8_lossy(&fs::read(&output)?).into_owned();
+    assert!(text.contains("(Penicillin \\(critical\\))"));
+    assert!(text.contains("Entry number 0") && text.contains("Entry number 59"));
+    assert!(text.contains("(Temp >= 38.5 -> sepsis screen)"));
+    assert!(text.contains("(Page 1 of "));
+    let commit = head_commit()?;
+    assert!(first_page_footer(&text).contains(&format!(
+        "Record commit {} \\(plus uncommitted changes\\)",
+        commit
+    )));
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn openehr_extract_versions_compositions_from_git_history() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+    with_active_author()?;
+    allergies::add("Penicillin", "Anaphylaxis", AllergySeverity::Critical, None)?;
+    allergies::add("Latex", "Rash", AllergySeverity::Low, None)?;
+    create_journal_entry("Seen in clinic. Well.")?;
+    let head = head_commit()?;
+
+    let extract = openehr::build()?;
+    assert_eq!(extract["_type"], "EXTRACT");
+    let content = &extract["chapters"][0]["content"];
+    assert_eq!(
+        extract["chapters"][0]["entity_identifier"]["subject_id"],
+        "subject-123"
+    );
+    let compositions = content["compositions"].as_array().unwrap();
+    // Allergy list, immunisation summary, and one note per journal entry.
+    assert_eq!(compositions.len(), 2 + 3);
+
+    let allergy_list = &compositions[0];
+    assert_eq!(allergy_list["total_version_count"], 2);
+    let version = &allergy_list["versions"][0];
+    assert!(
+        version["uid"]["value"]
+            .as_str()
+            .unwrap()
+            .ends_with("::gitehr::2")
+    );
+    assert_eq!(
+        version["commit_audit"]["change_type"]["defining_code"]["code_string"],
+        "251"
+    );
+    assert_eq!(version["commit_audit"]["committer"]["name"], "Dr. Smith");
+    let data = &version["data"];
+    assert_eq!(
+        data["archetype_node_id"],
+        "openEHR-EHR-COMPOSITION.adverse_reaction_list.v1"
+    );
+    assert_eq!(data["content"].as_array().unwrap().len(), 2);
+    assert_eq!(
+        data["content"][0]["data"]["items"][0]["value"]["value"],
+        "Penicillin"
+    );
+
+    let note = compositions.last().unwrap();
+    assert_eq!(note["total_version_count"], 1);
+    let version = &note["versions"][0];
+    assert_eq!(version["contribution"]["id"]["value"], head.as_str());
+    assert_eq!(
+        version["commit_audit"]["change_type"]["defining_code"]["code_string"],
+        "249"
+    );
+    assert_eq!(
+        version["data"]["content"][0]["data"]["items"][0]["value"]["value"],
+        "Seen in clinic. Well."
+    );
+    assert_eq!(version["data"]["composer"]["name"], "Dr. Smith");
+
+    assert_eq!(content["demographics"]["_type"], "X_VERSIONED_PARTY");
+    assert_eq!(
+        content["demographics"]["versions"][0]["data"]["_type"],
+        "PERSON"
+    );
+
+    // Identifiers are stable across exports.
+    let again = openehr::build()?;
+    assert_eq!(
+        again["chapters"][0]["content"]["compositions"][0]["uid"],
+        allergy_list["uid"]
+    );
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn openehr_extract_maps_entry_metadata_into_context() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+    let args = MetadataArgs {
+        encounter_type: Some(EncounterType::Phone),
+        tags: vec!["diabetes".to_string()],
+        codes: vec!["38341003|Hypertensive disorder".to_string()],
+        location: Some("Riverside Surgery".to_string()),
+    };
+    write_journal_entry(&args.apply(JournalEntry::new(None))?, "Phone review.")?;
+
+    let extract = openehr::build()?;
+    let compositions = extract["chapters"][0]["content"]["compositions"]
+        .as_array()
+        .unwrap();
+    let context = &compositions.last().unwrap()["versions"][0]["data"]["context"];
+    assert_eq!(context["health_care_facility"]["name"], "Riverside Surgery");
+    let items = context["other_context"]["items"].as_array().unwrap();
+    let values: Vec<&Value> = items.iter().map(|item| &item["value"]["value"]).collect();
+    assert_eq!(values, ["Telephone", "diabetes", "Hypertensive disorder"]);
+    assert_eq!(
+        items[2]["value"]["defining_code"]["terminology_id"]["value"],
+        "SNOMED-CT"
+    );
+    assert_eq!(
+        items[2]["value"]["defining_code"]["code_string"],
+        "38341003"
+    );
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn fhir_journal_maps_entry_metadata_into_encounters() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+    with_active_author()?;
+    let args = MetadataArgs {
+        encounter_type: Some(EncounterType::Phone),
+        tags: vec!["diabetes".to_string()],
+        codes: vec!["38341003|Hypertensive disorder".to_string()],
+        location: Some("Riverside Surgery".to_string()),
+    };
+    let mut entry = args.apply(JournalEntry::new(Some("doc001".to_string())))?;
+    entry.clinical_time = Some(ClinicalTime::parse("2026-03")?);
+    write_journal_entry(&entry, "Phone review.")?;
+    create_journal_entry("A note without metadata.")?;
+
+    let bundle = fhir_journal::build()?;
+    assert_eq!(bundle["type"], "collection");
+    let resources: Vec<&Value> = bundle["entry"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|e| &e["resource"])
+        .collect();
+    let of_type = |t: &str| -> Vec<&Value> {
+        resources
+            .iter()
+            .copied()
+            .filter(|r| r["resourceType"] == t)
+            .collect()
+    };
+    assert_eq!(of_type("Practitioner").len(), 1);
+    let encounters = of_type("Encounter");
+    let notes = of_type("DocumentReference");
+    assert_eq!((encounters.len(), notes.len()), (2, 2));
+
+    let encounter = encounters[0];
+    assert_eq!(encounter["class"]["code"], "VR");
+    assert_eq!(encounter["type"][0]["coding"][0]["code"], "phone");
+    assert_eq!(encounter["type"][0]["text"], "Telephone");
+    let reason = &encounter["reasonCode"][0]["coding"][0];
+    assert_eq!(reason["system"], "http://snomed.info/sct");
+    assert_eq!(reason["code"], "38341003");
+    assert_eq!(reason["display"], "Hypertensive disorder");
+    assert_eq!(
+        encounter["location"][0]["location"]["display"],
+        "Riverside Surgery"
+    );
+    assert_eq!(encounter["period"]["start"], "2026-03");
+    assert_eq!(
+        encounter["participant"][0]["individual"]["display"],
+        "Dr. Smith"
+    );
+
+    let note = notes[0];
+    assert_eq!(note["meta"]["tag"][0]["code"], "diabetes");
+    assert_eq!(
+        resolve(&bundle, &note["context"]["encounter"][0]["reference"])["id"],
+        encounter["id"]
+    );
+    assert_eq!(
+        note["content"][0]["attachment"]["data"],
+        "UGhvbmUgcmV2aWV3Lg=="
+    );
+
+    // An entry without metadata still gets an Encounter, of unknown class.
+    assert_eq!(encounters[1]["class"]["code"], "UNK");
+    assert!(encounters[1].get("reasonCode").is_none());
+    assert!(notes[1].get("meta").is_none());
+    Ok(())
+}
@@ -0,0 +1,250 @@
+// SPDX-FileCopyrightText: 2026 Marcus Baw and Baw Medical Ltd
+// SPDX-License-Identifier: AGPL-3.0-or-later
+
+use anyhow::Result;
+use serial_test::serial;
+use std::fs;
+use std::path::Path;
+
+use gitehr::commands::allergies::{self, AllergySeverity, AllergyStatus};
+use gitehr::commands::demographics;
+use gitehr::commands::import::{self, fhir};
+use gitehr::commands::journal::metadata::EncounterType;
+use gitehr::commands::journal::parsed_entries;
+use gitehr::commands::vaccinations;
+
+fn setup_with_git() -> Result<tempfile::TempDir> {
+    let temp_dir = tempfile::tempdir()?;
+    std::env::set_current_dir(&temp_dir)?;
+    fs::create_dir(".gitehr")?;
+    fs::create_dir("journal")?;
+    fs::create_dir("state")?;
+    fs::create_dir("documents")?;
+    std::process::Command::new("git").args(["init"]).output()?;
+    std::process::Command::new("git")
+        .args(["config", "user.name", "Test User"])
+        .output()?;
+    std::process::Command::new("git")
+        .args(["config", "user.email", "test@example.com"])
+        .output()?;
+    std::process::Command::new("git")
+        .args(["config", "commit.gpgsign", "false"])
+        .output()?;
+    Ok(temp_dir)
+}
+
+fn commit_count() -> Result<usize> {
+    let output = std::process::Command::new("git")
+        .args(["rev-list", "--count", "HEAD"])
+        .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .unwrap_or(0))
+}
+
+/// "%PDF-1.4 letter" base64-encoded.
+const LETTER_BASE64: &str = "JVBERi0xLjQgbGV0dGVy";
+
+fn write_bundle(path: &Path) -> Result<()> {
+    let bundle = serde_json::json!({
+        "resourceType": "Bundle",
+        "type": "collection",
+        "entry": [
+            {
+                "fullUrl": "urn:uuid:patient-1",
+                "resource": {
+                    "resourceType": "Patient",
+                    "id": "patient-1",
+                    "identifier": [
+                        { "system": "https://fhir.nhs.uk/Id/nhs-number", "value": "9434765919" }
+                    ],
+                    "name": [
+                        { "use": "official", "prefix": ["Mrs"], "given": ["Jane", "Anne"], "family": "Smith" },
+                        { "use": "usual", "given": ["Janey"] }
+                    ],
+                    "birthDate": "1970-01-31",
+                    "address": [
+                        { "line": ["1 High Street"], "city": "Leeds", "postalCode": "LS1 1AA" }
+                    ]
+                }
+            },
+            {
+                "resource": {
+                    "resourceType": "AllergyIntolerance",
+                    "id": "alg-1",
+                    "clinicalStatus": { "coding": [{ "code": "active" }] },
+                    "criticality": "high",
+                    "code": { "coding": [{ "display": "Penicillin" }] },
+                    "reaction": [
+                        { "manifestation": [{ "text": "Anaphylaxis" }], "severity": "severe" }
+                    ]
+                }
+            },
+            {
+                "resource": {
+                    "resourceType": "Immunization",
+                    "id": "imm-1",
+                    "status": "completed",
+                    "vaccineCode": { "text": "MMR" },
+                    "occurrenceDateTime": "2026-06-30T10:00:00Z",
+                    "lotNumber": "ABC123",
+                    "protocolApplied": [{ "doseNumberPositiveInt": 1 }]
+                }
+            },
+            {
+                "resource": {
+                    "resourceType": "Condition",
+                    "id": "cond-1",
+                    "code": { "text": "Asthma" }
+                }
+            },
+            {
+                "resource": {
+                    "resourceType": "DocumentReference",
+                    "id": "doc-1",
+                    "content": [
+                        { "attachment": { "contentType": "application/pdf", "title": "Clinic letter", "url": "Binary/bin-1" } }
+                    ]
+                }
+            },
+            {
+                "fullUrl": "https://example.org/fhir/Binary/bin-1",
+                "resource": {
+                    "resourceType": "Binary",
+                    "id": "bin-1",
+                    "contentType": "application/pdf",
+                    "data": LETTER_BASE64
+                }
+            }
+        ]
+    });
+    fs::write(path, serde_json::to_string_pretty(&bundle)?)?;
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn fhir_bundle_import_upserts_typed_state_in_one_commit() -> Result<()> {
+    let temp_dir = setup_with_git()?;
+    let bundle = temp_dir.path().join("bundle.json");
+    write_bundle(&bundle)?;
+
+    let summary = fhir::run(&bundle, false)?;
+    assert!(summary.committed);
+    assert!(summary.demographics_updated);
+    assert_eq!(summary.allergies.added, 1);
+    assert_eq!(summary.vaccinations.added, 1);
+    // The letter plus the Bundle itself.
+    assert_eq!(summary.documents_added, 2);
+    assert_eq!(summary.not_imported.get("Condition"), Some(&1));
+    assert_eq!(commit_count()?, 1);
+
+    let demographics = demographics::load()?;
+    assert_eq!(demographics.full_name.as_deref(), Some("Jane Anne Smith"));
+    assert_eq!(demographics.title.as_deref(), Some("Mrs"));
+    assert_eq!(demographics.preferred_name.as_deref(), Some("Janey"));
+    assert_eq!(demographics.date_of_birth.as_deref(), Some("1970-01-31"));
+    assert_eq!(demographics.nhs_number.as_deref(), Some("9434765919"));
+    assert_eq!(
+        demographics.address.as_deref(),
+        Some("1 High Street, Leeds, LS1 1AA")
+    );
+
+    let allergies = allergies::list(false)?;
+    assert_eq!(allergies.len(), 1);
+    assert_eq!(allergies[0].agent, "Penicillin");
+    assert_eq!(allergies[0].reaction, "Anaphylaxis");
+    assert_eq!(allergies[0].severity, AllergySeverity::Critical);
+    assert_eq!(allergies[0].status, AllergyStatus::Active);
+    assert_eq!(
+        allergies[0].source_key.as_deref(),
+        Some("fhir:AllergyIntolerance/alg-1")
+    );
+
+    let vaccinations = vaccinations::list(false)?;
+    assert_eq!(vaccinations.len(), 1);
+    assert_eq!(vaccinations[0].date, "2026-06-30");
+    assert_eq!(vaccinations[0].batch_number.as_deref(), Some("ABC123"));
+
+    let entries = parsed_entries()?;
+    assert_eq!(entries.len(), 1);
+    assert!(entries[0].content.contains("Imported FHIR R4 Bundle"));
+    let docs = entries[0].metadata.documents.as_ref().unwrap();
+    assert_eq!(docs.len(), 2);
+    let letter = docs
+        .iter()
+        .find(|doc| doc.path.contains("clinic-letter"))
+        .expect("letter stored as a Document");
+    assert!(letter.path.ends_with(".pdf"));
+    assert_eq!(fs::read(&letter.path)?, b"%PDF-1.4 letter");
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn fhir_bundle_reimport_is_idempotent() -> Result<()> {
+    let temp_dir = setup_with_git()?;
+    let bundle = temp_dir.path().join("bundle.json");
+    write_bundle(&bundle)?;
+
+    fhir::run(&bundle, false)?;
+    let summary = fhir::run(&bundle, false)?;
+
+    assert!(!summary.committed);
+    assert!(!summary.demographics_updated);
+    assert_eq!(summary.allergies.unchanged, 1);
+    assert_eq!(summary.vaccinations.unchanged, 1);
+    assert_eq!(summary.documents_added, 0);
+    assert_eq!(commit_count()?, 1);
+    assert_eq!(allergies::list(true)?.len(), 1);
+    assert_eq!(parsed_entries()?.len(), 1);
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn fhir_bundle_dry_run_writes_nothing() -> Result<()> {
+    let temp_dir = setup_with_git()?;
+    let bundle = temp_dir.path().join("bundle.json");
+    write_bundle(&bundle)?;
+
+    let summary = fhir::run(&bundle, true)?;
+    assert_eq!(summary.allergies.added, 1);
+    assert!(!summary.committed);
+    assert_eq!(commit_count()?, 0);
+    assert!(!Path::new("state/allergies.md").exists());
+    assert!(parsed_entries()?.is_empty());
+
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn journal_import_keeps_entry_metadata_and_unknown_keys() -> Result<()> {
+    let source = tempfile::tempdir()?;
+    let filename = "20260314T101500.000Z-8d9e1f2a-3b4c-4d5e-8f70-112233445566.md";
+    let content = "---\ntimestamp: 2026-03-14T10:15:00Z\nencounter_type: letter\nlocation: Cardiology, St Elsewhere\ntags:\n- cardiology\ncodes:\n- code: '22298006'\n  display: Myocardial infarction\nward: 7B\nsource_system:\n  name: OtherEHR\n  id: 42\n---\n\nDischarge letter received.\n";
+    fs::write(source.path().join(filename), content)?;
+
+    let _temp_dir = setup_with_git()?;
+    import::run(import::ImportMode::Journal, source.path(), false)?;
+
+    assert_eq!(
+        fs::read_to_string(Path::new("journal").join(filename))?,
+        content
+    );
+    let entries = parsed_entries()?;
+    let meta = &entries[0].metadata;
+    assert_eq!(meta.encounter_type, Some(EncounterType::Letter));
+    assert_eq!(meta.location.as_deref(), Some("Cardiology, St Elsewhere"));
+    assert_eq!(meta.tags, ["cardiology"]);
+    assert_eq!(meta.codes[0].code, "22298006");
+    assert_eq!(
+        meta.extra.keys().collect::<Vec<_>>(),
+        ["source_system", "ward"]
+    );
+    Ok(())
+}
@@ -8,7 +8,18 @@ use serial_test::serial;
 use std::fs;
 use tempfile::tempdir;
 
-use gitehr::commands::journal::{create_journal_entry, parsed_entries, sorted_entries};
+use gitehr::commands::journal::clinical_time::{ClinicalTime, TimeBasis};
+use gitehr::commands::journal::filter::{Filter, Query, matching_entries};
+use gitehr::commands::journal::metadata::{EncounterType, MetadataArgs};
+use gitehr::commands::journal::thread::{self, LinkArgs, LinkKind};
+use gitehr::commands::journal::{
+    DocumentRef, JournalEntry, create_journal_entry, create_journal_entry_as, parsed_entries,
+    sorted_entries, write_journal_entry,
+};
+use gitehr::commands::journal::{drafts, index, lint, promote, templates};
+use gitehr::commands::{allergies, vaccinations};
+use gitehr::medmd;
+use gitehr::utils::parse_bound;
 
 fn setup_with_git() -> Result<tempfile::TempDir> {
     let temp_dir = tempdir()?;
@@ -152,3 +163,570 @@ fn test_timestamp_uniqueness_and_ordering() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+#[serial]
+fn test_filters_select_entries() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+
+    create_journal_entry_as(
+        "# Diabetes review\nChest pain on exertion. #Diabetes",
+        Vec::new(),
+        Some("doc001".to_string()),
+    )?;
+    create_journal_entry_as(
+        "Letter scanned. #diabetes #eyes",
+        vec![DocumentRef {
+            path: "documents/letter.pdf".to_string(),
+            sha256: "0".repeat(64),
+            original_filename: None,
+        }],
+        Some("nurse01".to_string()),
+    )?;
+    create_journal_entry("BP 142/90, pain on exertion resolved")?;
+
+    let contents = |filter: Filter| -> Result<Vec<String>> {
+        Ok(matching_entries(&filter)?
+            .into_iter()
+            .map(|e| e.content)
+            .collect())
+    };
+
+    assert_eq!(contents(Filter::default())?.len(), 3);
+    let by_author = contents(Filter {
+        author: Some("DOC001".to_string()),
+        ..Default::default()
+    })?;
+    assert_eq!(by_author.len(), 1);
+    assert!(by_author[0].starts_with("# Diabetes review"));
+
+    let with_documents = contents(Filter {
+        has_documents: true,
+        ..Default::default()
+    })?;
+    assert_eq!(with_documents, ["Letter scanned. #diabetes #eyes"]);
+
+    let tagged = |tags: &[&str]| {
+        contents(Filter {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        })
+    };
+    assert_eq!(tagged(&["diabetes"])?.len(), 2);
+    assert_eq!(tagged(&["#diabetes", "eyes"])?.len(), 1);
+    assert!(tagged(&["asthma"])?.is_empty());
+
+    let phrase = contents(Filter {
+        query: Some(Query::parse("\"pain on exertion\" chest", false)?),
+        ..Default::default()
+    })?;
+    assert_eq!(phrase.len(), 1);
+    let regex = contents(Filter {
+        query: Some(Query::parse(r"BP \d+/\d+", true)?),
+        ..Default::default()
+    })?;
+    assert_eq!(regex.len(), 1);
+
+    let future = contents(Filter {
+        since: Some(Utc::now() + chrono::Duration::days(1)),
+        ..Default::default()
+    })?;
+    assert!(future.is_empty());
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_index_reuses_committed_entries() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+    fs::create_dir(".gitehr")?;
+
+    create_journal_entry("First entry")?;
+    create_journal_entry("Second entry")?;
+    assert_eq!(parsed_entries()?.len(), 2);
+    assert!(fs::metadata(index::INDEX_FILE).is_ok(), "index written");
+    let status = index::status()?;
+    assert_eq!((status.entries, status.indexed, status.pending), (2, 2, 0));
+
+    // The cache is never committed.
+    let porcelain = std::process::Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=all"])
+        .output()?;
+    assert!(!String::from_utf8_lossy(&porcelain.stdout).contains("cache"));
+
+    // A committed entry whose blob is unchanged is served from the index.
+    let cached = fs::read_to_string(index::INDEX_FILE)?;
+    fs::write(
+        index::INDEX_FILE,
+        cached.replace("First entry", "From the index"),
+    )?;
+    assert_eq!(parsed_entries()?[0].content, "From the index");
+
+    // Uncommitted entries are read from disk, and not indexed.
+    fs::write(
+        "journal/20990101T000000.000Z-uncommitted.md",
+        "---\ntimestamp: 2099-01-01T00:00:00Z\n---\n\nNot yet committed",
+    )?;
+    let entries = parsed_entries()?;
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[2].content, "Not yet committed");
+    assert_eq!(sorted_entries()?[0], "20990101T000000.000Z-uncommitted.md");
+    assert_eq!(index::status()?.pending, 1);
+
+    // A rebuild starts afresh from the files.
+    assert_eq!(index::rebuild()?, 2);
+    assert_eq!(parsed_entries()?[0].content, "First entry");
+
+    // The index is disposable.
+    fs::remove_dir_all(index::CACHE_DIR)?;
+    assert_eq!(parsed_entries()?.len(), 3);
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_index_notices_entries_rewritten_in_place() -> Result<()> {
+    use std::io::Write;
+    let _temp_dir = setup_with_git()?;
+    fs::create_dir(".gitehr")?;
+
+    create_journal_entry("Original text")?;
+    // Age everything past the window in which the index is not trusted.
+    let hour_ago = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    let name = sorted_entries()?[0].clone();
+    let entry = format!("journal/{}", name);
+    for path in [entry.as_str(), "journal", ".git/index"] {
+        fs::File::open(path)?.set_modified(hour_ago)?;
+    }
+    assert_eq!(parsed_entries()?[0].content, "Original text");
+    assert!(index::status()?.current, "index used without Git");
+
+    // Same length, same directory, same Git index: only the file changes.
+    let original = fs::read_to_string(&entry)?;
+    let mut file = fs::OpenOptions::new().write(true).open(&entry)?;
+    file.write_all(original.replace("Original", "Replaced").as_bytes())?;
+    drop(file);
+
+    assert!(!index::status()?.current);
+    assert_eq!(parsed_entries()?[0].content, "Replaced text");
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_medical_markdown_extracts_from_entries() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+
+    create_journal_entry("Patient reviewed; plan updated.")?;
+    create_journal_entry("Seen today.\nPC/ cough\nOE/ well\n  RS/ wheeze\nIMP/ asthma")?;
+    let entries = parsed_entries()?;
+
+    // Entries without codes stay valid and extract to an empty object.
+    assert!(!medmd::has_codes(&entries[0].content));
+    assert_eq!(medmd::extract(&entries[0].content), serde_json::json!({}));
+
+    let data = medmd::extract(&entries[1].content);
+    assert_eq!(data["PC"]["notes"], "cough");
+    assert_eq!(data["OE"]["RS"], "wheeze");
+    assert_eq!(data["IMP"]["notes"], "asthma");
+    assert_eq!(data["_source_map"]["OE.RS"], 4);
+    assert!(
+        medmd::render_html(&entries[1].content, &medmd::Registry::default())
+            .contains("data-med-code=\"IMP\"")
+    );
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_lint_reports_codes_outside_the_registry() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+    fs::create_dir(".gitehr")?;
+
+    create_journal_entry("Reviewed.\nBP/ 150/90\nimp/ hypertension")?;
+    create_journal_entry("PLAN/ recheck in a week")?;
+
+    let problems = lint::problems(None)?;
+    let found: Vec<(usize, &str)> = problems
+        .iter()
+        .map(|(_, d)| (d.line, d.message.as_str()))
+        .collect();
+    assert_eq!(
+        found,
+        [
+            (2, "unknown code BP/"),
+            (3, "'imp/' should be upper case: IMP/")
+        ]
+    );
+
+    fs::write(
+        medmd::REGISTRY_FILE,
+        r#"[{"code": "BP", "heading": "Blood pressure"}]"#,
+    )?;
+    assert_eq!(lint::problems(None)?.len(), 1);
+    assert!(lint::problems(Some("2999-01-01"))?.is_empty());
+
+    fs::write(medmd::REGISTRY_FILE, r#"[{"code": "bp"}]"#)?;
+    assert!(lint::problems(None).is_err());
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_promote_writes_facts_with_provenance() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+    fs::create_dir(".gitehr")?;
+
+    let note = "Seen in clinic.\nALLERGY/ penicillin - rash (severe)\nlatex\nVACC/ Influenza on 2026-10-01";
+    create_journal_entry(note)?;
+    let entry = parsed_entries()?.pop().unwrap();
+
+    let found = promote::candidates(&entry)?;
+    let facts: Vec<String> = found
+        .iter()
+        .map(|c| c.fact.as_ref().unwrap().to_string())
+        .collect();
+    assert_eq!(
+        facts,
+        [
+            "Allergy: penicillin - rash (high)",
+            "Allergy: latex - Not recorded (moderate)",
+            "Vaccination: Influenza on 2026-10-01",
+        ]
+    );
+    assert!(found.iter().all(|c| c.skip.is_none()));
+
+    promote::write(found[0].fact.as_ref().unwrap(), &entry.filename)?;
+    promote::write(found[2].fact.as_ref().unwrap(), &entry.filename)?;
+
+    let allergy = &allergies::list(false)?[0];
+    assert_eq!(allergy.agent, "penicillin");
+    assert_eq!(
+        allergy.source_entry.as_deref(),
+        Some(entry.filename.as_str())
+    );
+    let vaccination = &vaccinations::list(false)?[0];
+    assert_eq!(vaccination.date, "2026-10-01");
+    assert_eq!(
+        vaccination.source_entry.as_deref(),
+        Some(entry.filename.as_str())
+    );
+
+    // The narrative is untouched, and promoted facts are not offered again.
+    let journal = parsed_entries()?;
+    let original = journal
+        .iter()
+        .find(|e| e.filename == entry.filename)
+        .unwrap();
+    assert_eq!(original.content, note);
+    let skipped: Vec<bool> = promote::candidates(original)?
+        .iter()
+        .map(|c| c.skip.is_some())
+        .collect();
+    assert_eq!(skipped, [true, false, true]);
+    assert!(
+        journal
+            .last()
+            .unwrap()
+            .content
+            .contains(&format!("Promoted from journal entry {}.", entry.filename))
+    );
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_clinical_time_orders_and_filters_entries() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+
+    let at = |when: &str, body: &str| -> Result<String> {
+        let entry = JournalEntry {
+            clinical_time: Some(ClinicalTime::parse(when)?),
+            ..JournalEntry::new(None)
+        };
+        write_journal_entry(&entry, body)
+    };
+    create_journal_entry("Contemporaneous note")?;
+    at("2026-03", "Home visit in March")?;
+    at("2026-02-14T10:00:00Z", "Clinic on 14 February")?;
+
+    let entries = parsed_entries()?;
+    assert_eq!(
+        entries[1].metadata.clinical_time.as_ref().unwrap().as_str(),
+        "2026-03"
+    );
+    // Filenames stay in recorded order.
+    assert!(entries[1].filename < entries[2].filename);
+
+    let bodies = |filter: &Filter| -> Result<Vec<String>> {
+        Ok(matching_entries(filter)?
+            .into_iter()
+            .map(|e| e.content)
+            .collect())
+    };
+    let clinical = Filter {
+        time: TimeBasis::Clinical,
+        ..Filter::default()
+    };
+    assert_eq!(
+        bodies(&clinical)?,
+        [
+            "Clinic on 14 February",
+            "Home visit in March",
+            "Contemporaneous note"
+        ]
+    );
+
+    // A partial date matches any bound inside it.
+    let march = Filter {
+        since: Some(parse_bound("2026-03-20", false)?),
+        until: Some(parse_bound("2026-03-31", true)?),
+        ..clinical
+    };
+    assert_eq!(bodies(&march)?, ["Home visit in March"]);
+    let recorded = Filter {
+        until: Some(parse_bound("2026-03-31", true)?),
+        ..Filter::default()
+    };
+    assert!(bodies(&recorded)?.is_empty());
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_templates_prefer_repository_then_store_then_user() -> Result<()> {
+    let store = tempdir()?;
+    fs::write(store.path().join("gitehr-mpi.json"), "{}")?;
+    let subject = store.path().join("subject");
+    fs::create_dir_all(subject.join(templates::TEMPLATES_DIR))?;
+    fs::create_dir_all(store.path().join(templates::STORE_TEMPLATES_DIR))?;
+    let user = store.path().join("user");
+    fs::create_dir_all(user.join(templates::USER_TEMPLATES_DIR))?;
+    std::env::set_current_dir(&subject)?;
+
+    fs::write(
+        subject.join(templates::TEMPLATES_DIR).join("triage.md"),
+        "---\ndescription: Telephone triage\n---\nTriage on {{date}} for {{patient.name}}\n\n{{allergies}}\n\n{{unknown}}\n",
+    )?;
+    fs::write(
+        store
+            .path()
+            .join(templates::STORE_TEMPLATES_DIR)
+            .join("review.md"),
+        "Annual review",
+    )?;
+    for name in ["triage", "review", "mine"] {
+        fs::write(
+            user.join(templates::USER_TEMPLATES_DIR)
+                .join(format!("{}.md", name)),
+            "User template",
+        )?;
+    }
+    // An invalid name is skipped, not fatal to every other template.
+    fs::write(
+        user.join(templates::USER_TEMPLATES_DIR)
+            .join("Annual review.md"),
+        "User template",
+    )?;
+
+    unsafe { std::env::set_var("GITEHR_CONFIG", user.join("config.toml")) };
+    let available = templates::available();
+    unsafe { std::env::remove_var("GITEHR_CONFIG") };
+    let available = available?;
+
+    let sources: Vec<(&str, templates::Source)> = available
+        .values()
+        .map(|t| (t.name.as_str(), t.source))
+        .collect();
+    assert_eq!(
+        sources,
+        [
+            ("mine", templates::Source::User),
+            ("review", templates::Source::Store),
+            ("triage", templates::Source::Repository),
+        ]
+    );
+
+    let triage = &available["triage"];
+    assert_eq!(triage.description.as_deref(), Some("Telephone triage"));
+    let values = templates::placeholders()?;
+    let today = chrono::Local::now().format("%Y-%m-%d").to_string();
+    assert_eq!(
+        triage.render(&values),
+        format!("Triage on {today} for not recorded\n\nNone recorded.\n\n{{{{unknown}}}}")
+    );
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_entry_metadata_round_trips_and_filters() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+
+    let args = MetadataArgs {
+        encounter_type: Some(EncounterType::Phone),
+        tags: vec!["#Diabetes".to_string(), "diabetes".to_string()],
+        codes: vec!["38341003 |Hypertensive disorder|".to_string()],
+        location: Some(" Riverside Surgery ".to_string()),
+    };
+    let mut entry = args.apply(JournalEntry::new(None))?;
+    entry.extra.insert(
+        "ward".to_string(),
+        serde_yaml_ng::Value::String("7B".to_string()),
+    );
+    let filename = write_journal_entry(&entry, "Phone review. #bp")?;
+    create_journal_entry("Clinic review. #diabetes")?;
+
+    let raw = fs::read_to_string(&filename)?;
+    assert!(raw.contains("encounter_type: phone\n"), "{}", raw);
+    assert!(raw.contains("ward: 7B\n"), "{}", raw);
+
+    // Read twice: the second time from the index.
+    for _ in 0..2 {
+        let entries = parsed_entries()?;
+        let meta = &entries[0].metadata;
+        assert_eq!(meta.tags, ["diabetes"]);
+        assert_eq!(meta.location.as_deref(), Some("Riverside Surgery"));
+        assert_eq!(
+            meta.codes[0].display.as_deref(),
+            Some("Hypertensive disorder")
+        );
+        assert_eq!(meta.extra["ward"], serde_yaml_ng::Value::from("7B"));
+    }
+
+    let bodies = |filter: &Filter| -> Result<Vec<String>> {
+        Ok(matching_entries(filter)?
+            .into_iter()
+            .map(|e| e.content)
+            .collect())
+    };
+    // Front-matter tags and body hashtags both count.
+    let tagged = Filter {
+        tags: vec!["diabetes".to_string()],
+        ..Filter::default()
+    };
+    assert_eq!(bodies(&tagged)?.len(), 2);
+    let phone = Filter {
+        tags: vec!["diabetes".to_string(), "bp".to_string()],
+        encounter_type: Some(EncounterType::Phone),
+        codes: vec!["38341003".to_string()],
+        location: Some("riverside".to_string()),
+        ..Filter::default()
+    };
+    assert_eq!(bodies(&phone)?, ["Phone review. #bp"]);
+    let letters = Filter {
+        encounter_type: Some(EncounterType::Letter),
+        ..Filter::default()
+    };
+    assert!(bodies(&letters)?.is_empty());
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_drafts_stay_out_of_the_record_until_committed() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+    fs::create_dir(".gitehr")?;
+
+    let args = MetadataArgs {
+        encounter_type: Some(EncounterType::Letter),
+        ..MetadataArgs::default()
+    };
+    let kept = drafts::save(
+        &args.apply(JournalEntry::new(None))?,
+        "Letter from cardiology",
+    )?;
+    let dropped = drafts::save(&JournalEntry::new(None), "Wrong patient")?;
+    assert_ne!(kept, dropped);
+    assert_eq!(drafts::resolve("LATEST")?, dropped);
+    assert_eq!(drafts::resolve(&format!("{}.md", kept))?, kept);
+    assert!(drafts::resolve("20000101-000000").is_err());
+
+    let status = std::process::Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=all"])
+        .output()?;
+    assert!(
+        !String::from_utf8_lossy(&status.stdout).contains("drafts"),
+        "drafts must be ignored by git"
+    );
+    assert!(sorted_entries()?.is_empty());
+
+    let listed = drafts::list()?;
+    assert_eq!(listed.len(), 2);
+    assert_eq!(
+        listed[0].parsed.as_ref().unwrap().content,
+        "Letter from cardiology"
+    );
+
+    let filename = drafts::commit(&kept, false)?;
+    drafts::discard(&dropped)?;
+    assert!(drafts::list()?.is_empty());
+
+    let entries = parsed_entries()?;
+    assert_eq!(entries.len(), 1);
+    assert!(filename.ends_with(&entries[0].filename));
+    assert_eq!(entries[0].content, "Letter from cardiology");
+    assert_eq!(
+        entries[0].metadata.encounter_type,
+        Some(EncounterType::Letter)
+    );
+    Ok(())
+}
+
+#[test]
+#[serial]
+fn test_replies_form_threads_and_backlinks() -> Result<()> {
+    let _temp_dir = setup_with_git()?;
+
+    let add = |text: &str, reply_to: Option<&str>, related: &[&str]| -> Result<String> {
+        let links = LinkArgs {
+            reply_to: reply_to.map(str::to_string),
+            related: related.iter().map(|r| r.to_string()).collect(),
+        };
+        let path = write_journal_entry(&links.apply(JournalEntry::new(None))?, text)?;
+        Ok(path.trim_start_matches("journal/").to_string())
+    };
+    let referral = add("Referral to cardiology", None, &[])?;
+    let reply = add("Cardiology reply", Some("LATEST"), &[])?;
+    let chase = add(
+        "Chased referral",
+        Some(&format!("journal/{}", referral)),
+        &[],
+    )?;
+    let result = add("Result reviewed", Some(&reply), &[&referral, "LATEST~2"])?;
+    add("Unrelated", None, &[])?;
+    assert!(add("Reply to nothing", Some("missing.md"), &[]).is_err());
+
+    let entries = parsed_entries()?;
+    let linked = entries.iter().find(|e| e.filename == result).unwrap();
+    assert_eq!(linked.metadata.in_reply_to.as_deref(), Some(reply.as_str()));
+    // Two references to the same entry are recorded once.
+    assert_eq!(linked.metadata.related, [referral.as_str()]);
+
+    let (entries, missing) = thread::thread(&result)?;
+    assert!(missing.is_none());
+    let tree: Vec<(&str, usize)> = entries
+        .iter()
+        .map(|t| (t.entry.filename.as_str(), t.depth))
+        .collect();
+    assert_eq!(
+        tree,
+        [
+            (referral.as_str(), 0),
+            (reply.as_str(), 1),
+            (result.as_str(), 2),
+            (chase.as_str(), 1)
+        ]
+    );
+
+    assert_eq!(
+        thread::backlinks(&referral)?,
+        [
+            (reply.clone(), LinkKind::InReplyTo),
+            (chase, LinkKind::InReplyTo),
+            (result, LinkKind::Related)
+        ]
+    );
+    assert_eq!(thread::backlinks(&reply)?.len(), 1);
+    Ok(())
+}
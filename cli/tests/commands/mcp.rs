@@ -135,3 +135,1897 @@ fn mcp_serve_initialize_responds_on_stdout_only() {
         "tracing should go to stderr; got {stderr:?}"
     );
 }
+
+fn git_repo() -> tempfile::TempDir {
+    let dir = tempdir().unwrap();
+    for sub in [".gitehr", "journal", "state", "documents"] {
+        std::fs::create_dir(dir.path().join(sub)).unwrap();
+    }
+    std::fs::write(dir.path().join(".gitehr/GITEHR_VERSION"), "0.3.6\n").unwrap();
+    for args in [
+        &["init", "-q"][..],
+        &["config", "user.name", "Test User"],
+        &["config", "user.email", "test@example.com"],
+        &["config", "commit.gpgsign", "false"],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+    }
+    dir
+}
+
+fn mcp_session(
+    repo: &std::path::Path,
+    extra: &[&str],
+    requests: &[String],
+) -> Vec<serde_json::Value> {
+    let mut args = vec!["--repo-path", repo.to_str().unwrap()];
+    args.extend(extra);
+    mcp_stdio(&args, requests)
+}
+
+/// Run `gitehr mcp serve --stdio <args>` over `requests`, after initialize.
+fn mcp_stdio(args: &[&str], requests: &[String]) -> Vec<serde_json::Value> {
+    let mut child = gitehr()
+        .args(["mcp", "serve", "--stdio"])
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    {
+        let stdin = child.stdin.as_mut().unwrap();
+        writeln!(
+            stdin,
+            r#"{{"jsonrpc":"2.0","id":0,"method":"initialize","params":{{"clientInfo":{{"name":"gitehr-tests","version":"1.0"}}}}}}"#
+        )
+        .unwrap();
+        for request in requests {
+            writeln!(stdin, "{}", request).unwrap();
+        }
+    }
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success());
+    // Drop the initialize response; callers see replies to their own requests.
+    String::from_utf8_lossy(&out.stdout)
+        .lines()
+        .skip(1)
+        .map(|line| serde_json::from_str(line).expect("stdout carries protocol JSON only"))
+        .collect()
+}
+
+fn tool_call(id: u64, name: &str, arguments: serde_json::Value) -> String {
+    serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "tools/call",
+        "params": { "name": name, "arguments": arguments }
+    })
+    .to_string()
+}
+
+#[test]
+fn mcp_add_journal_entry_writes_and_commits() {
+    let dir = git_repo();
+    let repo = dir.path();
+    let run = |args: &[&str]| {
+        let out = gitehr().args(args).current_dir(repo).output().unwrap();
+        assert!(out.status.success(), "{:?}", out);
+    };
+    run(&["user", "add", "doc001", "Dr. Smith"]);
+    std::fs::write(repo.join("letter.pdf"), b"%PDF-1.4 letter").unwrap();
+    run(&["document", "add", "letter.pdf"]);
+    let document = format!(
+        "documents/{}",
+        std::fs::read_dir(repo.join("documents"))
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap()
+            .file_name()
+            .to_string_lossy()
+    );
+
+    let responses = mcp_session(
+        repo,
+        &["--contributor", "doc001"],
+        &[tool_call(
+            1,
+            "add_journal_entry",
+            serde_json::json!({ "content": "Seen in clinic today.", "documents": [document] }),
+        )],
+    );
+    assert_eq!(responses.len(), 1);
+    assert!(responses[0].get("error").is_none(), "{}", responses[0]);
+    let result: serde_json::Value = serde_json::from_str(
+        responses[0]["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap(),
+    )
+    .unwrap();
+
+    let filename = result["filename"].as_str().unwrap();
+    assert!(filename.starts_with("journal/"));
+    assert_eq!(result["author"], "doc001");
+    let entry = std::fs::read_to_string(repo.join(filename)).unwrap();
+    assert!(entry.contains("author: doc001"));
+    assert!(entry.contains(&format!("path: {}", document)));
+    assert!(entry.contains("Seen in clinic today."));
+
+    // The entry is committed as the contributor's; a second entry, committed
+    // with the audit record, names the agent that wrote it.
+    let git = |args: &[&str]| {
+        let out = Command::new("git")
+            .args(args)
+            .current_dir(repo)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&out.stdout).trim().to_string()
+    };
+    assert_eq!(
+        result["commit"].as_str().unwrap(),
+        git(&["rev-parse", "HEAD~1"])
+    );
+    let head = git(&["show", "--name-only", "--format=%B", "HEAD"]);
+    assert!(
+        head.contains(r#"MCP-Agent: "gitehr-tests" 1.0"#),
+        "{}",
+        head
+    );
+    assert!(head.contains(".gitehr/mcp-audit.jsonl"), "{}", head);
+    let agent_entry = head
+        .lines()
+        .find(|l| l.starts_with("journal/"))
+        .expect("the agent's entry is in the commit");
+    let agent_entry = std::fs::read_to_string(repo.join(agent_entry)).unwrap();
+    assert!(
+        agent_entry.contains(r#"Recorded through MCP by "gitehr-tests" 1.0, acting as doc001"#),
+        "{}",
+        agent_entry
+    );
+    assert!(agent_entry.contains(&format!("- {}", filename)));
+    let status = Command::new("git")
+        .args(["status", "--porcelain", "--", filename])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert!(status.stdout.is_empty(), "entry must be committed");
+}
+
+#[test]
+fn mcp_add_journal_entry_rejects_unknown_contributor_and_documents() {
+    let dir = git_repo();
+    let repo = dir.path();
+    let out = gitehr()
+        .args(["user", "add", "doc001", "Dr. Smith"])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+
+    let responses = mcp_session(
+        repo,
+        &["--contributor", "nobody"],
+        &[tool_call(
+            1,
+            "add_journal_entry",
+            serde_json::json!({ "content": "Note" }),
+        )],
+    );
+    assert!(
+        responses[0]["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Unknown contributor")
+    );
+
+    let responses = mcp_session(
+        repo,
+        &["--contributor", "doc001"],
+        &[tool_call(
+            1,
+            "add_journal_entry",
+            serde_json::json!({ "content": "Note", "documents": ["documents/missing.pdf"] }),
+        )],
+    );
+    assert!(responses[0].get("error").is_some());
+    assert_eq!(std::fs::read_dir(repo.join("journal")).unwrap().count(), 0);
+}
+
+fn structured(response: &serde_json::Value) -> &serde_json::Value {
+    assert!(response.get("error").is_none(), "{}", response);
+    &response["result"]["structuredContent"]
+}
+
+#[test]
+fn mcp_typed_state_tools_round_trip() {
+    let dir = git_repo();
+    let repo = dir.path();
+    let out = gitehr()
+        .args(["user", "add", "doc001", "Dr. Smith"])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let serve = |requests: &[String]| mcp_session(repo, &["--contributor", "doc001"], requests);
+
+    let tools =
+        serve(&[r#"{"jsonrpc":"2.0","id":1,"method":"tools/list","params":{}}"#.to_string()]);
+    let names: Vec<&str> = tools[0]["result"]["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["name"].as_str().unwrap())
+        .collect();
+    for name in [
+        "list_allergies",
+        "add_allergy",
+        "inactivate_allergy",
+        "list_vaccinations",
+        "add_vaccination",
+        "vaccination_entered_in_error",
+        "get_demographics",
+        "update_demographics",
+    ] {
+        assert!(names.contains(&name), "missing tool {name}");
+    }
+
+    let responses = serve(&[
+        tool_call(
+            1,
+            "add_allergy",
+            serde_json::json!({ "agent": "Penicillin", "reaction": "Anaphylaxis", "severity": "critical" }),
+        ),
+        tool_call(
+            2,
+            "add_vaccination",
+            serde_json::json!({ "vaccine": "MMR", "date": "2026-03-01", "dose_sequence": 1 }),
+        ),
+        tool_call(
+            3,
+            "update_demographics",
+            serde_json::json!({
+                "full_name": "Ada Lovelace",
+                "date_of_birth": "1815-12-10",
+                "identifiers": [{ "type": "MRN", "value": "X123" }]
+            }),
+        ),
+    ]);
+    let allergy = &structured(&responses[0])["allergy"];
+    assert_eq!(allergy["severity"], "critical");
+    assert_eq!(allergy["recorded_by"], "doc001");
+    let entry = structured(&responses[0])["journal_entry"].as_str().unwrap();
+    assert!(
+        std::fs::read_to_string(repo.join(entry))
+            .unwrap()
+            .contains("author: doc001")
+    );
+    let vaccination = &structured(&responses[1])["vaccination"];
+    assert_eq!(vaccination["status"], "completed");
+    assert_eq!(
+        structured(&responses[2])["demographics"]["identifiers"][0]["type"],
+        "MRN"
+    );
+
+    let allergy_id = allergy["id"].as_str().unwrap();
+    let vaccination_id = vaccination["id"].as_str().unwrap();
+    let responses = serve(&[
+        tool_call(
+            1,
+            "inactivate_allergy",
+            serde_json::json!({ "id": allergy_id, "reason": "Tolerated amoxicillin" }),
+        ),
+        tool_call(
+            2,
+            "vaccination_entered_in_error",
+            serde_json::json!({ "id": vaccination_id }),
+        ),
+        tool_call(3, "list_allergies", serde_json::json!({})),
+        tool_call(
+            4,
+            "list_allergies",
+            serde_json::json!({ "include_inactive": true }),
+        ),
+        tool_call(5, "list_vaccinations", serde_json::json!({})),
+        tool_call(6, "get_demographics", serde_json::json!({})),
+        tool_call(
+            7,
+            "add_allergy",
+            serde_json::json!({ "agent": "Latex", "reaction": "Rash", "severity": "severe" }),
+        ),
+    ]);
+    assert_eq!(structured(&responses[0])["allergy"]["status"], "inactive");
+    assert_eq!(
+        structured(&responses[1])["vaccination"]["status"],
+        "entered-in-error"
+    );
+    assert_eq!(
+        structured(&responses[2])["allergies"],
+        serde_json::json!([])
+    );
+    assert_eq!(
+        structured(&responses[3])["allergies"][0]["inactive_reason"],
+        "Tolerated amoxicillin"
+    );
+    assert_eq!(
+        structured(&responses[4])["vaccinations"],
+        serde_json::json!([])
+    );
+    assert_eq!(
+        structured(&responses[5])["demographics"]["full_name"],
+        "Ada Lovelace"
+    );
+    assert!(
+        responses[6]["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("unknown variant `severe`")
+    );
+
+    let log = Command::new("git")
+        .args(["log", "--oneline"])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    // Each of the five writes, then the entry naming its agent; the reads and
+    // the failed call after the last write are committed once, as the server
+    // stops.
+    let log = String::from_utf8_lossy(&log.stdout);
+    assert_eq!(log.lines().count(), 11);
+    assert!(
+        log.lines()
+            .next()
+            .unwrap()
+            .ends_with("MCP audit: records since the last write")
+    );
+}
+
+#[test]
+fn mcp_audit_log_records_reads_and_writes() {
+    let dir = git_repo();
+    let repo = dir.path();
+    let out = gitehr()
+        .args(["user", "add", "doc001", "Dr. Smith"])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+
+    let responses = mcp_session(
+        repo,
+        &["--contributor", "doc001"],
+        &[
+            r#"{"jsonrpc":"2.0","id":1,"method":"resources/read","params":{"uri":"gitehr://repo/status"}}"#.to_string(),
+            tool_call(
+                2,
+                "add_journal_entry",
+                serde_json::json!({ "content": "Seen in clinic today." }),
+            ),
+            tool_call(3, "add_allergy", serde_json::json!({ "agent": "Latex" })),
+        ],
+    );
+    let filename = structured(&responses[1])["filename"].as_str().unwrap();
+
+    let log = std::fs::read_to_string(repo.join(".gitehr/mcp-audit.jsonl")).unwrap();
+    let records: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0]["operation"], "resources/read");
+    assert_eq!(records[0]["target"], "gitehr://repo/status");
+    assert_eq!(records[0]["client"], "gitehr-tests");
+    assert_eq!(records[0]["contributor"], "doc001");
+    assert_eq!(records[0]["outcome"], "ok");
+    assert_eq!(records[1]["target"], "add_journal_entry");
+    assert_eq!(records[1]["journal_entry"], filename);
+    assert!(
+        !log.contains("Seen in clinic"),
+        "arguments are only digested"
+    );
+    assert_eq!(records[1]["arguments_sha256"].as_str().unwrap().len(), 64);
+    assert_eq!(records[2]["outcome"], "error");
+    assert!(
+        records[2]["error"]
+            .as_str()
+            .unwrap()
+            .contains("missing field `reaction`")
+    );
+
+    // The write's entry is followed by an entry naming the agent, committed
+    // with the audit log: the write's record and the read before it. The
+    // failed call after it is committed as the server stops, so no record is
+    // left uncommitted, and no read has a commit of its own.
+    let show = |commit: &str| {
+        let out = Command::new("git")
+            .args(["show", "--name-only", "--format=%B", commit])
+            .current_dir(repo)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&out.stdout).to_string()
+    };
+    let write = show(structured(&responses[1])["commit"].as_str().unwrap());
+    assert!(write.contains(filename), "{}", write);
+    let agent = show("HEAD~1");
+    assert!(agent.contains(".gitehr/mcp-audit.jsonl"), "{}", agent);
+    assert!(
+        agent.contains(r#"MCP-Agent: "gitehr-tests" 1.0"#),
+        "{}",
+        agent
+    );
+    assert_eq!(std::fs::read_dir(repo.join("journal")).unwrap().count(), 2);
+    assert!(show("HEAD").starts_with("MCP audit: records since the last write"));
+    let commits = Command::new("git")
+        .args(["rev-list", "--count", "HEAD"])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&commits.stdout).trim(), "3");
+    let status = Command::new("git")
+        .args(["status", "--porcelain", "--", ".gitehr/mcp-audit.jsonl"])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert!(status.stdout.is_empty(), "{:?}", status);
+
+    let audit = |args: &[&str]| {
+        gitehr()
+            .args(["mcp", "audit"])
+            .args(args)
+            .current_dir(repo)
+            .output()
+            .unwrap()
+    };
+    let out = audit(&["--client", "GITEHR-TESTS"]);
+    assert!(out.status.success());
+    let stdout = String::from_utf8_lossy(&out.stdout);
+    assert_eq!(stdout.lines().count(), 3);
+    assert!(stdout.contains("doc001  tools/call add_journal_entry  ok"));
+    assert!(stdout.contains("ERROR: "));
+    assert!(out.stderr.is_empty());
+
+    let out = audit(&["--client", "other-agent"]);
+    assert!(String::from_utf8_lossy(&out.stdout).contains("No MCP audit records."));
+    let count = |args: &[&str]| {
+        let out = audit(args);
+        assert!(out.status.success());
+        serde_json::from_slice::<Vec<serde_json::Value>>(&out.stdout)
+            .unwrap()
+            .len()
+    };
+    assert_eq!(count(&["--since", "2000-01-01", "--json"]), 3);
+    assert_eq!(count(&["--until", "2000-01-01", "--json"]), 0);
+    assert!(!audit(&["--since", "yesterday"]).status.success());
+
+    std::fs::write(
+        repo.join(".gitehr/mcp-audit.jsonl"),
+        log.replace("repo/status", "repo/journal"),
+    )
+    .unwrap();
+    let out = audit(&[]);
+    assert!(String::from_utf8_lossy(&out.stderr).contains("hash chain broken at line 2"));
+}
+
+#[test]
+fn mcp_prompts_are_prefilled_from_the_record() {
+    let dir = git_repo();
+    let repo = dir.path();
+    for args in [
+        &["user", "add", "doc001", "Dr. Smith"][..],
+        &[
+            "demographics",
+            "set",
+            "--full-name",
+            "Ada Lovelace",
+            "--date-of-birth",
+            "1815-12-10",
+        ],
+        &[
+            "allergies",
+            "add",
+            "--agent",
+            "Penicillin",
+            "--reaction",
+            "Anaphylaxis",
+            "--severity",
+            "high",
+        ],
+        &["allergies", "add", "--agent", "Latex", "--reaction", "Rash"],
+        &[
+            "journal",
+            "add",
+            "Reviewed in clinic; chest pain on exertion.",
+        ],
+    ] {
+        let out = gitehr().args(args).current_dir(repo).output().unwrap();
+        assert!(
+            out.status.success(),
+            "{:?}: {}",
+            args,
+            String::from_utf8_lossy(&out.stderr)
+        );
+    }
+    let listed: serde_json::Value = serde_json::from_slice(
+        &gitehr()
+            .args(["allergies", "list", "--json"])
+            .current_dir(repo)
+            .output()
+            .unwrap()
+            .stdout,
+    )
+    .unwrap();
+    let latex = listed
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|a| a["agent"] == "Latex")
+        .unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    let out = gitehr()
+        .args(["allergies", "inactive", &latex])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+
+    std::fs::create_dir_all(repo.join(".gitehr/prompts")).unwrap();
+    std::fs::write(
+        repo.join(".gitehr/prompts/ward_handover.md"),
+        "---\ndescription: Ward handover\narguments:\n  - name: ward\n    required: true\n---\nHandover for {{ward}} ({{unknown}}).\n\n{{allergies}}\n",
+    )
+    .unwrap();
+
+    let get = |id: u64, name: &str, arguments: serde_json::Value| {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "prompts/get",
+            "params": { "name": name, "arguments": arguments }
+        })
+        .to_string()
+    };
+    let responses = mcp_session(
+        repo,
+        &[],
+        &[
+            r#"{"jsonrpc":"2.0","id":1,"method":"prompts/list"}"#.to_string(),
+            get(
+                2,
+                "soap_note",
+                serde_json::json!({ "chief_complaint": "chest pain" }),
+            ),
+            get(
+                3,
+                "referral_letter",
+                serde_json::json!({ "specialty": "cardiology" }),
+            ),
+            get(4, "ward_handover", serde_json::json!({ "ward": "Ward 7" })),
+            get(
+                5,
+                "soap_note",
+                serde_json::json!({ "chief_complaint": "cough", "colour": "red" }),
+            ),
+            get(6, "nonesuch", serde_json::json!({})),
+        ],
+    );
+
+    let names: Vec<&str> = responses[0]["result"]["prompts"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|p| p["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        names,
+        [
+            "discharge_summary",
+            "medication_review",
+            "referral_letter",
+            "soap_note",
+            "ward_handover"
+        ]
+    );
+
+    let text = |response: &serde_json::Value| {
+        response["result"]["messages"][0]["content"]["text"]
+            .as_str()
+            .unwrap_or_else(|| panic!("{}", response))
+            .to_string()
+    };
+    let soap = text(&responses[1]);
+    assert!(soap.contains("who presents with: chest pain"));
+    assert!(soap.contains("Specialty: not specified"));
+    assert!(soap.contains("- Name: Ada Lovelace"));
+    assert!(soap.contains("- Date of birth: 1815-12-10"));
+    assert!(soap.contains("- Penicillin: Anaphylaxis (high)"));
+    let allergies =
+        &soap[soap.find("### Active allergies").unwrap()..soap.find("### Recent").unwrap()];
+    assert!(
+        !allergies.contains("Latex"),
+        "inactive allergies are left out"
+    );
+    assert!(soap.contains("chest pain on exertion"));
+    assert!(!soap.contains("{{"));
+    assert_eq!(
+        responses[1]["result"]["description"],
+        "SOAP note for a presenting complaint, pre-filled with the patient's record: chest pain"
+    );
+
+    assert!(
+        responses[2]["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Missing required argument for referral_letter: reason")
+    );
+    assert_eq!(
+        text(&responses[3]),
+        "Handover for Ward 7 ({{unknown}}).\n\n- Penicillin: Anaphylaxis (high)"
+    );
+    assert!(
+        responses[4]["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Unknown argument for soap_note: colour")
+    );
+    assert!(
+        responses[5]["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Unknown prompt: nonesuch")
+    );
+
+    let log = std::fs::read_to_string(repo.join(".gitehr/mcp-audit.jsonl")).unwrap();
+    assert_eq!(
+        log.lines()
+            .filter(|l| l.contains(r#""operation":"prompts/get""#))
+            .count(),
+        5
+    );
+}
+
+#[test]
+fn mcp_documents_are_read_as_blobs() {
+    use base64::Engine;
+
+    let dir = git_repo();
+    let repo = dir.path();
+    let run = |args: &[&str]| {
+        let out = gitehr().args(args).current_dir(repo).output().unwrap();
+        assert!(out.status.success(), "{:?}", out);
+    };
+    run(&["user", "add", "doc001", "Dr. Smith"]);
+    std::fs::write(repo.join("letter.pdf"), b"%PDF-1.4 letter").unwrap();
+    run(&["document", "add", "letter.pdf"]);
+    std::fs::create_dir_all(repo.join("study/series")).unwrap();
+    std::fs::write(repo.join("study/series/1.dcm"), b"DICM one").unwrap();
+    std::fs::write(repo.join("study/2.dcm"), b"DICM two").unwrap();
+    run(&["document", "add", "--imaging", "study"]);
+    let big = vec![7u8; 8 * 1024 * 1024 + 10];
+    std::fs::write(repo.join("big.bin"), &big).unwrap();
+    run(&["document", "add", "big.bin"]);
+
+    let read = |id: u64, uri: &str| {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "resources/read",
+            "params": { "uri": uri }
+        })
+        .to_string()
+    };
+    let listing = |response: &serde_json::Value| -> Vec<serde_json::Value> {
+        serde_json::from_str(
+            response["result"]["contents"][0]["text"]
+                .as_str()
+                .unwrap_or_else(|| panic!("{}", response)),
+        )
+        .unwrap()
+    };
+    let responses = mcp_session(
+        repo,
+        &[],
+        &[
+            read(1, "gitehr://repo/documents"),
+            read(2, "gitehr://repo/imaging"),
+            r#"{"jsonrpc":"2.0","id":3,"method":"resources/list"}"#.to_string(),
+        ],
+    );
+    let documents = listing(&responses[0]);
+    assert_eq!(documents.len(), 2);
+    let letter = documents
+        .iter()
+        .find(|d| d["mimeType"] == "application/pdf")
+        .unwrap();
+    assert_eq!(letter["kind"], "file");
+    assert_eq!(letter["original_filename"], "letter.pdf");
+    assert_eq!(letter["size"], 15);
+    assert!(
+        letter["referenced_by"][0]
+            .as_str()
+            .unwrap()
+            .starts_with("journal/")
+    );
+    let big_doc = documents.iter().find(|d| d["size"] == big.len()).unwrap();
+    let imaging = listing(&responses[1]);
+    assert_eq!(imaging.len(), 1);
+    assert_eq!(imaging[0]["kind"], "directory");
+    let listed: Vec<&str> = responses[2]["result"]["resources"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["uri"].as_str().unwrap())
+        .collect();
+    assert!(listed.contains(&"gitehr://repo/documents"));
+    assert!(listed.contains(&letter["uri"].as_str().unwrap()));
+
+    let study = imaging[0]["uri"].as_str().unwrap();
+    let big_uri = big_doc["uri"].as_str().unwrap();
+    let responses = mcp_session(
+        repo,
+        &[],
+        &[
+            read(1, letter["uri"].as_str().unwrap()),
+            read(2, study),
+            read(3, &format!("{}/series/1.dcm", study)),
+            read(4, &format!("{}/../../state/allergies.md", study)),
+            read(5, &format!("{}/unlisted.dcm", study)),
+            read(6, big_uri),
+            read(7, &format!("{}?offset=8388608&length=100", big_uri)),
+            read(8, "gitehr://repo/documents/not-linked.pdf"),
+        ],
+    );
+    let decode = |content: &serde_json::Value| {
+        base64::engine::general_purpose::STANDARD
+            .decode(content["blob"].as_str().unwrap())
+            .unwrap()
+    };
+
+    let content = &responses[0]["result"]["contents"][0];
+    assert_eq!(content["mimeType"], "application/pdf");
+    assert_eq!(decode(content), b"%PDF-1.4 letter");
+    assert_eq!(content["_meta"]["sha256"], letter["sha256"]);
+    assert_eq!(content["_meta"]["verified"], true);
+
+    let manifest = &responses[1]["result"]["contents"][0];
+    assert_eq!(manifest["mimeType"], "application/json");
+    assert_eq!(manifest["_meta"]["sha256"], imaging[0]["sha256"]);
+    assert_eq!(manifest["_meta"]["verified"], true);
+    let files: serde_json::Value =
+        serde_json::from_str(manifest["text"].as_str().unwrap()).unwrap();
+    let inner = &responses[2]["result"]["contents"][0];
+    assert_eq!(decode(inner), b"DICM one");
+    assert_eq!(inner["mimeType"], "application/dicom");
+    assert_eq!(inner["_meta"]["sha256"], files["files"]["series/1.dcm"]);
+
+    let error = |response: &serde_json::Value| {
+        response["error"]["message"]
+            .as_str()
+            .unwrap_or_else(|| panic!("{}", response))
+            .to_string()
+    };
+    assert!(error(&responses[3]).contains("Invalid path"));
+    assert!(error(&responses[4]).contains("not listed in the manifest"));
+    assert!(error(&responses[5]).contains("byte limit"));
+    let part = &responses[6]["result"]["contents"][0];
+    assert_eq!(decode(part), vec![7u8; 10]);
+    assert_eq!(part["_meta"]["offset"], 8388608);
+    assert_eq!(part["_meta"]["length"], 10);
+    assert_eq!(part["_meta"]["size"], big.len());
+    assert!(error(&responses[7]).contains("not referenced by any journal entry"));
+}
+
+#[test]
+fn mcp_store_server_spans_subjects() {
+    let dir = tempdir().unwrap();
+    let root = dir.path();
+    for args in [
+        &["store", "init", "ada"][..],
+        &["store", "add", "bob", "--identifier", "NHS:943 476 5919"],
+    ] {
+        let out = gitehr().args(args).current_dir(root).output().unwrap();
+        assert!(out.status.success(), "{:?}", out);
+    }
+    let bob = root.join("bob");
+    for args in [
+        &["config", "user.name", "Test User"][..],
+        &["config", "user.email", "test@example.com"],
+        &["config", "commit.gpgsign", "false"],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&bob)
+            .output()
+            .unwrap();
+    }
+    let out = gitehr()
+        .args(["user", "add", "doc001", "Dr. Smith"])
+        .current_dir(&bob)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+
+    let read = |id: u64, uri: &str| {
+        serde_json::json!({
+            "jsonrpc": "2.0", "id": id, "method": "resources/read", "params": { "uri": uri }
+        })
+        .to_string()
+    };
+    let responses = mcp_stdio(
+        &["--store", root.to_str().unwrap(), "--contributor", "doc001"],
+        &[
+            read(1, "gitehr://store/subjects"),
+            r#"{"jsonrpc":"2.0","id":2,"method":"resources/templates/list"}"#.to_string(),
+            tool_call(
+                3,
+                "find_subject",
+                serde_json::json!({ "query": "9434765919" }),
+            ),
+            tool_call(
+                4,
+                "add_journal_entry",
+                serde_json::json!({ "subject": "bob", "content": "Seen in clinic today." }),
+            ),
+            read(5, "gitehr://subject/bob/journal"),
+            tool_call(
+                6,
+                "add_journal_entry",
+                serde_json::json!({ "content": "No subject given." }),
+            ),
+            read(7, "gitehr://subject/nobody/journal"),
+            read(8, "gitehr://subject/../bob/journal"),
+            r#"{"jsonrpc":"2.0","id":9,"method":"tools/list"}"#.to_string(),
+        ],
+    );
+
+    let subjects: serde_json::Value = serde_json::from_str(
+        responses[0]["result"]["contents"][0]["text"]
+            .as_str()
+            .unwrap(),
+    )
+    .unwrap();
+    let names: Vec<&str> = subjects
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["ada", "bob"]);
+    let bob_id = subjects[1]["id"].as_str().unwrap();
+    assert_eq!(subjects[1]["uri"], format!("gitehr://subject/{bob_id}/"));
+
+    let templates: Vec<&str> = responses[1]["result"]["resourceTemplates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|t| t["uriTemplate"].as_str().unwrap())
+        .collect();
+    assert!(templates.contains(&"gitehr://subject/{subject}/journal/{entry}"));
+
+    let found = &structured(&responses[2])["subjects"];
+    assert_eq!(found.as_array().unwrap().len(), 1);
+    assert_eq!(found[0]["id"], bob_id);
+    assert_eq!(found[0]["matched_identifiers"], serde_json::json!(["NHS"]));
+
+    let filename = structured(&responses[3])["filename"].as_str().unwrap();
+    let entry = std::fs::read_to_string(bob.join(filename)).unwrap();
+    assert!(entry.contains("Seen in clinic today.") && entry.contains("doc001"));
+    let journal = responses[4]["result"]["contents"][0]["text"]
+        .as_str()
+        .unwrap();
+    assert!(journal.contains(filename.trim_start_matches("journal/")));
+
+    for (response, message) in [
+        (&responses[5], "Missing 'subject'"),
+        (&responses[6], "Unknown subject: nobody"),
+        (&responses[7], "Unknown subject: .."),
+    ] {
+        assert!(
+            response["error"]["message"]
+                .as_str()
+                .unwrap()
+                .contains(message),
+            "{}",
+            response
+        );
+    }
+    let tools = responses[8]["result"]["tools"].as_array().unwrap();
+    let add = tools
+        .iter()
+        .find(|t| t["name"] == "add_journal_entry")
+        .unwrap();
+    assert_eq!(add["inputSchema"]["required"][0], "subject");
+
+    // The subject's write and its attribution are in the subject's own log;
+    // lookups that name no subject go to the Store's.
+    let log = std::fs::read_to_string(bob.join(".gitehr/mcp-audit.jsonl")).unwrap();
+    assert_eq!(log.lines().count(), 2);
+    assert!(!root.join("ada/.gitehr/mcp-audit.jsonl").exists());
+    let store_log = std::fs::read_to_string(root.join("gitehr-mcp-audit.jsonl")).unwrap();
+    assert!(store_log.contains("find_subject") && store_log.contains("subject/nobody"));
+    let out = gitehr()
+        .args(["mcp", "audit", "--store"])
+        .current_dir(&bob)
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&out.stdout).contains("find_subject"));
+    let head = Command::new("git")
+        .args(["show", "--name-only", "--format=", "HEAD"])
+        .current_dir(&bob)
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&head.stdout).contains(".gitehr/mcp-audit.jsonl"));
+}
+
+#[test]
+fn mcp_hostile_paths_are_confined() {
+    // The repository sits one level down, so there is something outside it.
+    let dir = tempdir().unwrap();
+    let repo = dir.path().join("repo");
+    for sub in [".gitehr", "journal", "state", "documents"] {
+        std::fs::create_dir_all(repo.join(sub)).unwrap();
+    }
+    std::fs::write(repo.join(".gitehr/GITEHR_VERSION"), "0.3.6\n").unwrap();
+    for args in [
+        &["init", "-q"][..],
+        &["config", "user.name", "Test User"],
+        &["config", "user.email", "test@example.com"],
+        &["config", "commit.gpgsign", "false"],
+    ] {
+        Command::new("git")
+            .args(args)
+            .current_dir(&repo)
+            .output()
+            .unwrap();
+    }
+    const SECRET: &str = "OUTSIDE-THE-RECORD";
+    std::fs::write(dir.path().join("secret.txt"), SECRET).unwrap();
+    std::fs::write(repo.join(".gitehr/contributors.json"), SECRET).unwrap();
+    std::fs::write(repo.join("state/.hidden"), SECRET).unwrap();
+    std::fs::write(repo.join("state/allergies.md"), "Penicillin").unwrap();
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(dir.path().join("secret.txt"), repo.join("state/link.md")).unwrap();
+
+    let payloads = [
+        "..",
+        "../.gitehr/contributors.json",
+        "../../secret.txt",
+        "../../../../../../../../etc/passwd",
+        "./../secret.txt",
+        "/etc/passwd",
+        "//etc/passwd",
+        "..\\..\\secret.txt",
+        ".hidden",
+        ".",
+        "",
+        "subdir/../../.gitehr/contributors.json",
+        "allergies.md/../../.gitehr/contributors.json",
+        "link.md",
+        "C:secret.txt",
+        "%2e%2e/secret.txt",
+        "..%2fsecret.txt",
+        "allergies.md\u{0000}",
+        "..\u{2215}secret.txt",
+    ];
+    let mut requests = Vec::new();
+    let mut uris = Vec::new();
+    for prefix in ["journal/", "state/", "documents/", "imaging/", ""] {
+        for payload in payloads {
+            uris.push(format!("gitehr://repo/{}{}", prefix, payload));
+        }
+    }
+    uris.push("gitehr://repo/../secret.txt".to_string());
+    uris.push("gitehr://repo/documents/../../secret.txt?offset=0&length=10".to_string());
+    for (i, uri) in uris.iter().enumerate() {
+        requests.push(
+            serde_json::json!({
+                "jsonrpc": "2.0", "id": i, "method": "resources/read", "params": { "uri": uri }
+            })
+            .to_string(),
+        );
+    }
+    for (i, payload) in payloads.iter().enumerate() {
+        requests.push(tool_call(
+            (uris.len() + i) as u64,
+            "update_state",
+            serde_json::json!({ "filename": payload, "content": "overwritten" }),
+        ));
+    }
+    requests.push(tool_call(
+        9999,
+        "add_journal_entry",
+        serde_json::json!({ "content": "Note", "documents": ["documents/../secret.txt"] }),
+    ));
+
+    let responses = mcp_session(&repo, &[], &requests);
+    assert_eq!(responses.len(), requests.len());
+    for (request, response) in requests.iter().zip(&responses) {
+        assert!(
+            response.get("error").is_some(),
+            "{} was allowed: {}",
+            request,
+            response
+        );
+        assert!(!response.to_string().contains(SECRET), "{}", response);
+    }
+
+    // Nothing was written outside state/, nor through the symlink.
+    assert_eq!(
+        std::fs::read_to_string(dir.path().join("secret.txt")).unwrap(),
+        SECRET
+    );
+    assert_eq!(
+        std::fs::read_to_string(repo.join(".gitehr/contributors.json")).unwrap(),
+        SECRET
+    );
+    assert_eq!(
+        std::fs::read_to_string(repo.join("state/.hidden")).unwrap(),
+        SECRET
+    );
+    let state: Vec<_> = std::fs::read_dir(repo.join("state"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert!(
+        state
+            .iter()
+            .all(|name| ["allergies.md", ".hidden", "link.md"].contains(&name.as_str())),
+        "{:?}",
+        state
+    );
+
+    // Well-formed names still work.
+    let responses = mcp_session(
+        &repo,
+        &[],
+        &[
+            r#"{"jsonrpc":"2.0","id":1,"method":"resources/read","params":{"uri":"gitehr://repo/state/allergies.md"}}"#.to_string(),
+            tool_call(2, "update_state", serde_json::json!({ "filename": "plan.md", "content": "Review" })),
+        ],
+    );
+    assert_eq!(responses[0]["result"]["contents"][0]["text"], "Penicillin");
+    assert!(responses[1].get("error").is_none(), "{}", responses[1]);
+    assert_eq!(
+        std::fs::read_to_string(repo.join("state/plan.md")).unwrap(),
+        "Review"
+    );
+    let head = Command::new("git")
+        .args(["show", "--name-only", "--format=%s", "HEAD"])
+        .current_dir(&repo)
+        .output()
+        .unwrap();
+    let head = String::from_utf8_lossy(&head.stdout);
+    assert!(head.starts_with("State update: state/plan.md"), "{}", head);
+    assert!(head.contains(".gitehr/mcp-audit.jsonl"), "{}", head);
+}
+
+/// Serve `repo` with a listening transport; see `listen`.
+#[cfg(unix)]
+fn spawn_listening(repo: &std::path::Path, transport: &[&str]) -> (std::process::Child, String) {
+    let mut serve = gitehr();
+    serve
+        .args(["mcp", "serve", "--repo-path", repo.to_str().unwrap()])
+        .args(transport);
+    listen(serve)
+}
+
+/// Start `serve` and wait until it reports the address it is serving on.
+#[cfg(unix)]
+fn listen(mut serve: Command) -> (std::process::Child, String) {
+    use std::io::BufRead;
+
+    let mut child = serve
+        .env("NO_COLOR", "1")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stderr = std::io::BufReader::new(child.stderr.take().unwrap());
+    let mut line = String::new();
+    let listening = loop {
+        line.clear();
+        assert!(
+            stderr.read_line(&mut line).unwrap() > 0,
+            "server exited early"
+        );
+        if let Some((_, rest)) = line.split_once("Starting MCP server on ") {
+            break rest.trim().to_string();
+        }
+    };
+    // Keep draining the log so the server never blocks on a full pipe.
+    std::thread::spawn(move || std::io::copy(&mut stderr, &mut std::io::sink()));
+    (child, listening)
+}
+
+#[cfg(unix)]
+fn terminate(mut child: std::process::Child) -> std::process::ExitStatus {
+    Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    child.wait().unwrap()
+}
+
+/// Add a contributor and mint an MCP token for them with `scopes`.
+#[cfg(unix)]
+fn token_for(repo: &std::path::Path, contributor: &str, scopes: &[&str]) -> String {
+    if !repo.join(".gitehr/contributors.json").exists()
+        || !std::fs::read_to_string(repo.join(".gitehr/contributors.json"))
+            .unwrap()
+            .contains(&format!("\"{contributor}\""))
+    {
+        let out = gitehr()
+            .args(["user", "add", contributor, "Agent"])
+            .current_dir(repo)
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+    }
+    let mut args = vec!["mcp", "token", "create", contributor];
+    for scope in scopes {
+        args.extend(["--scope", scope]);
+    }
+    let out = gitehr().args(&args).current_dir(repo).output().unwrap();
+    assert!(out.status.success(), "{:?}", out);
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    stdout.lines().last().unwrap().trim().to_string()
+}
+
+/// One HTTP/1.1 POST (or other method) to the MCP endpoint. Returns the
+/// status, the session header and the body.
+#[cfg(unix)]
+fn http(
+    addr: &str,
+    token: Option<&str>,
+    method: &str,
+    session: Option<&str>,
+    body: &str,
+) -> (u16, Option<String>, String) {
+    use std::io::Read;
+
+    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+    let mut request = format!(
+        "{method} /mcp HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\nAccept: application/json, text/event-stream\r\nConnection: close\r\nContent-Length: {}\r\n",
+        body.len()
+    );
+    if let Some(session) = session {
+        request.push_str(&format!("Mcp-Session-Id: {session}\r\n"));
+    }
+    if let Some(token) = token {
+        request.push_str(&format!("Authorization: Bearer {token}\r\n"));
+    }
+    request.push_str("\r\n");
+    request.push_str(body);
+    stream.write_all(request.as_bytes()).unwrap();
+
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+    let session = head
+        .lines()
+        .find_map(|l| l.strip_prefix("Mcp-Session-Id: "))
+        .map(str::to_string);
+    (status, session, body.to_string())
+}
+
+#[cfg(unix)]
+#[test]
+fn mcp_store_sessions_on_different_subjects_stay_apart() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("store");
+    std::fs::create_dir(&root).unwrap();
+    for args in [&["store", "init", "ada"][..], &["store", "add", "bob"]] {
+        let out = gitehr().args(args).current_dir(&root).output().unwrap();
+        assert!(out.status.success(), "{:?}", out);
+    }
+    for subject in ["ada", "bob"] {
+        let repo = root.join(subject);
+        for args in [
+            &["config", "user.name", "Test User"][..],
+            &["config", "user.email", "test@example.com"],
+            &["config", "commit.gpgsign", "false"],
+        ] {
+            Command::new("git")
+                .args(args)
+                .current_dir(&repo)
+                .output()
+                .unwrap();
+        }
+        let out = gitehr()
+            .args(["user", "add", "agent1", "Agent"])
+            .current_dir(&repo)
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+    }
+    // `--store` finds the Store's tokens even from inside a subject.
+    let out = gitehr()
+        .args(["mcp", "token", "--store", "create", "agent1"])
+        .args(["--scope", "read", "--scope", "journal-write"])
+        .current_dir(root.join("ada"))
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{:?}", out);
+    assert!(root.join("gitehr-mcp-tokens.json").exists());
+    assert!(!root.join("ada/.gitehr/mcp-tokens.json").exists());
+    let token = String::from_utf8(out.stdout)
+        .unwrap()
+        .lines()
+        .last()
+        .unwrap()
+        .trim()
+        .to_string();
+
+    // The Store is named relative to where the server starts, and the
+    // sessions run in parallel even on one CPU.
+    let mut serve = gitehr();
+    serve
+        .args(["mcp", "serve", "--store", "store", "--http", "127.0.0.1:0"])
+        .current_dir(dir.path())
+        .env("TOKIO_WORKER_THREADS", "4");
+    let (child, url) = listen(serve);
+    let addr = url
+        .strip_prefix("http://")
+        .and_then(|u| u.strip_suffix("/mcp"))
+        .unwrap()
+        .to_string();
+
+    // Each session writes to and reads back from its own subject while the
+    // other does the same.
+    const NOTES: usize = 10;
+    let handles: Vec<_> = ["ada", "bob"]
+        .into_iter()
+        .map(|subject| {
+            let addr = addr.clone();
+            let token = token.clone();
+            std::thread::spawn(move || {
+                let token = Some(token.as_str());
+                let initialize = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+                let (_, session, _) = http(&addr, token, "POST", None, initialize);
+                let session = session.unwrap();
+                let request = |body: &str| {
+                    let (status, _, body) = http(&addr, token, "POST", Some(&session), body);
+                    assert_eq!(status, 200, "{body}");
+                    serde_json::from_str::<serde_json::Value>(&body).unwrap()
+                };
+                for n in 0..NOTES {
+                    let written = request(&tool_call(
+                        2,
+                        "add_journal_entry",
+                        serde_json::json!({ "subject": subject, "content": format!("{subject} note {n}") }),
+                    ));
+                    let filename = structured(&written)["filename"].as_str().unwrap();
+                    let read = request(
+                        &serde_json::json!({
+                            "jsonrpc": "2.0", "id": 3, "method": "resources/read",
+                            "params": { "uri": format!("gitehr://subject/{subject}/{filename}") }
+                        })
+                        .to_string(),
+                    );
+                    let text = read["result"]["contents"][0]["text"].as_str().unwrap();
+                    assert!(text.contains(&format!("{subject} note {n}")), "{read}");
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    assert!(terminate(child).success());
+
+    for (subject, other) in [("ada", "bob"), ("bob", "ada")] {
+        let repo = root.join(subject);
+        let mut notes = 0;
+        for entry in std::fs::read_dir(repo.join("journal")).unwrap() {
+            let content = std::fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert!(!content.contains(&format!("{other} note")), "{content}");
+            notes += usize::from(content.contains(&format!("{subject} note")));
+        }
+        assert_eq!(notes, NOTES);
+        let log = Command::new("git")
+            .args(["log", "-p"])
+            .current_dir(&repo)
+            .output()
+            .unwrap();
+        let log = String::from_utf8_lossy(&log.stdout);
+        assert!(!log.contains(&format!("{other} note")), "{log}");
+        assert_eq!(log.matches(&format!("+{subject} note")).count(), NOTES);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn mcp_http_sessions_are_independent_and_shut_down_on_signal() {
+    let dir = git_repo();
+    let token = token_for(dir.path(), "agent1", &["read"]);
+    let (child, url) = spawn_listening(dir.path(), &["--http", "127.0.0.1:0"]);
+    let addr = url
+        .strip_prefix("http://")
+        .and_then(|u| u.strip_suffix("/mcp"))
+        .unwrap()
+        .to_string();
+
+    let initialize = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+    let list = r#"{"jsonrpc":"2.0","id":2,"method":"resources/list","params":{}}"#;
+
+    // Sessions opened concurrently each get their own id and state.
+    let handles: Vec<_> = (0..4)
+        .map(|_| {
+            let addr = addr.clone();
+            let token = token.clone();
+            std::thread::spawn(move || {
+                let token = Some(token.as_str());
+                let (status, session, _) = http(&addr, token, "POST", None, initialize);
+                assert_eq!(status, 200);
+                let session = session.expect("initialize returns a session id");
+                let (status, _, body) = http(&addr, token, "POST", Some(&session), list);
+                assert_eq!(status, 200);
+                let response: serde_json::Value = serde_json::from_str(&body).unwrap();
+                assert!(response.get("error").is_none(), "{body}");
+                session
+            })
+        })
+        .collect();
+    let mut sessions: Vec<String> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    sessions.sort();
+    sessions.dedup();
+    assert_eq!(sessions.len(), 4);
+
+    let token = Some(token.as_str());
+    assert_eq!(http(&addr, token, "POST", None, list).0, 400);
+    assert_eq!(
+        http(&addr, token, "POST", Some("no-such-session"), list).0,
+        404
+    );
+    assert_eq!(
+        http(
+            &addr,
+            token,
+            "POST",
+            Some(&sessions[0]),
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#
+        )
+        .0,
+        202
+    );
+    assert_eq!(http(&addr, token, "DELETE", Some(&sessions[0]), "").0, 200);
+    assert_eq!(http(&addr, token, "POST", Some(&sessions[0]), list).0, 404);
+
+    // Requests that cannot be read are still answered before the close.
+    let raw = |request: String| -> String {
+        use std::io::Read;
+        let mut stream = std::net::TcpStream::connect(&addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response.lines().next().unwrap_or("").to_string()
+    };
+    assert_eq!(
+        raw(format!(
+            "POST /mcp HTTP/1.1\r\nX-Padding: {}\r\n\r\n",
+            "a".repeat(70_000)
+        )),
+        "HTTP/1.1 413 Content Too Large"
+    );
+    assert_eq!(
+        raw("POST /mcp HTTP/1.1\r\nContent-Length: 99999999999\r\n\r\n".to_string()),
+        "HTTP/1.1 413 Content Too Large"
+    );
+    assert_eq!(
+        raw("POST /mcp HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n".to_string()),
+        "HTTP/1.1 501 Not Implemented"
+    );
+    assert_eq!(
+        raw("POST /mcp HTTP/1.1\r\nContent-Length: 50\r\n\r\n{}".to_string()),
+        "HTTP/1.1 400 Bad Request"
+    );
+    assert_eq!(
+        raw("POST /mcp HTTP/1.1\r\nContent-Len".to_string()),
+        "HTTP/1.1 400 Bad Request"
+    );
+
+    assert!(terminate(child).success());
+}
+
+#[cfg(unix)]
+#[test]
+fn mcp_unix_socket_connections_initialise_separately() {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixStream;
+
+    let dir = git_repo();
+    let token = token_for(dir.path(), "agent1", &["read"]);
+    let socket = dir.path().join(".gitehr/mcp.sock");
+    let (child, _) = spawn_listening(dir.path(), &["--unix", socket.to_str().unwrap()]);
+
+    let exchange = |stream: &mut UnixStream, request: &str| -> serde_json::Value {
+        writeln!(stream, "{}", request).unwrap();
+        let mut line = String::new();
+        BufReader::new(stream.try_clone().unwrap())
+            .read_line(&mut line)
+            .unwrap();
+        serde_json::from_str(&line).unwrap()
+    };
+
+    let mut first = UnixStream::connect(&socket).unwrap();
+    let mut second = UnixStream::connect(&socket).unwrap();
+    let initialize = serde_json::json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": "initialize",
+        "params": { "_meta": { "authorization": format!("Bearer {token}") } }
+    })
+    .to_string();
+    let response = exchange(&mut first, &initialize);
+    assert_eq!(response["result"]["serverInfo"]["name"], "gitehr");
+
+    let list = r#"{"jsonrpc":"2.0","id":2,"method":"resources/list","params":{}}"#;
+    assert!(exchange(&mut first, list).get("error").is_none());
+    assert_eq!(
+        exchange(&mut second, list)["error"]["message"],
+        "Server not initialized"
+    );
+
+    assert!(terminate(child).success());
+    assert!(!socket.exists(), "socket file is removed on shutdown");
+}
+
+#[cfg(unix)]
+#[test]
+fn mcp_http_tokens_enforce_scopes_and_attribution() {
+    let dir = git_repo();
+    let repo = dir.path();
+    // The machine's active contributor must not be credited with agent writes.
+    let out = gitehr()
+        .args(["user", "add", "doc001", "Dr. Smith"])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    gitehr()
+        .args(["user", "activate", "doc001"])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    let reader = token_for(repo, "agent1", &["read"]);
+    let writer = token_for(repo, "agent2", &["read", "journal-write"]);
+    // The token file stays out of Git, so it never dirties the tree.
+    let ignored = Command::new("git")
+        .args([
+            "check-ignore",
+            ".gitehr/mcp-tokens.json",
+            ".gitehr/.gitignore",
+        ])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert_eq!(String::from_utf8_lossy(&ignored.stdout).lines().count(), 2);
+
+    let (child, url) = spawn_listening(repo, &["--http", "127.0.0.1:0"]);
+    let addr = url
+        .strip_prefix("http://")
+        .and_then(|u| u.strip_suffix("/mcp"))
+        .unwrap()
+        .to_string();
+    let initialize = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+    let open = |token: &str| {
+        let (status, session, _) = http(&addr, Some(token), "POST", None, initialize);
+        assert_eq!(status, 200);
+        session.unwrap()
+    };
+    let call = |token: &str, session: &str, name: &str, arguments: serde_json::Value| {
+        let (status, _, body) = http(
+            &addr,
+            Some(token),
+            "POST",
+            Some(session),
+            &tool_call(2, name, arguments),
+        );
+        assert_eq!(status, 200, "{body}");
+        serde_json::from_str::<serde_json::Value>(&body).unwrap()
+    };
+
+    // No token, or a bogus one, is refused before any session exists.
+    assert_eq!(http(&addr, None, "POST", None, initialize).0, 401);
+    assert_eq!(
+        http(&addr, Some("gehr_not-a-token"), "POST", None, initialize).0,
+        401
+    );
+
+    let read_session = open(&reader);
+    let denied = call(
+        &reader,
+        &read_session,
+        "add_journal_entry",
+        serde_json::json!({ "content": "Should not be written" }),
+    );
+    assert_eq!(denied["error"]["code"], -32001);
+    assert!(
+        denied["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("journal-write")
+    );
+    assert!(
+        call(
+            &reader,
+            &read_session,
+            "list_allergies",
+            serde_json::json!({})
+        )
+        .get("error")
+        .is_none()
+    );
+    // A session is bound to the token that opened it.
+    assert_eq!(
+        http(
+            &addr,
+            Some(&writer),
+            "POST",
+            Some(&read_session),
+            &tool_call(2, "list_allergies", serde_json::json!({}))
+        )
+        .0,
+        403
+    );
+
+    let write_session = open(&writer);
+    let written = call(
+        &writer,
+        &write_session,
+        "add_journal_entry",
+        serde_json::json!({ "content": "Summary drafted by agent" }),
+    );
+    let result = &written["result"]["structuredContent"];
+    assert_eq!(result["author"], "agent2", "{written}");
+    let entry = std::fs::read_to_string(repo.join(result["filename"].as_str().unwrap())).unwrap();
+    assert!(entry.contains("author: agent2"));
+    let denied = call(
+        &writer,
+        &write_session,
+        "add_allergy",
+        serde_json::json!({ "agent": "Latex", "reaction": "Rash" }),
+    );
+    assert!(
+        denied["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("state-write")
+    );
+    // Refusals and reads are logged, never committed one by one: the history
+    // holds the write and the entry naming its agent until the server stops.
+    let commits = || {
+        let out = Command::new("git")
+            .args(["rev-list", "--count", "HEAD"])
+            .current_dir(repo)
+            .output()
+            .unwrap();
+        String::from_utf8_lossy(&out.stdout).trim().to_string()
+    };
+    assert_eq!(commits(), "2");
+
+    // Revocation takes effect on the next request.
+    let list = gitehr()
+        .args(["mcp", "token", "list"])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    let listing = String::from_utf8(list.stdout).unwrap();
+    assert!(!listing.contains(&writer), "tokens are never listed");
+    let id = listing
+        .lines()
+        .find(|l| l.contains("agent2"))
+        .and_then(|l| l.split_whitespace().next())
+        .unwrap()
+        .to_string();
+    let out = gitehr()
+        .args(["mcp", "token", "revoke", &id])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert_eq!(
+        http(
+            &addr,
+            Some(&writer),
+            "POST",
+            Some(&write_session),
+            &tool_call(3, "list_allergies", serde_json::json!({}))
+        )
+        .0,
+        401
+    );
+
+    let stored = std::fs::read_to_string(repo.join(".gitehr/mcp-tokens.json")).unwrap();
+    assert!(!stored.contains(&reader) && !stored.contains(&writer));
+
+    assert!(terminate(child).success());
+    assert_eq!(commits(), "3");
+}
+
+#[cfg(unix)]
+#[test]
+fn mcp_unix_socket_requires_a_token() {
+    use std::io::{BufRead, BufReader};
+    use std::os::unix::net::UnixStream;
+
+    let dir = git_repo();
+    let socket = dir.path().join(".gitehr/mcp.sock");
+    let (child, _) = spawn_listening(dir.path(), &["--unix", socket.to_str().unwrap()]);
+
+    let mut stream = UnixStream::connect(&socket).unwrap();
+    writeln!(
+        stream,
+        r#"{{"jsonrpc":"2.0","id":1,"method":"initialize","params":{{}}}}"#
+    )
+    .unwrap();
+    let mut line = String::new();
+    BufReader::new(stream.try_clone().unwrap())
+        .read_line(&mut line)
+        .unwrap();
+    let response: serde_json::Value = serde_json::from_str(&line).unwrap();
+    assert_eq!(response["error"]["code"], -32001);
+
+    assert!(terminate(child).success());
+}
+
+/// Read lines from `reader` on a thread until `done` accepts one, or fail
+/// after a timeout.
+fn read_until<R: std::io::BufRead + Send + 'static>(
+    mut reader: R,
+    mut done: impl FnMut(&str) -> bool + Send + 'static,
+) -> R {
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || {
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                panic!("stream ended");
+            }
+            if done(line.trim()) {
+                break;
+            }
+        }
+        tx.send(reader).unwrap();
+    });
+    rx.recv_timeout(std::time::Duration::from_secs(20))
+        .expect("expected message not received")
+}
+
+#[test]
+fn mcp_subscriptions_notify_on_journal_and_state_changes() {
+    use std::io::BufReader;
+
+    let dir = git_repo();
+    let repo = dir.path();
+    let mut child = gitehr()
+        .args([
+            "mcp",
+            "serve",
+            "--stdio",
+            "--repo-path",
+            repo.to_str().unwrap(),
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    let stdout = BufReader::new(child.stdout.take().unwrap());
+    for request in [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"resources/subscribe","params":{"uri":"gitehr://repo/journal"}}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"resources/subscribe","params":{"uri":"gitehr://repo/state/plan.md"}}"#,
+        r#"{"jsonrpc":"2.0","id":4,"method":"resources/subscribe","params":{"uri":"gitehr://repo/status"}}"#,
+    ] {
+        writeln!(stdin, "{}", request).unwrap();
+    }
+
+    let replies = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+    let seen = replies.clone();
+    let stdout = read_until(stdout, move |line| {
+        let value: serde_json::Value = serde_json::from_str(line).unwrap();
+        let mut seen = seen.lock().unwrap();
+        seen.push(value);
+        seen.len() == 4
+    });
+    let replies = replies.lock().unwrap().clone();
+    assert_eq!(
+        replies[0]["result"]["capabilities"]["resources"]["subscribe"],
+        true
+    );
+    assert_eq!(replies[1]["result"], serde_json::json!({}));
+    assert_eq!(replies[2]["result"], serde_json::json!({}));
+    assert!(
+        replies[3]["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Not a subscribable resource")
+    );
+
+    // Another process adds an entry and changes the subscribed state file.
+    let out = gitehr()
+        .args(["journal", "add", "Seen in clinic."])
+        .current_dir(repo)
+        .output()
+        .unwrap();
+    assert!(out.status.success(), "{:?}", out);
+    std::fs::write(repo.join("state/plan.md"), "Review in 6 weeks").unwrap();
+
+    let mut pending: std::collections::BTreeSet<String> =
+        ["gitehr://repo/journal", "gitehr://repo/state/plan.md"]
+            .map(str::to_string)
+            .into();
+    let _stdout = read_until(stdout, move |line| {
+        let value: serde_json::Value = serde_json::from_str(line).unwrap();
+        assert_eq!(
+            value["method"], "notifications/resources/updated",
+            "{}",
+            line
+        );
+        assert!(value.get("id").is_none());
+        pending.remove(value["params"]["uri"].as_str().unwrap());
+        pending.is_empty()
+    });
+
+    drop(stdin);
+    assert!(child.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn mcp_http_event_stream_delivers_notifications() {
+    use std::io::{BufRead, BufReader};
+
+    let dir = git_repo();
+    let token = token_for(dir.path(), "agent1", &["read"]);
+    let (child, url) = spawn_listening(dir.path(), &["--http", "127.0.0.1:0"]);
+    let addr = url
+        .strip_prefix("http://")
+        .and_then(|u| u.strip_suffix("/mcp"))
+        .unwrap()
+        .to_string();
+    let secret = token;
+    let token = Some(secret.as_str());
+
+    let (_, session, _) = http(
+        &addr,
+        token,
+        "POST",
+        None,
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+    );
+    let session = session.unwrap();
+    let (status, _, body) = http(
+        &addr,
+        token,
+        "POST",
+        Some(&session),
+        r#"{"jsonrpc":"2.0","id":2,"method":"resources/subscribe","params":{"uri":"gitehr://repo/state"}}"#,
+    );
+    assert_eq!(status, 200, "{body}");
+
+    let mut stream = std::net::TcpStream::connect(&addr).unwrap();
+    write!(
+        stream,
+        "GET /mcp HTTP/1.1\r\nHost: {addr}\r\nAccept: text/event-stream\r\nMcp-Session-Id: {session}\r\nAuthorization: Bearer {}\r\n\r\n",
+        secret
+    )
+    .unwrap();
+    let mut events = BufReader::new(stream.try_clone().unwrap());
+    let mut status_line = String::new();
+    events.read_line(&mut status_line).unwrap();
+    assert!(status_line.starts_with("HTTP/1.1 200"), "{status_line}");
+    let mut line = String::new();
+    let mut content_type = None;
+    loop {
+        line.clear();
+        events.read_line(&mut line).unwrap();
+        if line == "\r\n" {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Type: ") {
+            content_type = Some(value.trim().to_string());
+        }
+    }
+    assert_eq!(content_type.as_deref(), Some("text/event-stream"));
+
+    // One stream per session at a time.
+    let (status, _, _) = http(&addr, token, "GET", Some(&session), "");
+    assert_eq!(status, 409);
+
+    std::fs::write(dir.path().join("state/plan.md"), "Review").unwrap();
+    let _events = read_until(events, |line| {
+        line.strip_prefix("data: ").is_some_and(|data| {
+            let value: serde_json::Value = serde_json::from_str(data).unwrap();
+            assert_eq!(value["method"], "notifications/resources/updated");
+            value["params"]["uri"] == "gitehr://repo/state"
+        })
+    });
+
+    drop(stream);
+    assert!(terminate(child).success());
+}
+
+#[test]
+fn mcp_structured_journal_entry_resource() {
+    let dir = git_repo();
+    let repo = dir.path();
+    for body in [
+        "PC/ chest pain\nOE/ alert\n  RS/ clear",
+        "Historic free text",
+    ] {
+        let out = gitehr()
+            .args(["journal", "add", body])
+            .current_dir(repo)
+            .output()
+            .unwrap();
+        assert!(out.status.success(), "{:?}", out);
+    }
+    let mut entries: Vec<String> = std::fs::read_dir(repo.join("journal"))
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    entries.sort();
+
+    let read = |id: u64, uri: String| {
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": "resources/read",
+            "params": { "uri": uri }
+        })
+        .to_string()
+    };
+    let responses = mcp_session(
+        repo,
+        &[],
+        &[
+            read(
+                1,
+                format!("gitehr://repo/journal/{}/structured", entries[0]),
+            ),
+            read(
+                2,
+                format!("gitehr://repo/journal/{}/structured", entries[1]),
+            ),
+            read(
+                3,
+                "gitehr://repo/journal/../.gitehr/ID/structured".to_string(),
+            ),
+            r#"{"jsonrpc":"2.0","id":4,"method":"resources/templates/list"}"#.to_string(),
+        ],
+    );
+    let content = &responses[0]["result"]["contents"][0];
+    assert_eq!(content["mimeType"], "application/json");
+    let data: serde_json::Value = serde_json::from_str(content["text"].as_str().unwrap()).unwrap();
+    assert_eq!(data["PC"]["notes"], "chest pain");
+    assert_eq!(data["OE"]["RS"], "clear");
+    assert_eq!(data["_source_map"]["OE.RS"], 3);
+
+    let historic = responses[1]["result"]["contents"][0]["text"]
+        .as_str()
+        .unwrap();
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(historic).unwrap(),
+        serde_json::json!({})
+    );
+    assert!(responses[2].get("error").is_some(), "{}", responses[2]);
+    assert!(
+        responses[3]["result"]["resourceTemplates"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|t| t["uriTemplate"] == "gitehr://repo/journal/{entry}/structured")
+    );
+}
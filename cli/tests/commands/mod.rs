@@ -7,7 +7,9 @@ pub mod contributor;
 pub mod demographics;
 pub mod document;
 pub mod encrypt;
+pub mod export;
 pub mod gui;
+pub mod import;
 pub mod journal;
 pub mod mcp;
 #[cfg(unix)]
@@ -84,7 +84,7 @@ fn unknown_subcommand_without_a_plugin_errors_helpfully() {
 #[test]
 fn plugins_lists_installed_and_excludes_builtins() {
     let dir = tempdir().unwrap();
-    write_plugin(dir.path(), "gitehr-export", "#!/bin/sh\n");
+    write_plugin(dir.path(), "gitehr-audit-report", "#!/bin/sh\n");
     write_plugin(dir.path(), "gitehr-status", "#!/bin/sh\n"); // `status` is a built-in
 
     let out = gitehr()
@@ -94,7 +94,10 @@ fn plugins_lists_installed_and_excludes_builtins() {
         .unwrap();
 
     let s = String::from_utf8_lossy(&out.stdout);
-    assert!(s.contains("export"), "installed plugin should be listed");
+    assert!(
+        s.contains("audit-report"),
+        "installed plugin should be listed"
+    );
     assert!(
         !s.contains("status"),
         "a name shadowed by a built-in must not be listed as a plugin"
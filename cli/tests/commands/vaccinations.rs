@@ -47,6 +47,7 @@ fn vaccination_input() -> VaccinationInput {
         performer: Some("Nurse Example".to_string()),
         fhir_json: None,
         note: None,
+        source_entry: None,
     }
 }
 